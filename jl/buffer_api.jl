@@ -114,6 +114,18 @@ function buffer_delete!(start::Int, stop::Int)
     return nothing
 end
 
+"""
+    buffer_replace!(start::Int, stop::Int, text::String)
+
+Replace the text from start to stop (exclusive, 0-indexed) with `text`.
+This directly modifies the buffer.
+"""
+function buffer_replace!(start::Int, stop::Int, text::String)
+    handle = _get_roe_handle()
+    ccall(Libdl.dlsym(handle, :roe_buffer_replace), Cvoid, (Clonglong, Clonglong, Cstring), start, stop, text)
+    return nothing
+end
+
 """
     buffer_major_mode() -> Union{String, Nothing}
 
@@ -151,3 +163,76 @@ function buffer_set_show_gutter!(show::Bool)
     ccall(Libdl.dlsym(handle, :roe_buffer_set_show_gutter), Cvoid, (Clonglong,), show ? 1 : 0)
     return nothing
 end
+
+"""
+    buffer_set_show_word_count!(show::Bool)
+
+Set whether the modeline shows word count and reading time for the current buffer.
+"""
+function buffer_set_show_word_count!(show::Bool)
+    handle = _get_roe_handle()
+    ccall(Libdl.dlsym(handle, :roe_buffer_set_show_word_count), Cvoid, (Clonglong,), show ? 1 : 0)
+    return nothing
+end
+
+"""
+    buffer_set_tab_width!(tab_width::Int)
+
+Set the columns between tab stops for the current buffer.
+"""
+function buffer_set_tab_width!(tab_width::Int)
+    handle = _get_roe_handle()
+    ccall(Libdl.dlsym(handle, :roe_buffer_set_tab_width), Cvoid, (Clonglong,), tab_width)
+    return nothing
+end
+
+"""
+    buffer_set_indent_tabs!(indent_tabs::Bool)
+
+Set whether the current buffer is indented with tabs rather than spaces.
+"""
+function buffer_set_indent_tabs!(indent_tabs::Bool)
+    handle = _get_roe_handle()
+    ccall(Libdl.dlsym(handle, :roe_buffer_set_indent_tabs), Cvoid, (Clonglong,), indent_tabs ? 1 : 0)
+    return nothing
+end
+
+"""
+    buffer_set_tree_sitter!(language::String) -> Bool
+
+Highlight the current buffer with roe's built-in tree-sitter grammar for
+`language` ("rust", "json" or "toml"), or stop with "". Spans added from Julia
+take precedence. Returns false if there is no such grammar.
+"""
+function buffer_set_tree_sitter!(language::String)
+    handle = _get_roe_handle()
+    result = ccall(Libdl.dlsym(handle, :roe_buffer_set_tree_sitter), Clonglong, (Cstring,), language)
+    return result != 0
+end
+
+"""
+    buffer_get_local(name::String) -> Union{String, Nothing}
+
+Get a buffer-local variable of the current buffer, or `nothing` if it isn't set.
+"""
+function buffer_get_local(name::String)
+    handle = _get_roe_handle()
+    ptr = ccall(Libdl.dlsym(handle, :roe_buffer_get_local), Ptr{Cchar}, (Cstring,), name)
+    if ptr == C_NULL
+        return nothing
+    end
+    result = unsafe_string(ptr)
+    ccall(Libdl.dlsym(handle, :roe_free_string), Cvoid, (Ptr{Cchar},), ptr)
+    return result
+end
+
+"""
+    buffer_set_local!(name::String, value::String)
+
+Set a buffer-local variable of the current buffer. It lasts until the buffer is killed.
+"""
+function buffer_set_local!(name::String, value::String)
+    handle = _get_roe_handle()
+    ccall(Libdl.dlsym(handle, :roe_buffer_set_local), Cvoid, (Cstring, Cstring), name, value)
+    return nothing
+end
@@ -17,6 +17,8 @@ define_mode("julia-buffer-switcher") do action, state
         state["selected"] = 1
         # Get purpose from action (switch or kill)
         state["purpose"] = get(action, "purpose", "switch")
+        # Rows to list at once (roe_config["menu"]["max_visible_items"])
+        state["max_visible"] = max(1, parse(Int, string(get(action, "max_visible", "4"))))
         # Get buffer list from action (JSON array of {index, name})
         buffers_json = get(action, "buffers", "[]")
         state["buffers"] = _parse_buffer_list(buffers_json)
@@ -51,12 +53,12 @@ define_mode("julia-buffer-switcher") do action, state
         return _buffer_switcher_render(state)
 
     elseif action_type == "cursor"
-        dir = get(action, "direction", "")
-        if dir == "up" && state["selected"] > 1
-            state["selected"] -= 1
-        elseif dir == "down" && state["selected"] < length(state["filtered_buffers"])
-            state["selected"] += 1
-        end
+        _menu_move_selection!(state, length(state["filtered_buffers"]), get(action, "direction", ""))
+        return _buffer_switcher_render(state)
+
+    elseif action_type == "resize"
+        # The window was resized; list as many rows as now fit
+        state["max_visible"] = max(1, parse(Int, string(get(action, "max_visible", "4"))))
         return _buffer_switcher_render(state)
 
     elseif action_type == "tab"
@@ -82,6 +84,12 @@ define_mode("julia-buffer-switcher") do action, state
                     "actions" => [SwitchBufferAction(buffer_index)]
                 )
             end
+        elseif state["purpose"] == "switch" && !isempty(state["input"])
+            # Nothing matches the typed name: create it, like Emacs
+            return Dict(
+                "result" => "consumed",
+                "actions" => [CreateBufferAction(state["input"])]
+            )
         end
         return Dict("result" => "consumed", "actions" => [])
 
@@ -168,8 +176,7 @@ function _buffer_switcher_render(state)
 
     push!(lines, "")
 
-    # Calculate max visible items (same as file selector)
-    max_visible = 4
+    max_visible = get(state, "max_visible", 4)
 
     selected = state["selected"]
     buffers = state["filtered_buffers"]
@@ -189,7 +196,7 @@ function _buffer_switcher_render(state)
             end_idx = n_items
         else
             start_idx = selected - half
-            end_idx = selected + half - 1
+            end_idx = start_idx + max_visible - 1
         end
     end
 
@@ -200,16 +207,24 @@ function _buffer_switcher_render(state)
 
     # Status line
     push!(lines, "")
-    action_hint = purpose == "kill" ? "kill" : "switch"
-    push!(lines, "[$(n_items) buffers] Arrow keys to navigate, Enter to $(action_hint)")
+    if n_items == 0 && has_filter && purpose == "switch"
+        push!(lines, "[no match] Enter to create buffer \"$(state["input"])\"")
+    else
+        action_hint = purpose == "kill" ? "kill" : "switch"
+        push!(lines, "[$(n_items) buffers] Arrow keys to navigate, Enter to $(action_hint)")
+    end
 
     content = join(lines, "\n")
 
+    # Preview the highlighted buffer beside the list
+    preview = n_items == 0 ? ClearPreviewAction() : PreviewBufferAction(buffers[selected]["index"])
+
     return Dict(
         "result" => "consumed",
         "actions" => [
             ClearTextAction(),
-            InsertTextModeAction(content, "start")
+            InsertTextModeAction(content, "start"),
+            preview
         ]
     )
 end
@@ -182,6 +182,7 @@ function call_command(name::String, context::Dict)
         result = func(ctx)
         return action_to_dict(result)
     catch e
+        report_error("Error in command $name", e)
         return Dict("type" => "error", "message" => "Command error: $(sprint(showerror, e))")
     end
 end
@@ -286,7 +287,6 @@ end
 
 # Registry of mode-specific indent commands
 const _indent_commands = Dict{String, String}()
-const _newline_indent_commands = Dict{String, String}()
 
 """
 Register an indent-line command for a major mode.
@@ -295,13 +295,6 @@ function register_indent_command(mode::String, command::String)
     _indent_commands[mode] = command
 end
 
-"""
-Register a newline-and-indent command for a major mode.
-"""
-function register_newline_indent_command(mode::String, command::String)
-    _newline_indent_commands[mode] = command
-end
-
 define_command("indent-line", "Re-indent current line based on major mode") do ctx
     mode = buffer_major_mode()
     if mode !== nothing && haskey(_indent_commands, mode)
@@ -310,13 +303,3 @@ define_command("indent-line", "Re-indent current line based on major mode") do c
     # Default: insert 4 spaces
     return InsertAction(ctx.cursor_pos, "    ")
 end
-
-define_command("newline-and-indent", "Insert newline and indent based on major mode") do ctx
-    mode = buffer_major_mode()
-    if mode !== nothing && haskey(_newline_indent_commands, mode)
-        # Call the mode-specific newline-and-indent command
-        return ExecuteCommandAction(_newline_indent_commands[mode])
-    end
-    # Default: just insert newline
-    return InsertAction(ctx.cursor_pos, "\n")
-end
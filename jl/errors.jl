@@ -0,0 +1,47 @@
+# Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free software: you can redistribute it and/or modify it under the terms of the GNU General Public License as published by the Free Software Foundation, version 3.
+#
+# This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+#
+# You should have received a copy of the GNU General Public License along with this program. If not, see <https://www.gnu.org/licenses/>.
+#
+# Error reporting
+# Exceptions caught in commands, modes and hooks are sent to the editor, which
+# collects them in the *Julia Errors* buffer and echoes a one-line summary.
+
+using Libdl
+
+"""
+    report_error(context::String, e, bt=catch_backtrace())
+
+Report an exception to the editor. `context` says where it happened (e.g.
+"Error in command insert-date"); the full error and backtrace go to the
+`*Julia Errors*` buffer. Must be called from inside a `catch` block unless a
+backtrace is passed explicitly.
+
+# Example
+```julia
+try
+    risky()
+catch e
+    report_error("Error in my-command", e)
+end
+```
+"""
+function report_error(context::String, e, bt=catch_backtrace())
+    try
+        message = sprint(showerror, e)
+        summary = "$context: $(first(split(message, '\n')))"
+        details = "$context\n" * sprint(showerror, e, bt)
+
+        handle = _get_roe_handle()
+        ccall(
+            Libdl.dlsym(handle, :roe_report_error),
+            Cvoid,
+            (Cstring, Cstring),
+            summary, details
+        )
+    catch
+        # Reporting must never raise into the caller's error path
+    end
+    return nothing
+end
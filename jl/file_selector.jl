@@ -20,6 +20,8 @@ define_mode("julia-file-selector") do action, state
         state["selected"] = 1
         # Get open_type from action if provided (for init)
         state["open_type"] = get(action, "open_type", get(state, "open_type", "new"))
+        # Rows to list at once (roe_config["menu"]["max_visible_items"])
+        state["max_visible"] = max(1, parse(Int, string(get(action, "max_visible", "4"))))
         _file_selector_load_dir!(state)
 
         # For init action, immediately return the rendered content
@@ -43,12 +45,12 @@ define_mode("julia-file-selector") do action, state
         return _file_selector_render(state)
 
     elseif action_type == "cursor"
-        dir = get(action, "direction", "")
-        if dir == "up" && state["selected"] > 1
-            state["selected"] -= 1
-        elseif dir == "down" && state["selected"] < length(state["filtered_items"])
-            state["selected"] += 1
-        end
+        _menu_move_selection!(state, length(state["filtered_items"]), get(action, "direction", ""))
+        return _file_selector_render(state)
+
+    elseif action_type == "resize"
+        # The window was resized; list as many rows as now fit
+        state["max_visible"] = max(1, parse(Int, string(get(action, "max_visible", "4"))))
         return _file_selector_render(state)
 
     elseif action_type == "tab"
@@ -184,8 +186,7 @@ function _file_selector_render(state)
 
     push!(lines, "")
 
-    # Calculate max visible items
-    max_visible = 4
+    max_visible = get(state, "max_visible", 4)
 
     selected = state["selected"]
     items = state["filtered_items"]
@@ -205,7 +206,7 @@ function _file_selector_render(state)
             end_idx = n_items
         else
             start_idx = selected - half
-            end_idx = selected + half - 1
+            end_idx = start_idx + max_visible - 1
         end
     end
 
@@ -220,11 +221,16 @@ function _file_selector_render(state)
 
     content = join(lines, "\n")
 
+    # Preview the highlighted file (read by the editor once the selection settles)
+    paths = state["filtered_paths"]
+    preview = n_items == 0 ? ClearPreviewAction() : PreviewFileAction(paths[selected])
+
     return Dict(
         "result" => "consumed",
         "actions" => [
             ClearTextAction(),
-            InsertTextModeAction(content, "start")
+            InsertTextModeAction(content, "start"),
+            preview
         ]
     )
 end
@@ -190,6 +190,7 @@ function highlight_julia(code::String; offset::Int=0)
     end
 
     # Convert tokens to spans
+    char_offsets = _char_offsets(code)
     starts = Int[]
     stops = Int[]
     faces = String[]
@@ -207,14 +208,13 @@ function highlight_julia(code::String; offset::Int=0)
         # Get byte range from token - tokens store range as UnitRange
         # Token structure: Token(head, start:stop)
         tok_range = tok.range
+        isempty(tok_range) && continue  # e.g. the end-of-input marker
         start_byte = first(tok_range)
         end_byte = last(tok_range)
 
-        # Convert byte positions to character positions
-        # For now assume ASCII/simple encoding (byte == char position)
-        # Note: ranges are 1-indexed in Julia, we need 0-indexed
-        start_pos = start_byte - 1 + offset
-        end_pos = end_byte + offset  # end_byte is inclusive, our end is exclusive
+        # Convert byte positions to 0-indexed character positions
+        start_pos = char_offsets[start_byte] - 1 + offset
+        end_pos = char_offsets[end_byte] + offset  # end_byte is inclusive, our end is exclusive
 
         # Handle rainbow brackets
         if kind_str == "("
@@ -362,6 +362,7 @@ Internal function that does all JuliaSyntax processing in one place.
 Returns (starts, stops, faces) arrays.
 """
 function _tokenize_and_extract_spans(code::String)
+    char_offsets = _char_offsets(code)
     starts = Int[]
     stops = Int[]
     faces = String[]
@@ -391,12 +392,13 @@ function _tokenize_and_extract_spans(code::String)
 
         # Get byte range from token
         tok_range = tok.range
+        isempty(tok_range) && continue  # e.g. the end-of-input marker
         start_byte = Int(first(tok_range))
         end_byte = Int(last(tok_range))
 
         # Convert byte positions to character positions (0-indexed)
-        start_pos = start_byte - 1
-        end_pos = end_byte  # end_byte is inclusive, our end is exclusive
+        start_pos = char_offsets[start_byte] - 1
+        end_pos = char_offsets[end_byte]  # end_byte is inclusive, our end is exclusive
 
         # Handle rainbow brackets
         if kind == K.LParen
@@ -549,7 +551,6 @@ function _julia_mode_init()
 
     # Register mode-specific indent commands
     register_indent_command("julia-mode", "julia-indent-line")
-    register_newline_indent_command("julia-mode", "julia-newline-and-indent")
 
     # Apply initial highlighting
     highlight_julia_buffer()
@@ -773,29 +774,6 @@ function _register_julia_indent_commands()
         # Return IndentLineAction with 0-indexed line for Rust
         return IndentLineAction(ctx.current_line - 1, target_indent)
     end
-
-    define_command(
-        "julia-newline-and-indent",
-        "Insert newline and indent (Enter behavior)"
-    ) do ctx
-        code = buffer_content()
-        cursor = ctx.cursor_pos  # 0-indexed from Rust
-        line_num = ctx.current_line  # Already 1-indexed from Rust
-
-        # Simulate what the buffer will look like after newline insertion
-        # to calculate the correct indent for the new line
-        # Convert 0-indexed cursor to 1-indexed Julia string position
-        before = cursor > 0 ? code[1:cursor] : ""
-        after = cursor < length(code) ? code[cursor+1:end] : ""
-        new_code = before * "\n" * after
-        new_line_num = line_num + 1
-
-        target_indent = calculate_julia_indent(new_code, new_line_num)
-
-        # Insert newline + indentation as a single insert
-        indent_str = " " ^ target_indent
-        return InsertAction(cursor, "\n" * indent_str)
-    end
 end
 
 _register_julia_indent_commands()
@@ -804,5 +782,6 @@ _register_julia_indent_commands()
 define_major_mode("julia-mode",
     extensions = [".jl"],
     init = _julia_mode_init,
-    after_change = _julia_mode_after_change
+    after_change = _julia_mode_after_change,
+    indent_line = calculate_julia_indent
 )
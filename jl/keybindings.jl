@@ -40,6 +40,7 @@ Special actions available:
 - `:kill-word`, `:backward-kill-word`
 - `:yank`, `:mark-start`, `:cancel`
 - `:undo`, `:redo`, `:redraw`
+- `:insert <char>` - type that character (e.g., `":insert é"`)
 
 # Example
 ```julia
@@ -117,6 +118,80 @@ function get_keybinding(key_sequence::String)
     get(_keybindings, key_sequence, nothing)
 end
 
+"""
+    bind_key(key_sequence::String, action::String)
+
+Bind a key sequence in the running editor, taking effect from the next key
+typed. Unlike `define_key`, which is read when the config loads, this works
+from commands and hooks after startup too. Throws an `ArgumentError` if the key
+sequence or action is malformed.
+
+# Example
+```julia
+bind_key("C-c t", "toggle-truncate-lines")
+```
+"""
+function bind_key(key_sequence::String, action::String)
+    handle = _get_roe_handle()
+    err = ccall(Libdl.dlsym(handle, :roe_bind_key), Ptr{Cchar}, (Cstring, Cstring),
+                key_sequence, action)
+    _check_binding_error(handle, err)
+    _keybindings[key_sequence] = action
+    return nothing
+end
+
+"""
+    unbind_key(key_sequence::String)
+
+Remove a key sequence's binding in the running editor, taking effect from the
+next key typed. Throws an `ArgumentError` if the key sequence is malformed.
+"""
+function unbind_key(key_sequence::String)
+    handle = _get_roe_handle()
+    err = ccall(Libdl.dlsym(handle, :roe_unbind_key), Ptr{Cchar}, (Cstring,), key_sequence)
+    _check_binding_error(handle, err)
+    delete!(_keybindings, key_sequence)
+    return nothing
+end
+
+# Throw the error the editor gave for a binding, if it gave one
+function _check_binding_error(handle, err::Ptr{Cchar})
+    err == C_NULL && return nothing
+    message = unsafe_string(err)
+    ccall(Libdl.dlsym(handle, :roe_free_string), Cvoid, (Ptr{Cchar},), err)
+    throw(ArgumentError(message))
+end
+
+"""
+    define_digraph(keys::String, char::Char)
+
+Bind `C-x 8` followed by `keys` to insert `char`, like Emacs's `C-x 8` map of
+accented and special characters. `keys` is a key sequence as for `define_key`;
+a capital letter or shifted symbol matches however the keyboard reports Shift.
+
+# Example
+```julia
+define_digraph("- >", '→')
+define_digraph("' c", 'ć')
+```
+"""
+function define_digraph(keys::String, char::Char)
+    define_key("C-x 8 " * keys, ":insert " * char)
+    return nothing
+end
+
+"""
+    define_digraphs(digraphs::Pair{String, Char}...)
+
+Define several `C-x 8` digraphs at once.
+"""
+function define_digraphs(digraphs::Pair{String, Char}...)
+    for (keys, char) in digraphs
+        define_digraph(keys, char)
+    end
+    return nothing
+end
+
 # ============================================
 # Default Keybindings (Emacs-style)
 # ============================================
@@ -152,24 +227,53 @@ define_key("C-Right", ":cursor-word-forward")
 define_key("M-{", ":cursor-paragraph-backward")
 define_key("M-}", ":cursor-paragraph-forward")
 
+# Bracketed group (sexp) movement
+define_key("C-M-f", ":cursor-sexp-forward")
+define_key("C-M-b", ":cursor-sexp-backward")
+
 # Buffer start/end
 define_key("C-Home", ":cursor-buffer-start")
 define_key("C-End", ":cursor-buffer-end")
 define_key("M-<", ":cursor-buffer-start")
 define_key("M->", ":cursor-buffer-end")
 
+# Go to a line or character by number
+define_key("M-g g", "goto-line")
+define_key("M-g M-g", "goto-line")
+define_key("M-g c", "goto-char")
+
 # --- Basic text manipulation ---
 define_key("Backspace", ":backspace")
 define_key("Delete", ":delete")
 define_key("C-d", ":delete")
 define_key("Enter", ":enter")
 define_key("Tab", ":tab")
+define_key("C-q", "quoted-insert")
+define_key("C-u", ":universal-argument")
 
 # --- Kill/yank ---
 define_key("C-k", ":kill-line")
 define_key("C-w", ":kill-region")
 define_key("M-w", ":copy-region")
 define_key("C-y", ":yank")
+define_key("M-y", "yank-pop")
+
+# --- Registers ---
+define_key("C-x r s", "copy-to-register")
+define_key("C-x r i", "insert-register")
+define_key("C-x r SPC", "point-to-register")
+define_key("C-x r j", "jump-to-register")
+define_key("C-x r m", "bookmark-set")
+define_key("C-x r b", "bookmark-jump")
+define_key("C-x r l", "bookmark-list")
+
+# --- Rectangles ---
+define_key("C-x r k", "kill-rectangle")
+define_key("C-x r y", "yank-rectangle")
+
+# --- Narrowing ---
+define_key("C-x n n", "narrow-to-region")
+define_key("C-x n w", "widen")
 
 # Kill word
 define_key("M-d", ":kill-word")
@@ -184,7 +288,7 @@ define_key("C-g", ":cancel")
 define_key("Escape", ":escape")
 
 # --- Display ---
-define_key("C-l", ":redraw")
+define_key("C-l", ":recenter-top-bottom")
 
 # --- Undo/redo ---
 define_key("C-/", ":undo")
@@ -194,6 +298,11 @@ define_key("M-/", ":redo")
 # Terminal sends Ctrl+/ as Ctrl+7 (ASCII control code limitation)
 define_key("C-7", ":undo")
 
+# --- Keyboard macros ---
+define_key("C-x (", ":kmacro-start-macro")
+define_key("C-x )", ":kmacro-end-macro")
+define_key("C-x e", ":kmacro-end-and-call-macro")
+
 # --- Search ---
 define_key("C-s", "isearch-forward")
 define_key("C-r", "isearch-backward")
@@ -201,6 +310,8 @@ define_key("C-r", "isearch-backward")
 # --- Commands (C-x prefix) ---
 define_key("C-x C-c", "quit")
 define_key("C-x C-s", "save-buffer")
+define_key("C-x s", "save-all-buffers")
+define_key("C-x C-q", "toggle-read-only")
 define_key("C-x C-f", "find-file")
 define_key("C-x C-v", "visit-file")
 
@@ -210,10 +321,95 @@ define_key("C-x 3", "split-window-vertically")
 define_key("C-x o", "other-window")
 define_key("C-x 0", "delete-window")
 define_key("C-x 1", "delete-other-windows")
+define_key("C-x +", "balance-windows")
+define_key("C-x ^", "enlarge-window")
+define_key("C-x }", "enlarge-window-horizontally")
+define_key("C-x {", "shrink-window-horizontally")
+define_key("C-c Left", "winner-undo")
+define_key("C-c Right", "winner-redo")
 
 # Buffer management
 define_key("C-x b", "switch-to-buffer")
 define_key("C-x k", "kill-buffer")
+define_key("C-x Right", "next-buffer")
+define_key("C-x Left", "previous-buffer")
+define_key("C-S-p", "command-palette")
+
+# --- Special characters (C-x 8 prefix) ---
+define_key("C-x 8 Enter", "insert-char")
+
+# Accents: C-x 8, then the accent, then the letter
+for (accent, letters) in [
+    "'" => "aeiouyAEIOUY" => "áéíóúýÁÉÍÓÚÝ",
+    "`" => "aeiouAEIOU" => "àèìòùÀÈÌÒÙ",
+    "^" => "aeiouAEIOU" => "âêîôûÂÊÎÔÛ",
+    "\"" => "aeiouyAEIOU" => "äëïöüÿÄËÏÖÜ",
+    "~" => "anoANO" => "ãñõÃÑÕ",
+    "," => "cC" => "çÇ",
+]
+    for (letter, accented) in zip(letters.first, letters.second)
+        define_digraph("$accent $letter", accented)
+    end
+end
+
+define_digraphs(
+    "/ /" => '÷',
+    "/ o" => 'ø',
+    "/ O" => 'Ø',
+    "/ a" => 'å',
+    "/ A" => 'Å',
+    "\" s" => 'ß',
+    "x" => '×',
+    "o" => '°',
+    "u" => 'µ',
+    "!" => '¡',
+    "?" => '¿',
+    "<" => '«',
+    ">" => '»',
+    "C" => '©',
+    "R" => '®',
+    "P" => '¶',
+    "S" => '§',
+    "L" => '£',
+    "Y" => '¥',
+    "* E" => '€',
+    "c" => '¢',
+    "." => '·',
+    "+" => '±',
+    "1 / 2" => '½',
+    "1 / 4" => '¼',
+    "3 / 4" => '¾',
+    "_ n" => '–',
+    "_ m" => '—',
+)
+
+# --- Help ---
+define_key("C-h e", "view-julia-errors")
+
+# --- Comments ---
+define_key("M-;", "comment-region")
+
+# --- Lines ---
+define_key("M-^", "join-line")
+define_key("C-c d", "duplicate-line")
+define_key("M-=", "count-words")
+
+# --- Case ---
+define_key("M-u", "upcase-word")
+define_key("M-l", "downcase-word")
+define_key("M-c", "capitalize-word")
+define_key("C-x C-u", "upcase-region")
+define_key("C-x C-l", "downcase-region")
+
+# --- Filling ---
+define_key("M-q", "fill-paragraph")
+define_key("C-x f", "set-fill-column")
+
+# --- Merge conflicts (smerge-style C-c ^ prefix) ---
+define_key("C-c ^ n", "next-conflict")
+define_key("C-c ^ p", "previous-conflict")
+define_key("C-c ^ u", "conflict-keep-ours")
+define_key("C-c ^ l", "conflict-keep-theirs")
 
 # --- M-x command mode ---
 define_key("M-x", "command-mode")
@@ -15,9 +15,18 @@ Use `mode_properties()` to create with defaults, then override as needed.
 """
 Base.@kwdef struct ModeProperties
     show_gutter::Bool = true
+    # Word count and reading time in the modeline, for prose
+    word_count::Bool = false
+    # Line comment prefix used by comment-region and uncomment-region
+    comment_syntax::String = "#"
+    # Columns between tab stops, and whether indentation uses tabs; nothing
+    # means roe_config["editor"]["tab_width"] / ["indent_tabs"], or 4 / false
+    tab_width::Union{Int, Nothing} = nothing
+    indent_tabs::Union{Bool, Nothing} = nothing
+    # Built-in tree-sitter grammar to highlight with ("rust", "json" or
+    # "toml"), for modes without an after_change highlighter of their own
+    tree_sitter::Union{String, Nothing} = nothing
     # Add more properties here as needed:
-    # indent_width::Int = 4
-    # use_tabs::Bool = false
     # word_wrap::Bool = false
     # etc.
 end
@@ -31,6 +40,10 @@ All properties have sensible defaults.
 # Example
 ```julia
 mode_properties(show_gutter = false)
+mode_properties(word_count = true)  # prose modes
+mode_properties(comment_syntax = "//")
+mode_properties(tab_width = 8, indent_tabs = true)  # e.g. for Makefiles
+mode_properties(tree_sitter = "toml")
 ```
 """
 mode_properties(; kwargs...) = ModeProperties(; kwargs...)
@@ -45,6 +58,7 @@ struct MajorModeDefinition
     extensions::Vector{String}
     init::Union{Function, Nothing}
     after_change::Union{Function, Nothing}
+    indent_line::Union{Function, Nothing}
     properties::ModeProperties
     # Future hooks can be added here:
     # before_save, after_save, on_enter, on_exit, etc.
@@ -60,7 +74,7 @@ const _extension_to_mode = Dict{String, String}()
 const _default_mode = Ref{String}("fundamental-mode")
 
 """
-    define_major_mode(name::String; extensions=String[], init=nothing, after_change=nothing, properties=ModeProperties())
+    define_major_mode(name::String; extensions=String[], init=nothing, after_change=nothing, indent_line=nothing, properties=ModeProperties())
 
 Define a major mode with the given name and configuration.
 
@@ -71,6 +85,11 @@ Define a major mode with the given name and configuration.
           Called with no arguments, should set up faces and initial highlighting.
 - `after_change`: Function called after buffer content changes.
                   Called with (start::Int, old_end::Int, new_end::Int) for incremental updates.
+- `indent_line`: Function deciding how a new line starts when Enter is pressed.
+                 Called with (code::String, line::Int), the buffer text with the newline
+                 already in and the 1-based new line. Returns a width in spaces, or the
+                 text itself (like a list marker to continue). Without one, the new line
+                 copies the indentation of the line above.
 - `properties`: Mode properties bundle (use `mode_properties()` to create)
 
 # Example
@@ -93,6 +112,7 @@ function define_major_mode(name::String;
                            extensions::Vector{String}=String[],
                            init::Union{Function, Nothing}=nothing,
                            after_change::Union{Function, Nothing}=nothing,
+                           indent_line::Union{Function, Nothing}=nothing,
                            properties::ModeProperties=ModeProperties())
 
     # Normalize extensions (ensure they start with .)
@@ -106,7 +126,7 @@ function define_major_mode(name::String;
     end
 
     # Create mode definition
-    mode_def = MajorModeDefinition(name, normalized_extensions, init, after_change, properties)
+    mode_def = MajorModeDefinition(name, normalized_extensions, init, after_change, indent_line, properties)
     _major_modes[name] = mode_def
 
     # Register extension mappings
@@ -134,6 +154,16 @@ function get_major_mode_for_file(filepath::String)
     return _default_mode[]
 end
 
+# roe_config["editor"][key], or `default` if the config doesn't set it
+function _editor_config(key::String, default)
+    isdefined(Main, :roe_config) || return default
+    config = getfield(Main, :roe_config)
+    config isa AbstractDict || return default
+    editor = get(config, "editor", nothing)
+    editor isa AbstractDict || return default
+    return get(editor, key, default)
+end
+
 """
     call_major_mode_init(mode_name::String) -> Bool
 
@@ -148,8 +178,13 @@ function call_major_mode_init(mode_name::String)
 
     mode_def = _major_modes[mode_name]
 
-    # Set gutter visibility based on mode configuration
+    # Set gutter visibility, modeline word count, tabs and highlighting based
+    # on mode configuration
     buffer_set_show_gutter!(mode_def.properties.show_gutter)
+    buffer_set_show_word_count!(mode_def.properties.word_count)
+    buffer_set_tab_width!(something(mode_def.properties.tab_width, _editor_config("tab_width", 4)))
+    buffer_set_indent_tabs!(something(mode_def.properties.indent_tabs, _editor_config("indent_tabs", false)))
+    buffer_set_tree_sitter!(something(mode_def.properties.tree_sitter, ""))
 
     if mode_def.init === nothing
         return true  # No init hook, but mode exists
@@ -159,11 +194,50 @@ function call_major_mode_init(mode_name::String)
         mode_def.init()
         return true
     catch e
-        @error "Error in major mode init hook" mode_name exception=(e, catch_backtrace())
+        report_error("Error in $mode_name init hook", e)
         return false
     end
 end
 
+"""
+    major_mode_comment_syntax(mode_name::String) -> String
+
+Get the line comment prefix for the given major mode, used by comment-region
+and uncomment-region. Returns "#" if the mode is not registered.
+"""
+function major_mode_comment_syntax(mode_name::String)
+    if !haskey(_major_modes, mode_name)
+        return "#"
+    end
+    return _major_modes[mode_name].properties.comment_syntax
+end
+
+"""
+    major_mode_indent_line(mode_name::String, code::String, line::Int) -> Union{String, Nothing}
+
+Call the indent_line hook for the given major mode, giving the text to start
+`line` (1-based) of `code` with. Returns nothing if the mode has no hook or it
+fails, leaving the new line to copy the indentation of the one above.
+"""
+function major_mode_indent_line(mode_name::String, code::String, line::Int)
+    if !haskey(_major_modes, mode_name)
+        return nothing
+    end
+
+    mode_def = _major_modes[mode_name]
+    if mode_def.indent_line === nothing
+        return nothing
+    end
+
+    try
+        indent = mode_def.indent_line(code, line)
+        return indent isa Integer ? " " ^ max(indent, 0) : String(indent)
+    catch e
+        report_error("Error in $mode_name indent-line hook", e)
+        return nothing
+    end
+end
+
 """
     call_major_mode_after_change(mode_name::String, start::Int, old_end::Int, new_end::Int) -> Bool
 
@@ -192,7 +266,7 @@ function call_major_mode_after_change(mode_name::String, start::Int, old_end::In
         mode_def.after_change(start, old_end, new_end)
         return true
     catch e
-        @error "Error in major mode after_change hook" mode_name exception=(e, catch_backtrace())
+        report_error("Error in $mode_name after-change hook", e)
         return false
     end
 end
@@ -243,3 +317,20 @@ end
 # fundamental-mode: The default mode with no special behavior.
 # Used for files with unrecognized extensions.
 define_major_mode("fundamental-mode")
+
+# scratch-mode: The *scratch* buffer for notes that aren't saved to a file.
+# Has no extensions, so it's never chosen for a file.
+define_major_mode("scratch-mode", properties = mode_properties(show_gutter = false))
+
+# text-mode: Plain prose, with word count and reading time in the modeline.
+define_major_mode("text-mode",
+    extensions = [".txt", ".text"],
+    properties = mode_properties(word_count = true))
+
+# json-mode and toml-mode: highlighted by roe's built-in tree-sitter grammars
+define_major_mode("json-mode",
+    extensions = [".json"],
+    properties = mode_properties(comment_syntax = "//", tree_sitter = "json"))
+define_major_mode("toml-mode",
+    extensions = [".toml"],
+    properties = mode_properties(tree_sitter = "toml"))
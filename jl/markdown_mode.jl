@@ -57,6 +57,10 @@ end
 Parse markdown and extract all highlight spans using regex.
 """
 function _parse_and_highlight(code::String)
+    # Matches are at byte offsets, spans are in characters
+    char_offsets = _char_offsets(code)
+    char_start(m) = char_offsets[m.offset] - 1
+
     starts = Int[]
     stops = Int[]
     faces = String[]
@@ -64,7 +68,7 @@ function _parse_and_highlight(code::String)
     # First, find all fenced code block ranges to exclude from other highlighting
     fenced_ranges = Tuple{Int, Int}[]
     for m in eachmatch(r"^(`{3,}|~{3,})(\w*)\n(.*?)\n\1"ms, code)
-        push!(fenced_ranges, (m.offset - 1, m.offset - 1 + length(m.match)))
+        push!(fenced_ranges, (char_start(m), char_start(m) + length(m.match)))
     end
 
     # Helper to check if a position is inside a fenced code block
@@ -79,82 +83,82 @@ function _parse_and_highlight(code::String)
 
     # Headers (include the # markers and full line)
     for m in eachmatch(r"^(#{1,6})\s+(.*)$"m, code)
-        if !in_fenced_block(m.offset - 1)
+        if !in_fenced_block(char_start(m))
             level = length(m.captures[1])
             face = level <= 6 ? "md-header-$level" : "md-header-6"
-            push!(starts, m.offset - 1)
-            push!(stops, m.offset - 1 + length(m.match))
+            push!(starts, char_start(m))
+            push!(stops, char_start(m) + length(m.match))
             push!(faces, face)
         end
     end
 
     # Bold (**text** or __text__)
     for m in eachmatch(r"\*\*[^*]+\*\*|__[^_]+__", code)
-        if !in_fenced_block(m.offset - 1)
-            push!(starts, m.offset - 1)
-            push!(stops, m.offset - 1 + length(m.match))
+        if !in_fenced_block(char_start(m))
+            push!(starts, char_start(m))
+            push!(stops, char_start(m) + length(m.match))
             push!(faces, "md-bold")
         end
     end
 
     # Italic (*text* or _text_)
     for m in eachmatch(r"(?<!\*)\*(?!\*)[^*]+(?<!\*)\*(?!\*)|(?<!_)_(?!_)[^_]+(?<!_)_(?!_)", code)
-        if !in_fenced_block(m.offset - 1)
-            push!(starts, m.offset - 1)
-            push!(stops, m.offset - 1 + length(m.match))
+        if !in_fenced_block(char_start(m))
+            push!(starts, char_start(m))
+            push!(stops, char_start(m) + length(m.match))
             push!(faces, "md-italic")
         end
     end
 
     # Inline code - double backticks (``code``)
     for m in eachmatch(r"``[^`]+``", code)
-        if !in_fenced_block(m.offset - 1)
-            push!(starts, m.offset - 1)
-            push!(stops, m.offset - 1 + length(m.match))
+        if !in_fenced_block(char_start(m))
+            push!(starts, char_start(m))
+            push!(stops, char_start(m) + length(m.match))
             push!(faces, "md-code")
         end
     end
 
     # Inline code - single backticks (`code`)
     for m in eachmatch(r"(?<!`)`(?!`)[^`\n]+`(?!`)", code)
-        if !in_fenced_block(m.offset - 1)
-            push!(starts, m.offset - 1)
-            push!(stops, m.offset - 1 + length(m.match))
+        if !in_fenced_block(char_start(m))
+            push!(starts, char_start(m))
+            push!(stops, char_start(m) + length(m.match))
             push!(faces, "md-code")
         end
     end
 
     # Links [text](url)
     for m in eachmatch(r"\[([^\]]+)\]\(([^)]+)\)", code)
-        if !in_fenced_block(m.offset - 1)
-            push!(starts, m.offset - 1)
-            push!(stops, m.offset - 1 + length(m.match))
+        if !in_fenced_block(char_start(m))
+            push!(starts, char_start(m))
+            push!(stops, char_start(m) + length(m.match))
             push!(faces, "md-link-text")
         end
     end
 
     # Images ![alt](url)
     for m in eachmatch(r"!\[([^\]]*)\]\(([^)]+)\)", code)
-        if !in_fenced_block(m.offset - 1)
-            push!(starts, m.offset - 1)
-            push!(stops, m.offset - 1 + length(m.match))
+        if !in_fenced_block(char_start(m))
+            push!(starts, char_start(m))
+            push!(stops, char_start(m) + length(m.match))
             push!(faces, "md-image")
         end
     end
 
     # Blockquote markers (>) at start of lines
     for m in eachmatch(r"^(>+)"m, code)
-        if !in_fenced_block(m.offset - 1)
-            push!(starts, m.offset - 1)
-            push!(stops, m.offset - 1 + length(m.match))
+        if !in_fenced_block(char_start(m))
+            push!(starts, char_start(m))
+            push!(stops, char_start(m) + length(m.match))
             push!(faces, "md-blockquote-marker")
         end
     end
 
     # List markers at start of lines
     for m in eachmatch(r"^(\s*)([-*+]|\d+[.)])\s"m, code)
-        if !in_fenced_block(m.offset - 1)
-            marker_start = m.offset - 1 + length(m.captures[1])
+        if !in_fenced_block(char_start(m))
+            marker_start = char_start(m) + length(m.captures[1])
             marker_end = marker_start + length(m.captures[2])
             push!(starts, marker_start)
             push!(stops, marker_end)
@@ -164,16 +168,16 @@ function _parse_and_highlight(code::String)
 
     # Horizontal rules (---, ***, ___)
     for m in eachmatch(r"^([-*_])\1{2,}\s*$"m, code)
-        if !in_fenced_block(m.offset - 1)
-            push!(starts, m.offset - 1)
-            push!(stops, m.offset - 1 + length(m.match))
+        if !in_fenced_block(char_start(m))
+            push!(starts, char_start(m))
+            push!(stops, char_start(m) + length(m.match))
             push!(faces, "md-hr")
         end
     end
 
     # Fenced code blocks
     for m in eachmatch(r"^(`{3,}|~{3,})(\w*)\n(.*?)\n\1"ms, code)
-        block_start = m.offset - 1
+        block_start = char_start(m)
         fence_chars = m.captures[1]
         lang = m.captures[2]
         content = m.captures[3]
@@ -324,6 +328,62 @@ function calculate_markdown_indent(code::String, line_num::Int)
     return 0
 end
 
+"""
+    markdown_line_continuation(line::String) -> Union{String, Nothing}
+
+What a line broken after `line` starts with to carry on its list or blockquote:
+the next list marker, or the `> ` markers. An empty string for an empty list
+item, which ends the list. Nothing if `line` is neither.
+"""
+function markdown_line_continuation(line::String)
+    # Check if the line is an empty list item
+    list_info = _get_list_info(line)
+    if list_info !== nothing
+        trimmed = strip(line)
+        if match(r"^[-*+]$|^\d+[.)]$", trimmed) !== nothing
+            return ""
+        end
+
+        # Continue the list
+        m = match(r"^(\s*)([-*+]|\d+)([.)])\s", line)
+        if m !== nothing
+            prefix_spaces = m.captures[1]
+            marker = m.captures[2]
+            suffix = m.captures[3]
+
+            if marker in ["-", "*", "+"]
+                return "$(prefix_spaces)$(marker)$(suffix) "
+            else
+                num = parse(Int, marker) + 1
+                return "$(prefix_spaces)$(num)$(suffix) "
+            end
+        end
+    end
+
+    # Check for blockquote continuation
+    bq_count = _count_blockquote_markers(line)
+    if bq_count > 0
+        return repeat("> ", bq_count)
+    end
+
+    return nothing
+end
+
+"""
+    markdown_newline_prefix(code::String, line_num::Int) -> String
+
+indent_line hook for markdown-mode: continue the list or blockquote of the line
+above the new line `line_num`, or indent it as `calculate_markdown_indent` does.
+"""
+function markdown_newline_prefix(code::String, line_num::Int)
+    lines = split(code, '\n', keepempty=true)
+    if line_num >= 2 && line_num <= length(lines)
+        continuation = markdown_line_continuation(String(lines[line_num - 1]))
+        continuation !== nothing && return continuation
+    end
+    return calculate_markdown_indent(code, line_num)
+end
+
 # ============================================
 # Mode registration
 # ============================================
@@ -334,7 +394,6 @@ function _markdown_mode_init()
     end
 
     register_indent_command("markdown-mode", "markdown-indent-line")
-    register_newline_indent_command("markdown-mode", "markdown-newline-and-indent")
 
     highlight_markdown_buffer()
 end
@@ -354,55 +413,6 @@ function _register_markdown_commands()
         return IndentLineAction(ctx.current_line - 1, target_indent)
     end
 
-    define_command(
-        "markdown-newline-and-indent",
-        "Insert newline and indent in markdown"
-    ) do ctx
-        code = buffer_content()
-        cursor = ctx.cursor_pos
-        line_num = ctx.current_line
-
-        lines = split(code, '\n', keepempty=true)
-        if line_num >= 1 && line_num <= length(lines)
-            current_line = String(lines[line_num])
-
-            # Check if current line is an empty list item
-            list_info = _get_list_info(current_line)
-            if list_info !== nothing
-                trimmed = strip(current_line)
-                if match(r"^[-*+]$|^\d+[.)]$", trimmed) !== nothing
-                    return InsertAction(cursor, "\n")
-                end
-
-                # Continue the list
-                m = match(r"^(\s*)([-*+]|\d+)([.)])\s", current_line)
-                if m !== nothing
-                    prefix_spaces = m.captures[1]
-                    marker = m.captures[2]
-                    suffix = m.captures[3]
-
-                    if marker in ["-", "*", "+"]
-                        new_marker = "$(prefix_spaces)$(marker)$(suffix) "
-                    else
-                        num = parse(Int, marker) + 1
-                        new_marker = "$(prefix_spaces)$(num)$(suffix) "
-                    end
-
-                    return InsertAction(cursor, "\n" * new_marker)
-                end
-            end
-
-            # Check for blockquote continuation
-            bq_count = _count_blockquote_markers(current_line)
-            if bq_count > 0
-                prefix = repeat("> ", bq_count)
-                return InsertAction(cursor, "\n" * prefix)
-            end
-        end
-
-        return InsertAction(cursor, "\n")
-    end
-
     define_command(
         "highlight-markdown",
         "Apply markdown syntax highlighting to the current buffer"
@@ -421,6 +431,8 @@ _register_markdown_commands()
 # Register the major mode
 define_major_mode("markdown-mode",
     extensions = [".md", ".markdown", ".mkd", ".mdown"],
+    properties = mode_properties(word_count = true),
     init = _markdown_mode_init,
-    after_change = _markdown_mode_after_change
+    after_change = _markdown_mode_after_change,
+    indent_line = markdown_newline_prefix
 )
@@ -40,6 +40,21 @@ struct KillBufferAction
     buffer_index::Int  # 0-based index into the buffer list
 end
 
+struct CreateBufferAction
+    buffer_name::String
+end
+
+# Preview of the highlighted menu entry, drawn beside the list
+struct PreviewBufferAction
+    buffer_index::Int  # 0-based index into the buffer list
+end
+
+struct PreviewFileAction
+    path::String
+end
+
+struct ClearPreviewAction end
+
 # Convert mode actions to dicts for Rust
 function mode_action_to_dict(a::ClearTextAction)
     Dict("type" => "clear_text")
@@ -86,6 +101,22 @@ function mode_action_to_dict(a::KillBufferAction)
     Dict("type" => "kill_buffer", "buffer_index" => a.buffer_index)
 end
 
+function mode_action_to_dict(a::CreateBufferAction)
+    Dict("type" => "create_buffer", "buffer_name" => a.buffer_name)
+end
+
+function mode_action_to_dict(a::PreviewBufferAction)
+    Dict("type" => "preview_buffer", "buffer_index" => a.buffer_index)
+end
+
+function mode_action_to_dict(a::PreviewFileAction)
+    Dict("type" => "preview_file", "path" => a.path)
+end
+
+function mode_action_to_dict(::ClearPreviewAction)
+    Dict("type" => "clear_preview")
+end
+
 """
     define_mode(name::String, handler::Function)
 
@@ -136,7 +167,7 @@ function mode_perform(mode_name::String, action::Dict)
 
         return result
     catch e
-        @error "Mode error" mode_name exception=(e, catch_backtrace())
+        report_error("Error in mode $mode_name", e)
         return Dict("result" => "ignored")
     end
 end
@@ -161,3 +192,26 @@ function reset_mode_state(name::String)
         _modes[name] = (handler, Dict{String, Any}())
     end
 end
+
+# Helper for selection-list modes (file selector, buffer switcher): move
+# state["selected"] for a cursor key. Up/down wrap around, page up/down move by
+# state["max_visible"] rows, and Home/End (or M-</M->) jump to the ends.
+function _menu_move_selection!(state, n_items::Int, dir::AbstractString)
+    n_items == 0 && return
+    page = get(state, "max_visible", 4)
+    selected = state["selected"]
+    if dir == "up"
+        selected = selected > 1 ? selected - 1 : n_items
+    elseif dir == "down"
+        selected = selected < n_items ? selected + 1 : 1
+    elseif dir == "page_up"
+        selected = max(1, selected - page)
+    elseif dir == "page_down"
+        selected = min(n_items, selected + page)
+    elseif dir == "line_start" || dir == "buffer_start"
+        selected = 1
+    elseif dir == "line_end" || dir == "buffer_end"
+        selected = n_items
+    end
+    state["selected"] = selected
+end
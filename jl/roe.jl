@@ -18,15 +18,19 @@ end
 module Roe
 
 export define_command, call_command, CommandContext, define_key, define_keys, undefine_key,
+       bind_key, unbind_key, define_digraph, define_digraphs,
        # Action types
        EchoAction, NoAction, InsertAction, DeleteAction, ReplaceAction,
        SetCursorAction, SetMarkAction, ClearMarkAction, SetContentAction, IndentLineAction,
        ExecuteCommandAction,
        # Buffer access functions
        buffer_content, buffer_line, buffer_line_count, buffer_char_count,
-       buffer_substring, buffer_insert!, buffer_delete!, buffer_major_mode,
+       buffer_substring, buffer_insert!, buffer_delete!, buffer_replace!, buffer_major_mode,
+       buffer_get_local, buffer_set_local!, buffer_set_tree_sitter!,
+       # Error reporting
+       report_error,
        # Indentation registration
-       register_indent_command, register_newline_indent_command,
+       register_indent_command,
        # Minor mode API (key handlers)
        define_mode, mode_perform, has_mode, reset_mode_state,
        ClearTextAction, InsertTextModeAction, OpenFileAction, ExecuteCommandAction,
@@ -34,10 +38,12 @@ export define_command, call_command, CommandContext, define_key, define_keys, un
        SwitchBufferAction, KillBufferAction,
        # Major mode API (file type associations)
        define_major_mode, get_major_mode_for_file, call_major_mode_init,
-       call_major_mode_after_change, has_major_mode, list_major_modes,
+       call_major_mode_after_change, major_mode_comment_syntax, major_mode_indent_line,
+       has_major_mode, list_major_modes,
        get_major_mode_extensions, set_default_major_mode,
        # Syntax highlighting API
-       define_face, face_exists, add_span, add_spans, clear_spans,
+       define_face, register_face, register_faces, apply_config_faces,
+       face_exists, add_span, add_spans, clear_spans,
        clear_spans_in_range, has_spans, define_standard_faces,
        Span, highlight_matches, apply_spans,
        # Julia syntax highlighting
@@ -54,6 +60,7 @@ const _module_dir = @__DIR__
 
 # Include sub-modules in dependency order
 include(joinpath(_module_dir, "buffer_api.jl"))
+include(joinpath(_module_dir, "errors.jl"))
 include(joinpath(_module_dir, "syntax.jl"))
 include(joinpath(_module_dir, "commands.jl"))
 include(joinpath(_module_dir, "keybindings.jl"))
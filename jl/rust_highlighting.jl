@@ -395,6 +395,7 @@ function highlight_rust(code::String; offset::Int = 0)
     end
 
     # Define arrays to collect results
+    char_offsets = _char_offsets(code)
     starts = Int[]
     stops = Int[]
     faces = String[]
@@ -410,9 +411,12 @@ function highlight_rust(code::String; offset::Int = 0)
 
         if face !== nothing
             br = Base.invokelatest(TS.byte_range, node)
-            # byte_range returns 1-indexed inclusive, convert to 0-indexed exclusive
-            push!(starts, br[1] - 1 + offset)
-            push!(stops, br[2] + offset)  # inclusive->exclusive cancels 1-indexed->0-indexed
+            # Zero-width nodes, like missing ones, have nothing to highlight
+            br[2] < br[1] && return
+            # byte_range returns 1-indexed inclusive bytes, convert to 0-indexed
+            # exclusive characters
+            push!(starts, char_offsets[br[1]] - 1 + offset)
+            push!(stops, char_offsets[br[2]] + offset)
             push!(faces, face)
         end
     end
@@ -701,27 +705,6 @@ function _register_rust_indent_commands()
         # Return IndentLineAction with 0-indexed line for Rust
         return IndentLineAction(ctx.current_line - 1, target_indent)
     end
-
-    define_command(
-        "rust-newline-and-indent",
-        "Insert newline and indent (Enter behavior)"
-    ) do ctx
-        code = buffer_content()
-        cursor = ctx.cursor_pos  # 0-indexed from Rust
-        line_num = ctx.current_line  # Already 1-indexed from Rust
-
-        # Simulate what the buffer will look like after newline insertion
-        before = cursor > 0 ? code[1:cursor] : ""
-        after = cursor < length(code) ? code[cursor+1:end] : ""
-        new_code = before * "\n" * after
-        new_line_num = line_num + 1
-
-        target_indent = calculate_rust_indent(new_code, new_line_num)
-
-        # Insert newline + indentation as a single insert
-        indent_str = " " ^ target_indent
-        return InsertAction(cursor, "\n" * indent_str)
-    end
 end
 
 _register_rust_indent_commands()
@@ -744,7 +727,6 @@ function _rust_mode_init()
 
     # Register mode-specific indent commands
     register_indent_command("rust-mode", "rust-indent-line")
-    register_newline_indent_command("rust-mode", "rust-newline-and-indent")
 
     # Apply initial highlighting
     highlight_rust_buffer()
@@ -764,4 +746,6 @@ function _rust_mode_after_change(start::Int, old_end::Int, new_end::Int)
 end
 
 # Register rust-mode as a major mode
-define_major_mode("rust-mode", extensions = [".rs"], init = _rust_mode_init, after_change = _rust_mode_after_change)
+define_major_mode("rust-mode", extensions = [".rs"], init = _rust_mode_init, after_change = _rust_mode_after_change,
+                  indent_line = calculate_rust_indent,
+                  properties = mode_properties(comment_syntax = "//"))
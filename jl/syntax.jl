@@ -13,11 +13,112 @@ using Libdl
 # Reuse the handle from buffer_api.jl
 # (included in same module, so _get_roe_handle is available)
 
+# Faces registered through register_face (typically from the user's config).
+# Mode-supplied defaults via define_face never override these.
+const _registered_faces = Set{String}()
+
+function _ffi_register_face(name::String,
+                            foreground::Union{String,Nothing},
+                            background::Union{String,Nothing},
+                            bold::Bool, italic::Bool, underline::Bool)
+    handle = _get_roe_handle()
+
+    fg_ptr = foreground === nothing ? C_NULL : pointer(foreground)
+    bg_ptr = background === nothing ? C_NULL : pointer(background)
+
+    result = ccall(
+        Libdl.dlsym(handle, :roe_register_face),
+        Clonglong,
+        (Cstring, Cstring, Cstring, Cuchar, Cuchar, Cuchar),
+        name, fg_ptr, bg_ptr,
+        bold ? 1 : 0,
+        italic ? 1 : 0,
+        underline ? 1 : 0
+    )
+
+    return result == 1
+end
+
+"""
+    register_face(name::String; foreground=nothing, background=nothing,
+                  bold=false, italic=false, underline=false) -> Bool
+
+Register a named face with full attributes. Intended for use from `.roe.jl`:
+faces registered this way take precedence over the defaults that major modes
+install with `define_face`, so a config can restyle e.g. `"julia-keyword"`
+before the mode is ever activated.
+
+# Example
+```julia
+using Roe
+register_face("julia-keyword", foreground="#ff79c6", bold=true, underline=true)
+register_face("comment", foreground="#6272a4", italic=true)
+```
+"""
+function register_face(name::String;
+                       foreground::Union{String,Nothing}=nothing,
+                       background::Union{String,Nothing}=nothing,
+                       bold::Bool=false,
+                       italic::Bool=false,
+                       underline::Bool=false)
+    ok = _ffi_register_face(name, foreground, background, bold, italic, underline)
+    ok && push!(_registered_faces, name)
+    return ok
+end
+
+"""
+    register_faces(faces::AbstractDict) -> Int
+
+Register several faces from a Dict of face name => attribute Dict, as found in
+the `"faces"` section of `roe_config`. Returns the number of faces registered.
+
+# Example
+```julia
+register_faces(Dict(
+    "keyword" => Dict("foreground" => "#ff79c6", "bold" => true),
+    "string"  => Dict("foreground" => "#f1fa8c"),
+))
+```
+"""
+function register_faces(faces::AbstractDict)
+    count = 0
+    for (name, attrs) in faces
+        attrs isa AbstractDict || continue
+        fg = get(attrs, "foreground", nothing)
+        bg = get(attrs, "background", nothing)
+        ok = register_face(String(name);
+                           foreground=fg === nothing ? nothing : String(fg),
+                           background=bg === nothing ? nothing : String(bg),
+                           bold=get(attrs, "bold", false) == true,
+                           italic=get(attrs, "italic", false) == true,
+                           underline=get(attrs, "underline", false) == true)
+        ok && (count += 1)
+    end
+    return count
+end
+
+"""
+    apply_config_faces() -> Int
+
+Register any faces listed under `roe_config["faces"]` in `Main`. Called by the
+editor after the user config has been loaded.
+"""
+function apply_config_faces()
+    isdefined(Main, :roe_config) || return 0
+    config = getfield(Main, :roe_config)
+    config isa AbstractDict || return 0
+    faces = get(config, "faces", nothing)
+    faces isa AbstractDict || return 0
+    return register_faces(faces)
+end
+
 """
     define_face(name::String; foreground=nothing, background=nothing,
                 bold=false, italic=false, underline=false) -> Bool
 
-Define a named face (style) for syntax highlighting.
+Define a named face (style) for syntax highlighting. This is what major modes
+use to install their default styles; if the face was already set up with
+`register_face` the call is a no-op so user customizations win.
 
 # Arguments
 - `name`: Unique name for this face (e.g., "keyword", "string", "comment")
@@ -43,22 +144,8 @@ function define_face(name::String;
                      bold::Bool=false,
                      italic::Bool=false,
                      underline::Bool=false)
-    handle = _get_roe_handle()
-
-    fg_ptr = foreground === nothing ? C_NULL : pointer(foreground)
-    bg_ptr = background === nothing ? C_NULL : pointer(background)
-
-    result = ccall(
-        Libdl.dlsym(handle, :roe_define_face),
-        Clonglong,
-        (Cstring, Cstring, Cstring, Cuchar, Cuchar, Cuchar),
-        name, fg_ptr, bg_ptr,
-        bold ? 1 : 0,
-        italic ? 1 : 0,
-        underline ? 1 : 0
-    )
-
-    return result == 1
+    name in _registered_faces && return true
+    return _ffi_register_face(name, foreground, background, bold, italic, underline)
 end
 
 """
@@ -114,6 +201,17 @@ function add_span(start::Int, stop::Int, face::String)
     return result == 1
 end
 
+"""
+    _char_offsets(code::String) -> Vector{Int}
+
+For each byte index of `code`, the number of characters up to and including
+it. Parsers report byte ranges and spans are in characters: the token at bytes
+`a:b` is the span from `offsets[a] - 1` to `offsets[b]`.
+"""
+function _char_offsets(code::String)
+    return cumsum(Int[isvalid(code, i) for i in 1:ncodeunits(code)])
+end
+
 """
     add_spans(starts::Vector{Int}, stops::Vector{Int}, faces::Vector{String}) -> Int
 
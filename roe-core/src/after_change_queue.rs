@@ -0,0 +1,292 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Coalescing work queue for major mode after-change hooks
+//!
+//! Renderers used to lock the shared Julia runtime and wait for every
+//! `after_change` hook to finish before drawing the next frame. Instead, edits
+//! are pushed onto this queue and the Julia thread drains it between other
+//! commands. While a buffer's change is still waiting to run, further edits to
+//! the same buffer are merged into a single range, so a burst of typing costs
+//! one re-highlight rather than one per keystroke.
+
+use crate::buffer::Buffer;
+use crate::julia_runtime::JuliaCommand;
+use crate::BufferId;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, Notify};
+
+/// A changed region of a buffer, in the coordinates used by `ChromeAction::BufferChanged`:
+/// `start..old_end` of the old text was replaced by `start..new_end` of the new text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChangeRange {
+    pub start: usize,
+    pub old_end: usize,
+    pub new_end: usize,
+}
+
+impl ChangeRange {
+    pub fn new(start: usize, old_end: usize, new_end: usize) -> Self {
+        Self {
+            start,
+            old_end,
+            new_end,
+        }
+    }
+
+    /// Combine this change with `next`, which was applied after it, into one range
+    /// relative to the text as it was before this change.
+    pub fn merge(self, next: ChangeRange) -> ChangeRange {
+        // Map next's old end back to pre-`self` coordinates
+        let next_old_end = if next.old_end >= self.new_end {
+            next.old_end - self.new_end + self.old_end
+        } else {
+            self.old_end
+        };
+        // Map our new end forward through `next`
+        let self_new_end = if self.new_end >= next.old_end {
+            self.new_end - next.old_end + next.new_end
+        } else {
+            next.new_end
+        };
+
+        ChangeRange {
+            start: self.start.min(next.start),
+            old_end: self.old_end.max(next_old_end),
+            new_end: next.new_end.max(self_new_end),
+        }
+    }
+}
+
+/// An after-change hook call waiting for the Julia thread
+#[derive(Debug)]
+pub(crate) struct PendingChange {
+    pub buffer_id: BufferId,
+    pub buffer: Buffer,
+    pub major_mode: String,
+    pub range: ChangeRange,
+}
+
+#[derive(Default)]
+struct QueueState {
+    pending: Vec<PendingChange>,
+    /// A flush command has been sent to the Julia thread and not yet picked up
+    flush_scheduled: bool,
+    /// The Julia thread is running hooks it took from the queue
+    in_flight: bool,
+    /// Edits the hooks made through the FFI, for the editor to take
+    hook_edits: Vec<(BufferId, ChangeRange)>,
+}
+
+/// Handle to the after-change queue; cheap to clone and safe to use without
+/// holding the Julia runtime lock.
+#[derive(Clone)]
+pub struct AfterChangeQueue {
+    state: Arc<Mutex<QueueState>>,
+    completed: Arc<AtomicBool>,
+    notify: Arc<Notify>,
+    command_tx: mpsc::UnboundedSender<JuliaCommand>,
+}
+
+impl AfterChangeQueue {
+    pub(crate) fn new(command_tx: mpsc::UnboundedSender<JuliaCommand>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(QueueState::default())),
+            completed: Arc::new(AtomicBool::new(false)),
+            notify: Arc::new(Notify::new()),
+            command_tx,
+        }
+    }
+
+    /// Queue an after-change hook call, merging it with any pending change for the
+    /// same buffer. Never blocks on Julia.
+    pub fn push(
+        &self,
+        buffer_id: BufferId,
+        buffer: Buffer,
+        major_mode: String,
+        range: ChangeRange,
+    ) {
+        let mut state = self.state.lock().expect("After-change queue lock poisoned");
+
+        match state
+            .pending
+            .iter_mut()
+            .find(|p| p.buffer_id == buffer_id && p.major_mode == major_mode)
+        {
+            Some(pending) => pending.range = pending.range.merge(range),
+            None => state.pending.push(PendingChange {
+                buffer_id,
+                buffer,
+                major_mode,
+                range,
+            }),
+        }
+
+        if !state.flush_scheduled {
+            state.flush_scheduled = self
+                .command_tx
+                .send(JuliaCommand::FlushAfterChanges)
+                .is_ok();
+        }
+    }
+
+    /// Take everything queued so far; called by the Julia thread on flush.
+    pub(crate) fn take_pending(&self) -> Vec<PendingChange> {
+        let mut state = self.state.lock().expect("After-change queue lock poisoned");
+        state.flush_scheduled = false;
+        state.in_flight = true;
+        std::mem::take(&mut state.pending)
+    }
+
+    /// Record that a hook edited `buffer_id` itself.
+    pub(crate) fn note_hook_edit(&self, buffer_id: BufferId, range: ChangeRange) {
+        let mut state = self.state.lock().expect("After-change queue lock poisoned");
+        state.hook_edits.push((buffer_id, range));
+    }
+
+    /// Take the edits hooks have made to their buffers since the last call,
+    /// in the order they were made.
+    pub fn take_hook_edits(&self) -> Vec<(BufferId, ChangeRange)> {
+        let mut state = self.state.lock().expect("After-change queue lock poisoned");
+        std::mem::take(&mut state.hook_edits)
+    }
+
+    /// Record that the hooks returned by `take_pending` have run.
+    pub(crate) fn mark_completed(&self) {
+        {
+            let mut state = self.state.lock().expect("After-change queue lock poisoned");
+            state.in_flight = false;
+        }
+        self.completed.store(true, Ordering::Release);
+        self.notify.notify_one();
+    }
+
+    /// True if hooks have finished since the last call, meaning highlighting may
+    /// have changed and the screen should be redrawn.
+    pub fn take_completed(&self) -> bool {
+        self.completed.swap(false, Ordering::AcqRel)
+    }
+
+    /// Wait until the Julia thread finishes a batch of hooks.
+    pub async fn completed(&self) {
+        self.notify.notified().await;
+    }
+
+    /// True if nothing is queued or running.
+    pub fn is_idle(&self) -> bool {
+        let state = self.state.lock().expect("After-change queue lock poisoned");
+        state.pending.is_empty() && !state.in_flight
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slotmap::SlotMap;
+
+    fn two_buffer_ids() -> (BufferId, BufferId) {
+        let mut buffers: SlotMap<BufferId, ()> = SlotMap::with_key();
+        (buffers.insert(()), buffers.insert(()))
+    }
+
+    #[test]
+    fn test_merge_consecutive_inserts() {
+        // Typing "a" then "b" at position 5
+        let merged = ChangeRange::new(5, 5, 6).merge(ChangeRange::new(6, 6, 7));
+        assert_eq!(merged, ChangeRange::new(5, 5, 7));
+    }
+
+    #[test]
+    fn test_merge_overlapping_delete() {
+        // Insert 5 chars at 10, then delete 8..12 of the result
+        let merged = ChangeRange::new(10, 10, 15).merge(ChangeRange::new(8, 12, 8));
+        assert_eq!(merged, ChangeRange::new(8, 10, 11));
+    }
+
+    #[test]
+    fn test_merge_disjoint_changes() {
+        // Insert at 5, then insert at 20 (post-insert coordinates)
+        let merged = ChangeRange::new(5, 5, 6).merge(ChangeRange::new(20, 20, 21));
+        assert_eq!(merged, ChangeRange::new(5, 19, 21));
+    }
+
+    #[test]
+    fn test_push_coalesces_same_buffer() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let queue = AfterChangeQueue::new(tx);
+        let (buffer_a, buffer_b) = two_buffer_ids();
+        let buffer = Buffer::new(&[]);
+
+        queue.push(
+            buffer_a,
+            buffer.clone(),
+            "julia".to_string(),
+            ChangeRange::new(0, 0, 1),
+        );
+        queue.push(
+            buffer_a,
+            buffer.clone(),
+            "julia".to_string(),
+            ChangeRange::new(1, 1, 2),
+        );
+        queue.push(
+            buffer_b,
+            buffer,
+            "rust".to_string(),
+            ChangeRange::new(3, 3, 4),
+        );
+
+        // Only one flush request is outstanding for the whole burst
+        assert!(matches!(rx.try_recv(), Ok(JuliaCommand::FlushAfterChanges)));
+        assert!(rx.try_recv().is_err());
+        assert!(!queue.is_idle());
+
+        let pending = queue.take_pending();
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].range, ChangeRange::new(0, 0, 2));
+        assert_eq!(pending[1].range, ChangeRange::new(3, 3, 4));
+
+        queue.mark_completed();
+        assert!(queue.is_idle());
+        assert!(queue.take_completed());
+        assert!(!queue.take_completed());
+    }
+
+    #[test]
+    fn test_push_after_take_schedules_new_flush() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let queue = AfterChangeQueue::new(tx);
+        let (buffer_id, _) = two_buffer_ids();
+        let buffer = Buffer::new(&[]);
+
+        queue.push(
+            buffer_id,
+            buffer.clone(),
+            "julia".to_string(),
+            ChangeRange::new(0, 0, 1),
+        );
+        assert!(rx.try_recv().is_ok());
+        let _ = queue.take_pending();
+
+        // An edit arriving while hooks run is queued for the next flush
+        queue.push(
+            buffer_id,
+            buffer,
+            "julia".to_string(),
+            ChangeRange::new(1, 1, 2),
+        );
+        assert!(matches!(rx.try_recv(), Ok(JuliaCommand::FlushAfterChanges)));
+    }
+}
@@ -0,0 +1,193 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! `bind-key` and `unbind-key`: prompt for a key sequence written as in
+//! `define_key` ("C-c k"), then for `bind-key` the command or `:` action to
+//! bind it to. The change applies to the keys typed from then on, until the
+//! config is reloaded.
+
+use crate::keys::{ConfigurableBindings, KeyAction};
+use crate::mode::{ActionPosition, Mode, ModeAction, ModeResult};
+
+/// Whether the prompt adds a binding or removes one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindKeyOperation {
+    Bind,
+    Unbind,
+}
+
+/// Prompt for a key sequence, and the action to bind it to
+pub struct BindKeyMode {
+    operation: BindKeyOperation,
+    /// Commands the key sequence can be bound to
+    command_names: Vec<String>,
+    /// The key sequence, once it's been entered
+    key_sequence: Option<String>,
+    /// Text typed at the current prompt
+    input: String,
+    /// Why the last RET wasn't accepted
+    error: Option<String>,
+}
+
+impl BindKeyMode {
+    pub fn new(operation: BindKeyOperation, command_names: Vec<String>) -> Self {
+        Self {
+            operation,
+            command_names,
+            key_sequence: None,
+            input: String::new(),
+            error: None,
+        }
+    }
+
+    /// Generate buffer content string
+    pub fn generate_buffer_content(&self) -> String {
+        let prompt = match (&self.key_sequence, self.operation) {
+            (Some(key_sequence), _) => format!("Bind {key_sequence} to command"),
+            (None, BindKeyOperation::Bind) => "Bind key".to_string(),
+            (None, BindKeyOperation::Unbind) => "Unbind key".to_string(),
+        };
+        match &self.error {
+            Some(error) => format!("{prompt}: {} [{error}]\n", self.input),
+            None => format!("{prompt}: {}\n", self.input),
+        }
+    }
+
+    fn redraw(&self) -> ModeResult {
+        ModeResult::Consumed(vec![
+            ModeAction::ClearText,
+            ModeAction::InsertText(ActionPosition::start(), self.generate_buffer_content()),
+        ])
+    }
+
+    /// RET at the key sequence prompt
+    fn accept_key_sequence(&mut self) -> ModeResult {
+        let key_sequence = self.input.trim().to_string();
+        if let Err(error) = ConfigurableBindings::try_parse_key_sequence(&key_sequence) {
+            self.error = Some(error);
+            return self.redraw();
+        }
+        match self.operation {
+            BindKeyOperation::Unbind => {
+                ModeResult::Consumed(vec![ModeAction::UnbindKey(key_sequence)])
+            }
+            BindKeyOperation::Bind => {
+                self.key_sequence = Some(key_sequence);
+                self.input.clear();
+                self.redraw()
+            }
+        }
+    }
+
+    /// RET at the command prompt
+    fn accept_action(&mut self, key_sequence: String) -> ModeResult {
+        let action = self.input.trim().to_string();
+        let checked = ConfigurableBindings::check_action(&action).and_then(|()| {
+            if action.starts_with(':') || self.command_names.contains(&action) {
+                Ok(())
+            } else {
+                Err(format!("No command named {action}"))
+            }
+        });
+        match checked {
+            Ok(()) => ModeResult::Consumed(vec![ModeAction::BindKey(key_sequence, action)]),
+            Err(error) => {
+                self.error = Some(error);
+                self.redraw()
+            }
+        }
+    }
+}
+
+impl Mode for BindKeyMode {
+    fn name(&self) -> &str {
+        "bind-key"
+    }
+
+    fn perform(&mut self, action: &KeyAction) -> ModeResult {
+        match action {
+            KeyAction::AlphaNumeric(c) => {
+                self.input.push(*c);
+                self.error = None;
+                self.redraw()
+            }
+            KeyAction::Backspace => {
+                self.input.pop();
+                self.error = None;
+                self.redraw()
+            }
+            KeyAction::Enter => match self.key_sequence.clone() {
+                None => self.accept_key_sequence(),
+                Some(key_sequence) => self.accept_action(key_sequence),
+            },
+            KeyAction::Escape | KeyAction::Cancel => ModeResult::Ignored,
+            _ => ModeResult::Consumed(vec![]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_text(mode: &mut BindKeyMode, text: &str) {
+        for c in text.chars() {
+            mode.perform(&KeyAction::AlphaNumeric(c));
+        }
+    }
+
+    #[test]
+    fn test_bind_key_prompt() {
+        let mut mode = BindKeyMode::new(BindKeyOperation::Bind, vec!["quit".to_string()]);
+        type_text(&mut mode, "C-c Foo");
+        mode.perform(&KeyAction::Enter);
+        assert_eq!(
+            mode.generate_buffer_content(),
+            "Bind key: C-c Foo [Invalid key \"Foo\" in \"C-c Foo\"]\n"
+        );
+
+        for _ in 0..3 {
+            mode.perform(&KeyAction::Backspace);
+        }
+        type_text(&mut mode, "q");
+        mode.perform(&KeyAction::Enter);
+        assert_eq!(mode.generate_buffer_content(), "Bind C-c q to command: \n");
+
+        type_text(&mut mode, "quot");
+        mode.perform(&KeyAction::Enter);
+        assert_eq!(
+            mode.generate_buffer_content(),
+            "Bind C-c q to command: quot [No command named quot]\n"
+        );
+        mode.perform(&KeyAction::Backspace);
+        mode.perform(&KeyAction::Backspace);
+        type_text(&mut mode, "it");
+        assert_eq!(
+            mode.perform(&KeyAction::Enter),
+            ModeResult::Consumed(vec![ModeAction::BindKey(
+                "C-c q".to_string(),
+                "quit".to_string()
+            )])
+        );
+    }
+
+    #[test]
+    fn test_unbind_key_prompt() {
+        let mut mode = BindKeyMode::new(BindKeyOperation::Unbind, vec![]);
+        type_text(&mut mode, "C-x C-s");
+        assert_eq!(
+            mode.perform(&KeyAction::Enter),
+            ModeResult::Consumed(vec![ModeAction::UnbindKey("C-x C-s".to_string())])
+        );
+    }
+}
@@ -0,0 +1,140 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! `bookmark-jump` and `bookmark-list` list the bookmarks to pick one to go to.
+
+use crate::bookmarks::Bookmark;
+use crate::keys::KeyAction;
+use crate::mode::{Mode, ModeAction, ModeResult};
+use crate::selection_menu::{MenuItem, SelectionMenu, DEFAULT_MAX_VISIBLE_ITEMS};
+
+/// Which bookmark list a command window is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookmarkOperation {
+    Jump,
+    List,
+}
+
+/// A bookmark in the list, with where it points
+#[derive(Clone)]
+pub struct BookmarkItem {
+    pub name: String,
+    /// `file:line:column`
+    pub location: String,
+}
+
+impl MenuItem for BookmarkItem {
+    fn display_text(&self) -> String {
+        format!("{}  {}", self.name, self.location)
+    }
+
+    fn matches_filter(&self, filter: &str) -> bool {
+        self.name.to_lowercase().contains(&filter.to_lowercase())
+    }
+}
+
+/// List of bookmarks to pick one to jump to
+pub struct BookmarkMenuMode {
+    menu: SelectionMenu<BookmarkItem>,
+    prompt: &'static str,
+}
+
+impl BookmarkMenuMode {
+    /// The list for bookmark-jump (`Jump`) or bookmark-list
+    pub fn new(operation: BookmarkOperation, bookmarks: &[Bookmark]) -> Self {
+        let mut menu = SelectionMenu::new(DEFAULT_MAX_VISIBLE_ITEMS);
+        menu.init_with_items(
+            bookmarks
+                .iter()
+                .map(|bookmark| BookmarkItem {
+                    name: bookmark.name.clone(),
+                    location: format!(
+                        "{}:{}:{}",
+                        bookmark.file.display(),
+                        bookmark.line,
+                        bookmark.column
+                    ),
+                })
+                .collect(),
+        );
+        let prompt = match operation {
+            BookmarkOperation::List => "Bookmarks:",
+            BookmarkOperation::Jump => "Jump to bookmark:",
+        };
+        Self { menu, prompt }
+    }
+
+    /// Set how many bookmarks are listed at once
+    pub fn set_max_visible_items(&mut self, max_visible_items: usize) {
+        self.menu.set_max_visible_items(max_visible_items);
+    }
+
+    /// Generate buffer content string
+    pub fn generate_buffer_content(&self) -> String {
+        self.menu.generate_buffer_content(Some(self.prompt))
+    }
+}
+
+impl Mode for BookmarkMenuMode {
+    fn name(&self) -> &str {
+        "bookmarks"
+    }
+
+    fn perform(&mut self, action: &KeyAction) -> ModeResult {
+        if self.menu.handle_key_action(action) {
+            return ModeResult::Consumed(self.menu.generate_update_actions(Some(self.prompt)));
+        }
+
+        match action {
+            KeyAction::Enter => match self.menu.get_selected_item() {
+                Some(item) => {
+                    ModeResult::Consumed(vec![ModeAction::JumpToBookmark(item.name.clone())])
+                }
+                None => ModeResult::Consumed(vec![]),
+            },
+            _ => ModeResult::Ignored,
+        }
+    }
+
+    fn resize(&mut self, list_rows: usize) -> ModeResult {
+        self.set_max_visible_items(list_rows);
+        ModeResult::Consumed(self.menu.generate_update_actions(Some(self.prompt)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_jump_list_completes_names() {
+        let bookmark = |name: &str| Bookmark {
+            name: name.to_string(),
+            file: PathBuf::from("/tmp/f"),
+            line: 1,
+            column: 1,
+        };
+        let mut mode = BookmarkMenuMode::new(
+            BookmarkOperation::Jump,
+            &[bookmark("alpha"), bookmark("beta")],
+        );
+        assert!(mode.generate_buffer_content().contains("alpha  /tmp/f:1:1"));
+
+        mode.perform(&KeyAction::AlphaNumeric('b'));
+        assert_eq!(
+            mode.perform(&KeyAction::Enter),
+            ModeResult::Consumed(vec![ModeAction::JumpToBookmark("beta".to_string())])
+        );
+    }
+}
@@ -0,0 +1,228 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Named places in files, set with `bookmark-set` and gone back to with
+//! `bookmark-jump`. They're kept in `bookmarks.toml` in the config directory
+//! (`$XDG_CONFIG_HOME/roe`, or `~/.config/roe`), one table per bookmark:
+//!
+//! ```toml
+//! [[bookmark]]
+//! name = "todo"
+//! file = "/home/me/notes.md"
+//! line = 12
+//! column = 1
+//! ```
+
+use crate::config::config_dir;
+use crate::file_location::FilePosition;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// A named line and column in a file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub name: String,
+    pub file: PathBuf,
+    /// 1-based
+    pub line: usize,
+    /// 1-based
+    pub column: usize,
+}
+
+impl Bookmark {
+    /// Where in its file to put the cursor
+    pub fn position(&self) -> FilePosition {
+        FilePosition {
+            line: self.line,
+            column: Some(self.column),
+        }
+    }
+}
+
+/// The bookmarks set, by name, and the file they're saved to
+#[derive(Debug, Default)]
+pub struct Bookmarks {
+    bookmarks: Vec<Bookmark>,
+    /// None to keep them only for the session
+    path: Option<PathBuf>,
+}
+
+impl Bookmarks {
+    /// `bookmarks.toml` in the config directory, if there's a home to find it in
+    pub fn default_path() -> Option<PathBuf> {
+        Some(config_dir()?.join("bookmarks.toml"))
+    }
+
+    /// The bookmarks saved at `path`, none if it doesn't exist yet. Setting a
+    /// bookmark saves them there.
+    pub fn load(path: PathBuf) -> Result<Self, String> {
+        let bookmarks = match std::fs::read_to_string(&path) {
+            Ok(text) => Self::from_toml(&text).map_err(|e| format!("{}: {e}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(format!("{}: {e}", path.display())),
+        };
+        Ok(Self {
+            bookmarks,
+            path: Some(path),
+        })
+    }
+
+    /// Every bookmark, by name
+    pub fn all(&self) -> &[Bookmark] {
+        &self.bookmarks
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bookmarks.is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Bookmark> {
+        self.bookmarks.iter().find(|bookmark| bookmark.name == name)
+    }
+
+    /// Add `bookmark`, replacing any with its name
+    pub fn set(&mut self, bookmark: Bookmark) {
+        match self
+            .bookmarks
+            .binary_search_by(|other| other.name.cmp(&bookmark.name))
+        {
+            Ok(index) => self.bookmarks[index] = bookmark,
+            Err(index) => self.bookmarks.insert(index, bookmark),
+        }
+    }
+
+    /// Write the bookmarks to the file they were loaded from
+    pub fn save(&self) -> std::io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let text = Self::to_toml(&self.bookmarks).map_err(std::io::Error::other)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, text)
+    }
+
+    /// `bookmarks` as the TOML they're saved in
+    pub fn to_toml(bookmarks: &[Bookmark]) -> Result<String, String> {
+        let file = BookmarkFile {
+            bookmark: bookmarks.to_vec(),
+        };
+        let text = toml::to_string(&file).map_err(|e| e.to_string())?;
+        Ok(format!("# Bookmarks set with bookmark-set\n\n{text}"))
+    }
+
+    /// The bookmarks in `text`, a TOML file of `[[bookmark]]` tables, by name.
+    /// Of two with the same name the first is kept.
+    pub fn from_toml(text: &str) -> Result<Vec<Bookmark>, String> {
+        let file: BookmarkFile = toml::from_str(text).map_err(|e| e.to_string())?;
+        let mut bookmarks = file.bookmark;
+        bookmarks.sort_by(|a, b| a.name.cmp(&b.name));
+        bookmarks.dedup_by(|later, earlier| later.name == earlier.name);
+        Ok(bookmarks)
+    }
+}
+
+/// The layout of the bookmarks file
+#[derive(Serialize, Deserialize)]
+struct BookmarkFile {
+    #[serde(default)]
+    bookmark: Vec<Bookmark>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut bookmarks = Bookmarks::default();
+        for (name, file, line) in [
+            ("todo", "/home/me/notes.md", 12),
+            ("a \"quoted\" name", "C:\\src\\main.rs", 3),
+        ] {
+            bookmarks.set(Bookmark {
+                name: name.to_string(),
+                file: PathBuf::from(file),
+                line,
+                column: 5,
+            });
+        }
+        // Setting a name again replaces it
+        bookmarks.set(Bookmark {
+            name: "todo".to_string(),
+            file: PathBuf::from("/home/me/notes.md"),
+            line: 40,
+            column: 1,
+        });
+
+        let text = Bookmarks::to_toml(bookmarks.all()).unwrap();
+        assert!(text.contains("[[bookmark]]"));
+        assert_eq!(Bookmarks::from_toml(&text).unwrap(), bookmarks.all());
+        assert_eq!(bookmarks.all().len(), 2);
+        assert_eq!(bookmarks.get("todo").unwrap().line, 40);
+    }
+
+    #[test]
+    fn test_bad_toml() {
+        assert!(Bookmarks::from_toml("name = \"x\"").is_err());
+        assert!(
+            Bookmarks::from_toml("[[bookmark]]\nname = \"x\"\nfile = \"f\"\nline = 1\n").is_err()
+        );
+        assert!(Bookmarks::from_toml("[[bookmark]]\nname = \"open").is_err());
+    }
+
+    #[test]
+    fn test_hand_edited_toml() {
+        // Any layout TOML allows, and bookmarks in any order
+        let text = "bookmark = [\n  { name = 'zed', file = '/z', line = 1, column = 1 },\n  \
+                    { name = \"abc\", file = \"/a\", column = 2, line = 3 }, # last\n]\n";
+        let mut bookmarks = Bookmarks {
+            bookmarks: Bookmarks::from_toml(text).unwrap(),
+            path: None,
+        };
+        let names = |bookmarks: &Bookmarks| -> Vec<String> {
+            bookmarks.all().iter().map(|b| b.name.clone()).collect()
+        };
+        assert_eq!(names(&bookmarks), ["abc", "zed"]);
+
+        // So setting one keeps them in order
+        bookmarks.set(Bookmark {
+            name: "mid".to_string(),
+            file: PathBuf::from("/m"),
+            line: 1,
+            column: 1,
+        });
+        assert_eq!(names(&bookmarks), ["abc", "mid", "zed"]);
+        assert!(Bookmarks::from_toml("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("roe-bookmarks-{}", std::process::id()));
+        let path = dir.join("roe").join("bookmarks.toml");
+        let mut bookmarks = Bookmarks::load(path.clone()).unwrap();
+        assert!(bookmarks.is_empty());
+        bookmarks.set(Bookmark {
+            name: "here".to_string(),
+            file: PathBuf::from("/tmp/x"),
+            line: 2,
+            column: 3,
+        });
+        bookmarks.save().unwrap();
+
+        let loaded = Bookmarks::load(path).unwrap();
+        assert_eq!(loaded.all(), bookmarks.all());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
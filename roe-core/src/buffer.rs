@@ -11,11 +11,91 @@
 // this program. If not, see <https://www.gnu.org/licenses/>.
 //
 
+use crate::encoding::Encoding;
 use crate::syntax::{FaceId, HighlightSpan, SpanStore};
+use crate::text_stats::TextStats;
+use crate::tree_sitter_highlight::TreeSitterLanguage;
 use crate::undo::{EditOp, UndoManager};
+use crate::window::WrapMode;
 use crate::ModeId;
+use std::collections::HashMap;
 use std::ops::Range;
 use std::sync::{Arc, RwLock};
+use unicode_width::UnicodeWidthChar;
+
+/// Tabs advance to the next multiple of this many display columns, unless the
+/// buffer's major mode or the config (`roe_config["editor"]["tab_width"]`)
+/// says otherwise
+pub const TAB_WIDTH: usize = 4;
+
+/// What editing commands echo when they refuse to change a read-only buffer
+pub const READ_ONLY_MESSAGE: &str = "Buffer is read-only";
+
+/// Bracket pairs `match_bracket` pairs up
+const BRACKET_PAIRS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+/// A bracket by the cursor and the bracket it pairs with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BracketMatch {
+    /// Position of the bracket at or just before the cursor
+    pub bracket: usize,
+    /// Position of its partner, or None if it's unmatched
+    pub partner: Option<usize>,
+}
+
+/// How the lines of a buffer's file end on disk. The buffer itself only holds
+/// `\n`; the file's ending is put back when it's written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    /// The ending most of `text`'s lines have (LF if it has none), and
+    /// whether some of them have the other
+    pub fn detect(text: &str) -> (Self, bool) {
+        let crlf = text.matches("\r\n").count();
+        let lf = text.matches('\n').count() - crlf;
+        let ending = if crlf > lf {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Lf
+        };
+        (ending, crlf > 0 && lf > 0)
+    }
+
+    /// `text`, read from a file, with each `\r\n` made `\n`
+    pub fn normalize(text: &str) -> String {
+        text.replace("\r\n", "\n")
+    }
+
+    /// Buffer `text`, with `\n` line endings, as written to the file
+    pub fn encode(self, text: &str) -> String {
+        match self {
+            LineEnding::Lf => text.to_string(),
+            LineEnding::CrLf => text.replace('\n', "\r\n"),
+        }
+    }
+
+    /// The other ending
+    pub fn toggled(self) -> Self {
+        match self {
+            LineEnding::Lf => LineEnding::CrLf,
+            LineEnding::CrLf => LineEnding::Lf,
+        }
+    }
+}
+
+impl std::fmt::Display for LineEnding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LineEnding::Lf => write!(f, "LF"),
+            LineEnding::CrLf => write!(f, "CRLF"),
+        }
+    }
+}
 
 /// The internal data structure for a buffer
 /// Contains the actual text and metadata
@@ -39,6 +119,154 @@ pub struct BufferInner {
     pub(crate) show_gutter: bool,
     /// Undo/redo history manager
     pub(crate) undo_manager: UndoManager,
+    /// Whether the modeline shows word count and reading time for this buffer
+    pub(crate) show_word_count: bool,
+    /// Words in the buffer, kept current across edits once first counted
+    pub(crate) word_count: Option<usize>,
+    /// How lines longer than the window are shown
+    pub(crate) wrap_mode: WrapMode,
+    /// Bracket match found for a cursor position, kept until the cursor moves
+    /// or the buffer is edited
+    pub(crate) bracket_match: Option<(usize, Option<BracketMatch>)>,
+    /// Edits between the last save and the current text: negative once undo
+    /// has gone back past the save, None when a new edit has made the saved
+    /// text unreachable by undo and redo
+    pub(crate) edits_since_save: Option<isize>,
+    /// Edits made since the buffer was created, undo and redo included, so
+    /// that it differs whenever the text may have changed
+    pub(crate) edit_count: u64,
+    /// Columns between tab stops
+    pub(crate) tab_width: usize,
+    /// Whether indentation is made with tabs rather than spaces
+    pub(crate) indent_tabs: bool,
+    /// Start and end of the text the buffer is narrowed to: all the cursor can
+    /// reach and the renderers show. None when that's the whole text.
+    pub(crate) narrow: Option<(usize, usize)>,
+    /// Whether editing commands refuse to change the text. Only they check:
+    /// the editor itself may still write to the buffer, e.g. to log a message.
+    pub(crate) read_only: bool,
+    /// Line ending the buffer's file is written with
+    pub(crate) line_ending: LineEnding,
+    /// Whether the file was read with lines ending both ways; writing it
+    /// makes them all `line_ending`
+    pub(crate) mixed_line_endings: bool,
+    /// Line ending the file had when last read or saved: until it's saved
+    /// again, a buffer set to write another one is modified
+    pub(crate) saved_line_ending: LineEnding,
+    /// Character encoding the buffer's file is read and written in
+    pub(crate) encoding: Encoding,
+    /// Named values set on this buffer alone, for extensions to keep state in
+    pub(crate) locals: HashMap<String, String>,
+    /// Built-in grammar the buffer is highlighted with, if its major mode
+    /// chose one
+    pub(crate) tree_sitter: Option<TreeSitterLanguage>,
+    /// Whether Julia has set spans on the buffer itself, which then win over
+    /// `tree_sitter`
+    pub(crate) julia_spans: bool,
+}
+
+/// Display columns `c` takes when it starts at display column `column`: tabs
+/// run to the next tab stop, wide (e.g. CJK) characters take two
+fn char_display_width(c: char, column: usize, tab_width: usize) -> usize {
+    match c {
+        '\t' => tab_cells(column, tab_width),
+        _ => c.width().unwrap_or(0),
+    }
+}
+
+/// Display columns `text` takes, with no tabs in it: wide (e.g. CJK)
+/// characters take two, combining marks none
+pub fn text_display_width(text: &str) -> usize {
+    text.chars().map(|c| c.width().unwrap_or(0)).sum()
+}
+
+/// Cells a tab starting at `column` takes up, running to the next tab stop
+fn tab_cells(column: usize, tab_width: usize) -> usize {
+    let tab_width = tab_width.max(1);
+    tab_width - column % tab_width
+}
+
+/// Fills the cell after a wide character in `expand_tabs`, and draws nothing:
+/// the wide character's glyph covers both cells
+pub const WIDE_CHAR_TAIL: char = '\u{200B}';
+
+/// `line` as the renderers draw it, one cell at a time: each tab becomes spaces
+/// up to the next tab stop, a wide character is followed by `WIDE_CHAR_TAIL`,
+/// and characters taking no columns (combining marks) get no cell. Each cell
+/// comes with the index of the character in `line` it's drawn for.
+pub fn expand_tabs(line: &str, tab_width: usize) -> Vec<(char, usize)> {
+    let mut cells = Vec::with_capacity(line.len());
+    for (i, c) in line.chars().enumerate() {
+        match (c, char_display_width(c, cells.len(), tab_width)) {
+            ('\t', spaces) => cells.extend(std::iter::repeat_n((' ', i), spaces)),
+            (_, 0) => {}
+            (_, 1) => cells.push((c, i)),
+            _ => cells.extend([(c, i), (WIDE_CHAR_TAIL, i)]),
+        }
+    }
+    cells
+}
+
+/// Prepare `cells`, cut out of a line laid out by `expand_tabs`, for drawing:
+/// half a wide character cut off at either end becomes a space, so the cells
+/// after it stay where they are
+pub fn clip_wide_chars(cells: &mut [(char, usize)]) {
+    if let Some(first) = cells.first_mut().filter(|(c, _)| *c == WIDE_CHAR_TAIL) {
+        first.0 = ' ';
+    }
+    if let Some(last) = cells.last_mut().filter(|(c, _)| c.width() == Some(2)) {
+        last.0 = ' ';
+    }
+}
+
+/// Whitespace drawn with a visible mark when whitespace is shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WhitespaceMark {
+    Tab,
+    /// A space after the line's last character that isn't whitespace
+    TrailingSpace,
+}
+
+/// Where `line`, without its line ending, has whitespace to mark: the cell
+/// each tab starts in, laid out as `expand_tabs` does, and each trailing space
+pub fn whitespace_marks(line: &str, tab_width: usize) -> Vec<(usize, WhitespaceMark)> {
+    let trailing_start = line.trim_end_matches([' ', '\t']).chars().count();
+    let mut marks = Vec::new();
+    let mut column = 0;
+    for (i, c) in line.chars().enumerate() {
+        match c {
+            '\t' => marks.push((column, WhitespaceMark::Tab)),
+            ' ' if i >= trailing_start => marks.push((column, WhitespaceMark::TrailingSpace)),
+            _ => {}
+        }
+        column += char_display_width(c, column, tab_width);
+    }
+    marks
+}
+
+fn is_bracket(c: char) -> bool {
+    BRACKET_PAIRS
+        .iter()
+        .any(|&(open, close)| c == open || c == close)
+}
+
+/// Whether `c` ends a word being moved over by forward-sexp or backward-sexp
+fn is_sexp_break(c: char) -> bool {
+    c.is_whitespace() || is_bracket(c)
+}
+
+/// Words in a piece of text: runs of non-whitespace, as `wc -w` counts them
+fn count_words(text: ropey::RopeSlice) -> usize {
+    let mut words = 0;
+    let mut in_word = false;
+    for c in text.chars() {
+        let is_word = !c.is_whitespace();
+        if is_word && !in_word {
+            words += 1;
+        }
+        in_word = is_word;
+    }
+    words
 }
 
 impl BufferInner {
@@ -53,41 +281,91 @@ impl BufferInner {
             major_mode: None,
             show_gutter: false, // Default to no gutter for scratch buffers
             undo_manager: UndoManager::new(),
+            show_word_count: false,
+            word_count: None,
+            wrap_mode: WrapMode::None,
+            bracket_match: None,
+            edits_since_save: Some(0),
+            edit_count: 0,
+            tab_width: TAB_WIDTH,
+            indent_tabs: false,
+            narrow: None,
+            read_only: false,
+            line_ending: LineEnding::Lf,
+            mixed_line_endings: false,
+            saved_line_ending: LineEnding::Lf,
+            encoding: Encoding::Utf8,
+            locals: HashMap::new(),
+            tree_sitter: None,
+            julia_spans: false,
         }
     }
 
     pub fn load_str(&mut self, text: &str) {
         self.buffer = ropey::Rope::from_str(text);
+        self.word_count = None;
+        self.bracket_match = None;
+        self.edits_since_save = Some(0);
+        self.edit_count += 1;
+        self.narrow = None;
     }
 
-    /// Create a new buffer inner and load content from a file
+    /// Create a new buffer inner and load content from a file, in the
+    /// encoding it looks to be in. Bytes that aren't valid in that encoding
+    /// are an `InvalidData` error.
     pub async fn from_file(file_path: &str, modes: &[ModeId]) -> Result<Self, std::io::Error> {
-        let content = tokio::fs::read_to_string(file_path).await?;
+        let bytes = tokio::fs::read(file_path).await?;
+        let encoding = Encoding::detect(&bytes);
+        let content = encoding
+            .decode(&bytes)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        let (line_ending, mixed_line_endings) = LineEnding::detect(&content);
         let buffer_inner = Self {
             object: file_path.to_string(),
             modes: modes.to_vec(),
-            buffer: ropey::Rope::from_str(&content),
+            buffer: ropey::Rope::from_str(&LineEnding::normalize(&content)),
             mark: None,
             transient_mark: false,
             spans: SpanStore::new(),
             major_mode: None,
             show_gutter: true, // Default to show gutter for file buffers
             undo_manager: UndoManager::new(),
+            show_word_count: false,
+            word_count: None,
+            wrap_mode: WrapMode::None,
+            bracket_match: None,
+            edits_since_save: Some(0),
+            edit_count: 0,
+            tab_width: TAB_WIDTH,
+            indent_tabs: false,
+            narrow: None,
+            read_only: false,
+            line_ending,
+            mixed_line_endings,
+            saved_line_ending: line_ending,
+            encoding,
+            locals: HashMap::new(),
+            tree_sitter: None,
+            julia_spans: false,
         };
         Ok(buffer_inner)
     }
 
     /// Insert a fragment of text into the buffer at the given line/col position.
     pub fn insert_col_line(&mut self, fragment: String, position: (u16, u16)) {
-        let buffer_location = self.buffer.line_to_char(position.1 as usize) + position.0 as usize;
+        let buffer_location = self.line_to_char(position.1 as usize) + position.0 as usize;
         self.insert_pos(fragment, buffer_location);
     }
 
+    /// Insert `fragment` at `position`, kept inside the narrowed text
     pub fn insert_pos(&mut self, fragment: String, position: usize) {
+        let position = self.clamp_position(position);
         let len = fragment.chars().count();
         // Record for undo before modifying
         self.undo_manager.record_insert(position, fragment.clone());
-        self.buffer.insert(position, &fragment);
+        self.count_words_across(position, position, len, |b| {
+            b.buffer.insert(position, &fragment)
+        });
         // Adjust highlight spans for the insertion
         self.spans.adjust_for_insert(position, len);
     }
@@ -95,11 +373,14 @@ impl BufferInner {
     /// Delete a fragment of text from the buffer at the given line/col position.
     /// Returns the deleted text.
     pub fn delete_col_line(&mut self, position: (u16, u16), count: isize) -> Option<String> {
-        let buffer_location = self.buffer.line_to_char(position.1 as usize) + position.0 as usize;
+        let buffer_location = self.line_to_char(position.1 as usize) + position.0 as usize;
         self.delete_pos(buffer_location, count)
     }
 
+    /// Delete `count` characters after `position`, or before it if negative.
+    /// None, deleting nothing, if that reaches outside the narrowed text.
     pub fn delete_pos(&mut self, position: usize, count: isize) -> Option<String> {
+        let accessible = self.accessible();
         let position = position as isize;
 
         // If count is negative then start is buffer_location - count and end is buffer_location
@@ -110,7 +391,7 @@ impl BufferInner {
             (position, position + count)
         };
 
-        if start < 0 || end as usize > self.buffer.len_chars() {
+        if start < accessible.start as isize || end > accessible.end as isize {
             return None;
         }
 
@@ -118,7 +399,9 @@ impl BufferInner {
         // Record for undo before modifying
         self.undo_manager
             .record_delete(start as usize, deleted.clone());
-        self.buffer.remove(start as usize..end as usize);
+        self.count_words_across(start as usize, end as usize, 0, |b| {
+            b.buffer.remove(start as usize..end as usize)
+        });
         // Adjust highlight spans for the deletion
         self.spans.adjust_for_delete(start as usize, end as usize);
         Some(deleted)
@@ -126,105 +409,108 @@ impl BufferInner {
 
     /// Return the position of the end of the line relative to the start position.
     pub fn eol_pos(&self, start_pos: usize) -> usize {
-        // Handle empty buffer
-        if self.buffer.len_chars() == 0 {
-            return 0;
-        }
-
-        // If we're already at or beyond the end of the buffer, stay there
-        if start_pos >= self.buffer.len_chars() {
-            return self.buffer.len_chars();
-        }
-
-        let line = self.buffer.char_to_line(start_pos);
-        let line_count = self.buffer.len_lines();
-
-        if line + 1 < line_count {
+        let line = self.char_to_line(start_pos);
+        if line + 1 < self.len_lines() {
             // Not the last line - end of line is just before the newline
-            let next_line_start = self.buffer.line_to_char(line + 1);
-            next_line_start - 1 // Position of the newline
+            self.line_to_char(line + 1) - 1
         } else {
-            // Last line - end of line is end of buffer
-            self.buffer.len_chars()
+            // Last line - end of line is end of the accessible text
+            self.accessible().end
         }
     }
 
+    /// Screen column and line `char_index` is drawn at: its display column,
+    /// laid out as the renderers draw it by `expand_tabs`
     pub fn to_column_line(&self, char_index: usize) -> (u16, u16) {
         // Clamp to valid range to prevent panic from stale cursor positions
         let char_index = self.clamp_position(char_index);
-        let line = self.buffer.char_to_line(char_index);
-        let col = char_index - self.buffer.line_to_char(line);
-        (col as u16, line as u16)
+        let line = self.char_to_line(char_index);
+        (self.display_column(char_index) as u16, line as u16)
     }
 
+    /// Position drawn at screen column `col` on `line`, the inverse of
+    /// `to_column_line`: the start of a tab or wide character spanning the
+    /// column, or the end of a line too short to reach it
     pub fn to_char_index(&self, col: u16, line: u16) -> usize {
-        let linestart_pos = self.buffer.line_to_char(line as usize);
-        linestart_pos + col as usize
+        self.display_column_to_pos(line as usize, col as usize)
     }
 
     // === PHASE 1: CLEAN CHARACTER-POSITION API ===
 
     /// Move cursor left by one character. O(1)
     pub fn move_left(&self, pos: usize) -> usize {
-        pos.saturating_sub(1)
+        self.clamp_position(pos.saturating_sub(1))
     }
 
     /// Move cursor right by one character. O(1)  
     pub fn move_right(&self, pos: usize) -> usize {
-        (pos + 1).min(self.buffer.len_chars())
+        self.clamp_position(pos + 1)
     }
 
     /// Move cursor up one line, preserving column when possible. O(log N)
     pub fn move_up(&self, pos: usize) -> usize {
-        if self.buffer.len_chars() == 0 {
-            return 0;
-        }
-
-        let line = self.buffer.char_to_line(pos);
+        let pos = self.clamp_position(pos);
+        let line = self.char_to_line(pos);
         if line == 0 {
             return pos; // Already at top
         }
 
-        let current_line_start = self.buffer.line_to_char(line);
-        let column = pos - current_line_start;
-
+        let column = pos - self.line_to_char(line);
         let target_line = line - 1;
-        let target_line_start = self.buffer.line_to_char(target_line);
-        let target_line_len = self.line_length(target_line);
-
-        target_line_start + column.min(target_line_len)
+        self.line_to_char(target_line) + column.min(self.line_length(target_line))
     }
 
     /// Move cursor down one line, preserving column when possible. O(log N)
     pub fn move_down(&self, pos: usize) -> usize {
-        if self.buffer.len_chars() == 0 {
-            return 0;
-        }
-
-        let line = self.buffer.char_to_line(pos);
-        let total_lines = self.buffer.len_lines();
-        if line + 1 >= total_lines {
+        let pos = self.clamp_position(pos);
+        let line = self.char_to_line(pos);
+        if line + 1 >= self.len_lines() {
             return pos; // Already at bottom
         }
 
-        let current_line_start = self.buffer.line_to_char(line);
-        let column = pos - current_line_start;
-
+        let column = pos - self.line_to_char(line);
         let target_line = line + 1;
-        let target_line_start = self.buffer.line_to_char(target_line);
-        let target_line_len = self.line_length(target_line);
-
-        target_line_start + column.min(target_line_len)
+        self.line_to_char(target_line) + column.min(self.line_length(target_line))
+    }
+
+    /// Display column of `pos` within its line, with tabs and wide characters
+    /// expanded. O(line length)
+    pub fn display_column(&self, pos: usize) -> usize {
+        let pos = self.clamp_position(pos);
+        let line_start = self.line_to_char(self.char_to_line(pos));
+        self.buffer
+            .slice(line_start..pos)
+            .chars()
+            .fold(0, |column, c| {
+                column + char_display_width(c, column, self.tab_width)
+            })
+    }
+
+    /// Position at display `column` on `line`: the start of a tab or wide
+    /// character spanning it, or the end of a line too short to reach it.
+    /// O(line length)
+    pub fn display_column_to_pos(&self, line: usize, column: usize) -> usize {
+        let line = line.min(self.len_lines() - 1);
+        let line_start = self.line_to_char(line);
+        let line_len = self.line_length(line);
+        let mut current = 0;
+        for (i, c) in self
+            .buffer
+            .slice(line_start..line_start + line_len)
+            .chars()
+            .enumerate()
+        {
+            current += char_display_width(c, current, self.tab_width);
+            if current > column {
+                return line_start + i;
+            }
+        }
+        line_start + line_len
     }
 
     /// Move cursor to start of current line. O(log N)
     pub fn move_line_start(&self, pos: usize) -> usize {
-        if self.buffer.len_chars() == 0 {
-            return 0;
-        }
-
-        let line = self.buffer.char_to_line(pos);
-        self.buffer.line_to_char(line)
+        self.line_to_char(self.char_to_line(pos))
     }
 
     /// Move cursor to end of current line. O(log N)
@@ -232,44 +518,130 @@ impl BufferInner {
         self.eol_pos(pos)
     }
 
-    /// Move cursor to start of buffer. O(1)
+    /// Move cursor to start of buffer, or of the text it's narrowed to. O(1)
     pub fn move_buffer_start(&self) -> usize {
-        0
+        self.accessible().start
     }
 
-    /// Move cursor to end of buffer. O(1)
+    /// Move cursor to end of buffer, or of the text it's narrowed to. O(1)
     pub fn move_buffer_end(&self) -> usize {
-        self.buffer.len_chars()
+        self.accessible().end
     }
 
     /// Get the length of a line (excluding newline). O(log N)
     pub fn line_length(&self, line: usize) -> usize {
-        if line >= self.buffer.len_lines() {
+        if line >= self.len_lines() {
             return 0;
         }
 
-        let line_start = self.buffer.line_to_char(line);
-        if line + 1 < self.buffer.len_lines() {
-            let next_line_start = self.buffer.line_to_char(line + 1);
-            next_line_start - line_start - 1 // -1 for newline
+        let line_start = self.line_to_char(line);
+        if line + 1 < self.len_lines() {
+            self.line_to_char(line + 1) - line_start - 1 // -1 for newline
         } else {
-            self.buffer.len_chars() - line_start
+            self.accessible().end - line_start
         }
     }
 
-    /// Ensure position is within buffer bounds. O(1)
+    /// Ensure position is within buffer bounds, or those of the text it's
+    /// narrowed to. O(1)
     pub fn clamp_position(&self, pos: usize) -> usize {
-        pos.min(self.buffer.len_chars())
+        let accessible = self.accessible();
+        pos.clamp(accessible.start, accessible.end)
+    }
+
+    // === NARROWING ===
+    //
+    // Narrowed, positions still count from the start of the whole text, but
+    // lines count from the first line of the narrowed part: line 0 is the one
+    // it starts on, however far into that line it starts.
+
+    /// The characters the cursor can reach and the renderers show: those the
+    /// buffer is narrowed to, or all of them
+    pub fn accessible(&self) -> Range<usize> {
+        match self.narrow {
+            Some((start, end)) => start..end,
+            None => 0..self.buffer.len_chars(),
+        }
     }
 
-    /// Move cursor forward by one word. O(N) where N is chars to scan
-    pub fn move_word_forward(&self, pos: usize) -> usize {
-        if self.buffer.len_chars() == 0 {
-            return 0;
+    /// Restrict the buffer to the text from `start` to `end`. False, leaving
+    /// it as it was, if that's empty.
+    pub fn narrow_to(&mut self, start: usize, end: usize) -> bool {
+        let end = end.min(self.buffer.len_chars());
+        if start >= end {
+            return false;
         }
+        self.narrow = Some((start, end));
+        self.bracket_match = None;
+        true
+    }
+
+    /// Make the whole text accessible again
+    pub fn widen(&mut self) {
+        self.narrow = None;
+        self.bracket_match = None;
+    }
+
+    /// Lines of the whole text before the first accessible one
+    pub fn narrowed_line_offset(&self) -> usize {
+        self.buffer.char_to_line(self.accessible().start)
+    }
+
+    /// Lines in the accessible text, counting the empty one after a final
+    /// newline as ropey does
+    pub fn len_lines(&self) -> usize {
+        self.buffer.char_to_line(self.accessible().end) - self.narrowed_line_offset() + 1
+    }
+
+    /// Position accessible `line` starts at, or the end of the accessible
+    /// text past the last line
+    pub fn line_to_char(&self, line: usize) -> usize {
+        let line = (self.narrowed_line_offset() + line).min(self.buffer.len_lines());
+        self.clamp_position(self.buffer.line_to_char(line))
+    }
+
+    /// Accessible line `pos` is on
+    pub fn char_to_line(&self, pos: usize) -> usize {
+        self.buffer.char_to_line(self.clamp_position(pos)) - self.narrowed_line_offset()
+    }
 
+    /// Accessible text of `line`, with its line ending
+    pub fn line(&self, line: usize) -> ropey::RopeSlice<'_> {
+        self.buffer
+            .slice(self.line_to_char(line)..self.line_to_char(line + 1))
+    }
+
+    /// Move the narrowed bounds for an edit replacing the text from `start` to
+    /// `end` with `inserted` characters. Text put in at either bound goes
+    /// inside.
+    fn adjust_narrow(&mut self, start: usize, end: usize, inserted: usize) {
+        let Some((narrow_start, narrow_end)) = self.narrow else {
+            return;
+        };
+        let shift = |pos: usize| {
+            if pos < end {
+                start
+            } else {
+                pos - (end - start) + inserted
+            }
+        };
+        let narrow_start = if narrow_start > start {
+            shift(narrow_start)
+        } else {
+            narrow_start
+        };
+        let narrow_end = if narrow_end >= start {
+            shift(narrow_end)
+        } else {
+            narrow_end
+        };
+        self.narrow = Some((narrow_start, narrow_end));
+    }
+
+    /// Move cursor forward by one word. O(N) where N is chars to scan
+    pub fn move_word_forward(&self, pos: usize) -> usize {
         let mut current_pos = self.clamp_position(pos);
-        let buffer_len = self.buffer.len_chars();
+        let buffer_len = self.accessible().end;
 
         if current_pos >= buffer_len {
             return buffer_len;
@@ -307,58 +679,89 @@ impl BufferInner {
 
     /// Move cursor backward by one word. O(N) where N is chars to scan
     pub fn move_word_backward(&self, pos: usize) -> usize {
-        if self.buffer.len_chars() == 0 {
-            return 0;
-        }
-
         let mut current_pos = self.clamp_position(pos);
+        let buffer_start = self.accessible().start;
 
-        if current_pos == 0 {
-            return 0;
+        if current_pos == buffer_start {
+            return buffer_start;
         }
 
         // Move back one position to start
-        current_pos = current_pos.saturating_sub(1);
+        current_pos -= 1;
 
         // Skip any whitespace we're currently in (moving backwards)
-        while current_pos > 0 {
+        while current_pos > buffer_start {
             let ch = self.buffer.char(current_pos);
             if !ch.is_whitespace() {
                 break;
             }
-            current_pos = current_pos.saturating_sub(1);
+            current_pos -= 1;
         }
 
         // Skip the current word (moving backwards through non-whitespace)
-        while current_pos > 0 {
-            let ch = self.buffer.char(current_pos.saturating_sub(1));
+        while current_pos > buffer_start {
+            let ch = self.buffer.char(current_pos - 1);
             if ch.is_whitespace() {
                 break;
             }
-            current_pos = current_pos.saturating_sub(1);
+            current_pos -= 1;
         }
 
         current_pos
     }
 
+    /// End of the word `pos` is on, or of the next one if it's on whitespace,
+    /// without the whitespace after it
+    pub fn word_end(&self, pos: usize) -> usize {
+        let pos = self.clamp_position(pos);
+        let mut end = self.move_word_forward(pos);
+        while end > pos && self.buffer.char(end - 1).is_whitespace() {
+            end -= 1;
+        }
+        end
+    }
+
+    /// The spaces and tabs indenting the line `pos` is on, up to `pos`: what a
+    /// newline typed there should be followed by
+    pub fn indentation_before(&self, pos: usize) -> String {
+        let pos = self.clamp_position(pos);
+        let line_start = self.line_to_char(self.char_to_line(pos));
+        self.buffer
+            .slice(line_start..pos)
+            .chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .collect()
+    }
+
+    /// Start and end of the word `pos` is on: the run of non-whitespace around
+    /// it, without the whitespace after. Empty at `pos` if it's on whitespace.
+    pub fn word_bounds(&self, pos: usize) -> (usize, usize) {
+        let pos = self.clamp_position(pos);
+        if self.buffer.get_char(pos).is_none_or(char::is_whitespace) {
+            return (pos, pos);
+        }
+        let start = self.move_word_backward(pos + 1);
+        let mut end = self.move_word_forward(start);
+        while end > pos && self.buffer.char(end - 1).is_whitespace() {
+            end -= 1;
+        }
+        (start, end)
+    }
+
     /// Check if a line is blank (contains only whitespace)
     fn is_line_blank(&self, line_idx: usize) -> bool {
-        if line_idx >= self.buffer.len_lines() {
+        if line_idx >= self.len_lines() {
             return true;
         }
-        let line_text = self.buffer.line(line_idx);
+        let line_text = self.line(line_idx);
         line_text.chars().all(|c| c.is_whitespace())
     }
 
     /// Move cursor forward by one paragraph. O(N) where N is lines to scan
     pub fn move_paragraph_forward(&self, pos: usize) -> usize {
-        if self.buffer.len_chars() == 0 {
-            return 0;
-        }
-
         let current_pos = self.clamp_position(pos);
-        let current_line = self.buffer.char_to_line(current_pos);
-        let total_lines = self.buffer.len_lines();
+        let current_line = self.char_to_line(current_pos);
+        let total_lines = self.len_lines();
 
         let mut target_line = current_line;
 
@@ -374,31 +777,27 @@ impl BufferInner {
 
         // If we reached end of buffer, return end
         if target_line >= total_lines {
-            return self.buffer.len_chars();
+            return self.accessible().end;
         }
 
         // Return start of the target line
-        self.buffer.line_to_char(target_line)
+        self.line_to_char(target_line)
     }
 
     /// Move cursor backward by one paragraph. O(N) where N is lines to scan
     pub fn move_paragraph_backward(&self, pos: usize) -> usize {
-        if self.buffer.len_chars() == 0 {
-            return 0;
-        }
-
         let current_pos = self.clamp_position(pos);
-        let current_line = self.buffer.char_to_line(current_pos);
+        let current_line = self.char_to_line(current_pos);
 
         if current_line == 0 {
-            return 0; // Already at start
+            return self.accessible().start; // Already at start
         }
 
         // First, find the start of the current paragraph
         let mut line_idx = current_line;
 
         // If we're already at the start of a non-blank line, we're at paragraph start
-        let line_start_pos = self.buffer.line_to_char(current_line);
+        let line_start_pos = self.line_to_char(current_line);
         let already_at_paragraph_start =
             current_pos == line_start_pos && !self.is_line_blank(current_line);
 
@@ -417,7 +816,7 @@ impl BufferInner {
             }
 
             // If we stopped on a blank line, skip forward to start of paragraph
-            if self.is_line_blank(line_idx) && line_idx + 1 < self.buffer.len_lines() {
+            if self.is_line_blank(line_idx) && line_idx + 1 < self.len_lines() {
                 line_idx += 1;
             }
         } else {
@@ -433,7 +832,7 @@ impl BufferInner {
         }
 
         // Return start of the target line
-        self.buffer.line_to_char(line_idx)
+        self.line_to_char(line_idx)
     }
 
     // === MARK AND REGION OPERATIONS ===
@@ -526,7 +925,7 @@ impl BufferInner {
         let deleted = self.buffer.slice(start..end).to_string();
         // Record for undo before modifying
         self.undo_manager.record_delete(start, deleted.clone());
-        self.buffer.remove(start..end);
+        self.count_words_across(start, end, 0, |b| b.buffer.remove(start..end));
         // Adjust highlight spans for the deletion
         self.spans.adjust_for_delete(start, end);
         self.clear_mark();
@@ -543,18 +942,194 @@ impl BufferInner {
         let deleted = self.buffer.slice(start..end).to_string();
         // Record for undo before modifying
         self.undo_manager.record_delete(start, deleted.clone());
-        self.buffer.remove(start..end);
+        self.count_words_across(start, end, 0, |b| b.buffer.remove(start..end));
         // Adjust highlight spans for the deletion
         self.spans.adjust_for_delete(start, end);
         Some(deleted)
     }
 
+    // === WORD COUNT ===
+
+    /// Words in the buffer. Counted in full the first time, then adjusted as
+    /// the buffer is edited.
+    pub fn word_count(&mut self) -> usize {
+        *self
+            .word_count
+            .get_or_insert_with(|| count_words(self.buffer.slice(..)))
+    }
+
+    /// Run `edit`, which replaces the text from `start` to `end` with `inserted`
+    /// characters, keeping the word count current by recounting only the words
+    /// around the edit: the text out to the nearest whitespace either side.
+    /// Every edit comes through here, so it also drops the cached bracket match,
+    /// moves the narrowed bounds and counts the edit, overall and against the
+    /// last save (unless it changed nothing, which undo doesn't record either).
+    fn count_words_across<R>(
+        &mut self,
+        start: usize,
+        end: usize,
+        inserted: usize,
+        edit: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        let before = self.word_count.map(|_| {
+            let mut from = start;
+            while from > 0 && !self.buffer.char(from - 1).is_whitespace() {
+                from -= 1;
+            }
+            let mut to = end;
+            while to < self.buffer.len_chars() && !self.buffer.char(to).is_whitespace() {
+                to += 1;
+            }
+            (from, to, count_words(self.buffer.slice(from..to)))
+        });
+
+        let result = edit(self);
+        self.bracket_match = None;
+        self.adjust_narrow(start, end, inserted);
+        if end > start || inserted > 0 {
+            self.edits_since_save = match self.edits_since_save {
+                Some(edits) if edits >= 0 => Some(edits + 1),
+                _ => None,
+            };
+            self.edit_count += 1;
+        }
+
+        if let (Some(words), Some((from, to, old_words))) = (self.word_count, before) {
+            let to = to - (end - start) + inserted;
+            let new_words = count_words(self.buffer.slice(from..to));
+            self.word_count = Some(words + new_words - old_words);
+        }
+        result
+    }
+
+    // === BRACKETS ===
+
+    /// Position of the bracket pairing with the one at `pos`, skipping over
+    /// nested pairs of the same kind. None if `pos` isn't a bracket or nothing
+    /// pairs with it. Brackets in strings and comments count like any other.
+    pub fn match_bracket(&self, pos: usize) -> Option<usize> {
+        let c = self.buffer.get_char(pos)?;
+        let (open, close) = BRACKET_PAIRS
+            .into_iter()
+            .find(|&(open, close)| c == open || c == close)?;
+        let mut depth = 0usize;
+        if c == open {
+            for (offset, c) in self.buffer.chars_at(pos + 1).enumerate() {
+                if c == open {
+                    depth += 1;
+                } else if c == close {
+                    if depth == 0 {
+                        return Some(pos + 1 + offset);
+                    }
+                    depth -= 1;
+                }
+            }
+        } else {
+            let mut chars = self.buffer.chars_at(pos);
+            let mut at = pos;
+            while let Some(c) = chars.prev() {
+                at -= 1;
+                if c == close {
+                    depth += 1;
+                } else if c == open {
+                    if depth == 0 {
+                        return Some(at);
+                    }
+                    depth -= 1;
+                }
+            }
+        }
+        None
+    }
+
+    /// The bracket under `cursor`, or failing that the one just before it, and
+    /// its partner. Remembered for `cursor` until it moves or the buffer is
+    /// edited, so redrawing doesn't rescan the buffer.
+    pub fn bracket_match_at(&mut self, cursor: usize) -> Option<BracketMatch> {
+        if let Some((cached_cursor, found)) = self.bracket_match {
+            if cached_cursor == cursor {
+                return found;
+            }
+        }
+        let found = [Some(cursor), cursor.checked_sub(1)]
+            .into_iter()
+            .flatten()
+            .find(|&pos| self.buffer.get_char(pos).is_some_and(is_bracket))
+            .map(|bracket| BracketMatch {
+                bracket,
+                partner: self.match_bracket(bracket),
+            });
+        self.bracket_match = Some((cursor, found));
+        found
+    }
+
+    /// Where forward-sexp goes from `pos`: past the next bracketed group, or
+    /// to the end of the next word (a run of anything but whitespace and
+    /// brackets), skipping whitespace first. Err with where to stop instead
+    /// when the brackets don't balance: the end for a group that's never
+    /// closed, or `pos` when a close bracket comes first.
+    pub fn sexp_forward(&self, pos: usize) -> Result<usize, usize> {
+        let end = self.accessible().end;
+        let mut at = self.clamp_position(pos);
+        while at < end && self.buffer.char(at).is_whitespace() {
+            at += 1;
+        }
+        if at == end {
+            return Ok(end);
+        }
+
+        let c = self.buffer.char(at);
+        if BRACKET_PAIRS.iter().any(|&(open, _)| c == open) {
+            return match self.match_bracket(at) {
+                Some(close) if close < end => Ok(close + 1),
+                _ => Err(end),
+            };
+        }
+        if is_bracket(c) {
+            return Err(pos);
+        }
+        while at < end && !is_sexp_break(self.buffer.char(at)) {
+            at += 1;
+        }
+        Ok(at)
+    }
+
+    /// Where backward-sexp goes from `pos`: to the start of the bracketed
+    /// group or word before it, as `sexp_forward` does the other way
+    pub fn sexp_backward(&self, pos: usize) -> Result<usize, usize> {
+        let start = self.accessible().start;
+        let mut at = self.clamp_position(pos);
+        while at > start && self.buffer.char(at - 1).is_whitespace() {
+            at -= 1;
+        }
+        if at == start {
+            return Ok(start);
+        }
+
+        let c = self.buffer.char(at - 1);
+        if BRACKET_PAIRS.iter().any(|&(_, close)| c == close) {
+            return match self.match_bracket(at - 1) {
+                Some(open) if open >= start => Ok(open),
+                _ => Err(start),
+            };
+        }
+        if is_bracket(c) {
+            return Err(pos);
+        }
+        while at > start && !is_sexp_break(self.buffer.char(at - 1)) {
+            at -= 1;
+        }
+        Ok(at)
+    }
+
     // === UNDO/REDO OPERATIONS ===
 
     /// Perform undo, returns the new cursor position if successful
     pub fn undo(&mut self) -> Option<usize> {
         let op = self.undo_manager.pop_undo()?;
+        let edits = self.edits_since_save;
         let cursor = self.apply_edit_op(&op.reverse());
+        self.edits_since_save = edits.map(|edits| edits - op.edit_count() as isize);
         self.undo_manager.did_undo(op);
         Some(cursor)
     }
@@ -562,7 +1137,9 @@ impl BufferInner {
     /// Perform redo, returns the new cursor position if successful
     pub fn redo(&mut self) -> Option<usize> {
         let op = self.undo_manager.pop_redo()?;
+        let edits = self.edits_since_save;
         let cursor = self.apply_edit_op(&op);
+        self.edits_since_save = edits.map(|edits| edits + op.edit_count() as isize);
         self.undo_manager.did_redo(op);
         Some(cursor)
     }
@@ -572,13 +1149,13 @@ impl BufferInner {
         match op {
             EditOp::Insert { pos, text } => {
                 let len = text.chars().count();
-                self.buffer.insert(*pos, text);
+                self.count_words_across(*pos, *pos, len, |b| b.buffer.insert(*pos, text));
                 self.spans.adjust_for_insert(*pos, len);
                 pos + len
             }
             EditOp::Delete { pos, text } => {
                 let end = pos + text.chars().count();
-                self.buffer.remove(*pos..end);
+                self.count_words_across(*pos, end, 0, |b| b.buffer.remove(*pos..end));
                 self.spans.adjust_for_delete(*pos, end);
                 *pos
             }
@@ -618,6 +1195,21 @@ impl BufferInner {
         self.undo_manager.boundary();
     }
 
+    // === MODIFICATION TRACKING ===
+
+    /// Whether the text differs from when it was last saved or loaded, going
+    /// by the edits made since: undoing them all makes it unmodified again.
+    /// Switching the line ending the file is written with modifies it too.
+    pub fn is_modified(&self) -> bool {
+        self.edits_since_save != Some(0) || self.line_ending != self.saved_line_ending
+    }
+
+    /// Record that the current text, with its line ending, is what's saved
+    pub fn mark_saved(&mut self) {
+        self.edits_since_save = Some(0);
+        self.saved_line_ending = self.line_ending;
+    }
+
     // === SYNTAX HIGHLIGHTING SPAN OPERATIONS ===
 
     /// Add a highlight span to the buffer
@@ -740,6 +1332,14 @@ impl Buffer {
         self.with_read(|b| b.move_down(pos))
     }
 
+    pub fn display_column(&self, pos: usize) -> usize {
+        self.with_read(|b| b.display_column(pos))
+    }
+
+    pub fn display_column_to_pos(&self, line: usize, column: usize) -> usize {
+        self.with_read(|b| b.display_column_to_pos(line, column))
+    }
+
     pub fn move_line_start(&self, pos: usize) -> usize {
         self.with_read(|b| b.move_line_start(pos))
     }
@@ -756,6 +1356,10 @@ impl Buffer {
         self.with_read(|b| b.move_buffer_end())
     }
 
+    pub fn clamp_position(&self, pos: usize) -> usize {
+        self.with_read(|b| b.clamp_position(pos))
+    }
+
     pub fn eol_pos(&self, start_pos: usize) -> usize {
         self.with_read(|b| b.eol_pos(start_pos))
     }
@@ -768,6 +1372,18 @@ impl Buffer {
         self.with_read(|b| b.move_word_backward(pos))
     }
 
+    pub fn word_bounds(&self, pos: usize) -> (usize, usize) {
+        self.with_read(|b| b.word_bounds(pos))
+    }
+
+    pub fn word_end(&self, pos: usize) -> usize {
+        self.with_read(|b| b.word_end(pos))
+    }
+
+    pub fn indentation_before(&self, pos: usize) -> String {
+        self.with_read(|b| b.indentation_before(pos))
+    }
+
     pub fn move_paragraph_forward(&self, pos: usize) -> usize {
         self.with_read(|b| b.move_paragraph_forward(pos))
     }
@@ -850,6 +1466,81 @@ impl Buffer {
         self.with_write(|b| b.undo_boundary())
     }
 
+    pub fn is_modified(&self) -> bool {
+        self.with_read(|b| b.is_modified())
+    }
+
+    pub fn mark_saved(&self) {
+        self.with_write(|b| b.mark_saved())
+    }
+
+    /// Edits made since the buffer was created, undo and redo included: if
+    /// it's the same as before, the text hasn't changed since
+    pub fn edit_count(&self) -> u64 {
+        self.with_read(|b| b.edit_count)
+    }
+
+    /// Whether editing commands refuse to change the text
+    pub fn is_read_only(&self) -> bool {
+        self.with_read(|b| b.read_only)
+    }
+
+    /// Set whether editing commands refuse to change the text
+    pub fn set_read_only(&self, read_only: bool) {
+        self.with_write(|b| b.read_only = read_only)
+    }
+
+    /// Line ending the buffer's file is written with
+    pub fn line_ending(&self) -> LineEnding {
+        self.with_read(|b| b.line_ending)
+    }
+
+    /// Set the line ending the buffer's file is written with, every line of
+    /// it from the next save on
+    pub fn set_line_ending(&self, line_ending: LineEnding) {
+        self.with_write(|b| {
+            b.line_ending = line_ending;
+            b.mixed_line_endings = false;
+        })
+    }
+
+    /// Whether the file was read with lines ending both ways
+    pub fn has_mixed_line_endings(&self) -> bool {
+        self.with_read(|b| b.mixed_line_endings)
+    }
+
+    /// Character encoding the buffer's file is read and written in
+    pub fn encoding(&self) -> Encoding {
+        self.with_read(|b| b.encoding)
+    }
+
+    /// Set the character encoding the buffer's file is read and written in
+    pub fn set_encoding(&self, encoding: Encoding) {
+        self.with_write(|b| b.encoding = encoding)
+    }
+
+    /// Decode `bytes`, read afresh from the buffer's file, in its encoding and
+    /// take on their line endings, returning the text with `\n` endings to go
+    /// in the buffer
+    pub fn adopt_file_contents(&self, bytes: &[u8]) -> Result<String, String> {
+        let file_text = self.encoding().decode(bytes)?;
+        let (line_ending, mixed) = LineEnding::detect(&file_text);
+        self.with_write(|b| {
+            b.line_ending = line_ending;
+            b.mixed_line_endings = mixed;
+        });
+        Ok(LineEnding::normalize(&file_text))
+    }
+
+    /// The text as written to the buffer's file, with its line ending and in
+    /// its encoding. Fails if the encoding can't represent some character.
+    pub fn file_content(&self) -> Result<Vec<u8>, String> {
+        self.with_read(|b| {
+            b.encoding
+                .encode(&b.line_ending.encode(&b.buffer.to_string()))
+        })
+    }
+
     // Properties that need read access
     pub fn object(&self) -> String {
         self.with_read(|b| b.object.clone())
@@ -863,21 +1554,51 @@ impl Buffer {
         self.with_write(|b| b.load_str(text))
     }
 
-    // Additional methods needed by the renderer
+    // Additional methods needed by the renderer. Lines are those of the text
+    // the buffer is narrowed to, if it is.
     pub fn buffer_len_lines(&self) -> usize {
-        self.with_read(|b| b.buffer.len_lines())
+        self.with_read(|b| b.len_lines())
     }
 
     pub fn buffer_line(&self, line_idx: usize) -> String {
-        self.with_read(|b| b.buffer.line(line_idx).to_string())
+        self.with_read(|b| b.line(line_idx).to_string())
     }
 
     pub fn buffer_line_to_char(&self, line_idx: usize) -> usize {
-        self.with_read(|b| b.buffer.line_to_char(line_idx))
+        self.with_read(|b| b.line_to_char(line_idx))
+    }
+
+    pub fn buffer_char_to_line(&self, char_idx: usize) -> usize {
+        self.with_read(|b| b.char_to_line(char_idx))
     }
 
     pub fn buffer_lines(&self) -> Vec<String> {
-        self.with_read(|b| b.buffer.lines().map(|line| line.to_string()).collect())
+        self.with_read(|b| {
+            (0..b.len_lines())
+                .map(|line| b.line(line).to_string())
+                .collect()
+        })
+    }
+
+    /// Restrict the buffer to the text from `start` to `end`; false if that's
+    /// empty
+    pub fn narrow_to(&self, start: usize, end: usize) -> bool {
+        self.with_write(|b| b.narrow_to(start, end))
+    }
+
+    /// Make the whole text accessible again
+    pub fn widen(&self) {
+        self.with_write(|b| b.widen())
+    }
+
+    /// Start and end of the text the buffer is narrowed to, if it is
+    pub fn narrowing(&self) -> Option<(usize, usize)> {
+        self.with_read(|b| b.narrow)
+    }
+
+    /// Lines of the whole text before the first accessible one
+    pub fn narrowed_line_offset(&self) -> usize {
+        self.with_read(|b| b.narrowed_line_offset())
     }
 
     // Add mutable field access for main.rs compatibility
@@ -905,6 +1626,94 @@ impl Buffer {
         self.with_write(|b| b.show_gutter = show)
     }
 
+    /// Get whether the modeline shows word count and reading time for this buffer
+    pub fn show_word_count(&self) -> bool {
+        self.with_read(|b| b.show_word_count)
+    }
+
+    /// Set whether the modeline shows word count and reading time for this buffer
+    pub fn set_show_word_count(&self, show: bool) {
+        self.with_write(|b| b.show_word_count = show)
+    }
+
+    /// Columns between tab stops
+    pub fn tab_width(&self) -> usize {
+        self.with_read(|b| b.tab_width)
+    }
+
+    /// Set the columns between tab stops
+    pub fn set_tab_width(&self, tab_width: usize) {
+        self.with_write(|b| b.tab_width = tab_width.max(1))
+    }
+
+    /// Whether indentation is made with tabs rather than spaces
+    pub fn indent_tabs(&self) -> bool {
+        self.with_read(|b| b.indent_tabs)
+    }
+
+    /// Set whether indentation is made with tabs rather than spaces
+    pub fn set_indent_tabs(&self, indent_tabs: bool) {
+        self.with_write(|b| b.indent_tabs = indent_tabs)
+    }
+
+    /// Value of the buffer-local variable `name`, if it has been set
+    pub fn get_local(&self, name: &str) -> Option<String> {
+        self.with_read(|b| b.locals.get(name).cloned())
+    }
+
+    /// Set the buffer-local variable `name`, replacing any earlier value
+    pub fn set_local(&self, name: &str, value: &str) {
+        self.with_write(|b| {
+            b.locals.insert(name.to_string(), value.to_string());
+        })
+    }
+
+    /// Forget every buffer-local variable
+    pub fn clear_locals(&self) {
+        self.with_write(|b| b.locals.clear())
+    }
+
+    /// How lines longer than the window are shown
+    pub fn wrap_mode(&self) -> WrapMode {
+        self.with_read(|b| b.wrap_mode)
+    }
+
+    /// Set how lines longer than the window are shown
+    pub fn set_wrap_mode(&self, mode: WrapMode) {
+        self.with_write(|b| b.wrap_mode = mode)
+    }
+
+    /// Position of the bracket pairing with the one at `pos`, if any
+    pub fn match_bracket(&self, pos: usize) -> Option<usize> {
+        self.with_read(|b| b.match_bracket(pos))
+    }
+
+    /// See `BufferInner::sexp_forward`
+    pub fn sexp_forward(&self, pos: usize) -> Result<usize, usize> {
+        self.with_read(|b| b.sexp_forward(pos))
+    }
+
+    /// See `BufferInner::sexp_backward`
+    pub fn sexp_backward(&self, pos: usize) -> Result<usize, usize> {
+        self.with_read(|b| b.sexp_backward(pos))
+    }
+
+    /// The bracket at or just before `cursor` and its partner (cached per
+    /// cursor position, so cheap to call every frame)
+    pub fn bracket_match_at(&self, cursor: usize) -> Option<BracketMatch> {
+        self.with_write(|b| b.bracket_match_at(cursor))
+    }
+
+    /// Words in the buffer (kept current incrementally, so cheap to call)
+    pub fn word_count(&self) -> usize {
+        self.with_write(|b| b.word_count())
+    }
+
+    /// Lines, words, characters and bytes between `start` and `end`
+    pub fn text_stats(&self, start: usize, end: usize) -> TextStats {
+        self.with_read(|b| TextStats::of(&b.buffer.slice(start..end).to_string()))
+    }
+
     pub fn content(&self) -> String {
         self.with_read(|b| b.content())
     }
@@ -957,6 +1766,30 @@ impl Buffer {
     pub fn has_spans(&self) -> bool {
         self.with_read(|b| b.has_spans())
     }
+
+    /// Built-in grammar the buffer is highlighted with
+    pub fn tree_sitter(&self) -> Option<TreeSitterLanguage> {
+        self.with_read(|b| b.tree_sitter)
+    }
+
+    /// Highlight the buffer with `language`, or stop with `None`. Spans Julia
+    /// set under an earlier mode no longer hold it off.
+    pub fn set_tree_sitter(&self, language: Option<TreeSitterLanguage>) {
+        self.with_write(|b| {
+            b.tree_sitter = language;
+            b.julia_spans = false;
+        })
+    }
+
+    /// Whether Julia has set spans on the buffer, which tree-sitter leaves be
+    pub fn julia_spans(&self) -> bool {
+        self.with_read(|b| b.julia_spans)
+    }
+
+    /// Note that Julia has set spans on the buffer
+    pub fn mark_julia_spans(&self) {
+        self.with_write(|b| b.julia_spans = true)
+    }
 }
 
 impl Clone for Buffer {
@@ -1160,6 +1993,161 @@ mod tests {
         let (col, line) = buffer.to_column_line(pos);
         assert_eq!(line, 1); // Second line
         assert_eq!(col, 2); // Same column position ('u' in "cruel")
+
+        // A column past a line's end lands at its end
+        assert_eq!(buffer.display_column_to_pos(1, 2), 8);
+        assert_eq!(buffer.display_column_to_pos(0, 40), 5);
+        assert_eq!(buffer.display_column_to_pos(9, 40), 18);
+    }
+
+    #[test]
+    fn test_display_columns_with_tabs_and_wide_chars() {
+        let mut buffer = BufferInner::new(&[]);
+        buffer.load_str("\tx\n漢字ab\nabcdefgh");
+
+        // The tab runs to the first tab stop; each CJK character is two wide
+        assert_eq!(buffer.display_column(1), TAB_WIDTH);
+        assert_eq!(buffer.display_column(4), 2);
+        assert_eq!(buffer.display_column(5), 4);
+        assert_eq!(buffer.display_column(12), 4);
+
+        // A column inside a tab or wide character lands at its start
+        assert_eq!(buffer.display_column_to_pos(0, 2), 0);
+        assert_eq!(buffer.display_column_to_pos(0, 4), 1);
+        assert_eq!(buffer.display_column_to_pos(1, 3), 4);
+        assert_eq!(buffer.display_column_to_pos(1, 4), 5);
+        assert_eq!(buffer.display_column_to_pos(2, 4), 12);
+    }
+
+    #[test]
+    fn test_columns_with_tabs() {
+        let mut buffer = BufferInner::new(&[]);
+        buffer.load_str("\tx\n  \ty\n\t \tz");
+
+        // At the default width a tab runs to column 4, however far along it starts
+        assert_eq!(buffer.to_column_line(1), (4, 0));
+        assert_eq!(buffer.to_column_line(6), (4, 1));
+        assert_eq!(buffer.to_column_line(11), (8, 2));
+        // A column inside a tab lands at its start; clicks round-trip
+        assert_eq!(buffer.to_char_index(2, 1), 5);
+        assert_eq!(buffer.to_char_index(8, 2), 11);
+        assert_eq!(buffer.to_char_index(5, 2), 10);
+
+        buffer.tab_width = 8;
+        assert_eq!(buffer.to_column_line(1), (8, 0));
+        assert_eq!(buffer.to_column_line(6), (8, 1));
+        assert_eq!(buffer.to_column_line(10), (9, 2));
+        assert_eq!(buffer.to_column_line(11), (16, 2));
+        assert_eq!(buffer.to_char_index(9, 2), 10);
+        assert_eq!(buffer.to_char_index(12, 2), 10);
+        assert_eq!(buffer.display_column(6), 8);
+    }
+
+    #[test]
+    fn test_columns_with_wide_chars() {
+        let mut buffer = BufferInner::new(&[]);
+        buffer.load_str("漢字\tx\ne\u{301}y");
+
+        // Screen columns agree with display columns: two for each CJK
+        // character, none for a combining mark
+        assert_eq!(buffer.to_column_line(1), (2, 0));
+        assert_eq!(buffer.to_column_line(3), (4, 0));
+        assert_eq!(buffer.to_column_line(4), (5, 0));
+        assert_eq!(buffer.to_column_line(7), (1, 1));
+        assert_eq!(buffer.to_column_line(8), (2, 1));
+        for pos in 0..buffer.len_chars() {
+            assert_eq!(
+                buffer.to_column_line(pos).0 as usize,
+                buffer.display_column(pos)
+            );
+        }
+
+        // Clicks round-trip; a column inside a wide character lands at its start
+        for pos in 0..=4 {
+            let (col, line) = buffer.to_column_line(pos);
+            assert_eq!(buffer.to_char_index(col, line), pos);
+        }
+        assert_eq!(buffer.to_char_index(1, 0), 0);
+        assert_eq!(buffer.to_char_index(3, 0), 1);
+    }
+
+    #[test]
+    fn test_text_display_width() {
+        assert_eq!(text_display_width("abc"), 3);
+        assert_eq!(text_display_width("a中b"), 4);
+        assert_eq!(text_display_width("e\u{301}"), 1);
+    }
+
+    #[test]
+    fn test_expand_tabs() {
+        let cells = expand_tabs("a\tb", 4);
+        let text: String = cells.iter().map(|(c, _)| c).collect();
+        assert_eq!(text, "a   b");
+        assert_eq!(
+            cells.iter().map(|(_, i)| *i).collect::<Vec<_>>(),
+            [0, 1, 1, 1, 2]
+        );
+
+        let text: String = expand_tabs(" \t\tc", 8).iter().map(|(c, _)| c).collect();
+        assert_eq!(text, format!("{}c", " ".repeat(16)));
+
+        // A wide character takes two cells, so tab stops after it line up;
+        // a combining mark takes none
+        let cells = expand_tabs("漢\tx\u{301}", 4);
+        assert_eq!(
+            cells,
+            [('漢', 0), (WIDE_CHAR_TAIL, 0), (' ', 1), (' ', 1), ('x', 2)]
+        );
+
+        // Halves of wide characters cut off at the edges draw as spaces
+        let mut cells = expand_tabs("漢字漢", 4)[1..5].to_vec();
+        clip_wide_chars(&mut cells);
+        assert_eq!(cells, [(' ', 0), ('字', 1), (WIDE_CHAR_TAIL, 1), (' ', 2)]);
+    }
+
+    #[test]
+    fn test_whitespace_marks() {
+        use WhitespaceMark::*;
+        // Spaces count only after the last character that isn't whitespace
+        assert_eq!(
+            whitespace_marks("a b  ", 4),
+            [(3, TrailingSpace), (4, TrailingSpace)]
+        );
+        // Tabs are marked where they start, wherever they are
+        assert_eq!(
+            whitespace_marks("\tx\t \t", 4),
+            [(0, Tab), (5, Tab), (8, TrailingSpace), (9, Tab)]
+        );
+        assert_eq!(whitespace_marks("   ", 4).len(), 3);
+        // Wide characters before them take two cells
+        assert_eq!(
+            whitespace_marks("字\t字 ", 4),
+            [(2, Tab), (6, TrailingSpace)]
+        );
+        assert!(whitespace_marks("plain", 4).is_empty());
+    }
+
+    #[test]
+    fn test_line_ending_detection() {
+        assert_eq!(LineEnding::detect("a\r\nb\r\n"), (LineEnding::CrLf, false));
+        assert_eq!(LineEnding::detect("a\nb\n"), (LineEnding::Lf, false));
+        assert_eq!(LineEnding::detect("no newline"), (LineEnding::Lf, false));
+        // Mixed files go with the majority
+        assert_eq!(
+            LineEnding::detect("a\r\nb\r\nc\n"),
+            (LineEnding::CrLf, true)
+        );
+        assert_eq!(LineEnding::detect("a\r\nb\nc\n"), (LineEnding::Lf, true));
+
+        let buffer = Buffer::new(&[]);
+        let text = buffer.adopt_file_contents(b"a\r\nb\r\n").unwrap();
+        buffer.load_str(&text);
+        assert_eq!(buffer.content(), "a\nb\n");
+        buffer.insert_pos("c\n".to_string(), 4);
+        assert_eq!(buffer.file_content().unwrap(), b"a\r\nb\r\nc\r\n");
+
+        buffer.set_line_ending(LineEnding::Lf);
+        assert_eq!(buffer.file_content().unwrap(), b"a\nb\nc\n");
     }
 
     #[test]
@@ -1180,6 +2168,68 @@ mod tests {
         assert_eq!(empty_buffer.move_right(0), 0);
     }
 
+    #[test]
+    fn test_narrowing() {
+        let mut buffer = test_buffer(); // "Hello\ncruel\nworld!"
+        assert!(!buffer.narrow_to(8, 8));
+        assert!(buffer.narrow_to(8, 15)); // "uel\nwor"
+
+        // Lines count from the first narrowed one
+        assert_eq!(buffer.len_lines(), 2);
+        assert_eq!(buffer.line(0), "uel\n");
+        assert_eq!(buffer.line(1), "wor");
+        assert_eq!(buffer.line_to_char(1), 12);
+        assert_eq!(buffer.to_column_line(13), (1, 1));
+
+        // The cursor can't leave the narrowed text
+        assert_eq!(buffer.move_buffer_start(), 8);
+        assert_eq!(buffer.move_buffer_end(), 15);
+        assert_eq!(buffer.move_left(8), 8);
+        assert_eq!(buffer.move_right(15), 15);
+        assert_eq!(buffer.move_up(13), 9);
+        assert_eq!(buffer.move_down(10), 14);
+        assert_eq!(buffer.move_line_start(9), 8);
+        assert_eq!(buffer.move_line_end(13), 15);
+        assert_eq!(buffer.move_word_backward(10), 8);
+        assert_eq!(buffer.clamp_position(2), 8);
+
+        // Edits move the bounds, text typed at the end going inside
+        buffer.insert_pos("X".to_string(), 15);
+        buffer.delete_range(0, 2);
+        assert_eq!(buffer.accessible(), 6..14);
+        assert_eq!(buffer.line(1), "worX");
+
+        buffer.widen();
+        assert_eq!(buffer.len_lines(), 3);
+        assert_eq!(buffer.move_buffer_start(), 0);
+        assert_eq!(buffer.line(2), "worXld!");
+    }
+
+    #[test]
+    fn test_edits_stay_inside_narrowing() {
+        let mut buffer = test_buffer(); // "Hello\ncruel\nworld!"
+        assert!(buffer.narrow_to(8, 15)); // "uel\nwor"
+
+        // Insertions outside land at the nearest edge
+        buffer.insert_pos("<".to_string(), 2);
+        buffer.insert_pos(">".to_string(), 40);
+        assert_eq!(buffer.content(), "Hello\ncr<uel\nwor>ld!");
+        assert_eq!(buffer.accessible(), 8..17);
+
+        // Deletions reaching past either edge delete nothing
+        assert_eq!(buffer.delete_pos(8, -1), None);
+        assert_eq!(buffer.delete_pos(7, 2), None);
+        assert_eq!(buffer.delete_pos(16, 2), None);
+        assert_eq!(buffer.delete_pos(18, -1), None);
+        assert_eq!(buffer.content(), "Hello\ncr<uel\nwor>ld!");
+
+        // Up to the edges is fine
+        assert_eq!(buffer.delete_pos(8, 1), Some("<".to_string()));
+        assert_eq!(buffer.delete_pos(16, -1), Some(">".to_string()));
+        assert_eq!(buffer.content(), "Hello\ncruel\nworld!");
+        assert_eq!(buffer.accessible(), 8..15);
+    }
+
     #[test]
     fn test_phase1_api_handles_original_edge_cases() {
         let buffer = test_buffer(); // "Hello\ncruel\nworld!"
@@ -1349,6 +2399,38 @@ mod tests {
         assert_eq!(buffer.move_word_backward(0), 0);
     }
 
+    #[test]
+    fn test_word_bounds() {
+        let mut buffer = BufferInner::new(&[]);
+        buffer.load_str("hello world  test\n");
+
+        assert_eq!(buffer.word_bounds(0), (0, 5));
+        assert_eq!(buffer.word_bounds(3), (0, 5));
+        assert_eq!(buffer.word_bounds(8), (6, 11));
+        assert_eq!(buffer.word_bounds(16), (13, 17));
+        // Whitespace and the end of the buffer select nothing
+        assert_eq!(buffer.word_bounds(12), (12, 12));
+        assert_eq!(buffer.word_bounds(18), (18, 18));
+
+        // The end of the word, or of the next from whitespace, and no further
+        assert_eq!(buffer.word_end(3), 5);
+        assert_eq!(buffer.word_end(5), 11);
+        assert_eq!(buffer.word_end(17), 17);
+    }
+
+    #[test]
+    fn test_indentation_before() {
+        let mut buffer = BufferInner::new(&[]);
+        buffer.load_str("top\n  \tindented text\n");
+
+        // The first line has none
+        assert_eq!(buffer.indentation_before(3), "");
+        // Only the indentation is copied, not the text after it
+        assert_eq!(buffer.indentation_before(12), "  \t");
+        // Breaking the line inside its indentation takes only what's before
+        assert_eq!(buffer.indentation_before(5), " ");
+    }
+
     #[test]
     fn test_paragraph_movement() {
         let mut buffer = BufferInner::new(&[]);
@@ -1402,4 +2484,175 @@ mod tests {
         // From start of first paragraph, should stay at start
         assert_eq!(buffer.move_paragraph_backward(0), 0);
     }
+
+    #[test]
+    fn test_word_count_follows_edits() {
+        let mut buffer = test_buffer();
+        assert_eq!(buffer.word_count(), 3);
+
+        // Each edit is checked against counting from scratch
+        let fresh = |b: &BufferInner| count_words(b.buffer.slice(..));
+
+        // Splitting a word, then joining it back up
+        buffer.insert_pos(" ".to_string(), 2);
+        assert_eq!(buffer.word_count(), 4);
+        buffer.delete_pos(2, 1);
+        assert_eq!(buffer.word_count(), 3);
+
+        // Joining two words across a line break
+        buffer.delete_pos(5, 1);
+        assert_eq!(buffer.word_count(), 2);
+        assert_eq!(buffer.word_count, Some(fresh(&buffer)));
+
+        // New words at either end, and a paste of several
+        buffer.insert_pos("Oh, ".to_string(), 0);
+        buffer.insert_pos(" \tThe end".to_string(), buffer.buffer.len_chars());
+        buffer.insert_pos("so very ".to_string(), 4);
+        assert_eq!(buffer.word_count(), 7);
+        assert_eq!(buffer.word_count, Some(fresh(&buffer)));
+
+        // Deleting a range that takes part of two words with it
+        buffer.delete_range(5, 14);
+        assert_eq!(buffer.word_count(), 5);
+        assert_eq!(buffer.word_count, Some(fresh(&buffer)));
+
+        // Undo and redo go through the same path
+        while buffer.undo().is_some() {
+            assert_eq!(buffer.word_count, Some(fresh(&buffer)));
+        }
+        assert_eq!(buffer.word_count(), 3);
+        buffer.redo();
+        assert_eq!(buffer.word_count, Some(fresh(&buffer)));
+
+        buffer.load_str("");
+        assert_eq!(buffer.word_count(), 0);
+    }
+
+    #[test]
+    fn test_modified_tracks_save_point() {
+        let mut buffer = test_buffer();
+        assert!(!buffer.is_modified());
+
+        // Undoing every edit since loading makes it unmodified again
+        buffer.insert_pos("!".to_string(), 5);
+        buffer.undo_boundary();
+        buffer.delete_pos(0, 1);
+        assert!(buffer.is_modified());
+        buffer.undo();
+        assert!(buffer.is_modified());
+        buffer.undo();
+        assert!(!buffer.is_modified());
+
+        // Saving moves the point it compares against, in both directions
+        buffer.redo();
+        buffer.mark_saved();
+        assert!(!buffer.is_modified());
+        buffer.undo();
+        assert!(buffer.is_modified());
+        buffer.redo();
+        assert!(!buffer.is_modified());
+
+        // A new edit after undoing past the save loses the way back to it
+        buffer.undo();
+        buffer.insert_pos("?".to_string(), 0);
+        buffer.undo();
+        assert!(buffer.is_modified());
+
+        // Loading starts over
+        buffer.load_str("fresh");
+        assert!(!buffer.is_modified());
+
+        // Edits that change nothing don't count, so undo still gets back
+        buffer.insert_pos(String::new(), 0);
+        buffer.delete_pos(2, 0);
+        assert!(!buffer.is_modified());
+        buffer.insert_pos("!".to_string(), 5);
+        buffer.insert_pos(String::new(), 6);
+        buffer.undo();
+        assert!(!buffer.is_modified());
+    }
+
+    #[test]
+    fn test_edit_count_only_grows() {
+        let mut buffer = BufferInner::new(&[]);
+        buffer.load_str("text");
+        let loaded = buffer.edit_count;
+
+        // Undo brings the text back, but not the count
+        buffer.insert_pos("!".to_string(), 4);
+        buffer.undo();
+        assert_eq!(buffer.edit_count, loaded + 2);
+
+        // Edits that change nothing leave it alone
+        buffer.insert_pos(String::new(), 0);
+        assert_eq!(buffer.edit_count, loaded + 2);
+    }
+
+    #[test]
+    fn test_match_bracket() {
+        let mut buffer = BufferInner::new(&[]);
+        buffer.load_str("f(a[0], {b: (c)}) )");
+
+        // Forward and backward, over nested pairs
+        assert_eq!(buffer.match_bracket(1), Some(16));
+        assert_eq!(buffer.match_bracket(16), Some(1));
+        assert_eq!(buffer.match_bracket(8), Some(15));
+        assert_eq!(buffer.match_bracket(3), Some(5));
+        assert_eq!(buffer.match_bracket(0), None);
+        assert_eq!(buffer.match_bracket(18), None);
+
+        // On a bracket, or just after one
+        let found = |bracket, partner| Some(BracketMatch { bracket, partner });
+        assert_eq!(buffer.bracket_match_at(1), found(1, Some(16)));
+        assert_eq!(buffer.bracket_match_at(17), found(16, Some(1)));
+        assert_eq!(buffer.bracket_match_at(19), found(18, None));
+        assert_eq!(buffer.bracket_match_at(10), None);
+
+        // The cached match is dropped on edits
+        assert_eq!(buffer.bracket_match_at(2), found(1, Some(16)));
+        buffer.insert_pos(")".to_string(), 2);
+        assert_eq!(buffer.bracket_match_at(2), found(2, Some(1)));
+    }
+
+    #[test]
+    fn test_sexp_movement() {
+        let mut buffer = BufferInner::new(&[]);
+        buffer.load_str("(let ((x 1)) [x])  word_2 )");
+
+        // Over a whole group, nested ones and all, and back
+        assert_eq!(buffer.sexp_forward(0), Ok(17));
+        assert_eq!(buffer.sexp_backward(17), Ok(0));
+        assert_eq!(buffer.sexp_forward(4), Ok(12));
+        assert_eq!(buffer.sexp_backward(12), Ok(5));
+
+        // Over a word, skipping the whitespace before it
+        assert_eq!(buffer.sexp_forward(17), Ok(25));
+        assert_eq!(buffer.sexp_backward(25), Ok(19));
+        assert_eq!(buffer.sexp_forward(1), Ok(4));
+
+        // A close bracket with nothing open, or a group never closed
+        assert_eq!(buffer.sexp_forward(25), Err(25));
+        assert_eq!(buffer.sexp_backward(27), Err(0));
+        buffer.load_str("a (b (c)");
+        assert_eq!(buffer.sexp_forward(1), Err(8));
+    }
+
+    #[test]
+    fn test_buffer_locals() {
+        let first = Buffer::new(&[]);
+        let second = Buffer::new(&[]);
+        assert_eq!(first.get_local("fill-column"), None);
+
+        first.set_local("fill-column", "72");
+        first.set_local("fill-column", "80");
+        second.set_local("fill-column", "100");
+        assert_eq!(first.get_local("fill-column"), Some("80".to_string()));
+        assert_eq!(second.get_local("fill-column"), Some("100".to_string()));
+
+        // Clones share their buffer's variables
+        let clone = first.clone();
+        clone.clear_locals();
+        assert_eq!(first.get_local("fill-column"), None);
+        assert_eq!(second.get_local("fill-column"), Some("100".to_string()));
+    }
 }
@@ -11,10 +11,13 @@
 // this program. If not, see <https://www.gnu.org/licenses/>.
 //
 
-use crate::buffer::Buffer;
+use crate::buffer::{Buffer, READ_ONLY_MESSAGE};
+use crate::fuzzy::COMPLETION_MATCH_FACE;
 use crate::keys::KeyAction;
 use crate::mode::{ActionPosition, Mode, ModeAction, ModeResult};
 use crate::renderer::DirtyRegion;
+use crate::syntax::HighlightSpan;
+use crate::tabify;
 use crate::{BufferId, ModeId};
 use tokio::sync::{mpsc, oneshot};
 
@@ -30,6 +33,11 @@ pub enum ModeMessage {
         event: crate::mode::MouseEvent,
         reply: oneshot::Sender<ModeResult>,
     },
+    /// The window was resized to fit this many list rows
+    Resize {
+        list_rows: usize,
+        reply: oneshot::Sender<ModeResult>,
+    },
 }
 
 /// Persistent mode actor that runs in its own task
@@ -72,6 +80,11 @@ impl ModeActor {
                         let _ = reply.send(result);
                         continue;
                     }
+                    ModeMessage::Resize { list_rows, reply } => {
+                        let result = self.mode_impl.resize(list_rows);
+                        let _ = reply.send(result);
+                        continue;
+                    }
                 };
             }
         })
@@ -130,6 +143,24 @@ impl ModeClient {
             .await
             .map_err(|_| format!("Mode {} reply failed", self.name))
     }
+
+    /// Tell the mode its window now fits `list_rows` list rows and wait for response
+    pub async fn resize(&self, list_rows: usize) -> Result<ModeResult, String> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let message = ModeMessage::Resize {
+            list_rows,
+            reply: reply_tx,
+        };
+
+        self.sender
+            .send(message)
+            .await
+            .map_err(|_| format!("Mode {} disconnected", self.name))?;
+
+        reply_rx
+            .await
+            .map_err(|_| format!("Mode {} reply failed", self.name))
+    }
 }
 
 /// Request sent to BufferHost
@@ -145,6 +176,8 @@ pub enum BufferRequest {
         event: crate::mode::MouseEvent,
         cursor_pos: usize,
     },
+    /// Tell the mode chain the window now fits this many list rows
+    Resize { list_rows: usize, cursor_pos: usize },
     /// Get current buffer state
     GetState,
     /// Save buffer to file
@@ -160,12 +193,33 @@ pub enum EditorAction {
     ExecuteCommand(String),
     /// Switch to a specific buffer
     SwitchToBuffer(crate::BufferId),
+    /// Create an empty buffer with this name and switch to it
+    CreateBuffer(String),
+    /// Insert a character into the window the insert-char prompt was opened from
+    InsertChar(char),
     /// Kill a specific buffer
     KillBuffer(crate::BufferId),
-    /// Open a file at a path with specified open type
+    /// Kill (or keep) a modified buffer, as answered at the confirmation prompt
+    ConfirmKillBuffer(crate::BufferId, crate::confirm_mode::KillConfirmation),
+    /// Save (or skip) a modified buffer, as answered at the save-some-buffers prompt
+    ConfirmSaveBuffer(crate::BufferId, crate::confirm_mode::SaveConfirmation),
+    /// Revert (or keep) a modified buffer whose file changed on disk, as answered
+    /// at the prompt
+    ConfirmRevertBuffer(crate::BufferId, crate::confirm_mode::RevertConfirmation),
+    /// Go to a line or character in the window the goto prompt was opened from
+    Goto(crate::goto_mode::GotoTarget, usize),
+    /// Bind a key sequence to an action, as entered at the bind-key prompt
+    BindKey(String, String),
+    /// Remove a key sequence's binding, as entered at the unbind-key prompt
+    UnbindKey(String),
+    /// Run a command again with the answer typed at its prompt added
+    PromptAnswered(crate::prompt_mode::PromptContinuation),
+    /// Open a file at a path with specified open type, putting the cursor at
+    /// a line and column if given
     OpenFile {
         path: std::path::PathBuf,
         open_type: crate::editor::OpenType,
+        position: Option<crate::file_location::FilePosition>,
     },
     /// Kill line (to kill-ring)
     KillLine,
@@ -186,6 +240,15 @@ pub enum EditorAction {
         position: crate::mode::ActionPosition,
         index: usize,
     },
+    /// Insert a kill-ring entry into the window browse-kill-ring was opened from
+    InsertKill(usize),
+    /// Reload the buffer revert-buffer-with-encoding was run in, in this encoding
+    RevertWithEncoding(crate::encoding::Encoding),
+    /// Open a bookmark's file at its line in the window the list was opened from
+    JumpToBookmark(String),
+    /// Open a recent file in the window open-recent was run in, forgetting it
+    /// if it's gone
+    OpenRecentFile(std::path::PathBuf),
     /// Update isearch highlights and cursor in target buffer/window
     UpdateIsearch {
         target_buffer_id: crate::BufferId,
@@ -204,6 +267,10 @@ pub enum EditorAction {
         target_window_id: crate::WindowId,
         original_cursor: usize,
     },
+    /// Show a preview of the highlighted menu entry in the command window
+    PreviewSelection(Option<crate::menu_preview::PreviewSource>),
+    /// Show a message in the echo area
+    Echo(String),
 }
 
 /// Represents a buffer content change for after-change hooks
@@ -300,6 +367,32 @@ impl BufferHostClient {
             .await
             .map_err(|_| "BufferHost reply failed".to_string())
     }
+
+    /// Tell the buffer's modes its window now fits `list_rows` list rows and wait for response
+    pub async fn resize(
+        &self,
+        list_rows: usize,
+        cursor_pos: usize,
+    ) -> Result<BufferResponse, String> {
+        let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+
+        let message = BufferMessage {
+            request: BufferRequest::Resize {
+                list_rows,
+                cursor_pos,
+            },
+            reply: reply_tx,
+        };
+
+        self.sender
+            .send(message)
+            .await
+            .map_err(|_| "BufferHost disconnected".to_string())?;
+
+        reply_rx
+            .await
+            .map_err(|_| "BufferHost reply failed".to_string())
+    }
 }
 
 /// The BufferHost that processes requests and coordinates with mode actors
@@ -380,6 +473,10 @@ impl BufferHost {
             BufferRequest::HandleMouse { event, cursor_pos } => {
                 self.handle_mouse_action(event, cursor_pos).await
             }
+            BufferRequest::Resize {
+                list_rows,
+                cursor_pos,
+            } => self.handle_resize(list_rows, cursor_pos).await,
             BufferRequest::GetState => self.get_state(),
             BufferRequest::Save => self.save_buffer().await,
             BufferRequest::Load(file_path) => self.load_buffer(file_path).await,
@@ -463,6 +560,24 @@ impl BufferHost {
         self.execute_actions(actions_to_execute, cursor_pos).await
     }
 
+    /// Let the first mode that cares redraw for a new window size
+    async fn handle_resize(&mut self, list_rows: usize, cursor_pos: usize) -> BufferResponse {
+        let mut actions_to_execute = vec![];
+
+        for mode_client in &self.mode_clients {
+            match mode_client.resize(list_rows).await {
+                Ok(ModeResult::Consumed(actions)) => {
+                    actions_to_execute.extend(actions);
+                    break;
+                }
+                Ok(ModeResult::Annotated(actions)) => actions_to_execute.extend(actions),
+                Ok(ModeResult::Ignored) | Err(_) => {}
+            }
+        }
+
+        self.execute_actions(actions_to_execute, cursor_pos).await
+    }
+
     /// Execute mode actions and return dirty regions
     async fn execute_actions(
         &mut self,
@@ -476,6 +591,14 @@ impl BufferHost {
 
         for action in actions {
             match action {
+                ModeAction::InsertText(..)
+                | ModeAction::NewlineAndIndent
+                | ModeAction::DeleteText(..)
+                | ModeAction::ClearText
+                    if self.buffer.is_read_only() =>
+                {
+                    editor_action = Some(EditorAction::Echo(READ_ONLY_MESSAGE.to_string()));
+                }
                 ModeAction::InsertText(pos, text) => {
                     match pos {
                         ActionPosition::Cursor => {
@@ -548,6 +671,46 @@ impl BufferHost {
                         }
                     }
                 }
+                ModeAction::NewlineAndIndent => {
+                    let mut indent = self.buffer.indentation_before(cursor_pos);
+
+                    // The major mode may know better, given the text as it will
+                    // be once the line is broken. While a background command
+                    // has the runtime, the previous line's indentation does.
+                    if let (Some(runtime), Some(major_mode)) = (
+                        self.julia_runtime
+                            .as_ref()
+                            .and_then(|julia_runtime| julia_runtime.try_lock().ok()),
+                        self.buffer.major_mode(),
+                    ) {
+                        let mut code = self.buffer.content();
+                        let byte_pos = code
+                            .char_indices()
+                            .nth(cursor_pos)
+                            .map_or(code.len(), |(i, _)| i);
+                        code.insert(byte_pos, '\n');
+                        // 1-based, and one past the line being broken
+                        let new_line = self.buffer.buffer_char_to_line(cursor_pos) + 2;
+                        let mode_indent = runtime
+                            .major_mode_indent_line(&major_mode, code, new_line)
+                            .await;
+                        if let Ok(Some(mode_indent)) = mode_indent {
+                            indent = mode_indent;
+                        }
+                    }
+                    if self.buffer.indent_tabs() {
+                        indent = tabify::retab_line(&indent, self.buffer.tab_width(), true);
+                    }
+
+                    let text = format!("\n{indent}");
+                    self.buffer.insert_pos(text.clone(), cursor_pos);
+                    buffer_changed = true;
+                    cursor_pos += text.chars().count();
+                    new_cursor_pos = Some(cursor_pos);
+                    dirty_regions.push(DirtyRegion::Buffer {
+                        buffer_id: self.buffer_id,
+                    });
+                }
                 ModeAction::DeleteText(pos, count) => {
                     match pos {
                         ActionPosition::Cursor => {
@@ -665,13 +828,57 @@ impl BufferHost {
                     // Store buffer switch for execution at Editor level
                     editor_action = Some(EditorAction::SwitchToBuffer(buffer_id));
                 }
+                ModeAction::CreateBuffer(name) => {
+                    // Store buffer creation for execution at Editor level
+                    editor_action = Some(EditorAction::CreateBuffer(name));
+                }
+                ModeAction::InsertChar(ch) => {
+                    // Store the character for insertion at Editor level
+                    editor_action = Some(EditorAction::InsertChar(ch));
+                }
                 ModeAction::KillBuffer(buffer_id) => {
                     // Store buffer kill for execution at Editor level
                     editor_action = Some(EditorAction::KillBuffer(buffer_id));
                 }
-                ModeAction::OpenFile { path, open_type } => {
+                ModeAction::ConfirmKillBuffer(buffer_id, confirmation) => {
+                    // Store the answer for execution at Editor level
+                    editor_action = Some(EditorAction::ConfirmKillBuffer(buffer_id, confirmation));
+                }
+                ModeAction::ConfirmSaveBuffer(buffer_id, confirmation) => {
+                    // Store the answer for execution at Editor level
+                    editor_action = Some(EditorAction::ConfirmSaveBuffer(buffer_id, confirmation));
+                }
+                ModeAction::ConfirmRevertBuffer(buffer_id, confirmation) => {
+                    // Store the answer for execution at Editor level
+                    editor_action =
+                        Some(EditorAction::ConfirmRevertBuffer(buffer_id, confirmation));
+                }
+                ModeAction::Goto(target, number) => {
+                    // Store the destination for execution at Editor level
+                    editor_action = Some(EditorAction::Goto(target, number));
+                }
+                ModeAction::BindKey(key_sequence, action) => {
+                    // Bindings belong to the Editor
+                    editor_action = Some(EditorAction::BindKey(key_sequence, action));
+                }
+                ModeAction::UnbindKey(key_sequence) => {
+                    editor_action = Some(EditorAction::UnbindKey(key_sequence));
+                }
+                ModeAction::PromptAnswered(continuation) => {
+                    // The command to resume is run by the Editor
+                    editor_action = Some(EditorAction::PromptAnswered(continuation));
+                }
+                ModeAction::OpenFile {
+                    path,
+                    open_type,
+                    position,
+                } => {
                     // Store file open for execution at Editor level
-                    editor_action = Some(EditorAction::OpenFile { path, open_type });
+                    editor_action = Some(EditorAction::OpenFile {
+                        path,
+                        open_type,
+                        position,
+                    });
                 }
                 ModeAction::KillLine => {
                     // Kill from cursor to end of line (store in kill-ring - will be handled at Editor level)
@@ -709,27 +916,27 @@ impl BufferHost {
                         index,
                     });
                 }
+                ModeAction::InsertKill(index) => {
+                    // Store the pick for execution at Editor level
+                    editor_action = Some(EditorAction::InsertKill(index));
+                }
+                ModeAction::RevertWithEncoding(encoding) => {
+                    // Store the encoding for execution at Editor level
+                    editor_action = Some(EditorAction::RevertWithEncoding(encoding));
+                }
+                ModeAction::JumpToBookmark(name) => {
+                    // Store the pick for execution at Editor level
+                    editor_action = Some(EditorAction::JumpToBookmark(name));
+                }
+                ModeAction::OpenRecentFile(path) => {
+                    // Store the pick for execution at Editor level
+                    editor_action = Some(EditorAction::OpenRecentFile(path));
+                }
                 ModeAction::MoveCursor(row, col) => {
-                    // Convert window coordinates to buffer position
-                    let line = row as usize;
-                    let column = col as usize;
-
-                    // Make sure we don't go past the end of the buffer
-                    let buffer_lines = self.buffer.buffer_len_lines();
-                    let target_line = line.min(buffer_lines.saturating_sub(1));
-
-                    // Get line start and make sure column is within line bounds
-                    let line_start = self.buffer.buffer_line_to_char(target_line);
-                    let line_len = if target_line < buffer_lines {
-                        self.buffer.buffer_line(target_line).len().saturating_sub(1)
-                    // -1 for newline
-                    } else {
-                        0
-                    };
-                    let target_column = column.min(line_len);
-
-                    let new_pos = line_start + target_column;
-                    new_cursor_pos = Some(new_pos);
+                    // Window coordinates to a buffer position, with tabs taking
+                    // the cells they're drawn in; past the end of a line or the
+                    // buffer lands at the end
+                    new_cursor_pos = Some(self.buffer.to_char_index(col, row));
 
                     dirty_regions.push(DirtyRegion::Buffer {
                         buffer_id: self.buffer_id,
@@ -737,14 +944,16 @@ impl BufferHost {
                 }
                 ModeAction::EvalJulia(expression) => {
                     if let Some(ref julia_runtime) = self.julia_runtime {
-                        let result = {
-                            let runtime = julia_runtime.lock().await;
-                            runtime.eval_expression(&expression).await
+                        // A background command has the runtime until it finishes
+                        let result = match julia_runtime.try_lock() {
+                            Ok(runtime) => Some(runtime.eval_expression(&expression).await),
+                            Err(_) => None,
                         };
 
                         let formatted_output = match result {
-                            Ok(output) => format!("{output}\njulia> "),
-                            Err(e) => format!("Error: {e}\njulia> "),
+                            Some(Ok(output)) => format!("{output}\njulia> "),
+                            Some(Err(e)) => format!("Error: {e}\njulia> "),
+                            None => "Error: Julia is busy running a command\njulia> ".to_string(),
                         };
 
                         let buffer_len = self.buffer.buffer_len_chars();
@@ -797,6 +1006,27 @@ impl BufferHost {
                         original_cursor,
                     });
                 }
+                ModeAction::PreviewSelection(source) => {
+                    // Menu previews are drawn by the renderers from Editor state
+                    editor_action = Some(EditorAction::PreviewSelection(source));
+                }
+                ModeAction::HighlightMatches(ranges) => {
+                    let face_id = crate::julia_runtime::face_registry()
+                        .lock()
+                        .ok()
+                        .and_then(|registry| registry.get_id(COMPLETION_MATCH_FACE));
+                    if let Some(face_id) = face_id {
+                        self.buffer.add_spans(
+                            ranges
+                                .into_iter()
+                                .map(|(start, end)| HighlightSpan::new(start, end, face_id))
+                                .collect(),
+                        );
+                        dirty_regions.push(DirtyRegion::Buffer {
+                            buffer_id: self.buffer_id,
+                        });
+                    }
+                }
                 _ => {}
             }
         }
@@ -839,9 +1069,12 @@ impl BufferHost {
     async fn save_buffer(&self) -> BufferResponse {
         let file_path = self.buffer.object();
 
-        let content = self.buffer.with_read(|b| b.buffer.to_string());
+        let content = match self.buffer.file_content() {
+            Ok(content) => content,
+            Err(e) => return BufferResponse::Error(format!("Save failed: {e}")),
+        };
 
-        match tokio::fs::write(&file_path, content.as_bytes()).await {
+        match tokio::fs::write(&file_path, content).await {
             Ok(()) => BufferResponse::Saved(file_path),
             Err(e) => BufferResponse::Error(format!("Save failed: {e}")),
         }
@@ -857,7 +1090,12 @@ impl BufferHost {
                         inner.object = new_inner.object.clone();
                         inner.modes = new_inner.modes.clone();
                         inner.buffer = new_inner.buffer.clone();
+                        inner.edit_count += 1;
                         inner.mark = new_inner.mark;
+                        inner.line_ending = new_inner.line_ending;
+                        inner.mixed_line_endings = new_inner.mixed_line_endings;
+                        inner.saved_line_ending = new_inner.saved_line_ending;
+                        inner.encoding = new_inner.encoding;
                     });
                 });
                 BufferResponse::Loaded(file_path)
@@ -11,9 +11,11 @@
 // this program. If not, see <https://www.gnu.org/licenses/>.
 //
 
+use crate::fuzzy::{fuzzy_match, fuzzy_match_positions};
 use crate::keys::KeyAction;
+use crate::menu_preview::PreviewSource;
 use crate::mode::{Mode, ModeAction, ModeResult};
-use crate::selection_menu::{MenuItem, SelectionMenu};
+use crate::selection_menu::{MenuItem, SelectionMenu, DEFAULT_MAX_VISIBLE_ITEMS};
 use crate::BufferId;
 
 /// Purpose of the buffer selection mode
@@ -36,6 +38,18 @@ impl MenuItem for BufferItem {
     fn display_text(&self) -> String {
         self.name.clone()
     }
+
+    fn matches_filter(&self, filter: &str) -> bool {
+        fuzzy_match(filter, &self.name).is_some()
+    }
+
+    fn match_score(&self, filter: &str) -> Option<i64> {
+        fuzzy_match(filter, &self.name)
+    }
+
+    fn matched_chars(&self, filter: &str) -> Vec<usize> {
+        fuzzy_match_positions(filter, &self.name).map_or_else(Vec::new, |m| m.positions)
+    }
 }
 
 /// Interactive buffer switching mode
@@ -53,7 +67,7 @@ impl BufferSwitchMode {
     /// Create a new BufferSwitchMode with initial state
     pub fn new() -> Self {
         Self {
-            menu: SelectionMenu::new(8), // Show 8 buffers at once
+            menu: SelectionMenu::new(DEFAULT_MAX_VISIBLE_ITEMS),
             buffer_id: None,
             purpose: BufferSwitchPurpose::Switch, // Default to switch
         }
@@ -62,12 +76,17 @@ impl BufferSwitchMode {
     /// Create a new BufferSwitchMode with specific purpose
     pub fn new_with_purpose(purpose: BufferSwitchPurpose) -> Self {
         Self {
-            menu: SelectionMenu::new(8), // Show 8 buffers at once
+            menu: SelectionMenu::new(DEFAULT_MAX_VISIBLE_ITEMS),
             buffer_id: None,
             purpose,
         }
     }
 
+    /// Set how many buffers are listed at once
+    pub fn set_max_visible_items(&mut self, max_visible_items: usize) {
+        self.menu.set_max_visible_items(max_visible_items);
+    }
+
     /// Initialize with buffer and buffer list
     pub fn init_with_buffer(&mut self, buffer_id: BufferId, buffer_list: Vec<(BufferId, String)>) {
         self.buffer_id = Some(buffer_id);
@@ -111,7 +130,7 @@ impl BufferSwitchMode {
             .iter()
             .position(|item| item.buffer_id == current_buffer_id)
         {
-            self.menu.selected_index = index;
+            self.menu.select_index(index);
         }
     }
 
@@ -124,6 +143,17 @@ impl BufferSwitchMode {
     pub fn get_selected_buffer(&self) -> Option<BufferId> {
         self.menu.get_selected_item().map(|item| item.buffer_id)
     }
+
+    /// What to preview beside the list for the selected buffer
+    pub fn selected_preview(&self) -> Option<PreviewSource> {
+        self.get_selected_buffer().map(PreviewSource::Buffer)
+    }
+
+    fn update_actions(&self) -> Vec<ModeAction> {
+        let mut actions = self.menu.generate_update_actions(None);
+        actions.push(ModeAction::PreviewSelection(self.selected_preview()));
+        actions
+    }
 }
 
 impl Default for BufferSwitchMode {
@@ -141,7 +171,7 @@ impl Mode for BufferSwitchMode {
     fn perform(&mut self, action: &KeyAction) -> ModeResult {
         // Try to handle with the generic menu first
         if self.menu.handle_key_action(action) {
-            return ModeResult::Consumed(self.menu.generate_update_actions(None));
+            return ModeResult::Consumed(self.update_actions());
         }
 
         // Handle buffer switch mode specific actions
@@ -154,6 +184,10 @@ impl Mode for BufferSwitchMode {
                         BufferSwitchPurpose::Kill => ModeAction::KillBuffer(buffer_id),
                     };
                     ModeResult::Consumed(vec![action])
+                } else if self.purpose == BufferSwitchPurpose::Switch && !self.menu.input.is_empty()
+                {
+                    // Nothing matches the typed name: create it, like Emacs
+                    ModeResult::Consumed(vec![ModeAction::CreateBuffer(self.menu.input.clone())])
                 } else {
                     ModeResult::Ignored
                 }
@@ -165,4 +199,45 @@ impl Mode for BufferSwitchMode {
             _ => ModeResult::Ignored,
         }
     }
+
+    fn resize(&mut self, list_rows: usize) -> ModeResult {
+        self.set_max_visible_items(list_rows);
+        ModeResult::Consumed(self.update_actions())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_buffer_matching() {
+        let mut mode = BufferSwitchMode::new();
+        let names = ["*scratch*", "abs.rs", "buffer_switch_mode.rs", "Cargo.toml"];
+        mode.init_with_buffer(
+            BufferId::default(),
+            names
+                .iter()
+                .map(|name| (BufferId::default(), name.to_string()))
+                .collect(),
+        );
+
+        for c in "bsm".chars() {
+            mode.perform(&KeyAction::AlphaNumeric(c));
+        }
+        let content = mode.generate_buffer_content();
+        assert_eq!(content, "bsm\n> buffer_switch_mode.rs\n");
+
+        let ModeResult::Consumed(actions) = mode.perform(&KeyAction::Backspace) else {
+            panic!("Expected backspace to be consumed");
+        };
+        // "bs" also matches "abs.rs", but the "b" starting a word counts for
+        // more than the "s" following it
+        assert!(mode
+            .generate_buffer_content()
+            .ends_with("> buffer_switch_mode.rs\n  abs.rs\n"));
+        assert!(actions.iter().any(
+            |action| matches!(action, ModeAction::HighlightMatches(ranges) if ranges[0] == (5, 6))
+        ));
+    }
 }
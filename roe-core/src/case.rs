@@ -0,0 +1,81 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Case conversion for `upcase-region`, `downcase-region` and
+//! `capitalize-region`, and the word commands `M-u`, `M-l` and `M-c`.
+//!
+//! Conversions go by Unicode's case mappings, which may change how many
+//! characters there are: `ß` upcases to `SS`.
+
+/// A change of case to make to some text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaseConversion {
+    Upcase,
+    Downcase,
+    /// Upper case for the first letter of each word, lower for the rest
+    Capitalize,
+}
+
+impl CaseConversion {
+    /// `text` in the new case
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            CaseConversion::Upcase => text.to_uppercase(),
+            CaseConversion::Downcase => text.to_lowercase(),
+            CaseConversion::Capitalize => capitalize(text),
+        }
+    }
+}
+
+/// `text` with each word's first letter or digit in upper case and the rest
+/// in lower. Words are runs of non-whitespace, so the `t` of `don't` and the
+/// `b` of `foo-bar` stay lower case, but the `f` of `(foo` is the first letter.
+fn capitalize(text: &str) -> String {
+    let mut capitalized = String::with_capacity(text.len());
+    let mut in_word = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            in_word = false;
+            capitalized.push(c);
+        } else if !in_word && c.is_alphanumeric() {
+            in_word = true;
+            capitalized.extend(c.to_uppercase());
+        } else {
+            capitalized.extend(c.to_lowercase());
+        }
+    }
+    capitalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case_conversions() {
+        let text = "hELLo (wORLD) don't foo-BAR straße";
+        assert_eq!(
+            CaseConversion::Upcase.apply(text),
+            "HELLO (WORLD) DON'T FOO-BAR STRASSE"
+        );
+        assert_eq!(
+            CaseConversion::Downcase.apply(text),
+            "hello (world) don't foo-bar straße"
+        );
+        assert_eq!(
+            CaseConversion::Capitalize.apply(text),
+            "Hello (World) Don't Foo-bar Straße"
+        );
+        assert_eq!(CaseConversion::Capitalize.apply("élan\tÉTÉ"), "Élan\tÉté");
+    }
+}
@@ -0,0 +1,102 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! The clipboard the kill ring shares text with.
+//!
+//! The kill ring works on its own; a frontend gives it a [`Clipboard`] to copy
+//! kills out to other programs and yank their copies in. With the
+//! `system-clipboard` feature, [`SystemClipboard`] is the operating system's.
+
+/// Somewhere text can be copied to and pasted from outside the editor
+pub trait Clipboard {
+    /// The clipboard's text, if it has any
+    fn get(&self) -> Option<String>;
+
+    /// Replace the clipboard's contents with `text`
+    fn set(&mut self, text: &str);
+}
+
+#[cfg(feature = "system-clipboard")]
+pub use system::SystemClipboard;
+
+#[cfg(feature = "system-clipboard")]
+mod system {
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::Clipboard;
+
+    /// Longest a yank waits for the clipboard's text before going on without it
+    const GET_TIMEOUT: Duration = Duration::from_millis(250);
+
+    enum Request {
+        Get(mpsc::Sender<Option<String>>),
+        Set(String),
+    }
+
+    /// The operating system clipboard.
+    ///
+    /// Reading it can block until the program owning the selection answers
+    /// (notably on X11), so it's driven from a thread of its own: setting never
+    /// waits, and getting gives up after [`GET_TIMEOUT`]. The editor thread, and
+    /// the async tasks sharing it, are never held up by the clipboard.
+    pub struct SystemClipboard {
+        requests: mpsc::Sender<Request>,
+    }
+
+    impl SystemClipboard {
+        /// Connect to the system clipboard. None if there isn't one to talk to,
+        /// e.g. with no display.
+        pub fn new() -> Option<Self> {
+            let (requests, incoming) = mpsc::channel();
+            let (ready, connected) = mpsc::channel();
+            thread::Builder::new()
+                .name("clipboard".to_string())
+                .spawn(move || {
+                    let Ok(mut clipboard) = arboard::Clipboard::new() else {
+                        let _ = ready.send(false);
+                        return;
+                    };
+                    let _ = ready.send(true);
+                    for request in incoming {
+                        match request {
+                            Request::Get(reply) => {
+                                let _ = reply.send(clipboard.get_text().ok());
+                            }
+                            Request::Set(text) => {
+                                let _ = clipboard.set_text(text);
+                            }
+                        }
+                    }
+                })
+                .ok()?;
+            connected
+                .recv()
+                .unwrap_or(false)
+                .then_some(Self { requests })
+        }
+    }
+
+    impl Clipboard for SystemClipboard {
+        fn get(&self) -> Option<String> {
+            let (reply, answer) = mpsc::channel();
+            self.requests.send(Request::Get(reply)).ok()?;
+            answer.recv_timeout(GET_TIMEOUT).ok().flatten()
+        }
+
+        fn set(&mut self, text: &str) {
+            let _ = self.requests.send(Request::Set(text.to_string()));
+        }
+    }
+}
@@ -13,7 +13,9 @@
 
 use crate::command_registry::{CommandContext, CommandRegistry, CMD_COMMAND_MODE};
 use crate::editor::ChromeAction;
+use crate::fuzzy::{fuzzy_match_positions, fuzzy_rank, highlight_ranges};
 use crate::keys::KeyAction;
+use crate::minibuffer_history::HistoryRecall;
 use crate::mode::{ActionPosition, Mode, ModeAction, ModeResult};
 use crate::BufferId;
 
@@ -34,6 +36,8 @@ pub struct CommandMode {
     pub buffer_id: Option<BufferId>,
     /// All available commands (unfiltered)
     all_commands: Vec<String>,
+    /// Commands run before, for recalling with Up and Down
+    history: HistoryRecall,
 }
 
 impl CommandMode {
@@ -49,6 +53,7 @@ impl CommandMode {
             completion_scroll_offset: 0,
             buffer_id: None,
             all_commands: Vec::new(),
+            history: HistoryRecall::default(),
         }
     }
 
@@ -65,17 +70,71 @@ impl CommandMode {
         self.update_scroll_to_center();
     }
 
+    /// Commands run before, most recent first, for Up to recall
+    pub fn set_history(&mut self, history: Vec<String>) {
+        self.history = HistoryRecall::new(history);
+    }
+
+    /// Up: recall an older command from the history when already recalling
+    /// or at the top of the list, otherwise move the selection up, wrapping
+    fn select_previous(&mut self) {
+        if self.history.is_recalling() || self.selected_index == 0 {
+            if let Some(entry) = self.history.older(&self.input) {
+                self.input = entry.to_string();
+                self.update_matches_internal();
+                return;
+            }
+            if self.history.is_recalling() {
+                // Already at the oldest
+                return;
+            }
+        }
+        if !self.matches.is_empty() {
+            if self.selected_index > 0 {
+                self.selected_index -= 1;
+            } else {
+                // Wrap to bottom
+                self.selected_index = self.matches.len() - 1;
+            }
+            self.update_scroll_to_center();
+        }
+    }
+
+    /// Down: recall a newer command, or what was typed, when recalling,
+    /// otherwise move the selection down, wrapping
+    fn select_next(&mut self) {
+        if let Some(entry) = self.history.newer() {
+            self.input = entry.to_string();
+            self.update_matches_internal();
+            return;
+        }
+        if !self.matches.is_empty() {
+            if self.selected_index < self.matches.len() - 1 {
+                self.selected_index += 1;
+            } else {
+                // Wrap to top
+                self.selected_index = 0;
+            }
+            self.update_scroll_to_center();
+        }
+    }
+
+    /// Change how many completions are shown at once, keeping the selection visible
+    pub fn set_max_visible_completions(&mut self, max_visible_completions: usize) {
+        self.max_visible_completions = max_visible_completions.max(1);
+        self.update_scroll_to_center();
+    }
+
     /// Update matches based on current input using stored command list
     fn update_matches_internal(&mut self) {
         self.matches = if self.input.is_empty() {
             // Show all commands if no input
             self.all_commands.clone()
         } else {
-            // Filter by prefix
-            self.all_commands
-                .iter()
-                .filter(|cmd| cmd.to_lowercase().starts_with(&self.input.to_lowercase()))
-                .cloned()
+            // Fuzzy match, best first
+            fuzzy_rank(&self.input, &self.all_commands)
+                .into_iter()
+                .map(|i| self.all_commands[i].clone())
                 .collect()
         };
 
@@ -89,28 +148,25 @@ impl CommandMode {
 
     /// Update matches based on current input
     pub fn update_matches(&mut self, registry: &CommandRegistry) {
-        let mut commands: Vec<String> = if self.input.is_empty() {
-            // Show all commands if no input
-            registry
-                .all_commands()
-                .iter()
-                .filter(|cmd| cmd.name != CMD_COMMAND_MODE) // Exclude command-mode from palette
-                .map(|cmd| cmd.name.clone())
-                .collect()
+        let mut commands: Vec<String> = registry
+            .all_commands()
+            .iter()
+            .filter(|cmd| cmd.name != CMD_COMMAND_MODE) // Exclude command-mode from palette
+            .map(|cmd| cmd.name.clone())
+            .collect();
+
+        // Sort alphabetically, then fuzzy match best first, ties staying in
+        // that order
+        commands.sort();
+        self.matches = if self.input.is_empty() {
+            commands
         } else {
-            // Filter by prefix
-            registry
-                .find_commands(&self.input)
-                .iter()
-                .filter(|cmd| cmd.name != CMD_COMMAND_MODE) // Exclude command-mode from palette
-                .map(|cmd| cmd.name.clone())
+            fuzzy_rank(&self.input, &commands)
+                .into_iter()
+                .map(|i| commands[i].clone())
                 .collect()
         };
 
-        // Sort alphabetically
-        commands.sort();
-        self.matches = commands;
-
         // Reset selection to first match
         self.selected_index = 0;
         self.completion_scroll_offset = 0;
@@ -128,7 +184,14 @@ impl CommandMode {
 
     /// Generate buffer content string
     pub fn generate_buffer_content(&self) -> String {
+        self.buffer_content_and_highlights().0
+    }
+
+    /// The buffer content, and the byte ranges in it of the characters of
+    /// each completion the input matched
+    fn buffer_content_and_highlights(&self) -> (String, Vec<(usize, usize)>) {
         let mut content = String::new();
+        let mut highlights = Vec::new();
 
         // Completion lines with highlighting
         let visible_completions = self.visible_completions();
@@ -145,10 +208,18 @@ impl CommandMode {
             let is_selected = self.visible_selection_index() == Some(idx);
             if is_selected {
                 // Mark selected item with arrow or highlighting
-                content.push_str(&format!("> {completion}"));
+                content.push_str("> ");
             } else {
-                content.push_str(&format!("  {completion}"));
+                content.push_str("  ");
+            }
+            if let Some(matched) = fuzzy_match_positions(&self.input, completion) {
+                highlights.extend(highlight_ranges(
+                    completion,
+                    &matched.positions,
+                    content.chars().count(),
+                ));
             }
+            content.push_str(completion);
 
             // Add newline except for the last item
             if idx < visible_completions.len() - 1 {
@@ -156,7 +227,20 @@ impl CommandMode {
             }
         }
 
-        content
+        (content, highlights)
+    }
+
+    /// Actions redrawing the buffer with the current input and completions
+    fn update_actions(&self) -> Vec<ModeAction> {
+        let (content, highlights) = self.buffer_content_and_highlights();
+        let mut actions = vec![
+            ModeAction::ClearText,
+            ModeAction::InsertText(ActionPosition::start(), content),
+        ];
+        if !highlights.is_empty() {
+            actions.push(ModeAction::HighlightMatches(highlights));
+        }
+        actions
     }
 
     /// Handle a key action in command mode
@@ -168,6 +252,7 @@ impl CommandMode {
         match action {
             KeyAction::AlphaNumeric(c) => {
                 // Add character to input
+                self.history.reset();
                 self.input.push(c);
                 self.update_matches_internal();
                 CommandModeResult::Continue
@@ -175,39 +260,23 @@ impl CommandMode {
             KeyAction::Backspace => {
                 // Remove last character
                 if !self.input.is_empty() {
+                    self.history.reset();
                     self.input.pop();
                     self.update_matches_internal();
                 }
                 CommandModeResult::Continue
             }
             KeyAction::Cursor(crate::keys::CursorDirection::Up) => {
-                // Move selection up with wrapping
-                if !self.matches.is_empty() {
-                    if self.selected_index > 0 {
-                        self.selected_index -= 1;
-                    } else {
-                        // Wrap to bottom
-                        self.selected_index = self.matches.len() - 1;
-                    }
-                    self.update_scroll_to_center();
-                }
+                self.select_previous();
                 CommandModeResult::Continue
             }
             KeyAction::Cursor(crate::keys::CursorDirection::Down) => {
-                // Move selection down with wrapping
-                if !self.matches.is_empty() {
-                    if self.selected_index < self.matches.len() - 1 {
-                        self.selected_index += 1;
-                    } else {
-                        // Wrap to top
-                        self.selected_index = 0;
-                    }
-                    self.update_scroll_to_center();
-                }
+                self.select_next();
                 CommandModeResult::Continue
             }
             KeyAction::Tab => {
                 // Tab completion - complete to longest common prefix
+                self.history.reset();
                 self.complete_to_common_prefix();
                 self.update_matches_internal();
                 CommandModeResult::Continue
@@ -348,70 +417,37 @@ impl Mode for CommandMode {
         // Handle command mode specific actions
         match action {
             KeyAction::AlphaNumeric(c) => {
+                self.history.reset();
                 self.input.push(*c);
                 self.update_matches_internal();
                 // Clear buffer and replace with new content
-                ModeResult::Consumed(vec![
-                    ModeAction::ClearText,
-                    ModeAction::InsertText(ActionPosition::start(), self.generate_buffer_content()),
-                ])
+                ModeResult::Consumed(self.update_actions())
             }
             KeyAction::Backspace => {
                 if !self.input.is_empty() {
+                    self.history.reset();
                     self.input.pop();
                     self.update_matches_internal();
-                    ModeResult::Consumed(vec![
-                        ModeAction::ClearText,
-                        ModeAction::InsertText(
-                            ActionPosition::start(),
-                            self.generate_buffer_content(),
-                        ),
-                    ])
+                    ModeResult::Consumed(self.update_actions())
                 } else {
                     ModeResult::Ignored
                 }
             }
             KeyAction::Cursor(crate::keys::CursorDirection::Up) => {
-                // Move selection up with wrapping
-                if !self.matches.is_empty() {
-                    if self.selected_index > 0 {
-                        self.selected_index -= 1;
-                    } else {
-                        // Wrap to bottom
-                        self.selected_index = self.matches.len() - 1;
-                    }
-                    self.update_scroll_to_center();
-                }
+                self.select_previous();
                 // Always consume arrow keys in command mode, even if we can't move
-                ModeResult::Consumed(vec![
-                    ModeAction::ClearText,
-                    ModeAction::InsertText(ActionPosition::start(), self.generate_buffer_content()),
-                ])
+                ModeResult::Consumed(self.update_actions())
             }
             KeyAction::Cursor(crate::keys::CursorDirection::Down) => {
-                // Move selection down with wrapping
-                if !self.matches.is_empty() {
-                    if self.selected_index < self.matches.len() - 1 {
-                        self.selected_index += 1;
-                    } else {
-                        // Wrap to top
-                        self.selected_index = 0;
-                    }
-                    self.update_scroll_to_center();
-                }
+                self.select_next();
                 // Always consume arrow keys in command mode, even if we can't move
-                ModeResult::Consumed(vec![
-                    ModeAction::ClearText,
-                    ModeAction::InsertText(ActionPosition::start(), self.generate_buffer_content()),
-                ])
+                ModeResult::Consumed(self.update_actions())
             }
             KeyAction::Tab => {
+                self.history.reset();
                 self.complete_to_common_prefix();
                 self.update_matches_internal();
-                ModeResult::Consumed(vec![
-                    ModeAction::ClearText,
-                    ModeAction::InsertText(ActionPosition::start(), self.generate_buffer_content()),
-                ])
+                ModeResult::Consumed(self.update_actions())
             }
             KeyAction::Enter => {
                 // Execute the selected command by returning a special action
@@ -429,6 +465,11 @@ impl Mode for CommandMode {
             _ => ModeResult::Ignored,
         }
     }
+
+    fn resize(&mut self, list_rows: usize) -> ModeResult {
+        self.set_max_visible_completions(list_rows);
+        ModeResult::Consumed(self.update_actions())
+    }
 }
 
 #[cfg(test)]
@@ -479,6 +520,89 @@ mod tests {
         assert_eq!(cmd_mode.selected_index, initial_selection);
     }
 
+    #[test]
+    fn test_fuzzy_matching() {
+        let registry = create_default_registry();
+        let mut cmd_mode = CommandMode::new();
+        cmd_mode.input = "stb".to_string();
+        cmd_mode.update_matches(&registry);
+        assert!(cmd_mode.matches.iter().any(|m| m == "switch-to-buffer"));
+
+        let mut cmd_mode = CommandMode::new();
+        cmd_mode.init_with_buffer(
+            BufferId::default(),
+            [
+                "isearch-forward",
+                "save-buffer",
+                "set-buffer-file-coding",
+                "switch-to-buffer",
+            ]
+            .map(String::from)
+            .to_vec(),
+        );
+        cmd_mode.perform(&KeyAction::AlphaNumeric('s'));
+        let ModeResult::Consumed(actions) = cmd_mode.perform(&KeyAction::AlphaNumeric('b')) else {
+            panic!("Expected the key to be consumed");
+        };
+        // Best match first: shorter gaps before the "b" score higher
+        assert_eq!(
+            cmd_mode.matches,
+            vec!["set-buffer-file-coding", "save-buffer", "switch-to-buffer"]
+        );
+
+        // The matched characters are highlighted, after the "sb\n" input
+        // line and the "> " marking the selection
+        let Some(ModeAction::HighlightMatches(ranges)) = actions.last() else {
+            panic!("Expected highlights for the matches");
+        };
+        assert_eq!(&ranges[..2], &[(5, 6), (9, 10)]);
+    }
+
+    #[test]
+    fn test_history_recall() {
+        use crate::keys::CursorDirection;
+        use crate::minibuffer_history::{HistoryKind, MinibufferHistory};
+
+        // Run save-buffer, then goto-line
+        let mut history = MinibufferHistory::default();
+        history.add(HistoryKind::Command, "save-buffer");
+        history.add(HistoryKind::Command, "goto-line");
+
+        let mut cmd_mode = CommandMode::new();
+        cmd_mode.init_with_buffer(
+            BufferId::default(),
+            ["goto-line", "quit", "save-buffer"]
+                .map(String::from)
+                .to_vec(),
+        );
+        cmd_mode.set_history(history.entries(HistoryKind::Command));
+
+        // Up from the top of the list recalls them, most recent first
+        let up = KeyAction::Cursor(CursorDirection::Up);
+        let down = KeyAction::Cursor(CursorDirection::Down);
+        cmd_mode.perform(&up);
+        assert_eq!(cmd_mode.input, "goto-line");
+        assert_eq!(
+            cmd_mode.get_selected_command().as_deref(),
+            Some("goto-line")
+        );
+        cmd_mode.perform(&up);
+        assert_eq!(cmd_mode.input, "save-buffer");
+        cmd_mode.perform(&up);
+        assert_eq!(cmd_mode.input, "save-buffer");
+
+        // Down goes back, then to the empty input and the whole list
+        cmd_mode.perform(&down);
+        assert_eq!(cmd_mode.input, "goto-line");
+        cmd_mode.perform(&down);
+        assert_eq!(cmd_mode.input, "");
+        assert_eq!(cmd_mode.matches.len(), 3);
+
+        // Then the arrows move through the list again
+        cmd_mode.perform(&down);
+        assert_eq!(cmd_mode.selected_index, 1);
+    }
+
     #[test]
     fn test_execution() {
         let registry = create_default_registry();
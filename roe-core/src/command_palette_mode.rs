@@ -0,0 +1,396 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! `command-palette` (`C-S-p`): one prompt over open buffers, the files under
+//! the working directory, and commands.
+//!
+//! With no input everything is listed. Plain input narrows the list to buffers
+//! and files; input starting with `>` narrows it to commands instead. Matching
+//! is fuzzy: the typed characters must appear in order, but not necessarily
+//! together, so `edrs` finds `src/editor.rs`.
+//!
+//! Files are listed by walking the working directory on a background thread,
+//! so the palette opens at once and gains them when the walk is done.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+
+use crate::editor::OpenType;
+use crate::fuzzy::{fuzzy_match, fuzzy_match_positions};
+use crate::keys::KeyAction;
+use crate::menu_preview::PreviewSource;
+use crate::mode::{Mode, ModeAction, ModeResult};
+use crate::selection_menu::{MenuItem, SelectionMenu, DEFAULT_MAX_VISIBLE_ITEMS};
+use crate::BufferId;
+
+/// Header line of the prompt
+const PROMPT: &str = "Go to buffer or file (> for commands):";
+
+/// Header line while the files are still being listed
+const LOADING_PROMPT: &str = "Go to buffer or file (> for commands, listing files...):";
+
+/// Input starting with this lists commands rather than buffers and files
+pub const COMMAND_PREFIX: char = '>';
+
+/// Most files listed from the working directory, so a palette opened in a
+/// huge tree still comes up promptly
+pub const MAX_PALETTE_FILES: usize = 5000;
+
+/// Directories never walked for files
+const SKIPPED_DIRS: &[&str] = &["target", "node_modules"];
+
+/// Where a palette entry came from, and what choosing it does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaletteSource {
+    /// Switch to an open buffer
+    Buffer(BufferId),
+    /// Open a file
+    File(PathBuf),
+    /// Run a command by name
+    Command,
+}
+
+/// An entry in the command palette
+#[derive(Clone)]
+pub struct PaletteItem {
+    /// Buffer name, file path relative to the working directory, or command name
+    pub label: String,
+    pub source: PaletteSource,
+}
+
+impl PaletteItem {
+    fn kind(&self) -> &'static str {
+        match self.source {
+            PaletteSource::Buffer(_) => "buffer",
+            PaletteSource::File(_) => "file",
+            PaletteSource::Command => "command",
+        }
+    }
+
+    /// The pattern to match the label against: commands match only after
+    /// `>`, everything else only without it. Whitespace in it is ignored.
+    fn pattern(&self, filter: &str) -> Option<String> {
+        let is_command = self.source == PaletteSource::Command;
+        let pattern = match filter.strip_prefix(COMMAND_PREFIX) {
+            Some(rest) if is_command => rest,
+            None if !is_command => filter,
+            _ => return None,
+        };
+        Some(pattern.chars().filter(|c| !c.is_whitespace()).collect())
+    }
+}
+
+impl MenuItem for PaletteItem {
+    fn display_text(&self) -> String {
+        format!("{}  ({})", self.label, self.kind())
+    }
+
+    fn matches_filter(&self, filter: &str) -> bool {
+        self.match_score(filter).is_some()
+    }
+
+    fn match_score(&self, filter: &str) -> Option<i64> {
+        fuzzy_match(&self.pattern(filter)?, &self.label)
+    }
+
+    fn matched_chars(&self, filter: &str) -> Vec<usize> {
+        self.pattern(filter)
+            .and_then(|pattern| fuzzy_match_positions(&pattern, &self.label))
+            .map_or_else(Vec::new, |m| m.positions)
+    }
+}
+
+/// Files under `root`, as paths relative to it, skipping hidden entries and
+/// build output. Stops after `limit` files.
+pub fn project_files(root: &Path, limit: usize) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut entries: Vec<_> = entries.flatten().collect();
+        entries.sort_by_key(|entry| entry.file_name());
+        // Popped from the end, so push subdirectories in reverse to walk them in order
+        for entry in entries.iter().rev() {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with('.') || SKIPPED_DIRS.contains(&name.as_ref()) {
+                continue;
+            }
+            if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+                dirs.push(entry.path());
+            }
+        }
+        for entry in entries {
+            if files.len() >= limit {
+                return files;
+            }
+            let name = entry.file_name();
+            if name.to_string_lossy().starts_with('.') {
+                continue;
+            }
+            if entry.file_type().is_ok_and(|file_type| file_type.is_file()) {
+                let path = entry.path();
+                files.push(path.strip_prefix(root).unwrap_or(&path).to_path_buf());
+            }
+        }
+    }
+    files
+}
+
+/// Prompt over buffers, files and commands
+pub struct CommandPaletteMode {
+    /// Selection menu over every source
+    menu: SelectionMenu<PaletteItem>,
+    /// Buffer ID this mode is managing
+    pub buffer_id: Option<BufferId>,
+    /// Buffers and commands, which the files go between once listed
+    items: Vec<PaletteItem>,
+    /// Directory the files are listed from
+    root: PathBuf,
+    /// The walk listing the files, until it's been collected
+    files: Option<JoinHandle<Vec<PathBuf>>>,
+}
+
+impl CommandPaletteMode {
+    pub fn new() -> Self {
+        Self {
+            menu: SelectionMenu::new(DEFAULT_MAX_VISIBLE_ITEMS),
+            buffer_id: None,
+            items: Vec::new(),
+            root: PathBuf::new(),
+            files: None,
+        }
+    }
+
+    /// Set how many entries are listed at once
+    pub fn set_max_visible_items(&mut self, max_visible_items: usize) {
+        self.menu.set_max_visible_items(max_visible_items);
+    }
+
+    /// Initialize with the buffer and every entry: buffers first, then files
+    /// under `root` (once they've been listed), then commands
+    pub fn init_with_buffer(
+        &mut self,
+        buffer_id: BufferId,
+        buffers: Vec<(BufferId, String)>,
+        root: &Path,
+        commands: Vec<String>,
+    ) {
+        self.buffer_id = Some(buffer_id);
+        let buffers = buffers.into_iter().map(|(id, name)| PaletteItem {
+            label: name,
+            source: PaletteSource::Buffer(id),
+        });
+        let commands = commands.into_iter().map(|name| PaletteItem {
+            label: name,
+            source: PaletteSource::Command,
+        });
+        self.items = buffers.chain(commands).collect();
+        self.menu.init_with_items(self.items.clone());
+
+        self.root = root.to_path_buf();
+        let root = self.root.clone();
+        self.files = Some(std::thread::spawn(move || {
+            project_files(&root, MAX_PALETTE_FILES)
+        }));
+    }
+
+    /// List the files once the walk is done, keeping the input
+    fn load_files(&mut self) {
+        if !self.files.as_ref().is_some_and(JoinHandle::is_finished) {
+            return;
+        }
+        let files = self.files.take().and_then(|walk| walk.join().ok());
+        let files = files
+            .unwrap_or_default()
+            .into_iter()
+            .map(|path| PaletteItem {
+                label: path.display().to_string(),
+                source: PaletteSource::File(self.root.join(path)),
+            });
+        let commands_at = self
+            .items
+            .iter()
+            .position(|item| item.source == PaletteSource::Command)
+            .unwrap_or(self.items.len());
+        let mut items = self.items.clone();
+        items.splice(commands_at..commands_at, files);
+        self.menu.set_items(items);
+    }
+
+    fn header(&self) -> &'static str {
+        if self.files.is_some() {
+            LOADING_PROMPT
+        } else {
+            PROMPT
+        }
+    }
+
+    /// Generate buffer content string
+    pub fn generate_buffer_content(&self) -> String {
+        self.menu.generate_buffer_content(Some(self.header()))
+    }
+
+    /// What to preview beside the list for the selected entry
+    pub fn selected_preview(&self) -> Option<PreviewSource> {
+        match &self.menu.get_selected_item()?.source {
+            PaletteSource::Buffer(id) => Some(PreviewSource::Buffer(*id)),
+            PaletteSource::File(path) => Some(PreviewSource::File(path.clone())),
+            PaletteSource::Command => None,
+        }
+    }
+
+    fn update_actions(&self) -> Vec<ModeAction> {
+        let mut actions = self.menu.generate_update_actions(Some(self.header()));
+        actions.push(ModeAction::PreviewSelection(self.selected_preview()));
+        actions
+    }
+}
+
+impl Default for CommandPaletteMode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Mode for CommandPaletteMode {
+    fn name(&self) -> &str {
+        "command-palette"
+    }
+
+    fn perform(&mut self, action: &KeyAction) -> ModeResult {
+        self.load_files();
+        if self.menu.handle_key_action(action) {
+            return ModeResult::Consumed(self.update_actions());
+        }
+
+        match action {
+            KeyAction::Enter => {
+                let Some(item) = self.menu.get_selected_item() else {
+                    return ModeResult::Ignored;
+                };
+                let action = match &item.source {
+                    PaletteSource::Buffer(id) => ModeAction::SwitchToBuffer(*id),
+                    PaletteSource::File(path) => ModeAction::OpenFile {
+                        path: path.clone(),
+                        open_type: OpenType::New,
+                        position: None,
+                    },
+                    PaletteSource::Command => ModeAction::ExecuteCommand(item.label.clone()),
+                };
+                ModeResult::Consumed(vec![action])
+            }
+            _ => ModeResult::Ignored,
+        }
+    }
+
+    fn resize(&mut self, list_rows: usize) -> ModeResult {
+        self.set_max_visible_items(list_rows);
+        ModeResult::Consumed(self.update_actions())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_input(mode: &mut CommandPaletteMode, input: &str) {
+        for c in input.chars() {
+            mode.perform(&KeyAction::AlphaNumeric(c));
+        }
+    }
+
+    fn enter(mode: &mut CommandPaletteMode) -> Vec<ModeAction> {
+        match mode.perform(&KeyAction::Enter) {
+            ModeResult::Consumed(actions) => actions,
+            _ => vec![],
+        }
+    }
+
+    #[test]
+    fn test_matches_filter() {
+        let file = PaletteItem {
+            label: "src/editor.rs".to_string(),
+            source: PaletteSource::File(PathBuf::from("src/editor.rs")),
+        };
+        let command = PaletteItem {
+            label: "save-buffer".to_string(),
+            source: PaletteSource::Command,
+        };
+        assert!(file.matches_filter("edrs"));
+        assert!(!file.matches_filter(">edrs"));
+        assert!(command.matches_filter(">SvBf"));
+        assert!(command.matches_filter("> save buf"));
+        assert!(!command.matches_filter(">bs"));
+        assert!(!command.matches_filter("save"));
+        assert!(command.matches_filter(">"));
+        assert_eq!(command.matched_chars("> sb"), vec![0, 5]);
+    }
+
+    #[test]
+    fn test_prefix_scopes_sources() {
+        let root = std::env::temp_dir().join(format!("roe-palette-{}", std::process::id()));
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join("src/save_file.rs"), "").unwrap();
+        fs::write(root.join(".git/config"), "").unwrap();
+
+        let buffer_id = BufferId::default();
+        let new_mode = || {
+            let mut mode = CommandPaletteMode::new();
+            mode.init_with_buffer(
+                buffer_id,
+                vec![(buffer_id, "*scratch*".to_string())],
+                &root,
+                vec!["save-buffer".to_string()],
+            );
+            // Let the walk finish so typing lists the files
+            while !mode.files.as_ref().unwrap().is_finished() {
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+            mode
+        };
+
+        // Plain input finds buffers and files but not commands
+        let mut mode = new_mode();
+        type_input(&mut mode, "scr");
+        assert!(
+            matches!(enter(&mut mode)[..], [ModeAction::SwitchToBuffer(id)] if id == buffer_id)
+        );
+
+        let mut mode = new_mode();
+        type_input(&mut mode, "save");
+        assert!(matches!(
+            &enter(&mut mode)[..],
+            [ModeAction::OpenFile { path, .. }] if *path == root.join("src/save_file.rs")
+        ));
+
+        // > switches to commands
+        let mut mode = new_mode();
+        type_input(&mut mode, "> save");
+        assert!(matches!(
+            &enter(&mut mode)[..],
+            [ModeAction::ExecuteCommand(name)] if name == "save-buffer"
+        ));
+
+        // Hidden directories aren't walked
+        assert_eq!(
+            project_files(&root, 10),
+            vec![PathBuf::from("src/save_file.rs")]
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
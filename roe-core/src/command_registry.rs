@@ -11,7 +11,16 @@
 // this program. If not, see <https://www.gnu.org/licenses/>.
 //
 
-use crate::editor::{BufferOperation, ChromeAction, OpenType};
+use crate::bind_key_mode::BindKeyOperation;
+use crate::bookmark_mode::BookmarkOperation;
+use crate::case::CaseConversion;
+use crate::conflict::ConflictSide;
+use crate::editor::{BufferOperation, ChromeAction, CopyName, OpenType};
+use crate::goto_mode::GotoTarget;
+use crate::prompt_mode::PromptContinuation;
+use crate::registers::RegisterCommand;
+use crate::renderer::DirtyRegion;
+use crate::window::WrapMode;
 use crate::{Buffer, BufferId, WindowId};
 use std::future::Future;
 use std::pin::Pin;
@@ -19,6 +28,7 @@ use std::pin::Pin;
 // Command name constants
 pub const CMD_COMMAND_MODE: &str = "command-mode";
 pub const CMD_FIND_FILE: &str = "find-file";
+pub const CMD_OPEN_RECENT: &str = "open-recent";
 pub const CMD_SAVE_BUFFER: &str = "save-buffer";
 pub const CMD_QUIT: &str = "quit";
 pub const CMD_EXIT: &str = "exit";
@@ -30,9 +40,16 @@ pub const CMD_OTHER_WINDOW: &str = "other-window";
 pub const CMD_SPLIT_BELOW: &str = "split-window-below";
 pub const CMD_SPLIT_RIGHT: &str = "split-window-right";
 pub const CMD_DESCRIBE_BUFFER: &str = "describe-buffer";
+pub const CMD_COUNT_WORDS: &str = "count-words";
 pub const CMD_DESCRIBE_MODE: &str = "describe-mode";
 pub const CMD_SWITCH_BUFFER: &str = "switch-to-buffer";
 pub const CMD_KILL_BUFFER: &str = "kill-buffer";
+pub const CMD_NEXT_BUFFER: &str = "next-buffer";
+pub const CMD_PREVIOUS_BUFFER: &str = "previous-buffer";
+pub const CMD_GOTO_MATCHING_PAREN: &str = "goto-matching-paren";
+pub const CMD_BOOKMARK_SET: &str = "bookmark-set";
+pub const CMD_BOOKMARK_JUMP: &str = "bookmark-jump";
+pub const CMD_BOOKMARK_LIST: &str = "bookmark-list";
 pub const CMD_VISIT_FILE: &str = "visit-file";
 pub const CMD_MESSAGES: &str = "messages";
 pub const CMD_SHOW_MESSAGES: &str = "show-messages";
@@ -41,6 +58,74 @@ pub const CMD_JULIA_REPL: &str = "julia-repl";
 pub const CMD_DUMP_MESSAGES: &str = "dump-messages";
 pub const CMD_ISEARCH_FORWARD: &str = "isearch-forward";
 pub const CMD_ISEARCH_BACKWARD: &str = "isearch-backward";
+pub const CMD_RELOAD_CONFIG: &str = "reload-config";
+pub const CMD_VIEW_JULIA_ERRORS: &str = "view-julia-errors";
+pub const CMD_SHOW_JULIA_ERRORS: &str = "show-julia-errors";
+pub const CMD_NEXT_CONFLICT: &str = "next-conflict";
+pub const CMD_PREVIOUS_CONFLICT: &str = "previous-conflict";
+pub const CMD_KEEP_OURS: &str = "conflict-keep-ours";
+pub const CMD_KEEP_THEIRS: &str = "conflict-keep-theirs";
+pub const CMD_TOGGLE_MOUSE: &str = "toggle-mouse";
+pub const CMD_DESCRIBE_BINDING_CONFLICTS: &str = "describe-binding-conflicts";
+pub const CMD_QUOTED_INSERT: &str = "quoted-insert";
+pub const CMD_INSERT_CHAR: &str = "insert-char";
+pub const CMD_COPY_FILE_PATH: &str = "copy-file-path";
+pub const CMD_COPY_FILE_NAME: &str = "copy-file-name";
+pub const CMD_COPY_BUFFER_NAME: &str = "copy-buffer-name";
+pub const CMD_COMMAND_PALETTE: &str = "command-palette";
+pub const CMD_TOGGLE_TRUNCATE_LINES: &str = "toggle-truncate-lines";
+pub const CMD_VISUAL_LINE_MODE: &str = "visual-line-mode";
+pub const CMD_GOTO_LINE: &str = "goto-line";
+pub const CMD_GOTO_CHAR: &str = "goto-char";
+pub const CMD_COPY_TO_REGISTER: &str = "copy-to-register";
+pub const CMD_INSERT_REGISTER: &str = "insert-register";
+pub const CMD_POINT_TO_REGISTER: &str = "point-to-register";
+pub const CMD_JUMP_TO_REGISTER: &str = "jump-to-register";
+pub const CMD_COMMENT_REGION: &str = "comment-region";
+pub const CMD_UNCOMMENT_REGION: &str = "uncomment-region";
+pub const CMD_TABIFY: &str = "tabify";
+pub const CMD_UNTABIFY: &str = "untabify";
+pub const CMD_DELETE_TRAILING_WHITESPACE: &str = "delete-trailing-whitespace";
+pub const CMD_JOIN_LINE: &str = "join-line";
+pub const CMD_DUPLICATE_LINE: &str = "duplicate-line";
+pub const CMD_UPCASE_REGION: &str = "upcase-region";
+pub const CMD_DOWNCASE_REGION: &str = "downcase-region";
+pub const CMD_CAPITALIZE_REGION: &str = "capitalize-region";
+pub const CMD_UPCASE_WORD: &str = "upcase-word";
+pub const CMD_DOWNCASE_WORD: &str = "downcase-word";
+pub const CMD_CAPITALIZE_WORD: &str = "capitalize-word";
+pub const CMD_FILL_PARAGRAPH: &str = "fill-paragraph";
+pub const CMD_SET_FILL_COLUMN: &str = "set-fill-column";
+pub const CMD_KILL_RECTANGLE: &str = "kill-rectangle";
+pub const CMD_YANK_RECTANGLE: &str = "yank-rectangle";
+pub const CMD_YANK_POP: &str = "yank-pop";
+pub const CMD_BROWSE_KILL_RING: &str = "browse-kill-ring";
+pub const CMD_NARROW_TO_REGION: &str = "narrow-to-region";
+pub const CMD_WIDEN: &str = "widen";
+pub const CMD_SAVE_ALL_BUFFERS: &str = "save-all-buffers";
+pub const CMD_SAVE_SOME_BUFFERS: &str = "save-some-buffers";
+pub const CMD_WRITE_FILE: &str = "write-file";
+pub const CMD_TOGGLE_READ_ONLY: &str = "toggle-read-only";
+pub const CMD_SET_BUFFER_FILE_EOL: &str = "set-buffer-file-eol";
+pub const CMD_REVERT_BUFFER_WITH_ENCODING: &str = "revert-buffer-with-encoding";
+pub const CMD_WINNER_UNDO: &str = "winner-undo";
+pub const CMD_WINNER_REDO: &str = "winner-redo";
+pub const CMD_BALANCE_WINDOWS: &str = "balance-windows";
+pub const CMD_ENLARGE_WINDOW: &str = "enlarge-window";
+pub const CMD_SHRINK_WINDOW: &str = "shrink-window";
+pub const CMD_ENLARGE_WINDOW_HORIZONTALLY: &str = "enlarge-window-horizontally";
+pub const CMD_SHRINK_WINDOW_HORIZONTALLY: &str = "shrink-window-horizontally";
+pub const CMD_BIND_KEY: &str = "bind-key";
+pub const CMD_UNBIND_KEY: &str = "unbind-key";
+
+/// Commands a prefix argument runs that many times, rather than just being
+/// used up
+pub const REPEATABLE_COMMANDS: &[&str] = &[
+    CMD_ENLARGE_WINDOW,
+    CMD_SHRINK_WINDOW,
+    CMD_ENLARGE_WINDOW_HORIZONTALLY,
+    CMD_SHRINK_WINDOW_HORIZONTALLY,
+];
 
 /// Context information passed to commands when they execute
 #[derive(Clone)]
@@ -61,6 +146,9 @@ pub struct CommandContext {
     pub current_line: u16,
     /// Current column number (1-based for display)
     pub current_column: u16,
+    /// Answers typed at the prompts the command asked for with
+    /// `ChromeAction::Prompt`, first first; empty when it's first run
+    pub prompt_answers: Vec<String>,
 }
 
 /// Category of command for organization and filtering
@@ -201,6 +289,13 @@ pub fn create_default_registry() -> CommandRegistry {
         sync_handler(|_context| Ok(vec![ChromeAction::OpenFile(OpenType::New)])),
     ));
 
+    registry.register_command(Command::new(
+        CMD_OPEN_RECENT,
+        "Open a file opened recently, in this session or an earlier one",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::OpenRecent])),
+    ));
+
     registry.register_command(Command::new(
         CMD_SAVE_BUFFER,
         "Save current buffer to file",
@@ -208,6 +303,33 @@ pub fn create_default_registry() -> CommandRegistry {
         sync_handler(|_context| Ok(vec![ChromeAction::Save])),
     ));
 
+    registry.register_command(Command::new(
+        CMD_SAVE_ALL_BUFFERS,
+        "Save every modified buffer visiting a file",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::SaveAllBuffers])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_SAVE_SOME_BUFFERS,
+        "Ask whether to save each modified buffer visiting a file",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::SaveSomeBuffers])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_WRITE_FILE,
+        "Save current buffer to a file named at the prompt, and visit it",
+        CommandCategory::Global,
+        sync_handler(|context| match context.prompt_answers.first() {
+            Some(path) if !path.is_empty() => Ok(vec![ChromeAction::WriteFile(path.clone())]),
+            _ => Ok(vec![ChromeAction::Prompt {
+                message: "Write file: ".to_string(),
+                continuation: PromptContinuation::new(CMD_WRITE_FILE),
+            }]),
+        }),
+    ));
+
     registry.register_command(Command::new(
         CMD_VISIT_FILE,
         "Visit file, replacing current buffer",
@@ -273,17 +395,77 @@ pub fn create_default_registry() -> CommandRegistry {
         sync_handler(|_context| Ok(vec![ChromeAction::SwitchWindow])),
     ));
 
-    // Alternative command names (common aliases)
+    registry.register_command(Command::new(
+        CMD_BALANCE_WINDOWS,
+        "Make all windows the same size",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::BalanceWindows])),
+    ));
+
+    for (name, description, horizontally, steps) in [
+        (
+            CMD_ENLARGE_WINDOW,
+            "Make the current window taller",
+            false,
+            1,
+        ),
+        (
+            CMD_SHRINK_WINDOW,
+            "Make the current window shorter",
+            false,
+            -1,
+        ),
+        (
+            CMD_ENLARGE_WINDOW_HORIZONTALLY,
+            "Make the current window wider",
+            true,
+            1,
+        ),
+        (
+            CMD_SHRINK_WINDOW_HORIZONTALLY,
+            "Make the current window narrower",
+            true,
+            -1,
+        ),
+    ] {
+        registry.register_command(Command::new(
+            name,
+            description,
+            CommandCategory::Global,
+            sync_handler(move |_context| {
+                Ok(vec![ChromeAction::ResizeWindow {
+                    horizontally,
+                    steps,
+                }])
+            }),
+        ));
+    }
+
+    registry.register_command(Command::new(
+        CMD_WINNER_UNDO,
+        "Restore the window layout before the last split or delete",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::WinnerUndo])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_WINNER_REDO,
+        "Restore the window layout the last winner-undo left",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::WinnerRedo])),
+    ));
+
+    // Emacs names for the splits, naming where the new window goes
     registry.register_command(Command::new(
         CMD_SPLIT_BELOW,
-        "Split current window horizontally (alias)",
+        "Split the current window, the new window below; stay in the current one",
         CommandCategory::Global,
         sync_handler(|_context| Ok(vec![ChromeAction::SplitHorizontal])),
     ));
 
     registry.register_command(Command::new(
         CMD_SPLIT_RIGHT,
-        "Split current window vertically (alias)",
+        "Split the current window, the new window on the right; stay in the current one",
         CommandCategory::Global,
         sync_handler(|_context| Ok(vec![ChromeAction::SplitVertical])),
     ));
@@ -302,6 +484,26 @@ pub fn create_default_registry() -> CommandRegistry {
         }),
     ));
 
+    registry.register_command(Command::new(
+        CMD_COUNT_WORDS,
+        "Count the lines, words, characters and bytes in the region, or the buffer",
+        CommandCategory::Global,
+        sync_handler(|context| {
+            let buffer = &context.buffer;
+            let (what, (start, end)) = match buffer.get_region(context.cursor_pos) {
+                Some(region) => ("Region", region),
+                // Only the accessible part of a narrowed buffer, as Emacs counts
+                None => (
+                    "Buffer",
+                    buffer.narrowing().unwrap_or((0, buffer.buffer_len_chars())),
+                ),
+            };
+            Ok(vec![ChromeAction::Echo(
+                buffer.text_stats(start, end).describe(what),
+            )])
+        }),
+    ));
+
     registry.register_command(Command::new(
         CMD_DESCRIBE_MODE,
         "Show information about current major mode",
@@ -313,6 +515,27 @@ pub fn create_default_registry() -> CommandRegistry {
         }),
     ));
 
+    registry.register_command(Command::new(
+        CMD_DESCRIBE_BINDING_CONFLICTS,
+        "List key sequences bound more than once and what they run",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::DescribeBindingConflicts])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_BIND_KEY,
+        "Bind a key sequence to a command until the config is reloaded",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::BindKey(BindKeyOperation::Bind)])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_UNBIND_KEY,
+        "Remove a key sequence's binding until the config is reloaded",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::BindKey(BindKeyOperation::Unbind)])),
+    ));
+
     // Buffer commands
     registry.register_command(Command::new(
         CMD_SWITCH_BUFFER,
@@ -328,6 +551,69 @@ pub fn create_default_registry() -> CommandRegistry {
         sync_handler(|_context| Ok(vec![ChromeAction::KillBuffer])),
     ));
 
+    registry.register_command(Command::new(
+        CMD_NEXT_BUFFER,
+        "Show the next buffer in this window, most recently used first",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::NextBuffer])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_PREVIOUS_BUFFER,
+        "Show the previous buffer in this window",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::PreviousBuffer])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_GOTO_MATCHING_PAREN,
+        "Move to the bracket pairing with the one at or before the cursor",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::GotoMatchingParen])),
+    ));
+
+    // Bookmarks
+    registry.register_command(Command::new(
+        CMD_BOOKMARK_SET,
+        "Bookmark the cursor's place in the file under a name",
+        CommandCategory::Global,
+        sync_handler(|context| {
+            let file = std::path::Path::new(&context.buffer_name);
+            if !file.is_file() {
+                return Ok(vec![ChromeAction::Echo(
+                    "Buffer is not visiting a file".to_string(),
+                )]);
+            }
+            // Named after the file unless another name is typed
+            let default_name = file
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            match context.prompt_answers.first().map(|name| name.trim()) {
+                Some("") => Ok(vec![ChromeAction::SetBookmark(default_name)]),
+                Some(name) => Ok(vec![ChromeAction::SetBookmark(name.to_string())]),
+                None => Ok(vec![ChromeAction::Prompt {
+                    message: format!("Set bookmark (default {default_name}): "),
+                    continuation: PromptContinuation::new(CMD_BOOKMARK_SET),
+                }]),
+            }
+        }),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_BOOKMARK_JUMP,
+        "Go to a bookmark, opening its file if need be",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::Bookmark(BookmarkOperation::Jump)])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_BOOKMARK_LIST,
+        "List the bookmarks and where they point",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::Bookmark(BookmarkOperation::List)])),
+    ));
+
     registry.register_command(Command::new(
         CMD_MESSAGES,
         "Switch to Messages buffer",
@@ -369,6 +655,13 @@ pub fn create_default_registry() -> CommandRegistry {
         sync_handler(|_context| Ok(vec![ChromeAction::FileWatcherStatus])),
     ));
 
+    registry.register_command(Command::new(
+        CMD_TOGGLE_MOUSE,
+        "Toggle mouse capture, freeing the terminal's own text selection",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::ToggleMouse])),
+    ));
+
     // Julia commands
     registry.register_command(Command::new(
         CMD_JULIA_REPL,
@@ -383,6 +676,330 @@ pub fn create_default_registry() -> CommandRegistry {
         }),
     ));
 
+    registry.register_command(Command::new(
+        CMD_RELOAD_CONFIG,
+        "Reload the Roe Julia module and user config",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::ReloadConfig])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_QUOTED_INSERT,
+        "Insert the next key literally, or a character by its octal or hex code",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::QuotedInsert])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_INSERT_CHAR,
+        "Insert a character by its Unicode name or code point",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::InsertChar])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_GOTO_LINE,
+        "Go to a line, by number, in the current buffer",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::Goto(GotoTarget::Line)])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_GOTO_CHAR,
+        "Go to a character position, by number, in the current buffer",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::Goto(GotoTarget::Char)])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_COPY_TO_REGISTER,
+        "Copy the region's text to a register",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::ReadRegister(RegisterCommand::CopyTo)])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_INSERT_REGISTER,
+        "Insert the text saved in a register",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::ReadRegister(RegisterCommand::Insert)])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_POINT_TO_REGISTER,
+        "Save the cursor's position in a register",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::ReadRegister(RegisterCommand::PointTo)])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_JUMP_TO_REGISTER,
+        "Go to the position saved in a register",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::ReadRegister(RegisterCommand::JumpTo)])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_COPY_FILE_PATH,
+        "Copy the absolute path of the current buffer's file",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::CopyName(CopyName::FilePath)])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_COPY_FILE_NAME,
+        "Copy the name of the current buffer's file, without its directory",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::CopyName(CopyName::FileName)])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_COPY_BUFFER_NAME,
+        "Copy the name of the current buffer",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::CopyName(CopyName::BufferName)])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_COMMAND_PALETTE,
+        "Go to a buffer or file, or run a command, from one list",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::CommandPalette])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_TOGGLE_TRUNCATE_LINES,
+        "Toggle between truncating and wrapping long lines in this buffer",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::ToggleWrap(WrapMode::Char)])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_VISUAL_LINE_MODE,
+        "Toggle wrapping long lines at word boundaries in this buffer",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::ToggleWrap(WrapMode::Word)])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_VIEW_JULIA_ERRORS,
+        "Show the most recent Julia error and its backtrace",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::ShowJuliaErrors])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_SHOW_JULIA_ERRORS,
+        "Show the most recent Julia error and its backtrace (alias)",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::ShowJuliaErrors])),
+    ));
+
+    // Merge conflict commands
+    registry.register_command(Command::new(
+        CMD_NEXT_CONFLICT,
+        "Move to the next merge conflict",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::NextConflict])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_PREVIOUS_CONFLICT,
+        "Move to the previous merge conflict",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::PreviousConflict])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_KEEP_OURS,
+        "Resolve the conflict at point by keeping our side",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::ResolveConflict(ConflictSide::Ours)])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_KEEP_THEIRS,
+        "Resolve the conflict at point by keeping their side",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::ResolveConflict(ConflictSide::Theirs)])),
+    ));
+
+    // Comment commands
+    registry.register_command(Command::new(
+        CMD_COMMENT_REGION,
+        "Comment out the lines of the region, or uncomment them if they all are",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::CommentRegion])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_UNCOMMENT_REGION,
+        "Uncomment the lines of the region",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::UncommentRegion])),
+    ));
+
+    // Indentation commands
+    registry.register_command(Command::new(
+        CMD_TABIFY,
+        "Indent the lines of the region, or the buffer, with tabs",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::Tabify])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_UNTABIFY,
+        "Indent the lines of the region, or the buffer, with spaces",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::Untabify])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_DELETE_TRAILING_WHITESPACE,
+        "Delete trailing spaces and tabs, and blank lines at the end of the buffer",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::DeleteTrailingWhitespace])),
+    ));
+
+    // Line commands
+    registry.register_command(Command::new(
+        CMD_JOIN_LINE,
+        "Join this line to the previous one",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::JoinLine])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_DUPLICATE_LINE,
+        "Copy this line, or the lines of the region, in below it",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::DuplicateLine])),
+    ));
+
+    // Case commands
+    registry.register_command(Command::new(
+        CMD_UPCASE_REGION,
+        "Convert the region to upper case",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::CaseRegion(CaseConversion::Upcase)])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_DOWNCASE_REGION,
+        "Convert the region to lower case",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::CaseRegion(CaseConversion::Downcase)])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_CAPITALIZE_REGION,
+        "Capitalize each word of the region",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::CaseRegion(CaseConversion::Capitalize)])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_UPCASE_WORD,
+        "Convert to upper case up to the end of the word",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::CaseWord(CaseConversion::Upcase)])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_DOWNCASE_WORD,
+        "Convert to lower case up to the end of the word",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::CaseWord(CaseConversion::Downcase)])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_CAPITALIZE_WORD,
+        "Capitalize up to the end of the word",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::CaseWord(CaseConversion::Capitalize)])),
+    ));
+
+    // Filling
+    registry.register_command(Command::new(
+        CMD_FILL_PARAGRAPH,
+        "Re-wrap the paragraph to the fill column",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::FillParagraph])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_SET_FILL_COLUMN,
+        "Set the fill column to the cursor's column",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::SetFillColumn])),
+    ));
+
+    // Kill ring commands
+    registry.register_command(Command::new(
+        CMD_YANK_POP,
+        "Replace the text just yanked with the kill before it",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::YankPop])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_BROWSE_KILL_RING,
+        "List the kill ring and insert the entry picked",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::BrowseKillRing])),
+    ));
+
+    // Rectangle commands
+    registry.register_command(Command::new(
+        CMD_KILL_RECTANGLE,
+        "Kill the rectangle between the mark and the cursor",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::KillRectangle])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_YANK_RECTANGLE,
+        "Yank the last killed rectangle at the cursor",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::YankRectangle])),
+    ));
+
+    // Narrowing commands
+    registry.register_command(Command::new(
+        CMD_NARROW_TO_REGION,
+        "Restrict the buffer to the region until widened",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::NarrowToRegion])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_WIDEN,
+        "Make the whole buffer accessible again",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::Widen])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_TOGGLE_READ_ONLY,
+        "Make the buffer read-only, or writable again",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::ToggleReadOnly])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_SET_BUFFER_FILE_EOL,
+        "Switch the line endings the buffer's file is saved with between LF and CRLF",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::SetBufferFileEol])),
+    ));
+
+    registry.register_command(Command::new(
+        CMD_REVERT_BUFFER_WITH_ENCODING,
+        "Reload the buffer from its file in the encoding picked",
+        CommandCategory::Global,
+        sync_handler(|_context| Ok(vec![ChromeAction::RevertBufferWithEncoding])),
+    ));
+
     // Search commands
     registry.register_command(Command::new(
         CMD_ISEARCH_FORWARD,
@@ -425,22 +1042,24 @@ pub fn julia_handler(
                 mark_pos,
             };
 
-            // Call the Julia command (pass buffer for direct access)
+            // Call the Julia command (pass buffer for direct access). The lock
+            // is held until it returns, telling the editor the runtime is busy.
             let runtime_guard = runtime.lock().await;
             match runtime_guard
                 .call_command(&name, julia_context, context.buffer.clone())
                 .await
             {
-                Ok(result) => convert_julia_result(result),
+                Ok(result) => convert_julia_result(result, context.buffer_id),
                 Err(e) => Err(format!("Julia command error: {:?}", e)),
             }
         })
     })
 }
 
-/// Convert JuliaCommandResult to ChromeActions
+/// Convert JuliaCommandResult to ChromeActions, for a command run on `buffer_id`
 fn convert_julia_result(
     result: crate::julia_runtime::JuliaCommandResult,
+    buffer_id: BufferId,
 ) -> Result<Vec<ChromeAction>, String> {
     use crate::julia_runtime::{JuliaBufferOp, JuliaCommandResult};
 
@@ -469,11 +1088,17 @@ fn convert_julia_result(
             Ok(vec![ChromeAction::BufferOps(buffer_ops)])
         }
         JuliaCommandResult::Multi(results) => {
+            // Edits made before an error still happened, so they're reported
+            // along with it, and the editor keeps up with the buffer
             let mut actions = Vec::new();
             for r in results {
-                match convert_julia_result(r) {
+                match convert_julia_result(r, buffer_id) {
                     Ok(mut a) => actions.append(&mut a),
-                    Err(e) => return Err(e),
+                    Err(e) if actions.is_empty() => return Err(e),
+                    Err(e) => {
+                        actions.push(ChromeAction::Echo(format!("Command error: {e}")));
+                        break;
+                    }
                 }
             }
             Ok(actions)
@@ -481,6 +1106,15 @@ fn convert_julia_result(
         JuliaCommandResult::ExecuteCommand(command) => {
             Ok(vec![ChromeAction::ExecuteCommand(command)])
         }
+        JuliaCommandResult::BufferEdited(range) => Ok(vec![
+            ChromeAction::BufferChanged {
+                buffer_id,
+                start: range.start,
+                old_end: range.old_end,
+                new_end: range.new_end,
+            },
+            ChromeAction::MarkDirty(DirtyRegion::Buffer { buffer_id }),
+        ]),
     }
 }
 
@@ -508,6 +1142,36 @@ pub async fn register_julia_commands(
     }
 }
 
+/// Replace the registered Julia commands with the runtime's current set.
+/// The existing commands are only dropped once the new list has been fetched,
+/// so a failed query leaves the registry untouched. Returns the number of
+/// Julia commands now registered.
+pub async fn reload_julia_commands(
+    registry: &mut CommandRegistry,
+    runtime: &crate::julia_runtime::SharedJuliaRuntime,
+) -> Result<usize, String> {
+    let runtime_guard = runtime.lock().await;
+    let commands = runtime_guard
+        .list_commands()
+        .await
+        .map_err(|e| format!("Failed to list Julia commands: {e}"))?;
+    drop(runtime_guard);
+
+    let julia_category = CommandCategory::Script("julia".to_string());
+    registry.remove_commands_by_category(&julia_category);
+
+    let count = commands.len();
+    for (name, description) in commands {
+        registry.register_command(Command::new(
+            name.clone(),
+            description,
+            julia_category.clone(),
+            julia_handler(runtime.clone(), name),
+        ));
+    }
+    Ok(count)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -593,4 +1257,127 @@ mod tests {
         assert_eq!(visit_commands.len(), 1);
         assert_eq!(visit_commands[0].name, "visit-file");
     }
+
+    #[tokio::test]
+    async fn test_count_words_region_or_buffer() {
+        let registry = create_default_registry();
+        let buffer = Buffer::new(&[]);
+        buffer.load_str("one two\nthree\n");
+        let context = |buffer: &Buffer| CommandContext {
+            buffer: buffer.clone(),
+            cursor_pos: 7,
+            buffer_id: BufferId::default(),
+            window_id: WindowId::default(),
+            buffer_name: "test".to_string(),
+            buffer_modified: false,
+            current_line: 1,
+            current_column: 8,
+            prompt_answers: Vec::new(),
+        };
+        let count_words = registry.get_command(CMD_COUNT_WORDS).unwrap();
+
+        assert_eq!(
+            count_words.execute(context(&buffer)).await.unwrap(),
+            vec![ChromeAction::Echo(
+                "Buffer has 2 lines, 3 words, 14 characters, 14 bytes".to_string()
+            )]
+        );
+
+        buffer.set_mark(4);
+        assert_eq!(
+            count_words.execute(context(&buffer)).await.unwrap(),
+            vec![ChromeAction::Echo(
+                "Region has 1 line, 1 word, 3 characters, 3 bytes".to_string()
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bookmark_set_prompts_for_name() {
+        let registry = create_default_registry();
+        let path = std::env::temp_dir().join(format!("roe-bookmark-set-{}", std::process::id()));
+        std::fs::write(&path, "").unwrap();
+        let context = |buffer_name: &str, answers: &[&str]| CommandContext {
+            buffer: Buffer::new(&[]),
+            cursor_pos: 0,
+            buffer_id: BufferId::default(),
+            window_id: WindowId::default(),
+            buffer_name: buffer_name.to_string(),
+            buffer_modified: false,
+            current_line: 1,
+            current_column: 1,
+            prompt_answers: answers.iter().map(|answer| answer.to_string()).collect(),
+        };
+        let bookmark_set = registry.get_command(CMD_BOOKMARK_SET).unwrap();
+        let file_name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let path_name = path.to_string_lossy().into_owned();
+
+        assert_eq!(
+            bookmark_set
+                .execute(context(&path_name, &[]))
+                .await
+                .unwrap(),
+            vec![ChromeAction::Prompt {
+                message: format!("Set bookmark (default {file_name}): "),
+                continuation: PromptContinuation::new(CMD_BOOKMARK_SET),
+            }]
+        );
+        assert_eq!(
+            bookmark_set
+                .execute(context(&path_name, &[" here "]))
+                .await
+                .unwrap(),
+            vec![ChromeAction::SetBookmark("here".to_string())]
+        );
+        assert_eq!(
+            bookmark_set
+                .execute(context(&path_name, &[""]))
+                .await
+                .unwrap(),
+            vec![ChromeAction::SetBookmark(file_name)]
+        );
+
+        // Only a place in a file can be bookmarked
+        assert_eq!(
+            bookmark_set
+                .execute(context("*scratch*", &[]))
+                .await
+                .unwrap(),
+            vec![ChromeAction::Echo(
+                "Buffer is not visiting a file".to_string()
+            )]
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_failed_julia_command_reports_its_edits() {
+        use crate::after_change_queue::ChangeRange;
+        use crate::julia_runtime::JuliaCommandResult;
+
+        let buffer_id = BufferId::default();
+        // The command inserted text, then threw
+        let result = JuliaCommandResult::Multi(vec![
+            JuliaCommandResult::BufferEdited(ChangeRange::new(3, 3, 8)),
+            JuliaCommandResult::Error("boom".to_string()),
+        ]);
+        assert_eq!(
+            convert_julia_result(result, buffer_id).unwrap(),
+            vec![
+                ChromeAction::BufferChanged {
+                    buffer_id,
+                    start: 3,
+                    old_end: 3,
+                    new_end: 8,
+                },
+                ChromeAction::MarkDirty(DirtyRegion::Buffer { buffer_id }),
+                ChromeAction::Echo("Command error: boom".to_string()),
+            ]
+        );
+
+        // With nothing edited the error is the result
+        let result = JuliaCommandResult::Multi(vec![JuliaCommandResult::Error("boom".to_string())]);
+        assert_eq!(convert_julia_result(result, buffer_id).unwrap_err(), "boom");
+    }
 }
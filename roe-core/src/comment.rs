@@ -0,0 +1,196 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! `comment-region` and `uncomment-region`: add or strip a major mode's line
+//! comment prefix on each line of the region.
+//!
+//! Commenting puts the prefix and a space at the smallest indentation among
+//! the lines, so the markers line up and the code keeps its indentation.
+//! Uncommenting strips the prefix, and the space after it if there is one,
+//! wherever it starts a line's text. Blank lines are left alone both ways.
+
+/// Line comment prefix for major modes that don't say what theirs is
+pub const DEFAULT_COMMENT_PREFIX: &str = "#";
+
+/// A change to one line: at `column` characters in, delete `delete` characters
+/// and insert `insert`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineEdit {
+    /// Index into the lines the edits were made for
+    pub line: usize,
+    pub column: usize,
+    pub delete: usize,
+    pub insert: String,
+}
+
+impl LineEdit {
+    /// Where a position `column` characters into the line ends up after the
+    /// edit. One right at the edit stays in front of any inserted text.
+    pub fn shift_column(&self, column: usize) -> usize {
+        if column <= self.column {
+            column
+        } else if column < self.column + self.delete {
+            self.column
+        } else {
+            column - self.delete + self.insert.chars().count()
+        }
+    }
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+/// Characters of whitespace the line's text is indented by
+fn indentation(line: &str) -> usize {
+    line.chars().take_while(|c| c.is_whitespace()).count()
+}
+
+/// Whether every non-blank line starts with `prefix`, and there is at least one
+pub fn is_commented(lines: &[&str], prefix: &str) -> bool {
+    let mut text_lines = lines.iter().filter(|line| !is_blank(line)).peekable();
+    text_lines.peek().is_some() && text_lines.all(|line| line.trim_start().starts_with(prefix))
+}
+
+/// Edits commenting out each non-blank line
+pub fn comment(lines: &[&str], prefix: &str) -> Vec<LineEdit> {
+    let Some(column) = lines
+        .iter()
+        .filter(|line| !is_blank(line))
+        .map(|line| indentation(line))
+        .min()
+    else {
+        return vec![];
+    };
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !is_blank(line))
+        .map(|(line, _)| LineEdit {
+            line,
+            column,
+            delete: 0,
+            insert: format!("{prefix} "),
+        })
+        .collect()
+}
+
+/// Edits stripping the comment prefix from each line that has one
+pub fn uncomment(lines: &[&str], prefix: &str) -> Vec<LineEdit> {
+    lines
+        .iter()
+        .enumerate()
+        .filter_map(|(line, text)| {
+            let after_prefix = text.trim_start().strip_prefix(prefix)?;
+            let space = usize::from(after_prefix.starts_with(' '));
+            Some(LineEdit {
+                line,
+                column: indentation(text),
+                delete: prefix.chars().count() + space,
+                insert: String::new(),
+            })
+        })
+        .collect()
+}
+
+/// Uncomment the lines if every non-blank one is already commented, otherwise
+/// comment them out
+pub fn toggle_comment(lines: &[&str], prefix: &str) -> Vec<LineEdit> {
+    if is_commented(lines, prefix) {
+        uncomment(lines, prefix)
+    } else {
+        comment(lines, prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Apply `edits` to `lines`, for checking the text they produce
+    fn apply(lines: &[&str], edits: &[LineEdit]) -> Vec<String> {
+        let mut lines: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+        for edit in edits {
+            let mut chars: Vec<char> = lines[edit.line].chars().collect();
+            chars.splice(edit.column..edit.column + edit.delete, edit.insert.chars());
+            lines[edit.line] = chars.into_iter().collect();
+        }
+        lines
+    }
+
+    #[test]
+    fn test_toggle_rust_comments() {
+        let lines = ["fn main() {", "    let x = 1;", "", "    x", "}"];
+        let commented = apply(&lines, &toggle_comment(&lines, "//"));
+        assert_eq!(
+            commented,
+            [
+                "// fn main() {",
+                "//     let x = 1;",
+                "",
+                "//     x",
+                "// }"
+            ]
+        );
+
+        // Every line commented now, so toggling again takes it back out
+        let commented: Vec<&str> = commented.iter().map(String::as_str).collect();
+        let edits = toggle_comment(&commented, "//");
+        assert_eq!(apply(&commented, &edits), lines);
+    }
+
+    #[test]
+    fn test_comment_at_smallest_indentation() {
+        let lines = ["    if x:", "", "        y()", "    # done"];
+        let prefix = DEFAULT_COMMENT_PREFIX;
+
+        // One line not commented yet means the whole region gets commented
+        assert!(!is_commented(&lines, prefix));
+        assert_eq!(
+            apply(&lines, &toggle_comment(&lines, prefix)),
+            ["    # if x:", "", "    #     y()", "    # # done"]
+        );
+
+        // Uncommenting leaves lines without the prefix alone, and takes a
+        // prefix not followed by a space on its own
+        let lines = ["  #x", "", "  y"];
+        assert_eq!(
+            apply(&lines, &uncomment(&lines, prefix)),
+            ["  x", "", "  y"]
+        );
+
+        // Nothing but blank lines has nothing to comment
+        assert!(toggle_comment(&["", "  "], prefix).is_empty());
+    }
+
+    #[test]
+    fn test_shift_column() {
+        let insert = LineEdit {
+            line: 0,
+            column: 4,
+            delete: 0,
+            insert: "// ".to_string(),
+        };
+        assert_eq!(insert.shift_column(4), 4);
+        assert_eq!(insert.shift_column(6), 9);
+
+        let delete = LineEdit {
+            line: 0,
+            column: 4,
+            delete: 3,
+            insert: String::new(),
+        };
+        assert_eq!(delete.shift_column(5), 4);
+        assert_eq!(delete.shift_column(8), 5);
+    }
+}
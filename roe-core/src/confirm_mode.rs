@@ -0,0 +1,279 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+use crate::keys::KeyAction;
+use crate::mode::{Mode, ModeAction, ModeResult};
+use crate::BufferId;
+
+/// Answer to "Buffer X modified; kill anyway?"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KillConfirmation {
+    /// Kill the buffer, discarding unsaved changes
+    Kill,
+    /// Save the buffer to its file, then kill it
+    SaveAndKill,
+    /// Leave the buffer alone
+    Keep,
+}
+
+/// Yes/no prompt shown before killing a buffer with unsaved changes
+pub struct ConfirmKillMode {
+    /// Buffer waiting to be killed
+    buffer_id: BufferId,
+    /// Its name, for the prompt
+    buffer_name: String,
+}
+
+impl ConfirmKillMode {
+    pub fn new(buffer_id: BufferId, buffer_name: String) -> Self {
+        Self {
+            buffer_id,
+            buffer_name,
+        }
+    }
+
+    /// Generate buffer content string
+    pub fn generate_buffer_content(&self) -> String {
+        format!(
+            "Buffer {} modified; kill anyway? (y)es, (n)o, (s)ave and kill\n",
+            self.buffer_name
+        )
+    }
+
+    fn answer(&self, confirmation: KillConfirmation) -> ModeResult {
+        ModeResult::Consumed(vec![ModeAction::ConfirmKillBuffer(
+            self.buffer_id,
+            confirmation,
+        )])
+    }
+}
+
+impl Mode for ConfirmKillMode {
+    fn name(&self) -> &str {
+        "confirm-kill"
+    }
+
+    fn perform(&mut self, action: &KeyAction) -> ModeResult {
+        match action {
+            KeyAction::AlphaNumeric('y' | 'Y') => self.answer(KillConfirmation::Kill),
+            KeyAction::AlphaNumeric('s' | 'S') => self.answer(KillConfirmation::SaveAndKill),
+            KeyAction::AlphaNumeric('n' | 'N') => self.answer(KillConfirmation::Keep),
+            KeyAction::Escape | KeyAction::Cancel => ModeResult::Ignored,
+            // Swallow everything else so the prompt can't be edited
+            _ => ModeResult::Consumed(vec![]),
+        }
+    }
+}
+
+/// Answer to "Save file X?" while saving some buffers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveConfirmation {
+    /// Save this buffer, then ask about the next
+    Save,
+    /// Leave this buffer unsaved, then ask about the next
+    Skip,
+    /// Save this buffer and the rest without asking
+    SaveAll,
+}
+
+/// Prompt asking whether to save each modified buffer in turn
+pub struct ConfirmSaveMode {
+    /// Buffer being asked about
+    buffer_id: BufferId,
+    /// The file it visits, for the prompt
+    file_path: String,
+}
+
+impl ConfirmSaveMode {
+    pub fn new(buffer_id: BufferId, file_path: String) -> Self {
+        Self {
+            buffer_id,
+            file_path,
+        }
+    }
+
+    /// Generate buffer content string
+    pub fn generate_buffer_content(&self) -> String {
+        format!(
+            "Save file {}? (y)es, (n)o, (!) save all the rest\n",
+            self.file_path
+        )
+    }
+
+    fn answer(&self, confirmation: SaveConfirmation) -> ModeResult {
+        ModeResult::Consumed(vec![ModeAction::ConfirmSaveBuffer(
+            self.buffer_id,
+            confirmation,
+        )])
+    }
+}
+
+impl Mode for ConfirmSaveMode {
+    fn name(&self) -> &str {
+        "confirm-save"
+    }
+
+    fn perform(&mut self, action: &KeyAction) -> ModeResult {
+        match action {
+            KeyAction::AlphaNumeric('y' | 'Y' | ' ') => self.answer(SaveConfirmation::Save),
+            KeyAction::AlphaNumeric('n' | 'N') => self.answer(SaveConfirmation::Skip),
+            KeyAction::AlphaNumeric('!') => self.answer(SaveConfirmation::SaveAll),
+            KeyAction::Escape | KeyAction::Cancel => ModeResult::Ignored,
+            // Swallow everything else so the prompt can't be edited
+            _ => ModeResult::Consumed(vec![]),
+        }
+    }
+}
+
+/// Answer to "File X changed on disk" for a buffer with unsaved changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevertConfirmation {
+    /// Reload the file, discarding the buffer's changes
+    Revert,
+    /// Keep the buffer as it is
+    Keep,
+    /// Show how the buffer differs from the file, then ask again
+    Diff,
+}
+
+/// Prompt shown when a modified buffer's file changes on disk while
+/// auto-revert is on
+pub struct ConfirmRevertMode {
+    /// Buffer whose file changed
+    buffer_id: BufferId,
+    /// The file's name, for the prompt
+    file_name: String,
+}
+
+impl ConfirmRevertMode {
+    pub fn new(buffer_id: BufferId, file_name: String) -> Self {
+        Self {
+            buffer_id,
+            file_name,
+        }
+    }
+
+    /// Generate buffer content string
+    pub fn generate_buffer_content(&self) -> String {
+        format!(
+            "{} changed on disk: (k)eep your changes, (r)eload it, or see the (d)iff?\n",
+            self.file_name
+        )
+    }
+
+    fn answer(&self, confirmation: RevertConfirmation) -> ModeResult {
+        ModeResult::Consumed(vec![ModeAction::ConfirmRevertBuffer(
+            self.buffer_id,
+            confirmation,
+        )])
+    }
+}
+
+impl Mode for ConfirmRevertMode {
+    fn name(&self) -> &str {
+        "confirm-revert"
+    }
+
+    fn perform(&mut self, action: &KeyAction) -> ModeResult {
+        match action {
+            KeyAction::AlphaNumeric('k' | 'K') => self.answer(RevertConfirmation::Keep),
+            KeyAction::AlphaNumeric('r' | 'R') => self.answer(RevertConfirmation::Revert),
+            KeyAction::AlphaNumeric('d' | 'D') => self.answer(RevertConfirmation::Diff),
+            KeyAction::Escape | KeyAction::Cancel => ModeResult::Ignored,
+            // Swallow everything else so the prompt can't be edited
+            _ => ModeResult::Consumed(vec![]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slotmap::SlotMap;
+
+    #[test]
+    fn test_confirm_kill_answers() {
+        let mut buffers: SlotMap<BufferId, ()> = SlotMap::with_key();
+        let buffer_id = buffers.insert(());
+        let mut mode = ConfirmKillMode::new(buffer_id, "notes.txt".to_string());
+
+        assert!(mode
+            .generate_buffer_content()
+            .starts_with("Buffer notes.txt modified; kill anyway?"));
+
+        for (key, expected) in [
+            ('y', KillConfirmation::Kill),
+            ('S', KillConfirmation::SaveAndKill),
+            ('n', KillConfirmation::Keep),
+        ] {
+            assert_eq!(
+                mode.perform(&KeyAction::AlphaNumeric(key)),
+                ModeResult::Consumed(vec![ModeAction::ConfirmKillBuffer(buffer_id, expected)])
+            );
+        }
+
+        assert_eq!(
+            mode.perform(&KeyAction::AlphaNumeric('x')),
+            ModeResult::Consumed(vec![])
+        );
+        assert_eq!(mode.perform(&KeyAction::Escape), ModeResult::Ignored);
+    }
+
+    #[test]
+    fn test_confirm_save_answers() {
+        let mut buffers: SlotMap<BufferId, ()> = SlotMap::with_key();
+        let buffer_id = buffers.insert(());
+        let mut mode = ConfirmSaveMode::new(buffer_id, "notes.txt".to_string());
+
+        assert!(mode
+            .generate_buffer_content()
+            .starts_with("Save file notes.txt?"));
+
+        for (key, expected) in [
+            ('y', SaveConfirmation::Save),
+            ('n', SaveConfirmation::Skip),
+            ('!', SaveConfirmation::SaveAll),
+        ] {
+            assert_eq!(
+                mode.perform(&KeyAction::AlphaNumeric(key)),
+                ModeResult::Consumed(vec![ModeAction::ConfirmSaveBuffer(buffer_id, expected)])
+            );
+        }
+
+        assert_eq!(mode.perform(&KeyAction::Cancel), ModeResult::Ignored);
+    }
+
+    #[test]
+    fn test_confirm_revert_answers() {
+        let mut buffers: SlotMap<BufferId, ()> = SlotMap::with_key();
+        let buffer_id = buffers.insert(());
+        let mut mode = ConfirmRevertMode::new(buffer_id, "main.rs".to_string());
+
+        assert!(mode
+            .generate_buffer_content()
+            .starts_with("main.rs changed on disk: (k)eep"));
+
+        for (key, expected) in [
+            ('k', RevertConfirmation::Keep),
+            ('R', RevertConfirmation::Revert),
+            ('d', RevertConfirmation::Diff),
+        ] {
+            assert_eq!(
+                mode.perform(&KeyAction::AlphaNumeric(key)),
+                ModeResult::Consumed(vec![ModeAction::ConfirmRevertBuffer(buffer_id, expected)])
+            );
+        }
+
+        assert_eq!(mode.perform(&KeyAction::Escape), ModeResult::Ignored);
+    }
+}
@@ -0,0 +1,352 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Detection and resolution of merge conflict markers.
+//!
+//! Recognizes the blocks git (and our own file-watcher merge) writes into a
+//! file when two versions can't be combined:
+//!
+//! ```text
+//! <<<<<<< ours
+//! our lines
+//! ||||||| base        (optional, diff3 style)
+//! common ancestor
+//! =======
+//! their lines
+//! >>>>>>> theirs
+//! ```
+//!
+//! Markers only count at the start of a line and inside a complete block, so a
+//! stray `=======` (e.g. a Markdown heading underline) isn't flagged.
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+use crate::buffer::Buffer;
+use crate::BufferId;
+
+/// Which side of a conflict to keep when resolving it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictSide {
+    /// The lines between `<<<<<<<` and `=======` (or `|||||||`)
+    Ours,
+    /// The lines between `=======` and `>>>>>>>`
+    Theirs,
+}
+
+/// A complete conflict block, by line index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictRegion {
+    /// The `<<<<<<<` line
+    pub start_line: usize,
+    /// The `|||||||` line, for diff3-style conflicts
+    pub base_line: Option<usize>,
+    /// The `=======` line
+    pub separator_line: usize,
+    /// The `>>>>>>>` line
+    pub end_line: usize,
+}
+
+impl ConflictRegion {
+    /// Whether `line` falls anywhere within this block, markers included
+    pub fn contains_line(&self, line: usize) -> bool {
+        (self.start_line..=self.end_line).contains(&line)
+    }
+
+    /// Line indices of this block's marker lines
+    pub fn marker_lines(&self) -> impl Iterator<Item = usize> {
+        [
+            Some(self.start_line),
+            self.base_line,
+            Some(self.separator_line),
+            Some(self.end_line),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    /// Lines holding one side's content, excluding markers
+    pub fn side_lines(&self, side: ConflictSide) -> Range<usize> {
+        match side {
+            ConflictSide::Ours => {
+                self.start_line + 1..self.base_line.unwrap_or(self.separator_line)
+            }
+            ConflictSide::Theirs => self.separator_line + 1..self.end_line,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Marker {
+    Ours,
+    Base,
+    Separator,
+    Theirs,
+}
+
+/// Classify a line as a conflict marker: seven marker characters at the start of
+/// the line, followed by a label (except for `=======`) or the end of the line
+fn marker_kind(line: &str) -> Option<Marker> {
+    let line = line.trim_end_matches(['\n', '\r']);
+    let (marker, ch) = match line.chars().next()? {
+        '<' => (Marker::Ours, '<'),
+        '|' => (Marker::Base, '|'),
+        '=' => (Marker::Separator, '='),
+        '>' => (Marker::Theirs, '>'),
+        _ => return None,
+    };
+
+    if !line.chars().take(7).eq(std::iter::repeat(ch).take(7)) {
+        return None;
+    }
+    let rest = &line[7..];
+    let valid = match marker {
+        Marker::Separator => rest.is_empty(),
+        _ => rest.is_empty() || rest.starts_with(' '),
+    };
+    valid.then_some(marker)
+}
+
+/// Find the complete conflict blocks in `lines`, in order
+fn find_conflicts_in_lines<'a>(lines: impl Iterator<Item = &'a str>) -> Vec<ConflictRegion> {
+    let mut regions = Vec::new();
+    // (start, base, separator) of the block we're inside, if any
+    let mut open: Option<(usize, Option<usize>, Option<usize>)> = None;
+
+    for (index, line) in lines.enumerate() {
+        let Some(marker) = marker_kind(line) else {
+            continue;
+        };
+        match marker {
+            // A new block start abandons an unterminated one
+            Marker::Ours => open = Some((index, None, None)),
+            Marker::Base => {
+                if let Some((_, base @ None, None)) = &mut open {
+                    *base = Some(index);
+                }
+            }
+            Marker::Separator => {
+                if let Some((_, _, separator @ None)) = &mut open {
+                    *separator = Some(index);
+                }
+            }
+            Marker::Theirs => {
+                if let Some((start_line, base_line, Some(separator_line))) = open.take() {
+                    regions.push(ConflictRegion {
+                        start_line,
+                        base_line,
+                        separator_line,
+                        end_line: index,
+                    });
+                }
+            }
+        }
+    }
+
+    regions
+}
+
+/// Find the complete conflict blocks in `buffer`, in order. Only the lines
+/// the buffer is narrowed to are searched, numbered as its line API numbers them.
+pub fn find_conflicts(buffer: &Buffer) -> Vec<ConflictRegion> {
+    buffer.with_read(|b| {
+        // Only materialize lines that could be markers
+        let lines: Vec<String> = b
+            .buffer
+            .lines_at(b.narrowed_line_offset())
+            .take(b.len_lines())
+            .map(|line| match line.chars().next() {
+                Some('<' | '|' | '=' | '>') => line.to_string(),
+                _ => String::new(),
+            })
+            .collect();
+        find_conflicts_in_lines(lines.iter().map(String::as_str))
+    })
+}
+
+/// Marker lines of complete conflict blocks within `lines`, for the gutter
+pub fn conflict_marker_lines(buffer: &Buffer, lines: Range<usize>) -> HashSet<usize> {
+    marker_lines_in(&find_conflicts(buffer), lines)
+}
+
+fn marker_lines_in(regions: &[ConflictRegion], lines: Range<usize>) -> HashSet<usize> {
+    regions
+        .iter()
+        .filter(|region| region.end_line >= lines.start && region.start_line < lines.end)
+        .flat_map(ConflictRegion::marker_lines)
+        .filter(|line| lines.contains(line))
+        .collect()
+}
+
+/// Conflict blocks of each buffer, for the gutter to draw every frame without
+/// scanning the text again. A buffer's entry is dropped when it changes.
+#[derive(Default)]
+pub struct ConflictCache {
+    regions: Mutex<HashMap<BufferId, Arc<Vec<ConflictRegion>>>>,
+}
+
+impl ConflictCache {
+    /// Marker lines of `buffer`'s conflict blocks within `lines`, scanning it
+    /// only if it changed since last asked
+    pub fn marker_lines(
+        &self,
+        buffer_id: BufferId,
+        buffer: &Buffer,
+        lines: Range<usize>,
+    ) -> HashSet<usize> {
+        let regions = self
+            .regions
+            .lock()
+            .expect("Conflict cache lock poisoned")
+            .entry(buffer_id)
+            .or_insert_with(|| Arc::new(find_conflicts(buffer)))
+            .clone();
+        marker_lines_in(&regions, lines)
+    }
+
+    /// Forget `buffer_id`'s conflict blocks, after its text or narrowing changed
+    pub fn invalidate(&self, buffer_id: BufferId) {
+        self.regions
+            .lock()
+            .expect("Conflict cache lock poisoned")
+            .remove(&buffer_id);
+    }
+}
+
+/// The conflict block containing `line`, if any
+pub fn conflict_at_line(buffer: &Buffer, line: usize) -> Option<ConflictRegion> {
+    find_conflicts(buffer)
+        .into_iter()
+        .find(|region| region.contains_line(line))
+}
+
+/// Char range covered by `region` and the text that replaces it when keeping `side`
+pub fn resolution(
+    buffer: &Buffer,
+    region: &ConflictRegion,
+    side: ConflictSide,
+) -> (Range<usize>, String) {
+    buffer.with_read(|b| {
+        let line_start = |line: usize| b.line_to_char(line);
+
+        let kept_lines = region.side_lines(side);
+        let kept = b
+            .buffer
+            .slice(line_start(kept_lines.start)..line_start(kept_lines.end))
+            .to_string();
+        let replaced = line_start(region.start_line)..line_start(region.end_line + 1);
+        (replaced, kept)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer_with(text: &str) -> Buffer {
+        let buffer = Buffer::new(&[]);
+        buffer.load_str(text);
+        buffer
+    }
+
+    const CONFLICT: &str = "before\n\
+                            <<<<<<< LOCAL\n\
+                            ours\n\
+                            =======\n\
+                            theirs 1\n\
+                            theirs 2\n\
+                            >>>>>>> EXTERNAL\n\
+                            after\n";
+
+    #[test]
+    fn test_find_two_way_conflict() {
+        let regions = find_conflicts(&buffer_with(CONFLICT));
+        assert_eq!(
+            regions,
+            vec![ConflictRegion {
+                start_line: 1,
+                base_line: None,
+                separator_line: 3,
+                end_line: 6,
+            }]
+        );
+        assert_eq!(regions[0].side_lines(ConflictSide::Ours), 2..3);
+        assert_eq!(regions[0].side_lines(ConflictSide::Theirs), 4..6);
+    }
+
+    #[test]
+    fn test_find_diff3_conflict() {
+        let text = "<<<<<<< HEAD\na\n||||||| base\nb\n=======\nc\n>>>>>>> branch\n";
+        let regions = find_conflicts(&buffer_with(text));
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].base_line, Some(2));
+        assert_eq!(regions[0].side_lines(ConflictSide::Ours), 1..2);
+    }
+
+    #[test]
+    fn test_lone_markers_are_not_conflicts() {
+        // A Markdown heading underline and an unterminated block
+        let text = "Title\n=======\n\n<<<<<<< HEAD\nstuff\n=======\n";
+        assert!(find_conflicts(&buffer_with(text)).is_empty());
+
+        // Markers must be exactly seven characters at the start of the line
+        assert_eq!(marker_kind("========"), None);
+        assert_eq!(marker_kind("  <<<<<<< HEAD"), None);
+        assert_eq!(marker_kind("<<<<<<<<"), None);
+        assert_eq!(marker_kind(">>>>>>>\n"), Some(Marker::Theirs));
+    }
+
+    #[test]
+    fn test_conflict_marker_lines_in_range() {
+        let buffer = buffer_with(CONFLICT);
+        assert_eq!(
+            conflict_marker_lines(&buffer, 0..10),
+            HashSet::from([1, 3, 6])
+        );
+        assert_eq!(conflict_marker_lines(&buffer, 2..5), HashSet::from([3]));
+    }
+
+    #[test]
+    fn test_cache_rescans_after_invalidate() {
+        let mut buffers: slotmap::SlotMap<BufferId, ()> = slotmap::SlotMap::with_key();
+        let buffer_id = buffers.insert(());
+        let buffer = buffer_with(CONFLICT);
+        let cache = ConflictCache::default();
+        assert_eq!(
+            cache.marker_lines(buffer_id, &buffer, 0..10),
+            HashSet::from([1, 3, 6])
+        );
+
+        // Resolved, but not reported yet: the cached blocks still show
+        let resolved = buffer_with("one\n");
+        assert_eq!(cache.marker_lines(buffer_id, &resolved, 0..10).len(), 3);
+        cache.invalidate(buffer_id);
+        assert!(cache.marker_lines(buffer_id, &resolved, 0..10).is_empty());
+    }
+
+    #[test]
+    fn test_resolution() {
+        let buffer = buffer_with(CONFLICT);
+        let region = conflict_at_line(&buffer, 4).unwrap();
+
+        let (range, ours) = resolution(&buffer, &region, ConflictSide::Ours);
+        assert_eq!(range, 7..69);
+        assert_eq!(ours, "ours\n");
+
+        let (_, theirs) = resolution(&buffer, &region, ConflictSide::Theirs);
+        assert_eq!(theirs, "theirs 1\ntheirs 2\n");
+
+        assert!(conflict_at_line(&buffer, 0).is_none());
+    }
+}
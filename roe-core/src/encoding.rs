@@ -0,0 +1,203 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Character encodings files are read and written in. Buffers always hold
+//! UTF-8; a file's encoding is detected when it's read and used again to write
+//! it back.
+//!
+//! A byte order mark picks UTF-8 or UTF-16. Without one, a file is UTF-8 if it
+//! decodes as such and Latin-1 otherwise, unless it has NUL bytes, which no
+//! text in either has. Decoding never inserts replacement characters: bytes
+//! that aren't valid in the encoding are an error.
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16_LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16_BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// Encodings a file can be read and written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    /// UTF-8 starting with a byte order mark
+    Utf8Bom,
+    /// UTF-16 little-endian, with a byte order mark
+    Utf16Le,
+    /// UTF-16 big-endian, with a byte order mark
+    Utf16Be,
+    /// ISO-8859-1: each byte is the character with that code point
+    Latin1,
+}
+
+impl Encoding {
+    /// Every encoding, in the order they're offered
+    pub const ALL: [Encoding; 5] = [
+        Encoding::Utf8,
+        Encoding::Utf8Bom,
+        Encoding::Utf16Le,
+        Encoding::Utf16Be,
+        Encoding::Latin1,
+    ];
+
+    /// Name shown in the mode line and accepted by `from_name`
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf8Bom => "UTF-8-BOM",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Latin1 => "Latin-1",
+        }
+    }
+
+    /// The encoding called `name`, ignoring case and `-`/`_`, with
+    /// `ISO-8859-1` also meaning Latin-1
+    pub fn from_name(name: &str) -> Option<Self> {
+        let wanted: String = name
+            .chars()
+            .filter(|c| !matches!(c, '-' | '_'))
+            .collect::<String>()
+            .to_lowercase();
+        if wanted == "iso88591" {
+            return Some(Encoding::Latin1);
+        }
+        Encoding::ALL
+            .into_iter()
+            .find(|encoding| encoding.name().replace('-', "").to_lowercase() == wanted)
+    }
+
+    /// The encoding `bytes`, read from a file, are most likely in
+    pub fn detect(bytes: &[u8]) -> Self {
+        if bytes.starts_with(UTF8_BOM) {
+            Encoding::Utf8Bom
+        } else if bytes.starts_with(UTF16_LE_BOM) {
+            Encoding::Utf16Le
+        } else if bytes.starts_with(UTF16_BE_BOM) {
+            Encoding::Utf16Be
+        } else if std::str::from_utf8(bytes).is_ok() || bytes.contains(&0) {
+            // Left as UTF-8 so decoding reports what's wrong
+            Encoding::Utf8
+        } else {
+            Encoding::Latin1
+        }
+    }
+
+    /// `bytes` decoded as this encoding, its byte order mark (if it has one)
+    /// left out
+    pub fn decode(self, bytes: &[u8]) -> Result<String, String> {
+        match self {
+            Encoding::Utf8 | Encoding::Utf8Bom => {
+                let (bom_len, text) = match bytes.strip_prefix(UTF8_BOM) {
+                    Some(text) => (UTF8_BOM.len(), text),
+                    None => (0, bytes),
+                };
+                String::from_utf8(text.to_vec()).map_err(|e| {
+                    format!(
+                        "not valid UTF-8 (byte {})",
+                        bom_len + e.utf8_error().valid_up_to()
+                    )
+                })
+            }
+            Encoding::Utf16Le => decode_utf16(bytes, UTF16_LE_BOM, u16::from_le_bytes),
+            Encoding::Utf16Be => decode_utf16(bytes, UTF16_BE_BOM, u16::from_be_bytes),
+            Encoding::Latin1 => Ok(bytes.iter().map(|&byte| byte as char).collect()),
+        }
+    }
+
+    /// `text` encoded to be written, with a byte order mark if this encoding
+    /// has one. Fails if the encoding can't represent some character.
+    pub fn encode(self, text: &str) -> Result<Vec<u8>, String> {
+        match self {
+            Encoding::Utf8 => Ok(text.as_bytes().to_vec()),
+            Encoding::Utf8Bom => Ok([UTF8_BOM, text.as_bytes()].concat()),
+            Encoding::Utf16Le => Ok(UTF16_LE_BOM
+                .iter()
+                .copied()
+                .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+                .collect()),
+            Encoding::Utf16Be => Ok(UTF16_BE_BOM
+                .iter()
+                .copied()
+                .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+                .collect()),
+            Encoding::Latin1 => text
+                .chars()
+                .map(|c| u8::try_from(u32::from(c)).map_err(|_| c))
+                .collect::<Result<Vec<u8>, char>>()
+                .map_err(|c| format!("{c:?} can't be written in Latin-1")),
+        }
+    }
+}
+
+impl std::fmt::Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// `bytes` as UTF-16 read two at a time with `to_unit`, after `bom` if they
+/// start with it
+fn decode_utf16(bytes: &[u8], bom: &[u8], to_unit: fn([u8; 2]) -> u16) -> Result<String, String> {
+    let bytes = bytes.strip_prefix(bom).unwrap_or(bytes);
+    if !bytes.len().is_multiple_of(2) {
+        return Err("not valid UTF-16 (odd number of bytes)".to_string());
+    }
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| to_unit([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16(&units).map_err(|_| "not valid UTF-16 (unpaired surrogate)".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf16_le_round_trip() {
+        let bytes = [0xFF, 0xFE, b'h', 0, b'i', 0, 0xE9, 0, b'\n', 0];
+        assert_eq!(Encoding::detect(&bytes), Encoding::Utf16Le);
+        let text = Encoding::Utf16Le.decode(&bytes).unwrap();
+        assert_eq!(text, "hié\n");
+        assert_eq!(Encoding::Utf16Le.encode(&text).unwrap(), bytes);
+
+        // Half a code unit at the end is an error, not a replacement character
+        assert!(Encoding::Utf16Le.decode(&bytes[..9]).is_err());
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Encoding::detect(b"plain"), Encoding::Utf8);
+        assert_eq!(Encoding::detect("caf\u{e9}".as_bytes()), Encoding::Utf8);
+        assert_eq!(Encoding::detect(b"\xEF\xBB\xBFbom"), Encoding::Utf8Bom);
+        assert_eq!(Encoding::detect(b"\xFE\xFF\0a"), Encoding::Utf16Be);
+        assert_eq!(Encoding::detect(b"caf\xE9"), Encoding::Latin1);
+        assert_eq!(Encoding::Latin1.decode(b"caf\xE9").unwrap(), "café");
+
+        // Binary files aren't taken for Latin-1
+        assert_eq!(Encoding::detect(b"\xE9\0\x01"), Encoding::Utf8);
+        assert!(Encoding::Utf8.decode(b"\xE9\0\x01").is_err());
+    }
+
+    #[test]
+    fn test_encode_errors_and_names() {
+        assert_eq!(Encoding::Latin1.encode("café").unwrap(), b"caf\xE9");
+        assert!(Encoding::Latin1.encode("€").is_err());
+        assert_eq!(Encoding::Utf8Bom.encode("a").unwrap(), b"\xEF\xBB\xBFa");
+
+        assert_eq!(Encoding::from_name("utf-16le"), Some(Encoding::Utf16Le));
+        assert_eq!(Encoding::from_name("ISO-8859-1"), Some(Encoding::Latin1));
+        assert_eq!(Encoding::from_name("utf8"), Some(Encoding::Utf8));
+        assert_eq!(Encoding::from_name("ebcdic"), None);
+    }
+}
@@ -0,0 +1,126 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! `revert-buffer-with-encoding`: list the encodings a file can be read in,
+//! and reload the buffer the list was opened from in the one picked.
+
+use crate::encoding::Encoding;
+use crate::keys::KeyAction;
+use crate::mode::{Mode, ModeAction, ModeResult};
+use crate::selection_menu::{MenuItem, SelectionMenu, DEFAULT_MAX_VISIBLE_ITEMS};
+
+/// Header line of the list
+const PROMPT: &str = "Revert buffer with encoding:";
+
+/// An encoding offered to re-read the file in
+#[derive(Clone)]
+pub struct EncodingItem {
+    pub encoding: Encoding,
+    /// Whether it's the encoding the buffer was read in
+    pub current: bool,
+}
+
+impl MenuItem for EncodingItem {
+    fn display_text(&self) -> String {
+        if self.current {
+            format!("{} (current)", self.encoding)
+        } else {
+            self.encoding.to_string()
+        }
+    }
+
+    fn matches_filter(&self, filter: &str) -> bool {
+        self.encoding
+            .name()
+            .to_lowercase()
+            .contains(&filter.to_lowercase())
+    }
+}
+
+/// List of encodings to pick one to revert the buffer with
+pub struct EncodingMode {
+    menu: SelectionMenu<EncodingItem>,
+}
+
+impl EncodingMode {
+    /// The list, with the buffer's `current` encoding marked
+    pub fn new(current: Encoding) -> Self {
+        let mut menu = SelectionMenu::new(DEFAULT_MAX_VISIBLE_ITEMS);
+        menu.init_with_items(
+            Encoding::ALL
+                .into_iter()
+                .map(|encoding| EncodingItem {
+                    encoding,
+                    current: encoding == current,
+                })
+                .collect(),
+        );
+        Self { menu }
+    }
+
+    /// Set how many encodings are listed at once
+    pub fn set_max_visible_items(&mut self, max_visible_items: usize) {
+        self.menu.set_max_visible_items(max_visible_items);
+    }
+
+    /// Generate buffer content string
+    pub fn generate_buffer_content(&self) -> String {
+        self.menu.generate_buffer_content(Some(PROMPT))
+    }
+}
+
+impl Mode for EncodingMode {
+    fn name(&self) -> &str {
+        "encoding"
+    }
+
+    fn perform(&mut self, action: &KeyAction) -> ModeResult {
+        if self.menu.handle_key_action(action) {
+            return ModeResult::Consumed(self.menu.generate_update_actions(Some(PROMPT)));
+        }
+
+        match action {
+            KeyAction::Enter => match self.menu.get_selected_item() {
+                Some(item) => {
+                    ModeResult::Consumed(vec![ModeAction::RevertWithEncoding(item.encoding)])
+                }
+                None => ModeResult::Consumed(vec![]),
+            },
+            _ => ModeResult::Ignored,
+        }
+    }
+
+    fn resize(&mut self, list_rows: usize) -> ModeResult {
+        self.set_max_visible_items(list_rows);
+        ModeResult::Consumed(self.menu.generate_update_actions(Some(PROMPT)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_encoding() {
+        let mut mode = EncodingMode::new(Encoding::Utf8);
+        assert!(mode.generate_buffer_content().contains("UTF-8 (current)"));
+
+        for c in "16be".chars() {
+            mode.perform(&KeyAction::AlphaNumeric(c));
+        }
+        assert_eq!(
+            mode.perform(&KeyAction::Enter),
+            ModeResult::Consumed(vec![ModeAction::RevertWithEncoding(Encoding::Utf16Be)])
+        );
+    }
+}
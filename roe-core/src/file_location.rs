@@ -0,0 +1,136 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! `path:line[:col]` file arguments, as compilers and grep print them.
+//!
+//! Only trailing runs of digits are taken as a line and column, so colons
+//! elsewhere in a path (`notes:draft.txt`, `C:\src\main.rs`) are left alone. A
+//! single letter before the colon is a Windows drive, never a file name.
+
+/// 1-based line, and optionally column, to put the cursor at in a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FilePosition {
+    pub line: usize,
+    pub column: Option<usize>,
+}
+
+/// A path with the position in it to open at, if one was given
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileLocation {
+    pub path: String,
+    pub position: Option<FilePosition>,
+}
+
+impl FileLocation {
+    /// Split a `path:line[:col]` argument, a trailing colon allowed. Anything
+    /// else is all path.
+    pub fn parse(spec: &str) -> Self {
+        let trimmed = spec.strip_suffix(':').unwrap_or(spec);
+        if let Some((rest, last)) = split_number(trimmed) {
+            if let Some((path, line)) = split_number(rest) {
+                return Self::at(path, line, Some(last));
+            }
+            if is_path(rest) {
+                return Self::at(rest, last, None);
+            }
+        }
+        Self {
+            path: spec.to_string(),
+            position: None,
+        }
+    }
+
+    /// Like `parse`, but an existing file named exactly `spec` (colon and all)
+    /// is opened as it is
+    pub fn resolve(spec: &str) -> Self {
+        if std::path::Path::new(spec).exists() {
+            return Self {
+                path: spec.to_string(),
+                position: None,
+            };
+        }
+        Self::parse(spec)
+    }
+
+    fn at(path: &str, line: usize, column: Option<usize>) -> Self {
+        Self {
+            path: path.to_string(),
+            position: Some(FilePosition { line, column }),
+        }
+    }
+}
+
+/// `text` split at its last colon, if what follows is a number and what comes
+/// before could be a path
+fn split_number(text: &str) -> Option<(&str, usize)> {
+    let (rest, digits) = text.rsplit_once(':')?;
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) || !is_path(rest) {
+        return None;
+    }
+    Some((rest, digits.parse().ok()?))
+}
+
+/// Whether `text` could be the path before a line number: not empty, and not a
+/// drive letter on its own
+fn is_path(text: &str) -> bool {
+    let mut chars = text.chars();
+    match (chars.next(), chars.next()) {
+        (None, _) => false,
+        (Some(c), None) => !c.is_ascii_alphabetic(),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(line: usize, column: Option<usize>) -> Option<FilePosition> {
+        Some(FilePosition { line, column })
+    }
+
+    #[test]
+    fn test_parse_line_and_column() {
+        let location = FileLocation::parse("src/editor.rs:10");
+        assert_eq!(location.path, "src/editor.rs");
+        assert_eq!(location.position, position(10, None));
+
+        let location = FileLocation::parse("src/editor.rs:10:3");
+        assert_eq!(location.path, "src/editor.rs");
+        assert_eq!(location.position, position(10, Some(3)));
+
+        // As grep -n and compilers print them
+        assert_eq!(FileLocation::parse("file:7:").position, position(7, None));
+    }
+
+    #[test]
+    fn test_parse_colons_that_are_not_positions() {
+        for spec in [
+            "notes:draft.txt",
+            "C:\\src\\main.rs",
+            "C:10",
+            "file:",
+            "file:10x",
+            ":10",
+        ] {
+            let location = FileLocation::parse(spec);
+            assert_eq!(location.path, spec);
+            assert_eq!(location.position, None);
+        }
+
+        // A drive letter with a line number after the path
+        let location = FileLocation::parse("C:\\src\\main.rs:4:2");
+        assert_eq!(location.path, "C:\\src\\main.rs");
+        assert_eq!(location.position, position(4, Some(2)));
+    }
+}
@@ -12,85 +12,94 @@
 //
 
 use crate::editor::OpenType;
-use crate::keys::KeyAction;
-use crate::mode::{ActionPosition, Mode, ModeAction, ModeResult};
+use crate::file_location::FileLocation;
+use crate::keys::{CursorDirection, KeyAction};
+use crate::menu_preview::PreviewSource;
+use crate::minibuffer_history::HistoryRecall;
+use crate::mode::{Mode, ModeAction, ModeResult};
+use crate::selection_menu::{MenuItem, SelectionMenu, DEFAULT_MAX_VISIBLE_ITEMS};
 use crate::BufferId;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// File or directory entry for the selection menu
+#[derive(Clone)]
+pub struct FileItem {
+    /// Name shown in the menu (directories end with `/`)
+    pub name: String,
+    /// Full path of the entry
+    pub path: PathBuf,
+}
+
+impl MenuItem for FileItem {
+    fn display_text(&self) -> String {
+        self.name.clone()
+    }
+
+    /// Matches on the name typed, leaving out a `:line[:col]` after it
+    fn matches_filter(&self, filter: &str) -> bool {
+        self.name
+            .to_lowercase()
+            .contains(&FileLocation::parse(filter).path.to_lowercase())
+    }
+}
 
 /// Interactive file selector mode for C-x C-f (find-file)
 /// This mode manages a command window buffer that displays files and directories
 pub struct FileSelectorMode {
-    /// Current user input (what they've typed to filter files)
-    pub input: String,
-    /// File/directory names that match the current input (filtered)
-    pub matches: Vec<String>,
-    /// Full paths corresponding to the matches
-    pub paths: Vec<PathBuf>,
-    /// Index of currently selected file/directory in matches
-    pub selected_index: usize,
-    /// Maximum number of items to show at once
-    pub max_visible_items: usize,
-    /// Starting index for the visible window of items
-    pub item_scroll_offset: usize,
+    /// Selection menu for the entries of the current directory
+    menu: SelectionMenu<FileItem>,
     /// Buffer ID this mode is managing
     pub buffer_id: Option<BufferId>,
     /// Current working directory we're browsing
     pub current_dir: PathBuf,
-    /// All files/directories in current directory (unfiltered)
-    all_items: Vec<String>,
-    /// All paths in current directory (unfiltered)
-    all_paths: Vec<PathBuf>,
     /// How to open the selected file
     open_type: OpenType,
+    /// Files opened before, for recalling with Up and Down
+    history: HistoryRecall,
+    /// Directory being browsed when recalling started, to go back to
+    recall_origin: Option<PathBuf>,
 }
 
 impl FileSelectorMode {
     /// Create a new FileSelectorMode with initial state
     pub fn new(open_type: OpenType) -> Self {
         Self {
-            input: String::new(),
-            matches: Vec::new(),
-            paths: Vec::new(),
-            selected_index: 0,
-            max_visible_items: 8, // Show 8 items at once
-            item_scroll_offset: 0,
+            menu: SelectionMenu::new(DEFAULT_MAX_VISIBLE_ITEMS),
             buffer_id: None,
             current_dir: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
-            all_items: Vec::new(),
-            all_paths: Vec::new(),
             open_type,
+            history: HistoryRecall::default(),
+            recall_origin: None,
         }
     }
 
+    /// Files opened before, most recent first, for Up to recall
+    pub fn set_history(&mut self, history: Vec<String>) {
+        self.history = HistoryRecall::new(history);
+    }
+
+    /// Set how many entries are listed at once
+    pub fn set_max_visible_items(&mut self, max_visible_items: usize) {
+        self.menu.set_max_visible_items(max_visible_items);
+    }
+
     /// Initialize with buffer and load current directory
     pub fn init_with_buffer(&mut self, buffer_id: BufferId) {
-        // Reset all state to ensure clean initialization
-        self.input.clear();
         self.buffer_id = Some(buffer_id);
-        self.selected_index = 0;
-        self.item_scroll_offset = 0;
-
-        // Load current directory contents
-        self.load_directory();
-        self.update_matches_internal();
-        self.update_scroll_to_center();
+        self.menu.init_with_items(self.load_directory());
     }
 
     /// Load the contents of the current directory
-    fn load_directory(&mut self) {
-        self.all_items.clear();
-        self.all_paths.clear();
+    fn load_directory(&self) -> Vec<FileItem> {
+        let mut items = Vec::new();
 
         // Always add ".." to go up a directory (unless we're at root)
-        if self.current_dir.parent().is_some() {
-            self.all_items.push("../".to_string());
-            self.all_paths.push(
-                self.current_dir
-                    .parent()
-                    .expect("Directory should have a parent")
-                    .to_path_buf(),
-            );
+        if let Some(parent) = self.current_dir.parent() {
+            items.push(FileItem {
+                name: "../".to_string(),
+                path: parent.to_path_buf(),
+            });
         }
 
         // Read directory contents
@@ -104,148 +113,126 @@ impl FileSelectorMode {
                     let path = entry.path();
 
                     if metadata.is_dir() {
-                        dirs.push((format!("{name}/"), path));
+                        dirs.push(FileItem {
+                            name: format!("{name}/"),
+                            path,
+                        });
                     } else {
-                        files.push((name, path));
+                        files.push(FileItem { name, path });
                     }
                 }
             }
 
-            // Sort directories and files separately
-            dirs.sort_by(|a, b| a.0.cmp(&b.0));
-            files.sort_by(|a, b| a.0.cmp(&b.0));
-
-            // Add directories first, then files
-            for (name, path) in dirs {
-                self.all_items.push(name);
-                self.all_paths.push(path);
-            }
-            for (name, path) in files {
-                self.all_items.push(name);
-                self.all_paths.push(path);
-            }
+            // Sort directories and files separately, directories first
+            dirs.sort_by(|a, b| a.name.cmp(&b.name));
+            files.sort_by(|a, b| a.name.cmp(&b.name));
+            items.extend(dirs);
+            items.extend(files);
         }
-    }
-
-    /// Update matches based on current input using stored file list
-    fn update_matches_internal(&mut self) {
-        if self.input.is_empty() {
-            // Show all items if no input
-            self.matches = self.all_items.clone();
-            self.paths = self.all_paths.clone();
-        } else {
-            // Filter by prefix/contains
-            let mut filtered_names = Vec::new();
-            let mut filtered_paths = Vec::new();
 
-            for (i, name) in self.all_items.iter().enumerate() {
-                if name.to_lowercase().contains(&self.input.to_lowercase()) {
-                    filtered_names.push(name.clone());
-                    filtered_paths.push(self.all_paths[i].clone());
-                }
-            }
-
-            self.matches = filtered_names;
-            self.paths = filtered_paths;
-        }
-
-        // Reset selection to first match
-        self.selected_index = 0;
-        self.item_scroll_offset = 0;
+        items
+    }
 
-        // Ensure we keep the selection centered
-        self.update_scroll_to_center();
+    fn header(&self) -> String {
+        format!("Directory: {}", self.current_dir.display())
     }
 
     /// Generate buffer content string
     pub fn generate_buffer_content(&self) -> String {
-        let mut content = String::new();
-
-        // Show current directory path
-        content.push_str(&format!("Directory: {}\n", self.current_dir.display()));
-
-        // Show user input on next line if any
-        if !self.input.is_empty() {
-            content.push_str(&format!("Filter: {}\n", self.input));
-        }
-
-        // File/directory lines with highlighting
-        let visible_items = self.visible_items();
-        for (idx, item_name) in visible_items.iter().enumerate() {
-            let is_selected = self.visible_selection_index() == Some(idx);
-            if is_selected {
-                // Mark selected item with arrow or highlighting
-                content.push_str(&format!("> {item_name}\n"));
-            } else {
-                content.push_str(&format!("  {item_name}\n"));
-            }
-        }
-
-        content
+        self.menu.generate_buffer_content(Some(&self.header()))
     }
 
     /// Get the currently selected file path
     pub fn get_selected_path(&self) -> Option<PathBuf> {
-        self.paths.get(self.selected_index).cloned()
+        self.menu.get_selected_item().map(|item| item.path.clone())
+    }
+
+    /// What to preview beside the list for the selected entry
+    pub fn selected_preview(&self) -> Option<PreviewSource> {
+        self.get_selected_path().map(PreviewSource::File)
     }
 
     /// Navigate to a directory
     pub fn navigate_to_directory(&mut self, path: PathBuf) -> bool {
         if path.is_dir() {
             self.current_dir = path;
-            self.input.clear(); // Clear filter when changing directories
-            self.load_directory();
-            self.update_matches_internal();
-            self.update_scroll_to_center();
+            // Clears the filter too
+            self.menu.init_with_items(self.load_directory());
             true
         } else {
             false
         }
     }
 
-    /// Update scroll offset to keep selection centered
-    fn update_scroll_to_center(&mut self) {
-        if self.matches.len() <= self.max_visible_items {
-            // All matches fit, no scrolling needed
-            self.item_scroll_offset = 0;
-            return;
+    /// List `path`'s directory with `path` selected, if the directory's still
+    /// there
+    fn show_path(&mut self, path: &Path) -> bool {
+        let Some(dir) = path.parent() else {
+            return false;
+        };
+        if !self.navigate_to_directory(dir.to_path_buf()) {
+            return false;
         }
-
-        let half_window = self.max_visible_items / 2;
-
-        // Try to center the selection
-        if self.selected_index < half_window {
-            // Near the beginning, show from start
-            self.item_scroll_offset = 0;
-        } else if self.selected_index >= self.matches.len() - half_window {
-            // Near the end, show until end
-            self.item_scroll_offset = self.matches.len() - self.max_visible_items;
-        } else {
-            // Center the selection
-            self.item_scroll_offset = self.selected_index - half_window;
+        if let Some(index) = self
+            .menu
+            .get_filtered_items()
+            .iter()
+            .position(|item| item.path == path)
+        {
+            self.menu.select_index(index);
         }
+        true
     }
 
-    /// Get the visible items for rendering
-    pub fn visible_items(&self) -> &[String] {
-        let start = self.item_scroll_offset;
-        let end = (start + self.max_visible_items).min(self.matches.len());
-        &self.matches[start..end]
+    /// Up: show an older file from the history when already recalling or at
+    /// the top of the list. False to let the menu move the selection instead.
+    fn recall_older(&mut self) -> bool {
+        let recalling = self.history.is_recalling();
+        if !recalling && self.menu.selected_index != 0 {
+            return false;
+        }
+        let origin = self.current_dir.clone();
+        let input = self.menu.input.clone();
+        while let Some(path) = self.history.older(&input).map(PathBuf::from) {
+            if self.show_path(&path) {
+                if !recalling {
+                    self.recall_origin = Some(origin);
+                }
+                return true;
+            }
+        }
+        if !recalling {
+            // Nothing to recall; the menu wraps to the bottom as usual
+            self.history.reset();
+        }
+        recalling
     }
 
-    /// Get the relative index of the selection within the visible items
-    pub fn visible_selection_index(&self) -> Option<usize> {
-        if self.matches.is_empty() {
-            return None;
+    /// Down while recalling: show a newer file from the history, or past the
+    /// newest go back to the directory and filter from before recalling
+    fn recall_newer(&mut self) -> bool {
+        while let Some(entry) = self.history.newer().map(str::to_string) {
+            if self.history.is_recalling() {
+                if self.show_path(Path::new(&entry)) {
+                    return true;
+                }
+                continue;
+            }
+            if let Some(origin) = self.recall_origin.take() {
+                self.navigate_to_directory(origin);
+            }
+            for c in entry.chars() {
+                self.menu.add_filter_char(c);
+            }
+            return true;
         }
+        false
+    }
 
-        if self.selected_index >= self.item_scroll_offset
-            && self.selected_index < self.item_scroll_offset + self.max_visible_items
-        {
-            Some(self.selected_index - self.item_scroll_offset)
-        } else {
-            None
-        }
+    fn update_actions(&self) -> Vec<ModeAction> {
+        let mut actions = self.menu.generate_update_actions(Some(&self.header()));
+        actions.push(ModeAction::PreviewSelection(self.selected_preview()));
+        actions
     }
 }
 
@@ -262,87 +249,40 @@ impl Mode for FileSelectorMode {
     }
 
     fn perform(&mut self, action: &KeyAction) -> ModeResult {
+        // Up and Down recall files opened before, from the top of the list
+        let recalled = match action {
+            KeyAction::Cursor(CursorDirection::Up) => self.recall_older(),
+            KeyAction::Cursor(CursorDirection::Down) => self.recall_newer(),
+            _ => false,
+        };
+        if recalled {
+            return ModeResult::Consumed(self.update_actions());
+        }
+        if matches!(action, KeyAction::AlphaNumeric(_) | KeyAction::Backspace) {
+            // Editing the filter keeps the recalled file's directory
+            self.history.reset();
+            self.recall_origin = None;
+        }
+
+        // Filtering, selection movement, and paging are handled by the menu
+        if self.menu.handle_key_action(action) {
+            return ModeResult::Consumed(self.update_actions());
+        }
+
         // Handle file selector mode specific actions
         match action {
-            KeyAction::AlphaNumeric(c) => {
-                self.input.push(*c);
-                self.update_matches_internal();
-                // Clear buffer and replace with new content
-                ModeResult::Consumed(vec![
-                    ModeAction::ClearText,
-                    ModeAction::InsertText(ActionPosition::start(), self.generate_buffer_content()),
-                ])
-            }
-            KeyAction::Backspace => {
-                if !self.input.is_empty() {
-                    self.input.pop();
-                    self.update_matches_internal();
-                    ModeResult::Consumed(vec![
-                        ModeAction::ClearText,
-                        ModeAction::InsertText(
-                            ActionPosition::start(),
-                            self.generate_buffer_content(),
-                        ),
-                    ])
-                } else {
-                    ModeResult::Ignored
-                }
-            }
-            KeyAction::Cursor(crate::keys::CursorDirection::Up) => {
-                if !self.matches.is_empty() && self.selected_index > 0 {
-                    self.selected_index -= 1;
-                    self.update_scroll_to_center();
-                }
-                // Always consume arrow keys in file selector mode, even if we can't move
-                ModeResult::Consumed(vec![
-                    ModeAction::ClearText,
-                    ModeAction::InsertText(ActionPosition::start(), self.generate_buffer_content()),
-                ])
-            }
-            KeyAction::Cursor(crate::keys::CursorDirection::Down) => {
-                if !self.matches.is_empty() && self.selected_index < self.matches.len() - 1 {
-                    self.selected_index += 1;
-                    self.update_scroll_to_center();
-                }
-                // Always consume arrow keys in file selector mode, even if we can't move
-                ModeResult::Consumed(vec![
-                    ModeAction::ClearText,
-                    ModeAction::InsertText(ActionPosition::start(), self.generate_buffer_content()),
-                ])
-            }
-            KeyAction::Tab => {
-                // Tab to cycle through matches
-                if !self.matches.is_empty() {
-                    self.selected_index = (self.selected_index + 1) % self.matches.len();
-                    self.update_scroll_to_center();
-                    ModeResult::Consumed(vec![
-                        ModeAction::ClearText,
-                        ModeAction::InsertText(
-                            ActionPosition::start(),
-                            self.generate_buffer_content(),
-                        ),
-                    ])
-                } else {
-                    ModeResult::Ignored
-                }
-            }
             KeyAction::Enter => {
                 if let Some(selected_path) = self.get_selected_path() {
                     if selected_path.is_dir() {
                         // Navigate to directory
                         self.navigate_to_directory(selected_path);
-                        ModeResult::Consumed(vec![
-                            ModeAction::ClearText,
-                            ModeAction::InsertText(
-                                ActionPosition::start(),
-                                self.generate_buffer_content(),
-                            ),
-                        ])
+                        ModeResult::Consumed(self.update_actions())
                     } else {
-                        // Open the selected file
+                        // Open the selected file, at the line typed after it
                         ModeResult::Consumed(vec![ModeAction::OpenFile {
                             path: selected_path,
                             open_type: self.open_type,
+                            position: FileLocation::parse(&self.menu.input).position,
                         }])
                     }
                 } else {
@@ -356,4 +296,9 @@ impl Mode for FileSelectorMode {
             _ => ModeResult::Ignored,
         }
     }
+
+    fn resize(&mut self, list_rows: usize) -> ModeResult {
+        self.set_max_visible_items(list_rows);
+        ModeResult::Consumed(self.update_actions())
+    }
 }
@@ -19,16 +19,60 @@
 //! - Line-based diff and merge with conflict detection
 //! - Integration with undo system for safety
 
+use notify::event::{ModifyKind, RenameMode};
 use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
-use similar::{ChangeTag, TextDiff};
-use std::collections::HashMap;
+use ropey::Rope;
+use similar::{ChangeTag, DiffOp, TextDiff};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::{Arc, RwLock};
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::Notify;
 
+use crate::buffer::Buffer;
 use crate::BufferId;
 
+/// How often renderers that can't await `FileWatcher::changed` should drain events
+pub const EVENT_DRAIN_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How often watched files' modification times are checked directly, in case the
+/// platform watcher drops or never delivers an event
+pub const FALLBACK_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Modification time of `path`, if it can be read
+fn file_mtime(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Lines of `text` split the way the buffer's rope splits them, without line endings
+fn rope_lines(text: &str) -> Vec<String> {
+    Rope::from_str(text)
+        .lines()
+        .map(|line| strip_line_ending(&line.to_string()).to_string())
+        .collect()
+}
+
+fn strip_line_ending(line: &str) -> &str {
+    line.trim_end_matches(['\n', '\r'])
+}
+
+/// Canonical form of `path`, which may no longer exist (e.g. after a delete);
+/// falls back to canonicalizing the parent directory
+fn canonical_path(path: &Path) -> PathBuf {
+    if let Ok(canonical) = path.canonicalize() {
+        return canonical;
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => parent
+            .canonicalize()
+            .map(|parent| parent.join(name))
+            .unwrap_or_else(|_| path.to_path_buf()),
+        _ => path.to_path_buf(),
+    }
+}
+
 /// Represents a change to a specific line range
 #[derive(Debug, Clone)]
 pub struct LineChange {
@@ -59,6 +103,121 @@ pub enum MergeResult {
     LocalPreserved { new_base: String, message: String },
 }
 
+/// Per-line change state of a buffer relative to its base content, updated
+/// incrementally from edits so the gutter doesn't need a full diff each frame
+#[derive(Debug, Clone, Default)]
+pub struct LineTracker {
+    /// Base content split into lines
+    base_lines: Vec<String>,
+    /// For each current line, the base line it came from (None if inserted)
+    origins: Vec<Option<usize>>,
+    /// Current lines that were changed and have since been written to disk
+    saved: Vec<bool>,
+}
+
+impl LineTracker {
+    /// Tracker for a buffer whose content is its base
+    pub fn clean(base: &str) -> Self {
+        let base_lines = rope_lines(base);
+        let line_count = base_lines.len();
+        Self {
+            base_lines,
+            origins: (0..line_count).map(Some).collect(),
+            saved: vec![false; line_count],
+        }
+    }
+
+    /// Tracker built from a full line diff of `current` against `base`
+    pub fn diff(base: &str, current: &str) -> Self {
+        let base_lines = rope_lines(base);
+        let current_lines = rope_lines(current);
+
+        let mut origins = vec![None; current_lines.len()];
+        let ops =
+            similar::capture_diff_slices(similar::Algorithm::Myers, &base_lines, &current_lines);
+        for op in ops {
+            if let DiffOp::Equal {
+                old_index,
+                new_index,
+                len,
+            } = op
+            {
+                for i in 0..len {
+                    origins[new_index + i] = Some(old_index + i);
+                }
+            }
+        }
+
+        Self {
+            base_lines,
+            origins,
+            saved: vec![false; current_lines.len()],
+        }
+    }
+
+    /// Number of lines in the content being tracked
+    pub fn line_count(&self) -> usize {
+        self.origins.len()
+    }
+
+    /// Account for an edit that replaced lines `start..=old_end` with
+    /// `start..=new_end`. The first and last lines keep their origins, so
+    /// splitting or joining a line only flags lines whose text changed.
+    /// Returns false if the edit doesn't fit the tracked content.
+    pub fn apply_edit(&mut self, start: usize, old_end: usize, new_end: usize) -> bool {
+        if start > old_end || start > new_end || old_end >= self.origins.len() {
+            return false;
+        }
+
+        let new_len = new_end - start + 1;
+        let mut origins = vec![None; new_len];
+        let mut saved = vec![false; new_len];
+        origins[0] = self.origins[start];
+        saved[0] = self.saved[start];
+        if new_len > 1 {
+            origins[new_len - 1] = self.origins[old_end];
+            saved[new_len - 1] = self.saved[old_end];
+        }
+
+        self.origins.splice(start..=old_end, origins);
+        self.saved.splice(start..=old_end, saved);
+        true
+    }
+
+    /// Whether `line`, whose current text is `text`, differs from the base
+    pub fn is_modified(&self, line: usize, text: &str) -> bool {
+        match self.origins.get(line) {
+            Some(Some(origin)) => {
+                self.base_lines.get(*origin).map(String::as_str) != Some(strip_line_ending(text))
+            }
+            _ => true,
+        }
+    }
+
+    /// Whether `line` was changed and then saved
+    pub fn is_saved(&self, line: usize) -> bool {
+        self.saved.get(line).copied().unwrap_or(false)
+    }
+
+    /// Rebase onto `content`, which has just been written to disk. Lines that
+    /// differed from the old base are remembered as saved.
+    pub fn mark_saved(&mut self, content: &str) {
+        let lines = rope_lines(content);
+        if lines.len() == self.line_count() {
+            for (i, line) in lines.iter().enumerate() {
+                if self.is_modified(i, line) {
+                    self.saved[i] = true;
+                }
+            }
+        } else {
+            self.saved = vec![false; lines.len()];
+        }
+
+        self.origins = (0..lines.len()).map(Some).collect();
+        self.base_lines = lines;
+    }
+}
+
 /// Tracks the base version and state for a single buffer
 #[derive(Debug, Clone)]
 pub struct BufferSyncState {
@@ -68,37 +227,58 @@ pub struct BufferSyncState {
     pub base_content: String,
     /// Timestamp of when base was last updated
     pub base_timestamp: Instant,
-    /// Whether we're currently ignoring changes (e.g., during our own save)
-    pub ignore_until: Option<Instant>,
+    /// Modification time of the file when last seen, including right after our
+    /// own writes; a change that leaves it as it was isn't reported
+    pub last_modified: Option<SystemTime>,
+    /// Whether the file is still on disk where we left it
+    pub disk_state: DiskState,
+    /// Which lines differ from the base, for the gutter
+    pub lines: LineTracker,
+}
+
+/// Whether a watched buffer's file still exists at its path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiskState {
+    Present,
+    /// Removed externally; the buffer holds the only copy
+    Deleted,
+    /// Moved externally to the given path
+    Renamed(PathBuf),
 }
 
 impl BufferSyncState {
     pub fn new(file_path: PathBuf, content: String) -> Self {
+        let last_modified = file_mtime(&file_path);
+        let lines = LineTracker::clean(&content);
         Self {
             file_path,
             base_content: content,
             base_timestamp: Instant::now(),
-            ignore_until: None,
+            last_modified,
+            disk_state: DiskState::Present,
+            lines,
         }
     }
 
-    /// Update the base content after a successful merge or save
+    /// Update the base content after a successful merge or save. The file's
+    /// modification time is recorded, so the change a save made isn't reported.
     pub fn update_base(&mut self, content: String) {
         self.base_content = content;
         self.base_timestamp = Instant::now();
+        self.last_modified = file_mtime(&self.file_path);
+        self.disk_state = DiskState::Present;
     }
+}
 
-    /// Temporarily ignore file changes (call before saving)
-    pub fn ignore_for(&mut self, duration: Duration) {
-        self.ignore_until = Some(Instant::now() + duration);
-    }
-
-    /// Check if we should ignore current changes
-    pub fn should_ignore(&self) -> bool {
-        self.ignore_until
-            .map(|until| Instant::now() < until)
-            .unwrap_or(false)
-    }
+/// What happened to a watched file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileChangeKind {
+    /// Contents changed, or the file was created or replaced
+    Modified,
+    /// The file no longer exists at its path
+    Deleted,
+    /// The file was moved to the given path
+    Renamed(PathBuf),
 }
 
 /// Event sent when an external file change is detected
@@ -106,6 +286,60 @@ impl BufferSyncState {
 pub struct FileChangeEvent {
     pub buffer_id: BufferId,
     pub file_path: PathBuf,
+    pub kind: FileChangeKind,
+}
+
+/// Translate a notify event into change events for the watched files it touches
+fn classify_event(
+    event: &Event,
+    path_to_buffer: &HashMap<PathBuf, BufferId>,
+) -> Vec<FileChangeEvent> {
+    let watched = |path: &Path| {
+        let canonical = canonical_path(path);
+        path_to_buffer
+            .get(&canonical)
+            .map(|buffer_id| (*buffer_id, canonical))
+    };
+    let events_for = |kind: FileChangeKind| -> Vec<FileChangeEvent> {
+        event
+            .paths
+            .iter()
+            .filter_map(|path| watched(path))
+            .map(|(buffer_id, file_path)| FileChangeEvent {
+                buffer_id,
+                file_path,
+                kind: kind.clone(),
+            })
+            .collect()
+    };
+
+    match event.kind {
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+            let mut events = Vec::new();
+            // Our file moved away...
+            if let Some((buffer_id, file_path)) = watched(&event.paths[0]) {
+                events.push(FileChangeEvent {
+                    buffer_id,
+                    file_path,
+                    kind: FileChangeKind::Renamed(canonical_path(&event.paths[1])),
+                });
+            }
+            // ...or something was moved over it, as editors do for atomic saves
+            if let Some((buffer_id, file_path)) = watched(&event.paths[1]) {
+                events.push(FileChangeEvent {
+                    buffer_id,
+                    file_path,
+                    kind: FileChangeKind::Modified,
+                });
+            }
+            events
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) | EventKind::Remove(_) => {
+            events_for(FileChangeKind::Deleted)
+        }
+        EventKind::Modify(_) | EventKind::Create(_) => events_for(FileChangeKind::Modified),
+        _ => Vec::new(),
+    }
 }
 
 /// Manages file watching for all open buffers
@@ -120,6 +354,10 @@ pub struct FileWatcher {
     path_to_buffer: Arc<RwLock<HashMap<PathBuf, BufferId>>>,
     /// Sync state per buffer
     sync_states: HashMap<BufferId, BufferSyncState>,
+    /// Signalled by the watcher callback when events are waiting
+    wake: Arc<Notify>,
+    /// When modification times were last checked by the fallback poll
+    last_fallback_poll: Instant,
 }
 
 impl FileWatcher {
@@ -131,6 +369,8 @@ impl FileWatcher {
             event_rx,
             path_to_buffer: Arc::new(RwLock::new(HashMap::new())),
             sync_states: HashMap::new(),
+            wake: Arc::new(Notify::new()),
+            last_fallback_poll: Instant::now(),
         }
     }
 
@@ -138,33 +378,17 @@ impl FileWatcher {
     pub fn init(&mut self) -> Result<(), notify::Error> {
         let tx = self.event_tx.clone();
         let path_to_buffer = self.path_to_buffer.clone();
+        let wake = self.wake.clone();
 
         let watcher = notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
             let Ok(event) = res else { return };
-
-            // Only care about modify/create/remove events
-            if !matches!(
-                event.kind,
-                EventKind::Modify(_) | EventKind::Create(_) | EventKind::Remove(_)
-            ) {
+            let Ok(map) = path_to_buffer.read() else {
                 return;
-            }
-
-            for path in &event.paths {
-                let Ok(canonical) = path.canonicalize() else {
-                    continue;
-                };
-                let Ok(map) = path_to_buffer.read() else {
-                    continue;
-                };
-                let Some(buffer_id) = map.get(&canonical) else {
-                    continue;
-                };
+            };
 
-                let _ = tx.send(FileChangeEvent {
-                    buffer_id: *buffer_id,
-                    file_path: canonical,
-                });
+            for change in classify_event(&event, &map) {
+                let _ = tx.send(change);
+                wake.notify_one();
             }
         })?;
 
@@ -215,28 +439,98 @@ impl FileWatcher {
                 map.remove(&state.file_path);
             }
 
-            if let Some(ref mut watcher) = self.watcher {
-                if let Some(parent) = state.file_path.parent() {
+            // Other files in the directory may still be watched through it
+            let parent = state.file_path.parent();
+            let shared = self
+                .sync_states
+                .values()
+                .any(|other| other.file_path.parent() == parent);
+            if !shared {
+                if let (Some(watcher), Some(parent)) = (&mut self.watcher, parent) {
                     let _ = watcher.unwatch(parent);
                 }
             }
         }
     }
 
-    /// Poll for file change events (non-blocking)
-    pub fn poll_events(&self) -> Vec<FileChangeEvent> {
+    /// Drain file change events delivered by the watcher (non-blocking). Every
+    /// `FALLBACK_POLL_INTERVAL` this also checks watched files' modification
+    /// times directly and reports any change the watcher missed.
+    pub fn poll_events(&mut self) -> Vec<FileChangeEvent> {
         let mut events = Vec::new();
         while let Ok(event) = self.event_rx.try_recv() {
-            // Check if we should ignore this event
-            if let Some(state) = self.sync_states.get(&event.buffer_id) {
-                if !state.should_ignore() {
-                    events.push(event);
+            // A modification that leaves the file as last seen is our own save
+            // or one already reported
+            if let Some(state) = self.sync_states.get_mut(&event.buffer_id) {
+                let modified = file_mtime(&state.file_path);
+                if event.kind == FileChangeKind::Modified
+                    && modified.is_some()
+                    && modified == state.last_modified
+                {
+                    continue;
+                }
+                state.last_modified = modified;
+                events.push(event);
+            }
+        }
+
+        if self.last_fallback_poll.elapsed() >= FALLBACK_POLL_INTERVAL {
+            self.last_fallback_poll = Instant::now();
+            for (buffer_id, state) in self.sync_states.iter_mut() {
+                let modified = file_mtime(&state.file_path);
+                if modified == state.last_modified {
+                    continue;
                 }
+                state.last_modified = modified;
+
+                if events.iter().any(|e| e.buffer_id == *buffer_id) {
+                    continue;
+                }
+                let kind = if modified.is_none() && !state.file_path.exists() {
+                    FileChangeKind::Deleted
+                } else {
+                    FileChangeKind::Modified
+                };
+                events.push(FileChangeEvent {
+                    buffer_id: *buffer_id,
+                    file_path: state.file_path.clone(),
+                    kind,
+                });
             }
         }
+
+        // A rename within the watched directory is also reported as the old name
+        // going away; the rename is the more useful of the two
+        let renamed: Vec<BufferId> = events
+            .iter()
+            .filter(|e| matches!(e.kind, FileChangeKind::Renamed(_)))
+            .map(|e| e.buffer_id)
+            .collect();
+        events.retain(|e| e.kind != FileChangeKind::Deleted || !renamed.contains(&e.buffer_id));
+
         events
     }
 
+    /// Make the next `poll_events` check modification times, as if the fallback
+    /// poll were due
+    #[cfg(test)]
+    pub(crate) fn expire_fallback_poll(&mut self) {
+        self.last_fallback_poll = Instant::now() - FALLBACK_POLL_INTERVAL;
+    }
+
+    /// Record whether a buffer's file is still on disk
+    pub fn set_disk_state(&mut self, buffer_id: BufferId, disk_state: DiskState) {
+        if let Some(state) = self.sync_states.get_mut(&buffer_id) {
+            state.disk_state = disk_state;
+        }
+    }
+
+    /// Wait until the watcher reports a change. Events are then collected with
+    /// `poll_events`; this only tells the caller it is worth doing so.
+    pub async fn changed(&self) {
+        self.wake.notified().await;
+    }
+
     /// Get sync state for a buffer
     pub fn get_sync_state(&self, buffer_id: BufferId) -> Option<&BufferSyncState> {
         self.sync_states.get(&buffer_id)
@@ -254,13 +548,6 @@ impl FileWatcher {
         }
     }
 
-    /// Mark that we're about to save, so ignore imminent file change events
-    pub fn mark_saving(&mut self, buffer_id: BufferId) {
-        if let Some(state) = self.sync_states.get_mut(&buffer_id) {
-            state.ignore_for(Duration::from_millis(500));
-        }
-    }
-
     /// Get diagnostic info about the file watcher state
     pub fn status(&self) -> String {
         let watcher_status = if self.watcher.is_some() {
@@ -289,41 +576,95 @@ impl FileWatcher {
         )
     }
 
-    /// Get the set of line indices that differ between base and current content
-    ///
-    /// Returns a HashSet of 0-indexed line numbers that have been modified
-    pub fn get_modified_lines(
-        &self,
+    /// Record an edit reported as `ChromeAction::BufferChanged` (char offsets) in
+    /// the buffer's line tracker, rebuilding it if it has fallen out of step
+    pub fn apply_edit(
+        &mut self,
         buffer_id: BufferId,
-        current_content: &str,
-    ) -> std::collections::HashSet<usize> {
-        use std::collections::HashSet;
+        buffer: &Buffer,
+        start: usize,
+        new_end: usize,
+    ) {
+        let Some(state) = self.sync_states.get_mut(&buffer_id) else {
+            return;
+        };
+
+        // Lines of the whole text, whatever the buffer is narrowed to
+        let (start_line, new_end_line, total_lines) = buffer.with_read(|b| {
+            let len_chars = b.buffer.len_chars();
+            (
+                b.buffer.char_to_line(start.min(len_chars)),
+                b.buffer.char_to_line(new_end.min(len_chars)),
+                b.buffer.len_lines(),
+            )
+        });
+        let delta = total_lines as isize - state.lines.line_count() as isize;
+        let old_end_line = new_end_line as isize - delta;
+
+        let applied = old_end_line >= 0
+            && state
+                .lines
+                .apply_edit(start_line, old_end_line as usize, new_end_line);
+        if !applied || state.lines.line_count() != total_lines {
+            state.lines = LineTracker::diff(&state.base_content, &buffer.content());
+        }
+    }
+
+    /// Rebuild a buffer's line tracker from a full diff against its base
+    pub fn resync_lines(&mut self, buffer_id: BufferId, current_content: &str) {
+        if let Some(state) = self.sync_states.get_mut(&buffer_id) {
+            state.lines = LineTracker::diff(&state.base_content, current_content);
+        }
+    }
 
+    /// Update base content after saving `content`, so changed lines show as saved
+    pub fn mark_saved(&mut self, buffer_id: BufferId, content: String) {
+        if let Some(state) = self.sync_states.get_mut(&buffer_id) {
+            if state.lines.line_count() != rope_lines(&content).len() {
+                state.lines = LineTracker::diff(&state.base_content, &content);
+            }
+            state.lines.mark_saved(&content);
+            state.update_base(content);
+        }
+    }
+
+    /// Modified and saved line indices among `lines` of the buffer, for the gutter.
+    /// Saved lines are those changed and then written to disk; a line is never
+    /// in both sets. Lines are numbered as the buffer's line API numbers them,
+    /// from the first the buffer is narrowed to.
+    pub fn line_changes(
+        &self,
+        buffer_id: BufferId,
+        buffer: &Buffer,
+        lines: Range<usize>,
+    ) -> (HashSet<usize>, HashSet<usize>) {
+        let mut modified = HashSet::new();
+        let mut saved = HashSet::new();
         let Some(state) = self.sync_states.get(&buffer_id) else {
-            return HashSet::new();
+            return (modified, saved);
         };
 
-        let changes = compute_line_diff(&state.base_content, current_content);
-        let mut modified_lines = HashSet::new();
-
-        for change in changes {
-            // Mark all lines in the affected range as modified
-            // For deletions, mark the line where deletion happened
-            // For insertions/modifications, mark the new lines
-            if change.new_lines.is_empty() {
-                // Deletion - mark the line where it was deleted
-                if change.start_line < current_content.lines().count() {
-                    modified_lines.insert(change.start_line);
-                }
-            } else {
-                // Insertion or modification - mark all new lines
-                for i in 0..change.new_lines.len() {
-                    modified_lines.insert(change.start_line + i);
-                }
+        // An edit that didn't come through apply_edit; fall back to a full diff
+        let (total_lines, offset) =
+            buffer.with_read(|b| (b.buffer.len_lines(), b.narrowed_line_offset()));
+        let rebuilt;
+        let tracker = if state.lines.line_count() == total_lines {
+            &state.lines
+        } else {
+            rebuilt = LineTracker::diff(&state.base_content, &buffer.content());
+            &rebuilt
+        };
+
+        for line in lines.start + offset..(lines.end + offset).min(total_lines) {
+            let text = buffer.with_read(|b| b.buffer.line(line).to_string());
+            if tracker.is_modified(line, &text) {
+                modified.insert(line - offset);
+            } else if tracker.is_saved(line) {
+                saved.insert(line - offset);
             }
         }
 
-        modified_lines
+        (modified, saved)
     }
 }
 
@@ -434,6 +775,45 @@ fn merge_non_overlapping(
     result
 }
 
+/// What auto-revert does when a watched file's contents change on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutoRevertAction {
+    /// The file holds what the buffer was last synced with
+    NoChange,
+    /// The buffer has no unsaved changes, so reload it
+    Reload,
+    /// The buffer already holds the file's new contents; only the base moves
+    AlreadyCurrent,
+    /// Reloading would discard unsaved changes, so ask what to do
+    Ask,
+}
+
+/// Decide how auto-revert handles the file changing from `base` to `external`
+/// while the buffer holds `local`
+pub fn auto_revert_action(base: &str, local: &str, external: &str) -> AutoRevertAction {
+    if external == base {
+        AutoRevertAction::NoChange
+    } else if local == base {
+        AutoRevertAction::Reload
+    } else if local == external {
+        AutoRevertAction::AlreadyCurrent
+    } else {
+        AutoRevertAction::Ask
+    }
+}
+
+/// Unified diff from a buffer's `local` text to its file's `external`
+/// contents on disk, both named after `file_name`
+pub fn unified_diff(file_name: &str, local: &str, external: &str) -> String {
+    TextDiff::from_lines(local, external)
+        .unified_diff()
+        .header(
+            &format!("{file_name} (buffer)"),
+            &format!("{file_name} (disk)"),
+        )
+        .to_string()
+}
+
 /// Attempt to merge external changes with local changes
 pub fn merge_changes(base: &str, local: &str, external: &str) -> MergeResult {
     // If local hasn't changed from base, just take external
@@ -552,6 +932,144 @@ fn merge_with_conflicts(_base: &str, local: &str, external: &str) -> (String, us
 #[cfg(test)]
 mod tests {
     use super::*;
+    use slotmap::SlotMap;
+
+    #[test]
+    fn test_fallback_poll_detects_missed_change() {
+        let path = std::env::temp_dir().join(format!("roe-fallback-{}.txt", std::process::id()));
+        std::fs::write(&path, "line1\n").unwrap();
+
+        let mut buffers: SlotMap<BufferId, ()> = SlotMap::with_key();
+        let buffer_id = buffers.insert(());
+
+        // Track the file without a platform watcher, as if notify were unavailable
+        let mut watcher = FileWatcher::new();
+        let mut state = BufferSyncState::new(path.clone(), "line1\n".to_string());
+        assert!(watcher.poll_events().is_empty());
+
+        // Pretend the file changed since we last looked and the poll is due
+        state.last_modified = None;
+        watcher.sync_states.insert(buffer_id, state);
+        watcher.last_fallback_poll = Instant::now() - FALLBACK_POLL_INTERVAL;
+
+        let events = watcher.poll_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].buffer_id, buffer_id);
+
+        // Nothing further until the file changes again
+        watcher.last_fallback_poll = Instant::now() - FALLBACK_POLL_INTERVAL;
+        assert!(watcher.poll_events().is_empty());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_own_save_absorbed_by_fallback_poll() {
+        let path = std::env::temp_dir().join(format!("roe-own-save-{}.txt", std::process::id()));
+        std::fs::write(&path, "line1\n").unwrap();
+
+        let mut buffers: SlotMap<BufferId, ()> = SlotMap::with_key();
+        let buffer_id = buffers.insert(());
+        let mut watcher = FileWatcher::new();
+        let state = BufferSyncState::new(path.clone(), "line1\n".to_string());
+        watcher.sync_states.insert(buffer_id, state);
+
+        // Our own save isn't reported, however late the poll comes
+        std::fs::write(&path, "line2\n").unwrap();
+        watcher.update_base(buffer_id, "line2\n".to_string());
+        watcher.expire_fallback_poll();
+        assert!(watcher.poll_events().is_empty());
+
+        // A change straight after it is
+        let saved = file_mtime(&path).unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(saved + Duration::from_secs(1)).unwrap();
+        drop(file);
+        watcher.expire_fallback_poll();
+        let events = watcher.poll_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, FileChangeKind::Modified);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    fn watched_temp_file(name: &str) -> (PathBuf, BufferId, HashMap<PathBuf, BufferId>) {
+        let path = std::env::temp_dir().canonicalize().unwrap().join(format!(
+            "roe-{}-{}.txt",
+            name,
+            std::process::id()
+        ));
+        let mut buffers: SlotMap<BufferId, ()> = SlotMap::with_key();
+        let buffer_id = buffers.insert(());
+        let mut path_to_buffer = HashMap::new();
+        path_to_buffer.insert(path.clone(), buffer_id);
+        (path, buffer_id, path_to_buffer)
+    }
+
+    #[test]
+    fn test_classify_delete() {
+        use notify::event::RemoveKind;
+
+        // The file is already gone by the time the event is handled
+        let (path, buffer_id, path_to_buffer) = watched_temp_file("deleted");
+        let event = Event::new(EventKind::Remove(RemoveKind::File)).add_path(path.clone());
+
+        let changes = classify_event(&event, &path_to_buffer);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].buffer_id, buffer_id);
+        assert_eq!(changes[0].file_path, path);
+        assert_eq!(changes[0].kind, FileChangeKind::Deleted);
+    }
+
+    #[test]
+    fn test_classify_rename() {
+        let (path, buffer_id, path_to_buffer) = watched_temp_file("renamed");
+        let new_path = path.with_extension("moved");
+        let event = Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+            .add_path(path.clone())
+            .add_path(new_path.clone());
+
+        let changes = classify_event(&event, &path_to_buffer);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].buffer_id, buffer_id);
+        assert_eq!(changes[0].kind, FileChangeKind::Renamed(new_path.clone()));
+
+        // Renaming another file over ours (an atomic save) is a modification
+        let event = Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+            .add_path(new_path)
+            .add_path(path);
+        let changes = classify_event(&event, &path_to_buffer);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, FileChangeKind::Modified);
+    }
+
+    #[test]
+    fn test_rename_supersedes_delete() {
+        let (path, buffer_id, _) = watched_temp_file("rename-batch");
+        let mut watcher = FileWatcher::new();
+        watcher
+            .sync_states
+            .insert(buffer_id, BufferSyncState::new(path.clone(), String::new()));
+
+        // inotify reports the old name vanishing as well as the rename itself
+        for kind in [
+            FileChangeKind::Deleted,
+            FileChangeKind::Renamed(path.with_extension("moved")),
+        ] {
+            watcher
+                .event_tx
+                .send(FileChangeEvent {
+                    buffer_id,
+                    file_path: path.clone(),
+                    kind,
+                })
+                .unwrap();
+        }
+
+        let events = watcher.poll_events();
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0].kind, FileChangeKind::Renamed(_)));
+    }
 
     #[test]
     fn test_no_changes() {
@@ -753,4 +1271,139 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_auto_revert_action() {
+        let base = "a\nb\n";
+        // Unmodified buffers follow the file; modified ones ask first
+        assert_eq!(
+            auto_revert_action(base, base, "a\nB\n"),
+            AutoRevertAction::Reload
+        );
+        assert_eq!(
+            auto_revert_action(base, "A\nb\n", "a\nB\n"),
+            AutoRevertAction::Ask
+        );
+        assert_eq!(
+            auto_revert_action(base, "a\nB\n", "a\nB\n"),
+            AutoRevertAction::AlreadyCurrent
+        );
+        assert_eq!(
+            auto_revert_action(base, "A\nb\n", base),
+            AutoRevertAction::NoChange
+        );
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        let diff = unified_diff("notes.txt", "a\nmine\nc\n", "a\ntheirs\nc\n");
+        assert_eq!(
+            diff,
+            "--- notes.txt (buffer)\n+++ notes.txt (disk)\n@@ -1,3 +1,3 @@\n a\n-mine\n+theirs\n c\n"
+        );
+    }
+
+    fn tracked_buffer(content: &str) -> (FileWatcher, BufferId, Buffer) {
+        let mut buffers: SlotMap<BufferId, ()> = SlotMap::with_key();
+        let buffer_id = buffers.insert(());
+        let buffer = Buffer::new(&[]);
+        buffer.load_str(content);
+
+        let mut watcher = FileWatcher::new();
+        watcher.sync_states.insert(
+            buffer_id,
+            BufferSyncState::new(PathBuf::from("/nonexistent/roe-lines.txt"), content.into()),
+        );
+        (watcher, buffer_id, buffer)
+    }
+
+    #[test]
+    fn test_line_changes_edit_and_revert() {
+        let (mut watcher, buffer_id, buffer) = tracked_buffer("a\nb\nc\n");
+
+        buffer.insert_pos("x".to_string(), 3);
+        watcher.apply_edit(buffer_id, &buffer, 3, 4);
+        let (modified, saved) = watcher.line_changes(buffer_id, &buffer, 0..10);
+        assert_eq!(modified, HashSet::from([1]));
+        assert!(saved.is_empty());
+
+        // Deleting the insertion puts the line back to its base text
+        buffer.delete_pos(3, 1);
+        watcher.apply_edit(buffer_id, &buffer, 3, 3);
+        let (modified, _) = watcher.line_changes(buffer_id, &buffer, 0..10);
+        assert!(modified.is_empty());
+    }
+
+    #[test]
+    fn test_line_changes_split_and_join() {
+        let (mut watcher, buffer_id, buffer) = tracked_buffer("a\nbc\nd\n");
+
+        // Splitting "bc" flags both halves but not the lines after it
+        buffer.insert_pos("\n".to_string(), 3);
+        watcher.apply_edit(buffer_id, &buffer, 3, 4);
+        let (modified, _) = watcher.line_changes(buffer_id, &buffer, 0..10);
+        assert_eq!(modified, HashSet::from([1, 2]));
+
+        // Joining them again restores the original line
+        buffer.delete_pos(3, 1);
+        watcher.apply_edit(buffer_id, &buffer, 3, 3);
+        let (modified, _) = watcher.line_changes(buffer_id, &buffer, 0..10);
+        assert!(modified.is_empty());
+    }
+
+    #[test]
+    fn test_line_changes_after_save() {
+        let (mut watcher, buffer_id, buffer) = tracked_buffer("a\nb\nc\n");
+
+        buffer.insert_pos("x".to_string(), 5);
+        watcher.apply_edit(buffer_id, &buffer, 5, 6);
+        watcher.mark_saved(buffer_id, buffer.content());
+        let (modified, saved) = watcher.line_changes(buffer_id, &buffer, 0..10);
+        assert!(modified.is_empty());
+        assert_eq!(saved, HashSet::from([2]));
+
+        // Editing a saved line again shows it as modified
+        buffer.insert_pos("y".to_string(), 5);
+        watcher.apply_edit(buffer_id, &buffer, 5, 6);
+        let (modified, saved) = watcher.line_changes(buffer_id, &buffer, 0..10);
+        assert_eq!(modified, HashSet::from([2]));
+        assert!(saved.is_empty());
+    }
+
+    #[test]
+    fn test_line_changes_untracked_edit_falls_back_to_diff() {
+        let (watcher, buffer_id, buffer) = tracked_buffer("a\nb\nc\n");
+
+        // Line count changed without apply_edit being told
+        buffer.insert_pos("new\n".to_string(), 0);
+        let (modified, _) = watcher.line_changes(buffer_id, &buffer, 0..10);
+        assert_eq!(modified, HashSet::from([0]));
+    }
+
+    /// Timing for gutter updates on a large buffer; run with
+    /// `cargo test -p roe-core bench_line_changes -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_line_changes_large_buffer() {
+        let content: String = (0..100_000).map(|i| format!("line {i}\n")).collect();
+        let (mut watcher, buffer_id, buffer) = tracked_buffer(&content);
+        let edits = 1_000;
+
+        let started = Instant::now();
+        for i in 0..edits {
+            // Type a character somewhere in the middle, then redraw a screenful
+            let pos = buffer.buffer_line_to_char(50_000 + i % 100);
+            buffer.insert_pos("x".to_string(), pos);
+            watcher.apply_edit(buffer_id, &buffer, pos, pos + 1);
+            let (modified, _) = watcher.line_changes(buffer_id, &buffer, 50_000..50_050);
+            assert!(!modified.is_empty());
+        }
+        let elapsed = started.elapsed();
+
+        eprintln!(
+            "{edits} edits on 100k lines: {:?} total, {:?} per edit",
+            elapsed,
+            elapsed / edits as u32
+        );
+    }
 }
@@ -0,0 +1,124 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! `fill-paragraph` (`M-q`): re-wrap a paragraph's words into lines no wider
+//! than the fill column.
+//!
+//! The whitespace between words, line breaks included, collapses to single
+//! spaces, and lines are broken greedily: each takes as many words as fit.
+//! Every line gets the indentation the paragraph's first line had. A word
+//! too long to fit on a line of its own still gets one, overhanging.
+
+use crate::tabify;
+
+/// Fill column when the config doesn't give one
+pub const DEFAULT_FILL_COLUMN: usize = 70;
+
+/// `paragraph` with its words wrapped to lines at most `fill_column` columns
+/// wide, counting tabs in the indentation to the next multiple of
+/// `tab_width`. Lines end as the paragraph's did, in `\r\n` or `\n`, with no
+/// line ending after the last.
+pub fn fill(paragraph: &str, fill_column: usize, tab_width: usize) -> String {
+    let text = paragraph.trim_start_matches([' ', '\t']);
+    let indent = &paragraph[..paragraph.len() - text.len()];
+    let indent_width = tabify::indent_width(indent, tab_width);
+    let line_ending = if paragraph.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+
+    let mut filled = String::with_capacity(paragraph.len());
+    let mut column = 0;
+    for word in text.split_whitespace() {
+        let width = word.chars().count();
+        if column == 0 {
+            filled.push_str(indent);
+            column = indent_width;
+        } else if column + 1 + width <= fill_column {
+            filled.push(' ');
+            column += 1;
+        } else {
+            filled.push_str(line_ending);
+            filled.push_str(indent);
+            column = indent_width;
+        }
+        filled.push_str(word);
+        column += width;
+    }
+    filled
+}
+
+/// Where a position `pos` characters into `paragraph` belongs in `filled`,
+/// the same text wrapped differently: on the same character, or right after
+/// the one before it if `pos` was on whitespace
+pub fn follow_position(paragraph: &str, filled: &str, pos: usize) -> usize {
+    let on_text = paragraph
+        .chars()
+        .nth(pos)
+        .is_some_and(|c| !c.is_whitespace());
+    let before = paragraph
+        .chars()
+        .take(pos)
+        .filter(|c| !c.is_whitespace())
+        .count();
+    let mut text_chars = filled
+        .chars()
+        .enumerate()
+        .filter(|(_, c)| !c.is_whitespace())
+        .map(|(index, _)| index);
+    match (on_text, before) {
+        (true, _) => text_chars.nth(before),
+        (false, 0) => Some(pos.min(filled.chars().count())),
+        (false, _) => text_chars.nth(before - 1).map(|index| index + 1),
+    }
+    .unwrap_or_else(|| filled.chars().count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_long_line() {
+        let paragraph = "  The quick brown fox jumps over the lazy dog, twice.";
+        assert_eq!(
+            fill(paragraph, 20, 4),
+            "  The quick brown\n  fox jumps over the\n  lazy dog, twice."
+        );
+
+        // A word wider than the column overhangs on a line of its own
+        assert_eq!(
+            fill("a supercalifragilistic b", 10, 4),
+            "a\nsupercalifragilistic\nb"
+        );
+    }
+
+    #[test]
+    fn test_fill_rejoins_broken_lines() {
+        let paragraph = "\tone\n\ttwo   three\r\n\tfour five six";
+        assert_eq!(fill(paragraph, 21, 8), "\tone two three\r\n\tfour five six");
+        assert_eq!(fill("a\nb\nc", DEFAULT_FILL_COLUMN, 4), "a b c");
+    }
+
+    #[test]
+    fn test_follow_position() {
+        let paragraph = "one\n  two three";
+        let filled = "one two three";
+        // On a character, after one on whitespace
+        assert_eq!(follow_position(paragraph, filled, 6), 4);
+        assert_eq!(follow_position(paragraph, filled, 4), 3);
+        assert_eq!(follow_position(paragraph, filled, 15), 13);
+        assert_eq!(follow_position(paragraph, filled, 0), 0);
+    }
+}
@@ -0,0 +1,224 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Fuzzy matching for completion: a pattern matches a candidate when its
+//! characters appear in the candidate in order, ignoring case. Matches are
+//! scored so that runs of consecutive characters and characters starting a
+//! word ("sbf" in "switch-buffer") rank above scattered ones.
+
+/// Face completion lists draw matched characters in
+pub const COMPLETION_MATCH_FACE: &str = "completion-match";
+
+/// Score for each matched character
+const SCORE_MATCH: i64 = 16;
+/// Bonus for a character matched right after the previous one
+const BONUS_CONSECUTIVE: i64 = 8;
+/// Bonus for a character matched at the start of a word
+const BONUS_BOUNDARY: i64 = 10;
+/// Penalty for skipping characters between two matched ones
+const PENALTY_GAP_START: i64 = 3;
+/// Further penalty for each skipped character after the first
+const PENALTY_GAP_EXTENSION: i64 = 1;
+
+/// A successful match: its score, and which characters of the candidate
+/// (as character indices) the pattern matched
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    pub positions: Vec<usize>,
+}
+
+/// Score `candidate` against `pattern`, higher being better, or None if the
+/// pattern's characters don't all appear in it in order. An empty pattern
+/// matches everything with a score of 0.
+pub fn fuzzy_match(pattern: &str, candidate: &str) -> Option<i64> {
+    fuzzy_match_positions(pattern, candidate).map(|m| m.score)
+}
+
+/// Like `fuzzy_match`, also giving the characters matched, for highlighting
+pub fn fuzzy_match_positions(pattern: &str, candidate: &str) -> Option<FuzzyMatch> {
+    let pattern: Vec<char> = pattern.chars().map(fold_case).collect();
+    if pattern.is_empty() {
+        return Some(FuzzyMatch {
+            score: 0,
+            positions: Vec::new(),
+        });
+    }
+    let original: Vec<char> = candidate.chars().collect();
+    let folded: Vec<char> = original.iter().copied().map(fold_case).collect();
+
+    // Cheap check first: most candidates don't contain the pattern at all
+    let mut remaining = pattern.iter().peekable();
+    for c in &folded {
+        if remaining.peek() == Some(&c) {
+            remaining.next();
+        }
+    }
+    if remaining.peek().is_some() {
+        return None;
+    }
+
+    // best[i][j]: best score for the first i+1 pattern characters with the
+    // last of them matched at candidate character j, and where the one before
+    // it was matched
+    let width = folded.len();
+    let mut best: Vec<Vec<Option<(i64, usize)>>> = vec![vec![None; width]; pattern.len()];
+    for (i, &p) in pattern.iter().enumerate() {
+        for j in i..width {
+            if folded[j] != p {
+                continue;
+            }
+            let here = SCORE_MATCH + boundary_bonus(&original, j);
+            if i == 0 {
+                best[0][j] = Some((here, 0));
+                continue;
+            }
+            best[i][j] = (i - 1..j)
+                .filter_map(|k| {
+                    let (score, _) = best[i - 1][k]?;
+                    let gap = j - k - 1;
+                    let step = if gap == 0 {
+                        BONUS_CONSECUTIVE
+                    } else {
+                        -(PENALTY_GAP_START + PENALTY_GAP_EXTENSION * (gap as i64 - 1))
+                    };
+                    Some((score + step + here, k))
+                })
+                .max_by_key(|&(score, _)| score);
+        }
+    }
+
+    let last = pattern.len() - 1;
+    let (mut j, score) = best[last]
+        .iter()
+        .enumerate()
+        .filter_map(|(j, cell)| cell.map(|(score, _)| (j, score)))
+        .max_by_key(|&(_, score)| score)?;
+    let mut positions = vec![0; pattern.len()];
+    for i in (0..=last).rev() {
+        positions[i] = j;
+        j = best[i][j]?.1;
+    }
+    Some(FuzzyMatch { score, positions })
+}
+
+/// Indices of the `candidates` `pattern` matches, best match first. Ties go
+/// to the shorter candidate, then keep the order they were given in.
+pub fn fuzzy_rank<S: AsRef<str>>(pattern: &str, candidates: &[S]) -> Vec<usize> {
+    let mut ranked: Vec<(usize, i64)> = candidates
+        .iter()
+        .enumerate()
+        .filter_map(|(i, candidate)| Some((i, fuzzy_match(pattern, candidate.as_ref())?)))
+        .collect();
+    ranked.sort_by_key(|&(i, score)| (-score, candidates[i].as_ref().chars().count()));
+    ranked.into_iter().map(|(i, _)| i).collect()
+}
+
+/// Character ranges of `text` covering the characters at `positions` (sorted
+/// character indices), merging neighbours, shifted by `offset` characters
+pub fn highlight_ranges(text: &str, positions: &[usize], offset: usize) -> Vec<(usize, usize)> {
+    let len = text.chars().count();
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &index in positions.iter().filter(|&&index| index < len) {
+        let (start, end) = (offset + index, offset + index + 1);
+        match ranges.last_mut() {
+            Some(last) if last.1 == start => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+    ranges
+}
+
+fn fold_case(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// Whether the character at `j` starts a word: the first character, one after
+/// a separator, or an uppercase letter after a lowercase one
+fn boundary_bonus(chars: &[char], j: usize) -> i64 {
+    let Some(&previous) = j.checked_sub(1).and_then(|k| chars.get(k)) else {
+        return BONUS_BOUNDARY;
+    };
+    let c = chars[j];
+    if (!previous.is_alphanumeric() && c.is_alphanumeric())
+        || (previous.is_lowercase() && c.is_uppercase())
+    {
+        BONUS_BOUNDARY
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_match_subsequence() {
+        assert!(fuzzy_match("sbf", "switch-buffer").is_some());
+        assert!(fuzzy_match("SBF", "switch-buffer").is_some());
+        assert!(fuzzy_match("bs", "switch-buffer").is_none());
+        assert_eq!(fuzzy_match("", "anything"), Some(0));
+        assert_eq!(
+            fuzzy_match_positions("sbf", "switch-buffer")
+                .unwrap()
+                .positions,
+            vec![0, 7, 9]
+        );
+    }
+
+    #[test]
+    fn test_fuzzy_rank() {
+        let candidates = [
+            "describe-buffer",
+            "switch-buffer",
+            "save-buffer",
+            "subst-buffer-file",
+            "kill-buffer",
+        ];
+        let ranked: Vec<&str> = fuzzy_rank("sbf", &candidates)
+            .into_iter()
+            .map(|i| candidates[i])
+            .collect();
+        // Every character starting a word beats two of three, and a shorter
+        // gap before the "b" beats a longer one. describe-buffer's "s" doesn't
+        // start a word, and kill-buffer has none.
+        assert_eq!(
+            ranked,
+            vec![
+                "subst-buffer-file",
+                "save-buffer",
+                "switch-buffer",
+                "describe-buffer"
+            ]
+        );
+
+        // Consecutive characters beat scattered ones
+        let candidates = ["find-next-search", "insert"];
+        assert_eq!(fuzzy_rank("ins", &candidates), vec![1, 0]);
+
+        // Equal matches go to the shorter candidate
+        let candidates = ["quit-window", "quoted-insert", "quit"];
+        assert_eq!(fuzzy_rank("quit", &candidates), vec![2, 0, 1]);
+    }
+
+    #[test]
+    fn test_highlight_ranges() {
+        assert_eq!(
+            highlight_ranges("switch-buffer", &[0, 7, 8, 9], 2),
+            vec![(2, 3), (9, 12)]
+        );
+        assert_eq!(highlight_ranges("é-b", &[0, 2], 0), vec![(0, 1), (2, 3)]);
+        assert_eq!(highlight_ranges("é-b", &[1, 2, 3], 4), vec![(5, 7)]);
+    }
+}
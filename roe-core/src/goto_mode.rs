@@ -0,0 +1,127 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! `goto-line` (`M-g g`) and `goto-char` (`M-g c`): prompt for a number and
+//! move there in the window the prompt was opened from.
+//!
+//! Both numbers are 1-based, as Emacs counts them: line 1 is the first line,
+//! and character 1 is the start of the buffer.
+
+use crate::keys::KeyAction;
+use crate::mode::{ActionPosition, Mode, ModeAction, ModeResult};
+
+/// What the number typed at the prompt counts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GotoTarget {
+    Line,
+    Char,
+}
+
+/// Prompt for a line or character number
+pub struct GotoMode {
+    target: GotoTarget,
+    /// Digits typed so far
+    input: String,
+    /// Why the last RET didn't go anywhere
+    error: Option<String>,
+}
+
+impl GotoMode {
+    pub fn new(target: GotoTarget) -> Self {
+        Self {
+            target,
+            input: String::new(),
+            error: None,
+        }
+    }
+
+    /// Generate buffer content string
+    pub fn generate_buffer_content(&self) -> String {
+        let prompt = match self.target {
+            GotoTarget::Line => "Goto line",
+            GotoTarget::Char => "Goto char",
+        };
+        match &self.error {
+            Some(error) => format!("{prompt}: {} [{error}]\n", self.input),
+            None => format!("{prompt}: {}\n", self.input),
+        }
+    }
+
+    fn redraw(&self) -> ModeResult {
+        ModeResult::Consumed(vec![
+            ModeAction::ClearText,
+            ModeAction::InsertText(ActionPosition::start(), self.generate_buffer_content()),
+        ])
+    }
+}
+
+impl Mode for GotoMode {
+    fn name(&self) -> &str {
+        "goto"
+    }
+
+    fn perform(&mut self, action: &KeyAction) -> ModeResult {
+        match action {
+            KeyAction::AlphaNumeric(c) if c.is_ascii_digit() => {
+                self.input.push(*c);
+                self.error = None;
+                self.redraw()
+            }
+            KeyAction::Backspace => {
+                self.input.pop();
+                self.error = None;
+                self.redraw()
+            }
+            KeyAction::Enter if self.input.is_empty() => {
+                // Keep the prompt open for a number to be typed
+                self.error = Some("Enter a number".to_string());
+                self.redraw()
+            }
+            KeyAction::Enter => {
+                // A number too big to parse is past the end all the same
+                let number = self.input.parse().unwrap_or(usize::MAX);
+                ModeResult::Consumed(vec![ModeAction::Goto(self.target, number)])
+            }
+            KeyAction::Escape | KeyAction::Cancel => ModeResult::Ignored,
+            // Swallow everything else so the prompt only ever holds a number
+            _ => ModeResult::Consumed(vec![]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_goto_prompt() {
+        let mut mode = GotoMode::new(GotoTarget::Line);
+        for c in "4x2".chars() {
+            mode.perform(&KeyAction::AlphaNumeric(c));
+        }
+        assert_eq!(mode.generate_buffer_content(), "Goto line: 42\n");
+        assert_eq!(
+            mode.perform(&KeyAction::Enter),
+            ModeResult::Consumed(vec![ModeAction::Goto(GotoTarget::Line, 42)])
+        );
+
+        // RET with nothing typed asks for a number
+        let mut mode = GotoMode::new(GotoTarget::Char);
+        mode.perform(&KeyAction::Enter);
+        assert_eq!(
+            mode.generate_buffer_content(),
+            "Goto char:  [Enter a number]\n"
+        );
+        assert_eq!(mode.perform(&KeyAction::Escape), ModeResult::Ignored);
+    }
+}
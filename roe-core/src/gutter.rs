@@ -26,7 +26,7 @@ pub enum LineStatus {
     Clean,
     /// Line has been modified locally (unsaved changes)
     Modified,
-    /// Line was modified and merged but not yet saved
+    /// Line was modified and has since been saved
     ModifiedSaved,
     /// Line contains conflict markers
     Conflict,
@@ -66,55 +66,52 @@ impl Default for GutterConfig {
 ///
 /// Gutter layout: [status][line_number][separator]
 /// - status: 1 char (modification indicator)
-/// - line_number: max(min_width, digits needed for total_lines)
+/// - line_number: `line_number_width` chars
 /// - separator: 1 char (space or line)
 pub fn calculate_gutter_width(total_lines: usize, config: &GutterConfig) -> usize {
     if !config.show_line_numbers && !config.show_status {
         return 0;
     }
 
-    let mut width = 0;
+    let status_width = if config.show_status { 1 } else { 0 };
+    status_width + line_number_width(total_lines, config) + 1
+}
 
-    // Status indicator
-    if config.show_status {
-        width += 1;
+/// Width of the line number column: enough digits for the last line number,
+/// and at least `min_line_number_width`. Numbers formatted to this width with
+/// `format_line_number` end exactly where the separator starts.
+pub fn line_number_width(total_lines: usize, config: &GutterConfig) -> usize {
+    if !config.show_line_numbers {
+        return 0;
     }
+    let digits_needed = total_lines.max(1).ilog10() as usize + 1;
+    digits_needed.max(config.min_line_number_width)
+}
 
-    // Line number width
-    if config.show_line_numbers {
-        let digits_needed = if total_lines == 0 {
-            1
-        } else {
-            ((total_lines as f64).log10().floor() as usize) + 1
-        };
-        width += digits_needed.max(config.min_line_number_width);
+/// Column within the gutter where the line numbers start
+pub fn line_number_column(config: &GutterConfig) -> usize {
+    if config.show_status {
+        1
+    } else {
+        0
     }
-
-    // Separator
-    width += 1;
-
-    width
 }
 
-/// Determine line status by checking if the line is in the modified set
-/// and if it contains conflict markers
+/// Determine line status from the sets of conflict marker, saved, and modified
+/// lines (see `conflict::conflict_marker_lines` and `FileWatcher::line_changes`)
 pub fn get_line_status(
-    line_content: &str,
     line_index: usize,
     modified_lines: &HashSet<usize>,
-    merged_lines: &HashSet<usize>,
+    saved_lines: &HashSet<usize>,
+    conflict_lines: &HashSet<usize>,
 ) -> LineStatus {
-    // Check for conflict markers first
-    let trimmed = line_content.trim_start();
-    if trimmed.starts_with("<<<<<<<")
-        || trimmed.starts_with("=======")
-        || trimmed.starts_with(">>>>>>>")
-    {
+    // Conflict markers take precedence
+    if conflict_lines.contains(&line_index) {
         return LineStatus::Conflict;
     }
 
-    // Check if line was part of a merge (modified but from external source)
-    if merged_lines.contains(&line_index) {
+    // Check if line was changed and then written to disk
+    if saved_lines.contains(&line_index) {
         return LineStatus::ModifiedSaved;
     }
 
@@ -159,20 +156,69 @@ mod tests {
         assert_eq!(calculate_gutter_width(10, &config), 4);
     }
 
+    #[test]
+    fn test_line_numbers_fill_their_column_across_digit_changes() {
+        let config = GutterConfig {
+            min_line_number_width: 1,
+            ..Default::default()
+        };
+        for (before, after) in [(9, 10), (99, 100), (9_999, 10_000)] {
+            for total_lines in [before, after] {
+                let width = line_number_width(total_lines, &config);
+                assert_eq!(width, total_lines.to_string().len());
+                // The last line number and the first both end at the separator
+                assert_eq!(format_line_number(total_lines, width).len(), width);
+                assert_eq!(format_line_number(1, width).len(), width);
+                assert_eq!(
+                    calculate_gutter_width(total_lines, &config),
+                    line_number_column(&config) + width + 1
+                );
+            }
+            assert_eq!(
+                line_number_width(after, &config),
+                line_number_width(before, &config) + 1
+            );
+        }
+    }
+
+    #[test]
+    fn test_line_number_width_exact_at_powers_of_ten() {
+        let config = GutterConfig::default();
+        assert_eq!(line_number_width(0, &config), 3);
+        assert_eq!(line_number_width(999, &config), 3);
+        assert_eq!(line_number_width(1_000, &config), 4);
+        assert_eq!(line_number_width(999_999_999_999_999, &config), 15);
+        assert_eq!(line_number_width(1_000_000_000_000_000, &config), 16);
+    }
+
+    #[test]
+    fn test_line_number_width_without_status() {
+        let config = GutterConfig {
+            show_status: false,
+            ..Default::default()
+        };
+        assert_eq!(line_number_column(&config), 0);
+        assert_eq!(
+            calculate_gutter_width(12_345, &config),
+            line_number_width(12_345, &config) + 1
+        );
+    }
+
     #[test]
     fn test_line_status_conflict() {
-        let modified = HashSet::new();
-        let merged = HashSet::new();
+        let modified = HashSet::from([0, 1]);
+        let saved = HashSet::new();
+        let conflicts = HashSet::from([0, 2]);
         assert_eq!(
-            get_line_status("<<<<<<< LOCAL", 0, &modified, &merged),
+            get_line_status(0, &modified, &saved, &conflicts),
             LineStatus::Conflict
         );
         assert_eq!(
-            get_line_status("=======", 0, &modified, &merged),
-            LineStatus::Conflict
+            get_line_status(1, &modified, &saved, &conflicts),
+            LineStatus::Modified
         );
         assert_eq!(
-            get_line_status(">>>>>>> EXTERNAL", 0, &modified, &merged),
+            get_line_status(2, &modified, &saved, &conflicts),
             LineStatus::Conflict
         );
     }
@@ -181,14 +227,15 @@ mod tests {
     fn test_line_status_modified() {
         let mut modified = HashSet::new();
         modified.insert(5);
-        let merged = HashSet::new();
+        let saved = HashSet::new();
+        let conflicts = HashSet::new();
 
         assert_eq!(
-            get_line_status("some content", 5, &modified, &merged),
+            get_line_status(5, &modified, &saved, &conflicts),
             LineStatus::Modified
         );
         assert_eq!(
-            get_line_status("other content", 6, &modified, &merged),
+            get_line_status(6, &modified, &saved, &conflicts),
             LineStatus::Clean
         );
     }
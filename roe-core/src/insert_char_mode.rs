@@ -0,0 +1,320 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! `insert-char` (`C-x 8 RET`): insert a character by Unicode name or code point.
+//!
+//! The prompt completes over character names; each word typed narrows the list
+//! to names it fuzzy-matches, so `heart black` finds `BLACK HEART SUIT`. RET
+//! inserts, in order of preference: the code point typed (`U+2764`, `#x2764`,
+//! `0x2764`), the character whose full name was typed, the highlighted
+//! completion, or else the code point if the input is bare hex.
+//!
+//! The table of names is built on a background thread the first time the
+//! prompt opens; until it's ready the list is empty and only code points and
+//! full names can be entered.
+
+use std::sync::{Once, OnceLock};
+
+use crate::fuzzy::fuzzy_match;
+use crate::keys::KeyAction;
+use crate::mode::{Mode, ModeAction, ModeResult};
+use crate::selection_menu::{MenuItem, SelectionMenu, DEFAULT_MAX_VISIBLE_ITEMS};
+use crate::BufferId;
+
+/// Header line of the prompt
+const PROMPT: &str = "Insert character (Unicode name or code point):";
+
+/// Header line while the names are still being loaded
+const LOADING_PROMPT: &str = "Insert character (loading names...):";
+
+/// Every named character, once built
+static NAMED_CHARS: OnceLock<Vec<CharItem>> = OnceLock::new();
+
+/// A named character offered for completion
+#[derive(Clone)]
+pub struct CharItem {
+    pub name: &'static str,
+    pub ch: char,
+}
+
+impl MenuItem for CharItem {
+    fn display_text(&self) -> String {
+        format!("{}  {}  U+{:04X}", self.name, self.ch, self.ch as u32)
+    }
+
+    fn matches_filter(&self, filter: &str) -> bool {
+        self.match_score(filter).is_some()
+    }
+
+    /// Every word of the filter must fuzzy-match the name; the scores add up
+    fn match_score(&self, filter: &str) -> Option<i64> {
+        filter
+            .split_whitespace()
+            .map(|word| fuzzy_match(word, self.name))
+            .sum()
+    }
+}
+
+/// Every named character. Ideographs and Hangul syllables are left out: their
+/// names are just their code points, and there are tens of thousands of them.
+fn build_named_chars() -> Vec<CharItem> {
+    (0..=char::MAX as u32)
+        .filter_map(char::from_u32)
+        .filter(|c| !c.is_control())
+        .filter_map(|ch| {
+            let name = unicode_names2::name(ch)?.to_string();
+            if name.contains("IDEOGRAPH-") || name.starts_with("HANGUL SYLLABLE ") {
+                return None;
+            }
+            Some(CharItem {
+                name: Box::leak(name.into_boxed_str()),
+                ch,
+            })
+        })
+        .collect()
+}
+
+/// The named characters if they're built yet. The first call starts building
+/// them on a background thread, as scanning every code point takes a while.
+fn named_chars() -> Option<&'static [CharItem]> {
+    static LOADING: Once = Once::new();
+    LOADING.call_once(|| {
+        std::thread::spawn(|| NAMED_CHARS.get_or_init(build_named_chars));
+    });
+    NAMED_CHARS.get().map(Vec::as_slice)
+}
+
+/// The character for a code point written `U+2764`, `#x2764` or `0x2764`,
+/// or as bare hex digits if `bare_hex` is set
+fn parse_code_point(input: &str, bare_hex: bool) -> Option<Result<char, String>> {
+    let input = input.trim();
+    let digits = ["U+", "u+", "#x", "0x"]
+        .iter()
+        .find_map(|prefix| input.strip_prefix(prefix))
+        .or_else(|| bare_hex.then_some(input))?;
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        // A prefix with no hex after it is an error; bare input just isn't hex
+        return (!bare_hex).then(|| Err(format!("Invalid code point: {input}")));
+    }
+    Some(
+        u32::from_str_radix(digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| format!("Invalid code point: {input}")),
+    )
+}
+
+/// Resolve what was typed at the prompt to a character, if it names one
+/// directly (rather than through the completion list)
+pub fn parse_char_input(input: &str) -> Option<Result<char, String>> {
+    if let Some(result) = parse_code_point(input, false) {
+        return Some(result);
+    }
+    if let Some(ch) = unicode_names2::character(&input.trim().to_uppercase()) {
+        return Some(Ok(ch));
+    }
+    parse_code_point(input, true)
+}
+
+/// Prompt for a character to insert
+pub struct InsertCharMode {
+    /// Selection menu of named characters
+    menu: SelectionMenu<CharItem>,
+    /// Buffer ID this mode is managing
+    pub buffer_id: Option<BufferId>,
+    /// Why the last RET didn't insert anything
+    error: Option<String>,
+    /// Whether the menu lists the named characters yet
+    loaded: bool,
+}
+
+impl InsertCharMode {
+    pub fn new() -> Self {
+        Self {
+            menu: SelectionMenu::new(DEFAULT_MAX_VISIBLE_ITEMS),
+            buffer_id: None,
+            error: None,
+            loaded: false,
+        }
+    }
+
+    /// Set how many characters are listed at once
+    pub fn set_max_visible_items(&mut self, max_visible_items: usize) {
+        self.menu.set_max_visible_items(max_visible_items);
+    }
+
+    /// Initialize with the buffer and the named characters, if they're loaded
+    pub fn init_with_buffer(&mut self, buffer_id: BufferId) {
+        self.buffer_id = Some(buffer_id);
+        self.menu.init_with_items(Vec::new());
+        self.load_names();
+    }
+
+    /// List the named characters once they're built, keeping the input
+    fn load_names(&mut self) {
+        if self.loaded {
+            return;
+        }
+        if let Some(items) = named_chars() {
+            self.menu.set_items(items.to_vec());
+            self.loaded = true;
+        }
+    }
+
+    fn header(&self) -> &str {
+        match &self.error {
+            Some(error) => error,
+            None if self.loaded => PROMPT,
+            None => LOADING_PROMPT,
+        }
+    }
+
+    /// Generate buffer content string
+    pub fn generate_buffer_content(&self) -> String {
+        self.menu.generate_buffer_content(Some(self.header()))
+    }
+
+    fn update_actions(&self) -> Vec<ModeAction> {
+        self.menu.generate_update_actions(Some(self.header()))
+    }
+
+    /// The character RET would insert for the current input
+    fn chosen_char(&self) -> Result<char, String> {
+        let input = &self.menu.input;
+        if let Some(result) = parse_code_point(input, false) {
+            return result;
+        }
+        if let Some(ch) = unicode_names2::character(&input.trim().to_uppercase()) {
+            return Ok(ch);
+        }
+        if let Some(item) = self.menu.get_selected_item() {
+            return Ok(item.ch);
+        }
+        parse_code_point(input, true)
+            .unwrap_or_else(|| Err(format!("Unknown character name: {}", input.trim())))
+    }
+}
+
+impl Default for InsertCharMode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Mode for InsertCharMode {
+    fn name(&self) -> &str {
+        "insert-char"
+    }
+
+    fn perform(&mut self, action: &KeyAction) -> ModeResult {
+        self.load_names();
+        if self.menu.handle_key_action(action) {
+            self.error = None;
+            return ModeResult::Consumed(self.update_actions());
+        }
+
+        match action {
+            KeyAction::Enter => match self.chosen_char() {
+                Ok(ch) => ModeResult::Consumed(vec![ModeAction::InsertChar(ch)]),
+                Err(message) => {
+                    // Keep the prompt open so the input can be fixed
+                    self.error = Some(message);
+                    ModeResult::Consumed(self.update_actions())
+                }
+            },
+            _ => ModeResult::Ignored,
+        }
+    }
+
+    fn resize(&mut self, list_rows: usize) -> ModeResult {
+        self.set_max_visible_items(list_rows);
+        ModeResult::Consumed(self.update_actions())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_code_point() {
+        assert_eq!(parse_char_input("U+2764"), Some(Ok('❤')));
+        assert_eq!(parse_char_input("u+e9"), Some(Ok('é')));
+        assert_eq!(parse_char_input("#x41"), Some(Ok('A')));
+        assert_eq!(parse_char_input("1F600"), Some(Ok('😀')));
+        assert!(matches!(parse_char_input("U+D800"), Some(Err(_))));
+        assert!(matches!(parse_char_input("U+110000"), Some(Err(_))));
+        assert!(matches!(parse_char_input("U+"), Some(Err(_))));
+        assert_eq!(parse_char_input("not a character"), None);
+    }
+
+    #[test]
+    fn test_parse_name() {
+        assert_eq!(parse_char_input("HEAVY BLACK HEART"), Some(Ok('❤')));
+        assert_eq!(parse_char_input("greek small letter lamda"), Some(Ok('λ')));
+        assert_eq!(parse_char_input("HEAVY BLACK"), None);
+    }
+
+    #[test]
+    fn test_matches_words_in_any_order() {
+        let item = CharItem {
+            name: "BLACK HEART SUIT",
+            ch: '♥',
+        };
+        assert!(item.matches_filter("heart black"));
+        assert!(item.matches_filter("suit"));
+        assert!(!item.matches_filter("white heart"));
+        assert_eq!(item.display_text(), "BLACK HEART SUIT  ♥  U+2665");
+    }
+
+    /// A mode listing every named character, without waiting on the loader
+    fn loaded_mode() -> InsertCharMode {
+        NAMED_CHARS.get_or_init(build_named_chars);
+        let mut mode = InsertCharMode::new();
+        mode.init_with_buffer(BufferId::default());
+        mode
+    }
+
+    #[test]
+    fn test_enter_inserts_or_reports() {
+        let mut mode = loaded_mode();
+        for c in "black heart suit".chars() {
+            mode.perform(&KeyAction::AlphaNumeric(c));
+        }
+        assert!(matches!(
+            mode.perform(&KeyAction::Enter),
+            ModeResult::Consumed(actions) if matches!(actions[..], [ModeAction::InsertChar('♥')])
+        ));
+
+        let mut mode = loaded_mode();
+        for c in "no such character anywhere".chars() {
+            mode.perform(&KeyAction::AlphaNumeric(c));
+        }
+        mode.perform(&KeyAction::Enter);
+        assert!(mode
+            .generate_buffer_content()
+            .starts_with("Unknown character name: no such character anywhere"));
+    }
+
+    #[test]
+    fn test_enter_prefers_completion_to_bare_hex() {
+        let mut mode = loaded_mode();
+        mode.perform(&KeyAction::AlphaNumeric('e'));
+        let selected = mode.menu.get_selected_item().unwrap().ch;
+        assert_ne!(selected, '\u{e}');
+        assert!(matches!(
+            mode.perform(&KeyAction::Enter),
+            ModeResult::Consumed(actions) if matches!(actions[..], [ModeAction::InsertChar(ch)] if ch == selected)
+        ));
+    }
+}
@@ -16,7 +16,8 @@
 //! This mode provides interactive search with:
 //! - Incremental matching as you type
 //! - Highlighting of all matches with current match distinct
-//! - Forward (C-s) and backward (C-r) navigation
+//! - Forward (C-s) and backward (C-r) navigation, wrapping around the buffer
+//! - Smartcase: case-insensitive unless the search term has an uppercase letter
 //! - Cancel to restore original cursor position
 
 use crate::buffer::Buffer;
@@ -41,6 +42,8 @@ pub struct IsearchMode {
     matches: Vec<(usize, usize)>,
     /// Index of current match (None if no matches)
     current_match_index: Option<usize>,
+    /// Whether the current match was reached by wrapping around a buffer end
+    wrapped: bool,
     /// Original cursor position (for cancel)
     original_cursor: usize,
     /// Target buffer ID (the buffer being searched)
@@ -66,6 +69,7 @@ impl IsearchMode {
             direction,
             matches: Vec::new(),
             current_match_index: None,
+            wrapped: false,
             original_cursor,
             target_buffer_id,
             target_window_id,
@@ -105,9 +109,11 @@ impl IsearchMode {
 
     /// Generate the command window content (the search prompt)
     pub fn generate_buffer_content(&self) -> String {
-        let direction_str = match self.direction {
-            SearchDirection::Forward => "I-search",
-            SearchDirection::Backward => "I-search backward",
+        let direction_str = match (self.wrapped, self.direction) {
+            (false, SearchDirection::Forward) => "I-search",
+            (false, SearchDirection::Backward) => "I-search backward",
+            (true, SearchDirection::Forward) => "Wrapped I-search",
+            (true, SearchDirection::Backward) => "Wrapped I-search backward",
         };
 
         let match_info = if self.search_term.is_empty() {
@@ -123,67 +129,67 @@ impl IsearchMode {
         format!("{}: {}{}", direction_str, self.search_term, match_info)
     }
 
-    /// Find all matches of search_term in the target buffer content
-    /// Returns byte positions for spans (consistent with syntax highlighting)
+    /// Find all matches of search_term in the target buffer content, as
+    /// character ranges like the buffer's highlight spans
     fn find_matches(&mut self) {
         self.matches.clear();
         self.current_match_index = None;
+        self.wrapped = false;
 
         if self.search_term.is_empty() {
             return;
         }
 
+        // Smartcase: an uppercase letter in the term makes the search exact
+        let ignore_case = !self.search_term.chars().any(char::is_uppercase);
         let content = self.target_buffer.content();
-        let needle_lower = self.search_term.to_lowercase();
-        let content_lower = content.to_lowercase();
-
-        // Find all matches using byte positions (for span highlighting)
-        let mut byte_start = 0;
-        while let Some(byte_pos) = content_lower[byte_start..].find(&needle_lower) {
-            let abs_byte_start = byte_start + byte_pos;
-            let abs_byte_end = abs_byte_start + needle_lower.len();
-            self.matches.push((abs_byte_start, abs_byte_end));
-            byte_start = abs_byte_start + 1;
+
+        // Compare in place so matches line up with the original text even
+        // where case folding changes a character's length
+        for (char_start, (byte_start, _)) in content.char_indices().enumerate() {
+            let rest = &content[byte_start..];
+            if let Some(len) = match_len(rest, &self.search_term, ignore_case) {
+                self.matches
+                    .push((char_start, char_start + rest[..len].chars().count()));
+            }
         }
 
         // Find the first match at or after original cursor position (for forward)
         // or before for backward
         if !self.matches.is_empty() {
-            self.current_match_index = Some(self.find_nearest_match());
+            let (index, wrapped) = self.find_nearest_match();
+            self.current_match_index = Some(index);
+            self.wrapped = wrapped;
         }
     }
 
-    /// Find the nearest match to the original cursor position based on direction
-    /// Note: original_cursor is in chars, matches are in bytes
-    fn find_nearest_match(&self) -> usize {
+    /// Find the nearest match to the original cursor position based on direction,
+    /// and whether it's only reached by wrapping around
+    fn find_nearest_match(&self) -> (usize, bool) {
         if self.matches.is_empty() {
-            return 0;
+            return (0, false);
         }
 
-        // Convert original_cursor (char position) to byte position for comparison
-        let content = self.target_buffer.content();
-        let cursor_byte_pos = char_to_byte_pos(&content, self.original_cursor);
-
         match self.direction {
             SearchDirection::Forward => {
                 // Find first match at or after original cursor
                 for (i, (start, _)) in self.matches.iter().enumerate() {
-                    if *start >= cursor_byte_pos {
-                        return i;
+                    if *start >= self.original_cursor {
+                        return (i, false);
                     }
                 }
                 // Wrap to beginning
-                0
+                (0, true)
             }
             SearchDirection::Backward => {
                 // Find last match before original cursor
                 for (i, (start, _)) in self.matches.iter().enumerate().rev() {
-                    if *start < cursor_byte_pos {
-                        return i;
+                    if *start < self.original_cursor {
+                        return (i, false);
                     }
                 }
                 // Wrap to end
-                self.matches.len().saturating_sub(1)
+                (self.matches.len().saturating_sub(1), true)
             }
         }
     }
@@ -194,6 +200,9 @@ impl IsearchMode {
             return;
         }
         if let Some(idx) = self.current_match_index {
+            if idx + 1 == self.matches.len() {
+                self.wrapped = true;
+            }
             self.current_match_index = Some((idx + 1) % self.matches.len());
         } else {
             self.current_match_index = Some(0);
@@ -207,6 +216,7 @@ impl IsearchMode {
         }
         if let Some(idx) = self.current_match_index {
             self.current_match_index = Some(if idx == 0 {
+                self.wrapped = true;
                 self.matches.len() - 1
             } else {
                 idx - 1
@@ -332,15 +342,108 @@ impl Mode for IsearchMode {
     }
 }
 
-/// Convert a character position to byte position in a string
-fn char_to_byte_pos(s: &str, char_pos: usize) -> usize {
-    s.char_indices()
-        .nth(char_pos)
-        .map(|(byte_idx, _)| byte_idx)
-        .unwrap_or(s.len())
+/// Length in bytes of the text at the start of `haystack` matching `needle`,
+/// comparing lowercased characters if `ignore_case`
+fn match_len(haystack: &str, needle: &str, ignore_case: bool) -> Option<usize> {
+    let mut chars = haystack.char_indices();
+    for n in needle.chars() {
+        let (_, h) = chars.next()?;
+        let same = if ignore_case {
+            h.to_lowercase().eq(n.to_lowercase())
+        } else {
+            h == n
+        };
+        if !same {
+            return None;
+        }
+    }
+    Some(chars.offset())
 }
 
-/// Convert a byte position to character position in a string
-pub fn byte_to_char_pos(s: &str, byte_pos: usize) -> usize {
-    s[..byte_pos.min(s.len())].chars().count()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search(text: &str, cursor: usize, direction: SearchDirection, term: &str) -> IsearchMode {
+        let buffer = Buffer::new(&[]);
+        buffer.load_str(text);
+        let mut mode = IsearchMode::new(
+            direction,
+            BufferId::default(),
+            WindowId::default(),
+            cursor,
+            buffer,
+            None,
+        );
+        for c in term.chars() {
+            mode.perform(&KeyAction::AlphaNumeric(c));
+        }
+        mode
+    }
+
+    fn current(mode: &IsearchMode) -> Option<(usize, usize)> {
+        mode.matches().get(mode.current_match_index()?).copied()
+    }
+
+    #[test]
+    fn test_forward_and_wrap() {
+        let mut mode = search("foo bar foo baz foo", 5, SearchDirection::Forward, "foo");
+        assert_eq!(mode.matches(), &[(0, 3), (8, 11), (16, 19)]);
+        assert_eq!(current(&mode), Some((8, 11)));
+        assert!(mode
+            .generate_buffer_content()
+            .starts_with("I-search: foo [2/3]"));
+
+        mode.perform(&KeyAction::Command("isearch-forward".to_string()));
+        assert_eq!(current(&mode), Some((16, 19)));
+        mode.perform(&KeyAction::Command("isearch-forward".to_string()));
+        assert_eq!(current(&mode), Some((0, 3)));
+        assert!(mode
+            .generate_buffer_content()
+            .starts_with("Wrapped I-search: "));
+
+        // Nothing after the cursor wraps straight away
+        let mode = search("foo bar", 5, SearchDirection::Forward, "foo");
+        assert_eq!(current(&mode), Some((0, 3)));
+        assert!(mode.generate_buffer_content().starts_with("Wrapped "));
+    }
+
+    #[test]
+    fn test_backward_and_wrap() {
+        let mut mode = search("foo bar foo baz foo", 12, SearchDirection::Backward, "foo");
+        assert_eq!(current(&mode), Some((8, 11)));
+        assert!(mode
+            .generate_buffer_content()
+            .starts_with("I-search backward: "));
+
+        mode.perform(&KeyAction::Command("isearch-backward".to_string()));
+        assert_eq!(current(&mode), Some((0, 3)));
+        mode.perform(&KeyAction::Command("isearch-backward".to_string()));
+        assert_eq!(current(&mode), Some((16, 19)));
+        assert!(mode
+            .generate_buffer_content()
+            .starts_with("Wrapped I-search backward: "));
+
+        // Changing the term starts the search afresh
+        mode.perform(&KeyAction::Backspace);
+        assert!(mode
+            .generate_buffer_content()
+            .starts_with("I-search backward: "));
+    }
+
+    #[test]
+    fn test_smartcase() {
+        // All lowercase ignores case
+        let mode = search("Foo foo FOO", 0, SearchDirection::Forward, "foo");
+        assert_eq!(mode.matches().len(), 3);
+
+        // Any uppercase makes the search exact
+        let mode = search("Foo foo FOO", 0, SearchDirection::Forward, "Foo");
+        assert_eq!(mode.matches(), &[(0, 3)]);
+
+        // Matches are character ranges of the original text, even where
+        // lowercasing would change a character's length
+        let mode = search("İx ix", 0, SearchDirection::Forward, "x");
+        assert_eq!(mode.matches(), &[(1, 2), (4, 5)]);
+    }
 }
@@ -14,8 +14,11 @@
 // jlrs AsyncTask trait requires this specific fn signature, not async fn
 #![allow(clippy::manual_async_fn)]
 
+use crate::after_change_queue::{AfterChangeQueue, ChangeRange};
 use crate::buffer::Buffer;
+use crate::comment::DEFAULT_COMMENT_PREFIX;
 use crate::syntax::{Color, Face, FaceRegistry, HighlightSpan};
+use crate::tree_sitter_highlight::TreeSitterLanguage;
 use jlrs::memory::target::frame::GcFrame;
 use jlrs::prelude::*;
 use jlrs::runtime::handle::async_handle::AsyncHandle;
@@ -37,16 +40,22 @@ pub type SharedJuliaRuntime = Arc<Mutex<RoeJuliaRuntime>>;
 /// This is set before calling a Julia command and cleared after
 static CURRENT_BUFFER: std::sync::Mutex<Option<Buffer>> = std::sync::Mutex::new(None);
 
+/// Region of the current buffer changed by the roe_buffer_* edit functions
+/// since it was set, so the editor can treat the edits as its own
+static CURRENT_BUFFER_EDIT: std::sync::Mutex<Option<ChangeRange>> = std::sync::Mutex::new(None);
+
 /// Set the current buffer for Julia command execution
 pub fn set_current_buffer(buffer: Buffer) {
     let mut guard = CURRENT_BUFFER.lock().expect("Buffer lock poisoned");
     *guard = Some(buffer);
 }
 
-/// Clear the current buffer after Julia command execution
-pub fn clear_current_buffer() {
+/// Clear the current buffer after Julia command execution, returning the
+/// region the roe_buffer_* edit functions changed in it, if any
+pub fn clear_current_buffer() -> Option<ChangeRange> {
     let mut guard = CURRENT_BUFFER.lock().expect("Buffer lock poisoned");
     *guard = None;
+    take_current_buffer_edit()
 }
 
 /// Get a clone of the current buffer (for use in extern functions)
@@ -55,6 +64,39 @@ fn get_current_buffer() -> Option<Buffer> {
     guard.clone()
 }
 
+/// Take the region the roe_buffer_* edit functions have changed in the
+/// current buffer, if they changed anything
+pub fn take_current_buffer_edit() -> Option<ChangeRange> {
+    CURRENT_BUFFER_EDIT
+        .lock()
+        .expect("Buffer edit lock poisoned")
+        .take()
+}
+
+/// Record an edit made to the current buffer through the FFI
+fn note_current_buffer_edit(range: ChangeRange) {
+    let mut guard = CURRENT_BUFFER_EDIT
+        .lock()
+        .expect("Buffer edit lock poisoned");
+    *guard = Some(match *guard {
+        Some(earlier) => earlier.merge(range),
+        None => range,
+    });
+}
+
+/// The current buffer, if Julia may edit it: set, and not read-only
+fn get_editable_buffer() -> Option<Buffer> {
+    get_current_buffer().filter(|buffer| !buffer.is_read_only())
+}
+
+/// `start..end` as char offsets into `buffer`, with `end` clamped to its
+/// length, or `None` if it's empty or starts past the end
+fn buffer_range(buffer: &Buffer, start: c_longlong, end: c_longlong) -> Option<(usize, usize)> {
+    let start = usize::try_from(start).ok()?;
+    let end = usize::try_from(end).ok()?.min(buffer.buffer_len_chars());
+    (start < end).then_some((start, end))
+}
+
 // ============================================
 // Face registry for syntax highlighting
 // ============================================
@@ -164,30 +206,68 @@ pub extern "C" fn roe_buffer_substring(start: c_longlong, end: c_longlong) -> *m
 /// The text pointer must be a valid null-terminated C string.
 #[no_mangle]
 pub unsafe extern "C" fn roe_buffer_insert(pos: c_longlong, text: *const c_char) {
-    let Some(buffer) = get_current_buffer() else {
+    let Some(buffer) = get_editable_buffer() else {
+        return;
+    };
+    let Ok(pos) = usize::try_from(pos) else {
         return;
     };
-    if pos < 0 || text.is_null() {
+    if pos > buffer.buffer_len_chars() || text.is_null() {
         return;
     }
     let text_str = match CStr::from_ptr(text).to_str() {
         Ok(s) => s.to_string(),
         Err(_) => return,
     };
-    buffer.insert_pos(text_str, pos as usize);
+    let new_end = pos + text_str.chars().count();
+    buffer.insert_pos(text_str, pos);
+    note_current_buffer_edit(ChangeRange::new(pos, pos, new_end));
 }
 
 /// Delete text from the buffer (start inclusive, end exclusive)
 #[no_mangle]
 pub extern "C" fn roe_buffer_delete(start: c_longlong, end: c_longlong) {
-    let Some(buffer) = get_current_buffer() else {
+    let Some(buffer) = get_editable_buffer() else {
         return;
     };
-    if start < 0 || end <= start {
+    let Some((start, end)) = buffer_range(&buffer, start, end) else {
+        return;
+    };
+    buffer.delete_pos(start, (end - start) as isize);
+    note_current_buffer_edit(ChangeRange::new(start, end, start));
+}
+
+/// Replace text in the buffer (start inclusive, end exclusive) with new text
+/// # Safety
+/// The text pointer must be a valid null-terminated C string.
+#[no_mangle]
+pub unsafe extern "C" fn roe_buffer_replace(
+    start: c_longlong,
+    end: c_longlong,
+    text: *const c_char,
+) {
+    let Some(buffer) = get_editable_buffer() else {
+        return;
+    };
+    let Some((start, end)) = buffer_range(&buffer, start, end) else {
+        return;
+    };
+    if text.is_null() {
         return;
     }
-    let count = end - start;
-    buffer.delete_pos(start as usize, count as isize);
+    let text_str = match CStr::from_ptr(text).to_str() {
+        Ok(s) => s.to_string(),
+        Err(_) => return,
+    };
+    let new_end = start + text_str.chars().count();
+    // Undone in one step, as it was made
+    buffer.with_write(|b| {
+        b.begin_undo_group();
+        b.delete_pos(start, (end - start) as isize);
+        b.insert_pos(text_str, start);
+        b.end_undo_group();
+    });
+    note_current_buffer_edit(ChangeRange::new(start, end, new_end));
 }
 
 /// Get the major mode of the current buffer
@@ -230,17 +310,118 @@ pub extern "C" fn roe_buffer_set_show_gutter(show: c_longlong) {
     buffer.set_show_gutter(show != 0);
 }
 
+/// Set whether the modeline shows word count and reading time for the current buffer
+/// Pass 1 to show, 0 to hide
+#[no_mangle]
+pub extern "C" fn roe_buffer_set_show_word_count(show: c_longlong) {
+    let Some(buffer) = get_current_buffer() else {
+        return;
+    };
+    buffer.set_show_word_count(show != 0);
+}
+
+/// Set the columns between tab stops for the current buffer
+#[no_mangle]
+pub extern "C" fn roe_buffer_set_tab_width(tab_width: c_longlong) {
+    let Some(buffer) = get_current_buffer() else {
+        return;
+    };
+    buffer.set_tab_width(tab_width.max(1) as usize);
+}
+
+/// Set whether the current buffer is indented with tabs rather than spaces
+/// Pass 1 for tabs, 0 for spaces
+#[no_mangle]
+pub extern "C" fn roe_buffer_set_indent_tabs(indent_tabs: c_longlong) {
+    let Some(buffer) = get_current_buffer() else {
+        return;
+    };
+    buffer.set_indent_tabs(indent_tabs != 0);
+}
+
+/// Get a buffer-local variable of the current buffer
+/// Returns a C string that Julia must free, or null if it isn't set
+/// # Safety
+/// The name pointer must be a valid null-terminated C string.
+#[no_mangle]
+pub unsafe extern "C" fn roe_buffer_get_local(name: *const c_char) -> *mut c_char {
+    let Some(buffer) = get_current_buffer() else {
+        return std::ptr::null_mut();
+    };
+    if name.is_null() {
+        return std::ptr::null_mut();
+    }
+    let Ok(name) = CStr::from_ptr(name).to_str() else {
+        return std::ptr::null_mut();
+    };
+    match buffer.get_local(name).map(CString::new) {
+        Some(Ok(cstr)) => cstr.into_raw(),
+        _ => std::ptr::null_mut(),
+    }
+}
+
+/// Set a buffer-local variable of the current buffer
+/// # Safety
+/// The name and value pointers must be valid null-terminated C strings.
+#[no_mangle]
+pub unsafe extern "C" fn roe_buffer_set_local(name: *const c_char, value: *const c_char) {
+    let Some(buffer) = get_current_buffer() else {
+        return;
+    };
+    if name.is_null() || value.is_null() {
+        return;
+    }
+    let (Ok(name), Ok(value)) = (
+        CStr::from_ptr(name).to_str(),
+        CStr::from_ptr(value).to_str(),
+    ) else {
+        return;
+    };
+    buffer.set_local(name, value);
+}
+
+/// Highlight the current buffer with the built-in grammar `name` ("rust",
+/// "json" or "toml"); an empty name stops it.
+/// Returns 1 on success, 0 if there is no such grammar.
+/// # Safety
+/// The name pointer must be a valid null-terminated C string.
+#[no_mangle]
+pub unsafe extern "C" fn roe_buffer_set_tree_sitter(name: *const c_char) -> c_longlong {
+    let Some(buffer) = get_current_buffer() else {
+        return 0;
+    };
+    if name.is_null() {
+        return 0;
+    }
+    let Ok(name) = CStr::from_ptr(name).to_str() else {
+        return 0;
+    };
+    if name.is_empty() {
+        buffer.set_tree_sitter(None);
+        return 1;
+    }
+    match TreeSitterLanguage::from_name(name) {
+        Some(language) => {
+            buffer.set_tree_sitter(Some(language));
+            1
+        }
+        None => 0,
+    }
+}
+
 // ============================================
 // Face and syntax highlighting FFI
 // ============================================
 
-/// Define a new face with the given name and attributes.
+/// Register a face with the given name and attributes in the global face
+/// registry. An existing face with the same name is replaced in place, so
+/// spans already referring to it pick up the new attributes.
 /// Returns 1 on success, 0 on failure.
 ///
 /// # Safety
 /// All string pointers must be valid null-terminated C strings or null.
 #[no_mangle]
-pub unsafe extern "C" fn roe_define_face(
+pub unsafe extern "C" fn roe_register_face(
     name: *const c_char,
     fg_hex: *const c_char,
     bg_hex: *const c_char,
@@ -283,19 +464,19 @@ pub unsafe extern "C" fn roe_define_face(
 
     let registry = get_face_registry();
     let mut guard = registry.lock().expect("Face registry lock poisoned");
-
-    // Check if face already exists
-    if let Some(existing_id) = guard.get_id(&name_str) {
-        // Update existing face
-        guard.update_face(existing_id, face);
-    } else {
-        // Define new face
-        guard.define_face(face);
-    }
+    guard.register_face(face);
 
     1 // Success
 }
 
+/// Resolve a face name to its ID in the global face registry.
+/// Used by the highlighting path to turn Julia-supplied face names into spans.
+pub fn resolve_face_id(name: &str) -> Option<crate::syntax::FaceId> {
+    let registry = get_face_registry();
+    let guard = registry.lock().expect("Face registry lock poisoned");
+    guard.get_id(name)
+}
+
 /// Check if a face with the given name exists.
 /// Returns 1 if found, 0 if not found.
 ///
@@ -347,17 +528,13 @@ pub unsafe extern "C" fn roe_add_span(
     };
 
     // Look up the face ID
-    let registry = get_face_registry();
-    let guard = registry.lock().expect("Face registry lock poisoned");
-
-    let Some(face_id) = guard.get_id(face_name_str) else {
+    let Some(face_id) = resolve_face_id(face_name_str) else {
         return 0; // Face not found
     };
 
-    drop(guard); // Release lock before accessing buffer
-
     let span = HighlightSpan::new(start as usize, end as usize, face_id);
     buffer.add_span(span);
+    buffer.mark_julia_spans();
 
     1 // Success
 }
@@ -413,6 +590,7 @@ pub unsafe extern "C" fn roe_add_spans(
     drop(guard); // Release lock before accessing buffer
 
     buffer.add_spans(spans);
+    buffer.mark_julia_spans();
 
     added
 }
@@ -453,6 +631,153 @@ pub extern "C" fn roe_buffer_has_spans() -> c_longlong {
     }
 }
 
+// ============================================
+// Julia error reporting
+// ============================================
+
+/// An exception raised by Julia code (a command, mode, or hook), with its
+/// full `showerror` text including the backtrace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JuliaError {
+    /// One-line description suitable for the echo area
+    pub summary: String,
+    /// Full error text with backtrace
+    pub details: String,
+}
+
+/// Errors reported since the editor last drained them
+static JULIA_ERRORS: std::sync::Mutex<Vec<JuliaError>> = std::sync::Mutex::new(Vec::new());
+
+/// Record a Julia error for the editor to pick up
+pub fn report_julia_error(summary: impl Into<String>, details: impl Into<String>) {
+    let mut guard = JULIA_ERRORS.lock().expect("Julia error lock poisoned");
+    guard.push(JuliaError {
+        summary: summary.into(),
+        details: details.into(),
+    });
+}
+
+/// Take all Julia errors reported since the last call
+pub fn take_julia_errors() -> Vec<JuliaError> {
+    let mut guard = JULIA_ERRORS.lock().expect("Julia error lock poisoned");
+    std::mem::take(&mut *guard)
+}
+
+/// Report an exception caught on the Julia side.
+///
+/// # Safety
+/// Both pointers must be valid null-terminated C strings or null.
+#[no_mangle]
+pub unsafe extern "C" fn roe_report_error(summary: *const c_char, details: *const c_char) {
+    if summary.is_null() {
+        return;
+    }
+
+    let summary = CStr::from_ptr(summary).to_string_lossy().into_owned();
+    let details = if details.is_null() {
+        summary.clone()
+    } else {
+        CStr::from_ptr(details).to_string_lossy().into_owned()
+    };
+
+    report_julia_error(summary, details);
+}
+
+// ============================================
+// Keybinding changes from Julia
+// ============================================
+
+/// A keybinding made or removed by Julia code while the editor runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyBindingChange {
+    Bind {
+        key_sequence: String,
+        action: String,
+    },
+    Unbind {
+        key_sequence: String,
+    },
+}
+
+/// Keybinding changes not yet applied by the editor
+static KEY_BINDING_CHANGES: std::sync::Mutex<Vec<KeyBindingChange>> =
+    std::sync::Mutex::new(Vec::new());
+
+/// Take all keybinding changes made since the last call, oldest first
+pub fn take_key_binding_changes() -> Vec<KeyBindingChange> {
+    let mut guard = KEY_BINDING_CHANGES
+        .lock()
+        .expect("Keybinding change lock poisoned");
+    std::mem::take(&mut *guard)
+}
+
+fn queue_key_binding_change(change: KeyBindingChange) {
+    let mut guard = KEY_BINDING_CHANGES
+        .lock()
+        .expect("Keybinding change lock poisoned");
+    guard.push(change);
+}
+
+/// Error message for Julia, which must free it with roe_free_string
+fn error_string(message: String) -> *mut c_char {
+    CString::new(message)
+        .map(CString::into_raw)
+        .unwrap_or(std::ptr::null_mut())
+}
+
+/// Bind a key sequence for the keys typed from now on. Returns null if it
+/// was accepted, or a message saying what's wrong with it that Julia must free
+/// with roe_free_string.
+///
+/// # Safety
+/// Both pointers must be valid null-terminated C strings or null.
+#[no_mangle]
+pub unsafe extern "C" fn roe_bind_key(
+    key_sequence: *const c_char,
+    action: *const c_char,
+) -> *mut c_char {
+    if key_sequence.is_null() || action.is_null() {
+        return error_string("No key sequence or action given".to_string());
+    }
+    let key_sequence = CStr::from_ptr(key_sequence).to_string_lossy().into_owned();
+    let action = CStr::from_ptr(action).to_string_lossy().into_owned();
+
+    let checked = crate::keys::ConfigurableBindings::try_parse_key_sequence(&key_sequence)
+        .and_then(|_| crate::keys::ConfigurableBindings::check_action(&action));
+    match checked {
+        Ok(()) => {
+            queue_key_binding_change(KeyBindingChange::Bind {
+                key_sequence,
+                action,
+            });
+            std::ptr::null_mut()
+        }
+        Err(message) => error_string(message),
+    }
+}
+
+/// Remove the binding for a key sequence. Returns null if the sequence was
+/// valid, or a message saying what's wrong with it that Julia must free with
+/// roe_free_string.
+///
+/// # Safety
+/// The pointer must be a valid null-terminated C string or null.
+#[no_mangle]
+pub unsafe extern "C" fn roe_unbind_key(key_sequence: *const c_char) -> *mut c_char {
+    if key_sequence.is_null() {
+        return error_string("No key sequence given".to_string());
+    }
+    let key_sequence = CStr::from_ptr(key_sequence).to_string_lossy().into_owned();
+
+    match crate::keys::ConfigurableBindings::try_parse_key_sequence(&key_sequence) {
+        Ok(_) => {
+            queue_key_binding_change(KeyBindingChange::Unbind { key_sequence });
+            std::ptr::null_mut()
+        }
+        Err(message) => error_string(message),
+    }
+}
+
 /// Error types for Julia runtime operations
 #[derive(Debug)]
 pub enum JuliaRuntimeError {
@@ -594,6 +919,14 @@ impl AsyncTask for ConfigLoadTask {
                     return Ok(HashMap::new());
                 };
 
+                // Register any faces declared under roe_config["faces"]
+                let _ = unsafe {
+                    Value::eval_string(
+                        &mut frame,
+                        "isdefined(Main, :Roe) && Main.Roe.apply_config_faces()",
+                    )
+                };
+
                 // Return a test value to show it worked
                 let mut config_map = HashMap::new();
                 config_map.insert("_loaded".to_string(), ConfigValue::Boolean(true));
@@ -603,6 +936,56 @@ impl AsyncTask for ConfigLoadTask {
     }
 }
 
+/// Task for re-evaluating a configuration file in the live runtime, reporting
+/// any Julia exception back to the caller instead of swallowing it
+pub struct ConfigReloadTask {
+    config_path: PathBuf,
+}
+
+impl ConfigReloadTask {
+    pub fn new(config_path: PathBuf) -> Self {
+        Self { config_path }
+    }
+}
+
+impl AsyncTask for ConfigReloadTask {
+    type Output = JlrsResult<Result<(), String>>;
+
+    fn run(self, mut frame: AsyncGcFrame<'_>) -> impl std::future::Future<Output = Self::Output> {
+        async move {
+            frame.scope(|mut frame| {
+                let content = match std::fs::read_to_string(&self.config_path) {
+                    Ok(content) => content,
+                    Err(e) => {
+                        return Ok(Err(format!(
+                            "Failed to read {}: {e}",
+                            self.config_path.display()
+                        )))
+                    }
+                };
+
+                if let Err(e) = unsafe { Value::eval_string(&mut frame, &content) } {
+                    return Ok(Err(format!(
+                        "Error in {}: {:?}",
+                        self.config_path.display(),
+                        e
+                    )));
+                }
+
+                // Register any faces declared under roe_config["faces"]
+                let _ = unsafe {
+                    Value::eval_string(
+                        &mut frame,
+                        "isdefined(Main, :Roe) && Main.Roe.apply_config_faces()",
+                    )
+                };
+
+                Ok(Ok(()))
+            })
+        }
+    }
+}
+
 /// Task for executing Julia expressions in REPL mode
 pub struct JuliaReplTask {
     expression: String,
@@ -1091,9 +1474,15 @@ impl AsyncTask for CallCommandTask {
                             _ => Ok(JuliaCommandResult::None),
                         }
                     }
-                    Err(_) => Ok(JuliaCommandResult::Error(
-                        "Failed to call Julia command".to_string(),
-                    )),
+                    Err(e) => {
+                        report_julia_error(
+                            format!("Error calling command {}", self.command_name),
+                            format!("{e:?}"),
+                        );
+                        Ok(JuliaCommandResult::Error(
+                            "Failed to call Julia command".to_string(),
+                        ))
+                    }
                 }
             })
         }
@@ -1404,6 +1793,8 @@ impl AsyncTask for ModePerformTask {
                         mode_action.path = get_str_field("path", &mut frame, action_dict);
                         mode_action.open_type = get_str_field("open_type", &mut frame, action_dict);
                         mode_action.command = get_str_field("command", &mut frame, action_dict);
+                        mode_action.buffer_name =
+                            get_str_field("buffer_name", &mut frame, action_dict);
 
                         // Get buffer_index as integer
                         mode_action.buffer_index = {
@@ -1487,6 +1878,95 @@ impl AsyncTask for GetMajorModeForFileTask {
     }
 }
 
+/// Task to get a major mode's line comment prefix
+pub struct MajorModeCommentSyntaxTask {
+    pub mode_name: String,
+}
+
+impl AsyncTask for MajorModeCommentSyntaxTask {
+    type Output = JlrsResult<String>;
+
+    fn run(self, mut frame: AsyncGcFrame<'_>) -> impl std::future::Future<Output = Self::Output> {
+        async move {
+            frame.scope(|mut frame| {
+                let main_module = Module::main(&frame);
+
+                // Get the Roe module
+                let Ok(roe_module) = main_module.global(&mut frame, "Roe") else {
+                    return Ok(DEFAULT_COMMENT_PREFIX.to_string());
+                };
+                let roe_module = roe_module.cast::<Module>().unwrap();
+
+                // Get major_mode_comment_syntax function
+                let Ok(syntax_fn) = roe_module.global(&mut frame, "major_mode_comment_syntax")
+                else {
+                    return Ok(DEFAULT_COMMENT_PREFIX.to_string());
+                };
+
+                // Call Roe.major_mode_comment_syntax(mode_name)
+                let mode_name_jl = JuliaString::new(&mut frame, &self.mode_name);
+                let result = unsafe { syntax_fn.call(&mut frame, [mode_name_jl.as_value()]) };
+
+                let prefix = result
+                    .ok()
+                    .and_then(|prefix| prefix.cast::<JuliaString>().ok())
+                    .and_then(|prefix| prefix.as_str().ok().map(str::to_string))
+                    .unwrap_or_else(|| DEFAULT_COMMENT_PREFIX.to_string());
+                Ok(prefix)
+            })
+        }
+    }
+}
+
+/// Task to ask a major mode what a new line starts with
+pub struct MajorModeIndentLineTask {
+    pub mode_name: String,
+    pub code: String,
+    /// 1-based line number
+    pub line: i64,
+}
+
+impl AsyncTask for MajorModeIndentLineTask {
+    type Output = JlrsResult<Option<String>>;
+
+    fn run(self, mut frame: AsyncGcFrame<'_>) -> impl std::future::Future<Output = Self::Output> {
+        async move {
+            frame.scope(|mut frame| {
+                let main_module = Module::main(&frame);
+
+                // Get the Roe module
+                let Ok(roe_module) = main_module.global(&mut frame, "Roe") else {
+                    return Ok(None);
+                };
+                let roe_module = roe_module.cast::<Module>().unwrap();
+
+                // Get major_mode_indent_line function
+                let Ok(indent_fn) = roe_module.global(&mut frame, "major_mode_indent_line") else {
+                    return Ok(None);
+                };
+
+                // Call Roe.major_mode_indent_line(mode_name, code, line)
+                let mode_name_jl = JuliaString::new(&mut frame, &self.mode_name);
+                let code_jl = JuliaString::new(&mut frame, &self.code);
+                let line_jl = Value::new(&mut frame, self.line);
+                let result = unsafe {
+                    indent_fn.call(
+                        &mut frame,
+                        [mode_name_jl.as_value(), code_jl.as_value(), line_jl],
+                    )
+                };
+
+                // Nothing back means the mode has no say
+                let indent = result
+                    .ok()
+                    .and_then(|indent| indent.cast::<JuliaString>().ok())
+                    .and_then(|indent| indent.as_str().ok().map(str::to_string));
+                Ok(indent)
+            })
+        }
+    }
+}
+
 /// Task to call a major mode's init hook
 pub struct CallMajorModeInitTask {
     pub mode_name: String,
@@ -1524,7 +2004,13 @@ impl AsyncTask for CallMajorModeInitTask {
                             .unwrap_or(false);
                         Ok(success_bool)
                     }
-                    Err(_) => Ok(false),
+                    Err(e) => {
+                        report_julia_error(
+                            format!("Error in {} init hook", self.mode_name),
+                            format!("{e:?}"),
+                        );
+                        Ok(false)
+                    }
                 }
             })
         }
@@ -1582,7 +2068,13 @@ impl AsyncTask for CallMajorModeAfterChangeTask {
                             .unwrap_or(false);
                         Ok(success_bool)
                     }
-                    Err(_) => Ok(false),
+                    Err(e) => {
+                        report_julia_error(
+                            format!("Error in {} after-change hook", self.mode_name),
+                            format!("{e:?}"),
+                        );
+                        Ok(false)
+                    }
                 }
             })
         }
@@ -1641,6 +2133,9 @@ pub enum JuliaCommandResult {
     Multi(Vec<JuliaCommandResult>),
     /// Execute another command by name
     ExecuteCommand(String),
+    /// The command edited its buffer directly, through roe_buffer_insert and
+    /// the like, changing this region
+    BufferEdited(ChangeRange),
 }
 
 /// A single action returned from a Julia mode handler
@@ -1653,6 +2148,7 @@ pub struct JuliaModeAction {
     pub open_type: Option<String>,
     pub command: Option<String>,
     pub buffer_index: Option<i64>,
+    pub buffer_name: Option<String>,
 }
 
 /// Result from a Julia mode perform call
@@ -1689,18 +2185,29 @@ pub enum JuliaCommand {
         String,                               // file path
         tokio::sync::oneshot::Sender<String>, // mode name
     ),
+    /// Get a major mode's line comment prefix
+    MajorModeCommentSyntax(
+        String,                               // mode name
+        tokio::sync::oneshot::Sender<String>, // comment prefix
+    ),
+    /// Ask a major mode what a new line starts with
+    MajorModeIndentLine(
+        String,                                       // mode name
+        String,                                       // buffer content
+        i64,                                          // 1-based line number
+        tokio::sync::oneshot::Sender<Option<String>>, // indentation, if the mode has a hook
+    ),
     /// Call a major mode's init hook
     CallMajorModeInit(
         String,                             // mode name
+        Buffer,                             // buffer the mode is being enabled in
         tokio::sync::oneshot::Sender<bool>, // success
     ),
-    /// Call a major mode's after_change hook
-    CallMajorModeAfterChange(
-        String, // mode name
-        i64,
-        i64,
-        i64,                                // start, old_end, new_end
-        tokio::sync::oneshot::Sender<bool>, // success
+    /// Run the after_change hooks waiting in the `AfterChangeQueue`
+    FlushAfterChanges,
+    ReloadConfig(
+        PathBuf,
+        tokio::sync::oneshot::Sender<Result<(), String>>, // Ok or error message
     ),
     Shutdown,
 }
@@ -1716,6 +2223,8 @@ pub struct RoeJuliaRuntime {
     config_loaded: bool,
     /// Path to the current configuration file
     config_path: Option<PathBuf>,
+    /// After-change hooks waiting for the Julia thread
+    after_change_queue: AfterChangeQueue,
 }
 
 impl RoeJuliaRuntime {
@@ -1728,6 +2237,8 @@ impl RoeJuliaRuntime {
     /// Initializes a persistent jlrs async runtime for live queries
     pub fn new() -> Result<Self, JuliaRuntimeError> {
         let (command_tx, command_rx) = mpsc::unbounded_channel();
+        let after_change_queue = AfterChangeQueue::new(command_tx.clone());
+        let handler_queue = after_change_queue.clone();
 
         // Spawn the Julia runtime in a separate thread
         let thread_handle = std::thread::spawn(move || {
@@ -1741,7 +2252,7 @@ impl RoeJuliaRuntime {
                     .expect("Failed to spawn Julia runtime");
 
                 // Run the command handler
-                Self::julia_command_handler(julia, command_rx).await;
+                Self::julia_command_handler(julia, command_rx, handler_queue).await;
 
                 // Clean up Julia thread
                 julia_thread.join().expect("Julia thread failed");
@@ -1753,6 +2264,7 @@ impl RoeJuliaRuntime {
             thread_handle: Some(thread_handle),
             config_loaded: false,
             config_path: None,
+            after_change_queue,
         })
     }
 
@@ -1760,6 +2272,7 @@ impl RoeJuliaRuntime {
     async fn julia_command_handler(
         julia: AsyncHandle,
         mut command_rx: mpsc::UnboundedReceiver<JuliaCommand>,
+        after_change_queue: AfterChangeQueue,
     ) {
         while let Some(command) = command_rx.recv().await {
             match command {
@@ -1850,11 +2363,19 @@ impl RoeJuliaRuntime {
                     };
 
                     // Clear the buffer after command execution
-                    clear_current_buffer();
+                    let edit = clear_current_buffer();
 
                     let output = result.unwrap_or(JuliaCommandResult::Error(
                         "Result processing failed".to_string(),
                     ));
+                    // Report any direct edits along with what the command returned
+                    let output = match edit {
+                        Some(range) => JuliaCommandResult::Multi(vec![
+                            JuliaCommandResult::BufferEdited(range),
+                            output,
+                        ]),
+                        None => output,
+                    };
                     let _ = response_tx.send(output);
                 }
                 JuliaCommand::ListCommands(response_tx) => {
@@ -1929,47 +2450,108 @@ impl RoeJuliaRuntime {
                     let mode_name = result.unwrap_or_else(|_| "fundamental-mode".to_string());
                     let _ = response_tx.send(mode_name);
                 }
-                JuliaCommand::CallMajorModeInit(mode_name, response_tx) => {
-                    let task = CallMajorModeInitTask { mode_name };
+                JuliaCommand::MajorModeCommentSyntax(mode_name, response_tx) => {
+                    let task = MajorModeCommentSyntaxTask { mode_name };
                     let Ok(async_task) = julia.task(task).try_dispatch() else {
-                        let _ = response_tx.send(false);
+                        let _ = response_tx.send(DEFAULT_COMMENT_PREFIX.to_string());
                         continue;
                     };
 
                     let Ok(result) = async_task.await else {
-                        let _ = response_tx.send(false);
+                        let _ = response_tx.send(DEFAULT_COMMENT_PREFIX.to_string());
                         continue;
                     };
 
-                    let success = result.unwrap_or(false);
-                    let _ = response_tx.send(success);
+                    let prefix = result.unwrap_or_else(|_| DEFAULT_COMMENT_PREFIX.to_string());
+                    let _ = response_tx.send(prefix);
                 }
-                JuliaCommand::CallMajorModeAfterChange(
-                    mode_name,
-                    start,
-                    old_end,
-                    new_end,
-                    response_tx,
-                ) => {
-                    let task = CallMajorModeAfterChangeTask {
+                JuliaCommand::MajorModeIndentLine(mode_name, code, line, response_tx) => {
+                    let task = MajorModeIndentLineTask {
                         mode_name,
-                        start,
-                        old_end,
-                        new_end,
+                        code,
+                        line,
                     };
                     let Ok(async_task) = julia.task(task).try_dispatch() else {
+                        let _ = response_tx.send(None);
+                        continue;
+                    };
+
+                    let Ok(result) = async_task.await else {
+                        let _ = response_tx.send(None);
+                        continue;
+                    };
+
+                    let _ = response_tx.send(result.unwrap_or(None));
+                }
+                JuliaCommand::CallMajorModeInit(mode_name, buffer, response_tx) => {
+                    set_current_buffer(buffer);
+
+                    let task = CallMajorModeInitTask { mode_name };
+                    let Ok(async_task) = julia.task(task).try_dispatch() else {
+                        clear_current_buffer();
                         let _ = response_tx.send(false);
                         continue;
                     };
 
                     let Ok(result) = async_task.await else {
+                        clear_current_buffer();
                         let _ = response_tx.send(false);
                         continue;
                     };
 
+                    // Edits made by init are part of the text the buffer
+                    // starts with, as it's yet to be shown
+                    clear_current_buffer();
                     let success = result.unwrap_or(false);
                     let _ = response_tx.send(success);
                 }
+                JuliaCommand::FlushAfterChanges => {
+                    // Everything queued since the last flush runs as one batch; edits
+                    // made while it runs are picked up by the next flush.
+                    for change in after_change_queue.take_pending() {
+                        set_current_buffer(change.buffer);
+
+                        // Exceptions in the hook are reported by the task; this
+                        // is for when it couldn't be run at all
+                        let summary = format!("Error in {} after-change hook", change.major_mode);
+                        let task = CallMajorModeAfterChangeTask {
+                            mode_name: change.major_mode,
+                            start: change.range.start as i64,
+                            old_end: change.range.old_end as i64,
+                            new_end: change.range.new_end as i64,
+                        };
+                        match julia.task(task).try_dispatch() {
+                            Ok(async_task) => {
+                                if !matches!(async_task.await, Ok(Ok(_))) {
+                                    report_julia_error(summary, "Task execution failed");
+                                }
+                            }
+                            Err(_) => report_julia_error(summary, "Failed to dispatch task"),
+                        }
+
+                        // The hook's own edits aren't hooked again, but the
+                        // editor still has to know of them
+                        if let Some(range) = clear_current_buffer() {
+                            after_change_queue.note_hook_edit(change.buffer_id, range);
+                        }
+                    }
+                    after_change_queue.mark_completed();
+                }
+                JuliaCommand::ReloadConfig(path, response_tx) => {
+                    let task = ConfigReloadTask::new(path);
+                    let Ok(async_task) = julia.task(task).try_dispatch() else {
+                        let _ = response_tx.send(Err("Failed to dispatch task".to_string()));
+                        continue;
+                    };
+
+                    let Ok(result) = async_task.await else {
+                        let _ = response_tx.send(Err("Task execution failed".to_string()));
+                        continue;
+                    };
+
+                    let output = result.unwrap_or_else(|e| Err(format!("Julia error: {:?}", e)));
+                    let _ = response_tx.send(output);
+                }
                 JuliaCommand::Shutdown => {
                     break;
                 }
@@ -2058,6 +2640,58 @@ impl RoeJuliaRuntime {
         Ok(true)
     }
 
+    /// Reload the bundled Roe module and then re-evaluate the user config in
+    /// the live runtime. The runtime itself (and every buffer host holding a
+    /// handle to it) stays the same; only Julia-side definitions are replaced.
+    pub async fn reload_config(&mut self) -> Result<(), JuliaRuntimeError> {
+        self.reload_roe_module().await?;
+        self.reload_user_config().await
+    }
+
+    /// Reload the bundled Roe module, restoring its default definitions
+    pub async fn reload_roe_module(&mut self) -> Result<(), JuliaRuntimeError> {
+        if let Some(roe_module_path) = Self::bundled_roe_module_path() {
+            self.load_roe_module(roe_module_path).await?;
+        }
+        Ok(())
+    }
+
+    /// Re-evaluate the user config on top of whatever the runtime has loaded
+    pub async fn reload_user_config(&mut self) -> Result<(), JuliaRuntimeError> {
+        let config_path = self
+            .config_path
+            .clone()
+            .unwrap_or_else(Self::default_config_path);
+
+        if !config_path.exists() {
+            return Ok(());
+        }
+
+        let Some(ref command_tx) = self.command_tx else {
+            return Err(JuliaRuntimeError::ConfigLoadFailed(
+                "Runtime not initialized".to_string(),
+            ));
+        };
+
+        let (response_tx, response_rx) = tokio::sync::oneshot::channel();
+
+        command_tx
+            .send(JuliaCommand::ReloadConfig(config_path.clone(), response_tx))
+            .map_err(|_| {
+                JuliaRuntimeError::ConfigLoadFailed("Command channel closed".to_string())
+            })?;
+
+        let result = response_rx.await.map_err(|_| {
+            JuliaRuntimeError::ConfigLoadFailed("Response channel closed".to_string())
+        })?;
+
+        result.map_err(JuliaRuntimeError::ConfigLoadFailed)?;
+
+        self.config_loaded = true;
+        self.config_path = Some(config_path);
+        Ok(())
+    }
+
     /// Query a configuration value from the live Julia runtime
     pub async fn get_config(&self, key: &str) -> Result<Option<ConfigValue>, JuliaRuntimeError> {
         let Some(ref command_tx) = self.command_tx else {
@@ -2302,8 +2936,11 @@ impl RoeJuliaRuntime {
         })
     }
 
-    /// Call a major mode's init hook
-    pub async fn call_major_mode_init(&self, mode_name: &str) -> Result<bool, JuliaRuntimeError> {
+    /// Get the line comment prefix for a major mode, `#` if it doesn't say
+    pub async fn major_mode_comment_syntax(
+        &self,
+        mode_name: &str,
+    ) -> Result<String, JuliaRuntimeError> {
         let Some(ref command_tx) = self.command_tx else {
             return Err(JuliaRuntimeError::TaskExecutionFailed(
                 "Runtime not initialized".to_string(),
@@ -2313,7 +2950,7 @@ impl RoeJuliaRuntime {
         let (response_tx, response_rx) = tokio::sync::oneshot::channel();
 
         command_tx
-            .send(JuliaCommand::CallMajorModeInit(
+            .send(JuliaCommand::MajorModeCommentSyntax(
                 mode_name.to_string(),
                 response_tx,
             ))
@@ -2326,13 +2963,44 @@ impl RoeJuliaRuntime {
         })
     }
 
-    /// Call a major mode's after_change hook
-    pub async fn call_major_mode_after_change(
+    /// Ask a major mode what `line` (1-based) of `code` starts with: its
+    /// indentation, or a list marker to continue. None if it has no
+    /// `indent_line` hook.
+    pub async fn major_mode_indent_line(
         &self,
         mode_name: &str,
-        start: i64,
-        old_end: i64,
-        new_end: i64,
+        code: String,
+        line: usize,
+    ) -> Result<Option<String>, JuliaRuntimeError> {
+        let Some(ref command_tx) = self.command_tx else {
+            return Err(JuliaRuntimeError::TaskExecutionFailed(
+                "Runtime not initialized".to_string(),
+            ));
+        };
+
+        let (response_tx, response_rx) = tokio::sync::oneshot::channel();
+
+        command_tx
+            .send(JuliaCommand::MajorModeIndentLine(
+                mode_name.to_string(),
+                code,
+                line as i64,
+                response_tx,
+            ))
+            .map_err(|_| {
+                JuliaRuntimeError::TaskExecutionFailed("Command channel closed".to_string())
+            })?;
+
+        response_rx.await.map_err(|_| {
+            JuliaRuntimeError::TaskExecutionFailed("Response channel closed".to_string())
+        })
+    }
+
+    /// Call a major mode's init hook with `buffer` as the current buffer
+    pub async fn call_major_mode_init(
+        &self,
+        mode_name: &str,
+        buffer: Buffer,
     ) -> Result<bool, JuliaRuntimeError> {
         let Some(ref command_tx) = self.command_tx else {
             return Err(JuliaRuntimeError::TaskExecutionFailed(
@@ -2343,11 +3011,9 @@ impl RoeJuliaRuntime {
         let (response_tx, response_rx) = tokio::sync::oneshot::channel();
 
         command_tx
-            .send(JuliaCommand::CallMajorModeAfterChange(
+            .send(JuliaCommand::CallMajorModeInit(
                 mode_name.to_string(),
-                start,
-                old_end,
-                new_end,
+                buffer,
                 response_tx,
             ))
             .map_err(|_| {
@@ -2359,6 +3025,12 @@ impl RoeJuliaRuntime {
         })
     }
 
+    /// Handle to the queue used to run major mode after_change hooks without
+    /// waiting on the runtime lock
+    pub fn after_change_queue(&self) -> AfterChangeQueue {
+        self.after_change_queue.clone()
+    }
+
     /// Get path to the bundled roe.jl module
     pub fn bundled_roe_module_path() -> Option<PathBuf> {
         // Look for roe.jl in the jl/ directory
@@ -2459,4 +3131,36 @@ mod tests {
             assert!(bg_result.is_ok());
         }
     }
+
+    #[test]
+    fn test_direct_edits_report_changed_region() {
+        let buffer = Buffer::new(&[]);
+        set_current_buffer(buffer.clone());
+        let hello = CString::new("Hello world").unwrap();
+        let there = CString::new("there").unwrap();
+        unsafe {
+            roe_buffer_insert(0, hello.as_ptr());
+            // Past the end of the buffer, so ignored
+            roe_buffer_insert(99, hello.as_ptr());
+            roe_buffer_replace(6, 11, there.as_ptr());
+        }
+        assert_eq!(buffer.content(), "Hello there");
+        // A replacement is undone at once
+        buffer.undo_boundary();
+        buffer.undo();
+        assert_eq!(buffer.content(), "Hello world");
+        buffer.redo();
+        assert_eq!(buffer.content(), "Hello there");
+        // The end of a deletion is clamped to the buffer
+        roe_buffer_delete(5, 100);
+        assert_eq!(buffer.content(), "Hello");
+        assert_eq!(take_current_buffer_edit(), Some(ChangeRange::new(0, 0, 5)));
+        assert_eq!(take_current_buffer_edit(), None);
+
+        // Read-only buffers aren't edited
+        buffer.set_read_only(true);
+        unsafe { roe_buffer_insert(0, hello.as_ptr()) };
+        assert_eq!(clear_current_buffer(), None);
+        assert_eq!(buffer.content(), "Hello");
+    }
 }
@@ -0,0 +1,149 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Keyboard macros: `C-x (` starts recording the keys pressed, `C-x )` stops,
+//! and `C-x e` plays them back.
+//!
+//! Keys are recorded as the events `Editor::key_event` receives, each a key and
+//! the modifiers held with it, and played back through it the same way, so the
+//! bindings, modes and prompts see exactly what was typed.
+
+use crate::keys::LogicalKey;
+
+/// The keys the editor takes in at once: a key and the modifiers held with it
+pub type KeyEvent = Vec<LogicalKey>;
+
+/// Recorder and player for the last keyboard macro
+#[derive(Debug, Default)]
+pub struct KeyboardMacro {
+    /// Events recorded so far, while a macro is being defined
+    recording: Option<Vec<KeyEvent>>,
+    /// Where in the recording the key sequence being typed began, so the
+    /// sequence that ends the definition can be left out of it
+    sequence_start: usize,
+    /// The last macro defined
+    last: Vec<KeyEvent>,
+    /// Whether the last macro is being played back
+    replaying: bool,
+}
+
+impl KeyboardMacro {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether a macro is being defined
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Whether the last macro is being played back
+    pub fn is_replaying(&self) -> bool {
+        self.replaying
+    }
+
+    pub fn set_replaying(&mut self, replaying: bool) {
+        self.replaying = replaying;
+    }
+
+    /// Record `event` if a macro is being defined. `starts_sequence` is
+    /// whether it's the first event of a key sequence rather than a later
+    /// chord of one, like the `(` of `C-x (`.
+    pub fn record(&mut self, event: &[LogicalKey], starts_sequence: bool) {
+        if let Some(recording) = &mut self.recording {
+            if starts_sequence {
+                self.sequence_start = recording.len();
+            }
+            recording.push(event.to_vec());
+        }
+    }
+
+    /// Start defining a macro. False if one is already being defined, in
+    /// which case the sequence that asked is left out of it.
+    pub fn start(&mut self) -> bool {
+        if self.is_recording() {
+            self.drop_sequence();
+            return false;
+        }
+        self.recording = Some(Vec::new());
+        self.sequence_start = 0;
+        true
+    }
+
+    /// Stop defining the macro, which becomes the last macro without the key
+    /// sequence that stopped it. Returns how many key events it has, or None if
+    /// no macro was being defined.
+    pub fn end(&mut self) -> Option<usize> {
+        self.drop_sequence();
+        self.last = self.recording.take()?;
+        Some(self.last.len())
+    }
+
+    /// The last macro defined, to play back
+    pub fn last(&self) -> &[KeyEvent] {
+        &self.last
+    }
+
+    /// Leave the key sequence just typed out of the recording
+    fn drop_sequence(&mut self) {
+        if let Some(recording) = &mut self.recording {
+            recording.truncate(self.sequence_start);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{KeyModifier, Side};
+
+    fn ctrl(c: char) -> KeyEvent {
+        vec![
+            LogicalKey::Modifier(KeyModifier::Control(Side::Left)),
+            LogicalKey::AlphaNumeric(c),
+        ]
+    }
+
+    fn key(c: char) -> KeyEvent {
+        vec![LogicalKey::AlphaNumeric(c)]
+    }
+
+    #[test]
+    fn test_records_between_start_and_end() {
+        let mut kmacro = KeyboardMacro::new();
+
+        // Nothing is recorded until a definition starts
+        kmacro.record(&key('a'), true);
+        assert!(kmacro.start());
+        assert!(kmacro.is_recording());
+
+        kmacro.record(&key('b'), true);
+        kmacro.record(&ctrl('a'), true);
+
+        // A second start is refused and leaves no trace
+        kmacro.record(&ctrl('x'), true);
+        kmacro.record(&key('('), false);
+        assert!(!kmacro.start());
+
+        // The sequence that ends the definition isn't part of it
+        kmacro.record(&ctrl('x'), true);
+        kmacro.record(&key(')'), false);
+        assert_eq!(kmacro.end(), Some(2));
+        assert_eq!(kmacro.last(), &[key('b'), ctrl('a')]);
+        assert!(!kmacro.is_recording());
+
+        // Ending with no definition under way keeps the last macro
+        assert_eq!(kmacro.end(), None);
+        assert_eq!(kmacro.last().len(), 2);
+    }
+}
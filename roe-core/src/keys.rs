@@ -16,6 +16,79 @@ use std::time::Instant;
 
 pub trait Bindings {
     fn keystroke(&self, keys: Vec<LogicalKey>) -> KeyAction;
+
+    /// Key sequences bound more than once, for `describe-binding-conflicts`
+    fn conflicts(&self) -> Vec<BindingConflict> {
+        Vec::new()
+    }
+
+    /// The keys that can follow the chord prefix `prefix`, and what each does,
+    /// for the pending-chord hints
+    fn continuations(&self, _prefix: &[LogicalKey]) -> Vec<KeyHint> {
+        Vec::new()
+    }
+
+    /// Bind `key_sequence` to `action` for the keys typed from now on, as
+    /// `define_key` does when the config loads
+    fn bind(&mut self, _key_sequence: &str, _action: &str) -> Result<(), String> {
+        Err("These keybindings can't be changed".to_string())
+    }
+
+    /// Remove the binding for `key_sequence`, returning the action it had
+    fn unbind(&mut self, _key_sequence: &str) -> Result<String, String> {
+        Err("These keybindings can't be changed".to_string())
+    }
+}
+
+/// A key that can follow a pending chord prefix
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyHint {
+    /// The next chord as it's written in keybindings, e.g. "C-f"
+    pub key: String,
+    /// What it's bound to, or "+prefix" when more keys must follow
+    pub action: String,
+}
+
+/// Where a keybinding was defined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindingSource {
+    /// The defaults in jl/keybindings.jl
+    Default,
+    /// The user's config file
+    User,
+}
+
+impl std::fmt::Display for BindingSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BindingSource::Default => write!(f, "default"),
+            BindingSource::User => write!(f, "user config"),
+        }
+    }
+}
+
+/// One binding as it was written in Julia
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BindingOrigin {
+    pub key_sequence: String,
+    pub action: String,
+    /// `None` when the caller didn't say where the binding came from
+    pub source: Option<BindingSource>,
+}
+
+/// A key sequence bound more than once; the last binding is the one that wins
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BindingConflict {
+    pub bindings: Vec<BindingOrigin>,
+}
+
+impl BindingConflict {
+    /// The binding the key sequence resolves to
+    pub fn winner(&self) -> &BindingOrigin {
+        self.bindings
+            .last()
+            .expect("a conflict has at least two bindings")
+    }
 }
 
 /// An enumeration of our logical actions caused by keystrokes.
@@ -70,6 +143,12 @@ pub enum KeyAction {
     Undo,
     /// Redo last undone operation
     Redo,
+    /// Start recording a keyboard macro
+    StartMacro,
+    /// Stop recording the keyboard macro
+    EndMacro,
+    /// Play back the last keyboard macro, ending its definition first if need be
+    CallMacro,
     /// Delete word forward
     DeleteWord,
     /// Backspace word backward
@@ -84,6 +163,10 @@ pub enum KeyAction {
     MarkEnd,
     /// Force a full screen redraw
     Redraw,
+    /// Scroll the cursor's line to the middle, top, then bottom of the window
+    Recenter,
+    /// Start or extend a count for the next command (C-u)
+    UniversalArgument,
 
     // TEMPORARY: Keep these during transition
     CommandMode,
@@ -115,6 +198,23 @@ pub enum CursorDirection {
     WordBackward,
     ParagraphForward,
     ParagraphBackward,
+    /// Past the bracketed group or word after the cursor
+    SexpForward,
+    /// Back to the start of the bracketed group or word before the cursor
+    SexpBackward,
+}
+
+impl CursorDirection {
+    /// Moves between lines, aiming for the goal column rather than resetting it
+    pub fn is_vertical(&self) -> bool {
+        matches!(
+            self,
+            CursorDirection::Up
+                | CursorDirection::Down
+                | CursorDirection::PageUp
+                | CursorDirection::PageDown
+        )
+    }
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
@@ -221,10 +321,21 @@ impl KeyState {
         KeyState { keys: Vec::new() }
     }
 
+    /// Record a key press. Each chord of a sequence is its modifiers followed by
+    /// its key, so "C-x C-f" accumulates as [Control, x, Control, f]. A modifier
+    /// already held for the chord being built isn't recorded twice; any other key
+    /// is, so repeats like "C-c C-c" reach the bindings.
     pub fn press(&mut self, key_code: LogicalKey) {
-        // If we already have this key pressed, ignore it.
-        if self.keys.iter().any(|kp| kp.key == key_code) {
-            return;
+        if let LogicalKey::Modifier(_) = key_code {
+            let held = self
+                .keys
+                .iter()
+                .rev()
+                .take_while(|kp| matches!(kp.key, LogicalKey::Modifier(_)))
+                .any(|kp| kp.key == key_code);
+            if held {
+                return;
+            }
         }
         self.keys.push(KeyPress {
             key: key_code,
@@ -258,12 +369,20 @@ pub struct DefaultBindings {}
 pub struct ConfigurableBindings {
     /// Map from key sequences to actions
     bindings: std::collections::HashMap<Vec<LogicalKey>, KeyAction>,
+    /// Every proper prefix of a bound sequence, so a partial sequence of any
+    /// length is recognized as a chord in progress
+    prefixes: std::collections::HashSet<Vec<LogicalKey>>,
+    /// Every binding added for each key sequence, in order, so overrides can
+    /// be reported
+    origins: std::collections::HashMap<Vec<LogicalKey>, Vec<BindingOrigin>>,
 }
 
 impl ConfigurableBindings {
     pub fn new() -> Self {
         Self {
             bindings: std::collections::HashMap::new(),
+            prefixes: std::collections::HashSet::new(),
+            origins: std::collections::HashMap::new(),
         }
     }
 
@@ -271,56 +390,68 @@ impl ConfigurableBindings {
     /// key_sequence: "C-x C-c", "M-x", "C-p", etc.
     /// action: "quit" (command name) or ":cursor-up" (direct action)
     pub fn add_binding(&mut self, key_sequence: &str, action: &str) {
+        self.add_binding_with_source(key_sequence, action, None);
+    }
+
+    /// Like `add_binding`, remembering where the binding was defined
+    pub fn add_binding_from(&mut self, key_sequence: &str, action: &str, source: BindingSource) {
+        self.add_binding_with_source(key_sequence, action, Some(source));
+    }
+
+    fn add_binding_with_source(
+        &mut self,
+        key_sequence: &str,
+        action: &str,
+        source: Option<BindingSource>,
+    ) {
         if let Some(keys) = Self::parse_key_sequence(key_sequence) {
             if let Some(key_action) = Self::parse_action(action) {
+                self.origins
+                    .entry(keys.clone())
+                    .or_default()
+                    .push(BindingOrigin {
+                        key_sequence: key_sequence.to_string(),
+                        action: action.to_string(),
+                        source,
+                    });
+                for len in 1..keys.len() {
+                    self.prefixes.insert(keys[..len].to_vec());
+                }
                 self.bindings.insert(keys, key_action);
             }
         }
     }
 
+    /// Load the bindings Julia had after the Roe module (`defaults`) and after the
+    /// user config (`current`). A default the config rebinds is added before the
+    /// config's binding so the override is recorded; one it undefined is dropped.
+    pub fn add_layered_bindings(
+        &mut self,
+        defaults: &[(String, String)],
+        current: &[(String, String)],
+    ) {
+        let current_sequences: std::collections::HashSet<&str> =
+            current.iter().map(|(seq, _)| seq.as_str()).collect();
+        for (key_seq, action) in defaults {
+            if current_sequences.contains(key_seq.as_str()) {
+                self.add_binding_from(key_seq, action, BindingSource::Default);
+            }
+        }
+        for (key_seq, action) in current {
+            if !defaults.iter().any(|(s, a)| s == key_seq && a == action) {
+                self.add_binding_from(key_seq, action, BindingSource::User);
+            }
+        }
+    }
+
     /// Parse a key sequence string like "C-x C-c" into Vec<LogicalKey>
-    /// For chords like "C-x C-c", the result is [Control, 'x', 'c'] (not [Control, 'x', Control, 'c'])
+    /// Each space-separated chord contributes its modifiers and then its key, so
+    /// "C-x C-c" is [Control, 'x', Control, 'c'] and "C-x c" is [Control, 'x', 'c'].
     /// This matches how the key state machine accumulates chord keys.
     fn parse_key_sequence(seq: &str) -> Option<Vec<LogicalKey>> {
-        let parts: Vec<&str> = seq.split_whitespace().collect();
         let mut keys = Vec::new();
-        let mut chord_modifier: Option<LogicalKey> = None;
-
-        for (i, part) in parts.iter().enumerate() {
-            if let Some(parsed_keys) = Self::parse_single_key(part) {
-                if i == 0 {
-                    // First part: include all keys (modifier + base key)
-                    keys.extend(parsed_keys.clone());
-                    // If this part has a modifier, remember it for chord continuation
-                    if parsed_keys.len() > 1 {
-                        if let LogicalKey::Modifier(_) = &parsed_keys[0] {
-                            chord_modifier = Some(parsed_keys[0]);
-                        }
-                    }
-                } else {
-                    // Subsequent parts in a chord
-                    if parsed_keys.len() > 1 {
-                        // This part has a modifier (like C-c in "C-x C-c")
-                        // Check if it's the same modifier as the chord started with
-                        if let LogicalKey::Modifier(_) = &parsed_keys[0] {
-                            if chord_modifier.is_some() {
-                                // Same modifier continuation - only add the base key
-                                keys.extend(parsed_keys.into_iter().skip(1));
-                            } else {
-                                // Different or no chord modifier - add all keys
-                                keys.extend(parsed_keys);
-                            }
-                        } else {
-                            keys.extend(parsed_keys);
-                        }
-                    } else {
-                        // Single key (like 'b' in "C-x b")
-                        keys.extend(parsed_keys);
-                    }
-                }
-            } else {
-                return None; // Invalid key in sequence
-            }
+        for part in seq.split_whitespace() {
+            keys.extend(Self::parse_single_key(part)?);
         }
 
         if keys.is_empty() {
@@ -330,6 +461,31 @@ impl ConfigurableBindings {
         }
     }
 
+    /// Parse a key sequence as `parse_key_sequence` does, saying which key
+    /// isn't valid when one isn't
+    pub fn try_parse_key_sequence(seq: &str) -> Result<Vec<LogicalKey>, String> {
+        if seq.trim().is_empty() {
+            return Err("No key sequence given".to_string());
+        }
+        for part in seq.split_whitespace() {
+            if Self::parse_single_key(part).is_none() {
+                return Err(format!("Invalid key \"{part}\" in \"{seq}\""));
+            }
+        }
+        Self::parse_key_sequence(seq).ok_or_else(|| format!("Invalid key sequence \"{seq}\""))
+    }
+
+    /// Check that `action` is a command name or one of the `:` actions
+    pub fn check_action(action: &str) -> Result<(), String> {
+        if action.trim().is_empty() {
+            return Err("No action given".to_string());
+        }
+        match Self::parse_action(action) {
+            Some(_) => Ok(()),
+            None => Err(format!("Unknown action \"{action}\"")),
+        }
+    }
+
     /// Parse a single key like "C-x", "M-f", "a", "F5", "Left", "C-S-/"
     fn parse_single_key(key_str: &str) -> Option<Vec<LogicalKey>> {
         let mut modifiers = Vec::new();
@@ -357,6 +513,14 @@ impl ConfigurableBindings {
         // Parse the base key
         let base_key = Self::parse_base_key(rest)?;
 
+        // Renderers report modifiers in this order, whatever order they're written in
+        modifiers.sort_by_key(|key| match key {
+            LogicalKey::Modifier(KeyModifier::Control(_)) => 0,
+            LogicalKey::Modifier(KeyModifier::Meta(_) | KeyModifier::Alt(_)) => 1,
+            LogicalKey::Modifier(KeyModifier::Shift(_)) => 2,
+            _ => 3,
+        });
+
         if modifiers.is_empty() {
             Some(vec![base_key])
         } else {
@@ -428,6 +592,8 @@ impl ConfigurableBindings {
                 "cursor-paragraph-backward" => {
                     Some(KeyAction::Cursor(CursorDirection::ParagraphBackward))
                 }
+                "cursor-sexp-forward" => Some(KeyAction::Cursor(CursorDirection::SexpForward)),
+                "cursor-sexp-backward" => Some(KeyAction::Cursor(CursorDirection::SexpBackward)),
 
                 // Cursor movement with selection (CUA-style shift-arrow)
                 "cursor-up-select" => Some(KeyAction::CursorSelect(CursorDirection::Up)),
@@ -460,6 +626,12 @@ impl ConfigurableBindings {
                 "cursor-paragraph-backward-select" => {
                     Some(KeyAction::CursorSelect(CursorDirection::ParagraphBackward))
                 }
+                "cursor-sexp-forward-select" => {
+                    Some(KeyAction::CursorSelect(CursorDirection::SexpForward))
+                }
+                "cursor-sexp-backward-select" => {
+                    Some(KeyAction::CursorSelect(CursorDirection::SexpBackward))
+                }
 
                 // Text manipulation
                 "delete" => Some(KeyAction::Delete),
@@ -484,12 +656,24 @@ impl ConfigurableBindings {
                 "escape" => Some(KeyAction::Escape),
                 "undo" => Some(KeyAction::Undo),
                 "redo" => Some(KeyAction::Redo),
+                "kmacro-start-macro" => Some(KeyAction::StartMacro),
+                "kmacro-end-macro" => Some(KeyAction::EndMacro),
+                "kmacro-end-and-call-macro" => Some(KeyAction::CallMacro),
+                "universal-argument" => Some(KeyAction::UniversalArgument),
                 "redraw" => Some(KeyAction::Redraw),
+                "recenter-top-bottom" => Some(KeyAction::Recenter),
 
                 // Chord continuation
                 "chord-next" => Some(KeyAction::ChordNext),
 
-                _ => None,
+                // ":insert é" types a character, for C-x 8 digraphs
+                _ => {
+                    let mut chars = action_name.strip_prefix("insert ")?.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => Some(KeyAction::AlphaNumeric(c)),
+                        _ => None,
+                    }
+                }
             }
         } else {
             // Command name
@@ -500,12 +684,7 @@ impl ConfigurableBindings {
     /// Check if a key sequence is a prefix of any binding (for chord detection)
     /// Note: keys should already be normalized before calling this
     pub fn is_prefix(&self, keys: &[LogicalKey]) -> bool {
-        for bound_keys in self.bindings.keys() {
-            if bound_keys.len() > keys.len() && bound_keys.starts_with(keys) {
-                return true;
-            }
-        }
-        false
+        self.prefixes.contains(keys)
     }
 
     /// Get the number of bindings (for debugging)
@@ -555,9 +734,144 @@ impl ConfigurableBindings {
     fn normalize_keys(keys: &[LogicalKey]) -> Vec<LogicalKey> {
         keys.iter().map(Self::normalize_key).collect()
     }
+
+    /// Drop the Shift from each chord whose key is a character, uppercasing
+    /// letters, and from a trailing run of modifiers still waiting for its key.
+    /// Returns the folded keys and whether they end in a chord without its key
+    /// yet, or `None` if there was no Shift to fold.
+    fn fold_shifted_chars(keys: &[LogicalKey]) -> Option<(Vec<LogicalKey>, bool)> {
+        let shift = LogicalKey::Modifier(KeyModifier::Shift(Side::Left));
+        let mut folded = Vec::with_capacity(keys.len());
+        let mut chord_start = 0;
+        let mut changed = false;
+        // Remove the Shift (if any) from the chord being built
+        let mut unshift = |folded: &mut Vec<LogicalKey>, chord_start: usize| {
+            let position = folded[chord_start..].iter().position(|k| *k == shift);
+            if let Some(i) = position {
+                folded.remove(chord_start + i);
+                changed = true;
+            }
+            position.is_some()
+        };
+        for key in keys {
+            match key {
+                LogicalKey::Modifier(_) => folded.push(*key),
+                LogicalKey::AlphaNumeric(c) => {
+                    let c = if unshift(&mut folded, chord_start) {
+                        c.to_ascii_uppercase()
+                    } else {
+                        *c
+                    };
+                    folded.push(LogicalKey::AlphaNumeric(c));
+                    chord_start = folded.len();
+                }
+                _ => {
+                    folded.push(*key);
+                    chord_start = folded.len();
+                }
+            }
+        }
+        let chord_pending = chord_start < folded.len();
+        if chord_pending {
+            unshift(&mut folded, chord_start);
+        }
+        changed.then_some((folded, chord_pending))
+    }
 }
 
 impl Bindings for ConfigurableBindings {
+    fn conflicts(&self) -> Vec<BindingConflict> {
+        let mut conflicts: Vec<BindingConflict> = self
+            .origins
+            .values()
+            .filter(|bindings| bindings.len() > 1)
+            .map(|bindings| BindingConflict {
+                bindings: bindings.clone(),
+            })
+            .collect();
+        conflicts.sort_by(|a, b| a.winner().key_sequence.cmp(&b.winner().key_sequence));
+        conflicts
+    }
+
+    fn continuations(&self, prefix: &[LogicalKey]) -> Vec<KeyHint> {
+        let mut prefix = Self::normalize_keys(prefix);
+        if !self.is_prefix(&prefix) {
+            match Self::fold_shifted_chars(&prefix) {
+                Some((folded, false)) if self.is_prefix(&folded) => prefix = folded,
+                _ => return Vec::new(),
+            }
+        }
+        let chords = prefix
+            .iter()
+            .filter(|key| !matches!(key, LogicalKey::Modifier(_)))
+            .count();
+
+        // Sorted by key, a binding for the next chord itself taking precedence
+        // over longer ones passing through it
+        let mut hints = std::collections::BTreeMap::new();
+        for (keys, origins) in &self.origins {
+            if keys.len() <= prefix.len() || !keys.starts_with(&prefix) {
+                continue;
+            }
+            let Some(winner) = origins.last() else {
+                continue;
+            };
+            let parts: Vec<&str> = winner.key_sequence.split_whitespace().collect();
+            let Some(next) = parts.get(chords) else {
+                continue;
+            };
+            if parts.len() == chords + 1 {
+                hints.insert(next.to_string(), winner.action.clone());
+            } else {
+                hints
+                    .entry(next.to_string())
+                    .or_insert_with(|| "+prefix".to_string());
+            }
+        }
+        hints
+            .into_iter()
+            .map(|(key, action)| KeyHint { key, action })
+            .collect()
+    }
+
+    fn bind(&mut self, key_sequence: &str, action: &str) -> Result<(), String> {
+        let keys = Self::try_parse_key_sequence(key_sequence)?;
+        Self::check_action(action)?;
+        // Binding a sequence to what it already runs isn't an override
+        let unchanged = self
+            .origins
+            .get(&keys)
+            .and_then(|origins| origins.last())
+            .is_some_and(|winner| winner.action == action);
+        if !unchanged {
+            self.add_binding_from(key_sequence, action, BindingSource::User);
+        }
+        Ok(())
+    }
+
+    fn unbind(&mut self, key_sequence: &str) -> Result<String, String> {
+        let keys = Self::try_parse_key_sequence(key_sequence)?;
+        if self.bindings.remove(&keys).is_none() {
+            return Err(if self.is_prefix(&keys) {
+                format!("{key_sequence} is a prefix; unbind the sequences that start with it")
+            } else {
+                format!("{key_sequence} is not bound")
+            });
+        }
+        let action = self
+            .origins
+            .remove(&keys)
+            .and_then(|origins| origins.last().map(|winner| winner.action.clone()))
+            .unwrap_or_default();
+        // The sequence may have been all that made its prefixes chords
+        self.prefixes = self
+            .bindings
+            .keys()
+            .flat_map(|keys| (1..keys.len()).map(move |len| keys[..len].to_vec()))
+            .collect();
+        Ok(action)
+    }
+
     fn keystroke(&self, keys: Vec<LogicalKey>) -> KeyAction {
         // Normalize keys to ignore Side differences in modifiers
         let normalized = Self::normalize_keys(&keys);
@@ -572,6 +886,19 @@ impl Bindings for ConfigurableBindings {
             return KeyAction::ChordNext;
         }
 
+        // Shift that went into typing a character ("E", "{", a quote) is part of
+        // the character, so "C-x 8 ' E" and "M-{" match however they're reported
+        if let Some((folded, chord_pending)) = Self::fold_shifted_chars(&normalized) {
+            if !chord_pending {
+                if let Some(action) = self.bindings.get(&folded) {
+                    return action.clone();
+                }
+            }
+            if !folded.is_empty() && self.is_prefix(&folded) {
+                return KeyAction::ChordNext;
+            }
+        }
+
         // Handle single alphanumeric keys as self-insert
         if keys.len() == 1 {
             if let LogicalKey::AlphaNumeric(c) = keys[0] {
@@ -902,3 +1229,287 @@ impl Bindings for DefaultBindings {
         KeyAction::Unbound
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(bindings: &[(&str, &str)]) -> Vec<(String, String)> {
+        bindings
+            .iter()
+            .map(|(seq, action)| (seq.to_string(), action.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_layered_bindings_report_overrides() {
+        let defaults = pairs(&[
+            ("C-s", "isearch-forward"),
+            ("C-x C-s", "save-buffer"),
+            ("C-g", ":cancel"),
+        ]);
+        // The user rebinds C-s and C-x C-s, and undefines C-g
+        let current = pairs(&[("C-s", "save-buffer"), ("C-x C-s", "quit")]);
+
+        let mut bindings = ConfigurableBindings::new();
+        bindings.add_layered_bindings(&defaults, &current);
+
+        let ctrl = LogicalKey::Modifier(KeyModifier::Control(Side::Left));
+        assert_eq!(
+            bindings.keystroke(vec![ctrl, LogicalKey::AlphaNumeric('s')]),
+            KeyAction::Command("save-buffer".to_string())
+        );
+        assert_eq!(
+            bindings.keystroke(vec![ctrl, LogicalKey::AlphaNumeric('g')]),
+            KeyAction::Unbound
+        );
+
+        let conflicts = bindings.conflicts();
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(
+            conflicts[0].bindings,
+            vec![
+                BindingOrigin {
+                    key_sequence: "C-s".to_string(),
+                    action: "isearch-forward".to_string(),
+                    source: Some(BindingSource::Default),
+                },
+                BindingOrigin {
+                    key_sequence: "C-s".to_string(),
+                    action: "save-buffer".to_string(),
+                    source: Some(BindingSource::User),
+                },
+            ]
+        );
+        assert_eq!(conflicts[1].winner().key_sequence, "C-x C-s");
+        assert_eq!(conflicts[1].winner().action, "quit");
+        assert_eq!(conflicts[1].winner().source, Some(BindingSource::User));
+    }
+
+    fn ctrl() -> LogicalKey {
+        LogicalKey::Modifier(KeyModifier::Control(Side::Left))
+    }
+
+    fn press_all(state: &mut KeyState, keys: &[LogicalKey]) -> Vec<LogicalKey> {
+        for key in keys {
+            state.press(*key);
+        }
+        state.pressed().iter().map(|kp| kp.key).collect()
+    }
+
+    #[test]
+    fn test_key_state_records_each_chord() {
+        let c = LogicalKey::AlphaNumeric('c');
+        let mut state = KeyState::new();
+        // A lone Control press, then C-c twice: Control is recorded once per chord
+        // and the repeated key isn't swallowed
+        press_all(&mut state, &[ctrl()]);
+        press_all(&mut state, &[ctrl(), c]);
+        assert_eq!(
+            press_all(&mut state, &[ctrl(), c]),
+            vec![ctrl(), c, ctrl(), c]
+        );
+    }
+
+    #[test]
+    fn test_long_sequences_and_prefixes() {
+        let mut bindings = ConfigurableBindings::new();
+        bindings.add_binding("C-c C-x C-f", "visit-file");
+        bindings.add_binding("C-c C-x f", "quit");
+        bindings.add_binding("C-c ^ n", "next-conflict");
+
+        let key = |c| LogicalKey::AlphaNumeric(c);
+        let c_c = vec![ctrl(), key('c')];
+        let c_c_c_x = vec![ctrl(), key('c'), ctrl(), key('x')];
+        assert_eq!(bindings.keystroke(vec![ctrl()]), KeyAction::ChordNext);
+        assert_eq!(bindings.keystroke(c_c.clone()), KeyAction::ChordNext);
+        assert_eq!(bindings.keystroke(c_c_c_x.clone()), KeyAction::ChordNext);
+        assert_eq!(
+            bindings.keystroke([c_c_c_x.clone(), vec![ctrl(), key('f')]].concat()),
+            KeyAction::Command("visit-file".to_string())
+        );
+        // Releasing Control for the last chord is a different sequence
+        assert_eq!(
+            bindings.keystroke([c_c_c_x, vec![key('f')]].concat()),
+            KeyAction::Command("quit".to_string())
+        );
+        assert_eq!(
+            bindings.keystroke([c_c.clone(), vec![key('^'), key('n')]].concat()),
+            KeyAction::Command("next-conflict".to_string())
+        );
+        assert_eq!(
+            bindings.keystroke([c_c, vec![key('^'), key('x')]].concat()),
+            KeyAction::Unbound
+        );
+    }
+
+    #[test]
+    fn test_prefix_continuations() {
+        let mut bindings = ConfigurableBindings::new();
+        bindings.add_binding("C-x C-f", "find-file");
+        bindings.add_binding("C-x C-s", "save-buffer");
+        bindings.add_binding("C-x C-s", "save-some-buffers");
+        bindings.add_binding("C-x 4 f", "find-file-other-window");
+        bindings.add_binding("C-x 4 b", "switch-buffer-other-window");
+        bindings.add_binding("C-c a", "quit");
+
+        let key = |c| LogicalKey::AlphaNumeric(c);
+        let hint = |key: &str, action: &str| KeyHint {
+            key: key.to_string(),
+            action: action.to_string(),
+        };
+        // Sorted by key, the last binding winning, and a key more must follow
+        // shown as a prefix
+        assert_eq!(
+            bindings.continuations(&[ctrl(), key('x')]),
+            vec![
+                hint("4", "+prefix"),
+                hint("C-f", "find-file"),
+                hint("C-s", "save-some-buffers"),
+            ]
+        );
+        assert_eq!(
+            bindings.continuations(&[
+                LogicalKey::Modifier(KeyModifier::Control(Side::Right)),
+                key('x'),
+                key('4')
+            ]),
+            vec![
+                hint("b", "switch-buffer-other-window"),
+                hint("f", "find-file-other-window"),
+            ]
+        );
+        // Not a prefix, or a complete binding: nothing follows
+        assert!(bindings.continuations(&[ctrl(), key('z')]).is_empty());
+        assert!(bindings
+            .continuations(&[ctrl(), key('c'), key('a')])
+            .is_empty());
+    }
+
+    #[test]
+    fn test_rebind_at_runtime() {
+        let mut bindings = ConfigurableBindings::new();
+        bindings.add_binding("C-x C-s", "save-buffer");
+        bindings.add_binding("C-c k", "kill-buffer");
+
+        let key = |c| LogicalKey::AlphaNumeric(c);
+        let c_x_c_s = vec![ctrl(), key('x'), ctrl(), key('s')];
+        let c_c_k = vec![ctrl(), key('c'), key('k')];
+
+        bindings.bind("C-x C-s", "save-some-buffers").unwrap();
+        assert_eq!(
+            bindings.keystroke(c_x_c_s.clone()),
+            KeyAction::Command("save-some-buffers".to_string())
+        );
+        assert_eq!(bindings.conflicts().len(), 1);
+        // Binding again to the same action isn't another override
+        bindings.bind("C-x C-s", "save-some-buffers").unwrap();
+        assert_eq!(bindings.conflicts()[0].bindings.len(), 2);
+
+        assert_eq!(bindings.unbind("C-c k"), Ok("kill-buffer".to_string()));
+        assert_eq!(bindings.keystroke(c_c_k), KeyAction::Unbound);
+        // C-c is no longer the start of anything
+        assert_eq!(
+            bindings.keystroke(vec![ctrl(), key('c')]),
+            KeyAction::Unbound
+        );
+        assert_eq!(
+            bindings.unbind("C-c k"),
+            Err("C-c k is not bound".to_string())
+        );
+        assert_eq!(
+            bindings.unbind("C-x"),
+            Err("C-x is a prefix; unbind the sequences that start with it".to_string())
+        );
+
+        // Malformed input is rejected and changes nothing
+        assert_eq!(
+            bindings.bind("C-x Foo", "quit"),
+            Err("Invalid key \"Foo\" in \"C-x Foo\"".to_string())
+        );
+        assert_eq!(
+            bindings.bind("C-x C-s", ":no-such-action"),
+            Err("Unknown action \":no-such-action\"".to_string())
+        );
+        assert!(bindings.bind("  ", "quit").is_err());
+        assert_eq!(
+            bindings.keystroke(c_x_c_s),
+            KeyAction::Command("save-some-buffers".to_string())
+        );
+    }
+
+    #[test]
+    fn test_shifted_characters_match_their_bindings() {
+        let mut bindings = ConfigurableBindings::new();
+        bindings.add_binding("C-x 8 ' e", ":insert é");
+        bindings.add_binding("C-x 8 ' E", ":insert É");
+        bindings.add_binding("C-x 8 \" u", ":insert ü");
+        bindings.add_binding("M-{", ":cursor-paragraph-backward");
+
+        let key = |c| LogicalKey::AlphaNumeric(c);
+        let shift = LogicalKey::Modifier(KeyModifier::Shift(Side::Left));
+        let meta = LogicalKey::Modifier(KeyModifier::Meta(Side::Left));
+        let c_x_8 = vec![ctrl(), key('x'), key('8')];
+        assert_eq!(
+            bindings.keystroke([c_x_8.clone(), vec![key('\''), key('e')]].concat()),
+            KeyAction::AlphaNumeric('é')
+        );
+        // Shift held for the capital, whether or not the key reports it
+        assert_eq!(
+            bindings.keystroke([c_x_8.clone(), vec![key('\''), shift, key('e')]].concat()),
+            KeyAction::AlphaNumeric('É')
+        );
+        assert_eq!(
+            bindings.keystroke([c_x_8.clone(), vec![key('\''), shift, key('E')]].concat()),
+            KeyAction::AlphaNumeric('É')
+        );
+        // ... and for a shifted symbol, including while it is still held alone
+        assert_eq!(
+            bindings.keystroke([c_x_8.clone(), vec![shift]].concat()),
+            KeyAction::ChordNext
+        );
+        assert_eq!(
+            bindings.keystroke([c_x_8.clone(), vec![shift, key('"')]].concat()),
+            KeyAction::ChordNext
+        );
+        assert_eq!(
+            bindings.keystroke([c_x_8, vec![shift, key('"'), key('u')]].concat()),
+            KeyAction::AlphaNumeric('ü')
+        );
+        assert_eq!(
+            bindings.keystroke(vec![meta, shift, key('{')]),
+            KeyAction::Cursor(CursorDirection::ParagraphBackward)
+        );
+    }
+
+    #[test]
+    fn test_insert_action() {
+        assert_eq!(
+            ConfigurableBindings::parse_action(":insert ÷"),
+            Some(KeyAction::AlphaNumeric('÷'))
+        );
+        assert_eq!(ConfigurableBindings::parse_action(":insert ab"), None);
+        assert_eq!(ConfigurableBindings::parse_action(":insert "), None);
+    }
+
+    #[test]
+    fn test_modifier_order_is_canonical() {
+        let mut bindings = ConfigurableBindings::new();
+        bindings.add_binding("M-C-x", "quit");
+        let meta = LogicalKey::Modifier(KeyModifier::Meta(Side::Left));
+        assert_eq!(
+            bindings.keystroke(vec![ctrl(), meta, LogicalKey::AlphaNumeric('x')]),
+            KeyAction::Command("quit".to_string())
+        );
+    }
+
+    #[test]
+    fn test_plain_bindings_have_no_source() {
+        let mut bindings = ConfigurableBindings::new();
+        bindings.add_binding("M-x", "command-mode");
+        bindings.add_binding("M-x", "quit");
+        let conflicts = bindings.conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].winner().source, None);
+    }
+}
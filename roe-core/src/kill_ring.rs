@@ -11,7 +11,7 @@
 // this program. If not, see <https://www.gnu.org/licenses/>.
 //
 
-use arboard::Clipboard;
+use crate::clipboard::Clipboard;
 
 /// Emacs-style kill-ring implementation with system clipboard integration
 ///
@@ -19,8 +19,8 @@ use arboard::Clipboard;
 /// It maintains a history of killed text that can be yanked (pasted) back.
 /// Multiple consecutive kills are appended together, following Emacs conventions.
 ///
-/// Kill operations copy to both the kill-ring and the system clipboard.
-/// Yank operations check the system clipboard first - if it contains text
+/// Kill operations copy to both the kill-ring and the clipboard, when one is set.
+/// Yank operations check the clipboard first - if it contains text
 /// that differs from the kill-ring head, it's treated as external input.
 
 pub struct KillRing {
@@ -32,8 +32,8 @@ pub struct KillRing {
     current_index: usize,
     /// Whether the last operation was a kill (for appending consecutive kills)
     last_was_kill: bool,
-    /// System clipboard handle (optional - clipboard may not be available)
-    clipboard: Option<Clipboard>,
+    /// Clipboard shared with other programs (none until a frontend sets one)
+    clipboard: Option<Box<dyn Clipboard + Send>>,
 }
 
 impl Default for KillRing {
@@ -50,32 +50,30 @@ impl KillRing {
 
     /// Create a new kill-ring with specified maximum capacity
     pub fn with_capacity(max_size: usize) -> Self {
-        // Try to initialize clipboard, but don't fail if unavailable
-        let clipboard = Clipboard::new().ok();
-
         KillRing {
             entries: Vec::new(),
             max_size: max_size.max(1), // Ensure at least 1 entry
             current_index: 0,
             last_was_kill: false,
-            clipboard,
+            clipboard: None,
         }
     }
 
-    /// Copy text to system clipboard (best effort, ignores errors)
+    /// Share kills and yanks with a clipboard
+    pub fn set_clipboard(&mut self, clipboard: impl Clipboard + Send + 'static) {
+        self.clipboard = Some(Box::new(clipboard));
+    }
+
+    /// Copy text to the clipboard (best effort)
     fn copy_to_clipboard(&mut self, text: &str) {
         if let Some(ref mut clipboard) = self.clipboard {
-            let _ = clipboard.set_text(text.to_string());
+            clipboard.set(text);
         }
     }
 
-    /// Get text from system clipboard
-    fn get_from_clipboard(&mut self) -> Option<String> {
-        if let Some(ref mut clipboard) = self.clipboard {
-            clipboard.get_text().ok()
-        } else {
-            None
-        }
+    /// Get text from the clipboard
+    fn get_from_clipboard(&self) -> Option<String> {
+        self.clipboard.as_ref()?.get()
     }
 
     /// Add text to the kill-ring and copy to system clipboard
@@ -157,7 +155,7 @@ impl KillRing {
     pub fn yank(&mut self) -> Option<&str> {
         self.last_was_kill = false;
 
-        // Check system clipboard for external content
+        // Check the clipboard for external content
         if let Some(clipboard_text) = self.get_from_clipboard() {
             if !clipboard_text.is_empty() {
                 // Check if this differs from our most recent entry
@@ -372,4 +370,35 @@ mod tests {
         assert_eq!(ring.current(), Some("test"));
         assert_eq!(ring.yank(), Some("test"));
     }
+
+    /// Clipboard that's just a string, standing in for the system's
+    struct TestClipboard(std::sync::Arc<std::sync::Mutex<String>>);
+
+    impl Clipboard for TestClipboard {
+        fn get(&self) -> Option<String> {
+            Some(self.0.lock().unwrap().clone())
+        }
+
+        fn set(&mut self, text: &str) {
+            *self.0.lock().unwrap() = text.to_string();
+        }
+    }
+
+    #[test]
+    fn test_clipboard() {
+        let contents = std::sync::Arc::new(std::sync::Mutex::new(String::new()));
+        let mut ring = KillRing::new();
+        ring.set_clipboard(TestClipboard(contents.clone()));
+
+        // Kills are copied out, appended kills included
+        ring.kill("hello".to_string());
+        ring.kill(" world".to_string());
+        assert_eq!(*contents.lock().unwrap(), "hello world");
+
+        // Text copied elsewhere is yanked, and joins the ring
+        *contents.lock().unwrap() = "from outside".to_string();
+        assert_eq!(ring.yank(), Some("from outside"));
+        assert_eq!(ring.yank_pop(), Some("hello world"));
+        assert_eq!(ring.len(), 2);
+    }
 }
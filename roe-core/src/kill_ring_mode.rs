@@ -0,0 +1,169 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! `browse-kill-ring`: list the kill ring, most recent first, and insert the
+//! entry picked into the window the list was opened from. Typing filters the
+//! entries by their text.
+
+use crate::keys::KeyAction;
+use crate::mode::{Mode, ModeAction, ModeResult};
+use crate::selection_menu::{MenuItem, SelectionMenu, DEFAULT_MAX_VISIBLE_ITEMS};
+use crate::BufferId;
+
+/// Header line of the list
+const PROMPT: &str = "Insert from kill ring:";
+
+/// Characters of an entry shown before it's cut off
+const ENTRY_DISPLAY_CHARS: usize = 70;
+
+/// A kill ring entry offered for insertion
+#[derive(Clone)]
+pub struct KillItem {
+    /// Position in the ring, 0 being the most recent
+    pub index: usize,
+    pub text: String,
+}
+
+impl MenuItem for KillItem {
+    /// The entry on one line, newlines shown as `\n`, cut off if long
+    fn display_text(&self) -> String {
+        let flat = self.text.replace('\n', "\\n");
+        if flat.chars().count() > ENTRY_DISPLAY_CHARS {
+            let shown: String = flat.chars().take(ENTRY_DISPLAY_CHARS).collect();
+            format!("{shown}…")
+        } else {
+            flat
+        }
+    }
+
+    /// Match against the whole entry, not just the part shown
+    fn matches_filter(&self, filter: &str) -> bool {
+        self.text.to_lowercase().contains(&filter.to_lowercase())
+    }
+}
+
+/// List of the kill ring's entries to pick one to insert
+pub struct KillRingMode {
+    menu: SelectionMenu<KillItem>,
+    /// Buffer ID this mode is managing
+    pub buffer_id: Option<BufferId>,
+}
+
+impl KillRingMode {
+    pub fn new() -> Self {
+        Self {
+            menu: SelectionMenu::new(DEFAULT_MAX_VISIBLE_ITEMS),
+            buffer_id: None,
+        }
+    }
+
+    /// Set how many entries are listed at once
+    pub fn set_max_visible_items(&mut self, max_visible_items: usize) {
+        self.menu.set_max_visible_items(max_visible_items);
+    }
+
+    /// Initialize with the buffer and the ring's entries, oldest first as
+    /// `KillRing::entries` keeps them
+    pub fn init_with_buffer(&mut self, buffer_id: BufferId, entries: &[String]) {
+        self.buffer_id = Some(buffer_id);
+        let items = entries
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(index, text)| KillItem {
+                index,
+                text: text.clone(),
+            })
+            .collect();
+        self.menu.init_with_items(items);
+    }
+
+    /// Generate buffer content string
+    pub fn generate_buffer_content(&self) -> String {
+        self.menu.generate_buffer_content(Some(PROMPT))
+    }
+}
+
+impl Default for KillRingMode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Mode for KillRingMode {
+    fn name(&self) -> &str {
+        "kill-ring"
+    }
+
+    fn perform(&mut self, action: &KeyAction) -> ModeResult {
+        if self.menu.handle_key_action(action) {
+            return ModeResult::Consumed(self.menu.generate_update_actions(Some(PROMPT)));
+        }
+
+        match action {
+            KeyAction::Enter => match self.menu.get_selected_item() {
+                Some(item) => ModeResult::Consumed(vec![ModeAction::InsertKill(item.index)]),
+                None => ModeResult::Consumed(vec![]),
+            },
+            _ => ModeResult::Ignored,
+        }
+    }
+
+    fn resize(&mut self, list_rows: usize) -> ModeResult {
+        self.set_max_visible_items(list_rows);
+        ModeResult::Consumed(self.menu.generate_update_actions(Some(PROMPT)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lists_most_recent_first() {
+        let mut mode = KillRingMode::new();
+        let entries = ["first", "second\nline", "third"].map(String::from);
+        mode.init_with_buffer(BufferId::default(), &entries);
+
+        let content = mode.generate_buffer_content();
+        let third = content.find("third").unwrap();
+        let second = content.find("second\\nline").unwrap();
+        let first = content.find("first").unwrap();
+        assert!(third < second && second < first);
+        assert_eq!(
+            mode.perform(&KeyAction::Enter),
+            ModeResult::Consumed(vec![ModeAction::InsertKill(0)])
+        );
+
+        // Filtering goes by the text, giving the entry's place in the ring
+        for c in "line".chars() {
+            mode.perform(&KeyAction::AlphaNumeric(c));
+        }
+        assert_eq!(
+            mode.perform(&KeyAction::Enter),
+            ModeResult::Consumed(vec![ModeAction::InsertKill(1)])
+        );
+    }
+
+    #[test]
+    fn test_long_entries_are_cut_off() {
+        let item = KillItem {
+            index: 0,
+            text: "x".repeat(100),
+        };
+        assert_eq!(
+            item.display_text(),
+            format!("{}…", "x".repeat(ENTRY_DISPLAY_CHARS))
+        );
+    }
+}
@@ -13,25 +13,59 @@
 
 use slotmap::new_key_type;
 
+pub mod after_change_queue;
+pub mod bind_key_mode;
+pub mod bookmark_mode;
+pub mod bookmarks;
 pub mod buffer;
 pub mod buffer_host;
 pub mod buffer_switch_mode;
+pub mod case;
+pub mod clipboard;
 pub mod command_mode;
+pub mod command_palette_mode;
 pub mod command_registry;
+pub mod comment;
+pub mod config;
+pub mod conflict;
+pub mod confirm_mode;
 pub mod editor;
+pub mod encoding;
+pub mod encoding_mode;
+pub mod file_location;
 pub mod file_selector_mode;
 pub mod file_watcher;
+pub mod fill;
+pub mod fuzzy;
+pub mod goto_mode;
 pub mod gutter;
+pub mod insert_char_mode;
 pub mod isearch_mode;
 pub mod julia_runtime;
+pub mod keyboard_macro;
 pub mod keys;
 pub mod kill_ring;
+pub mod kill_ring_mode;
+pub mod menu_preview;
+pub mod minibuffer_history;
 pub mod mode;
+pub mod prefix_arg;
+pub mod prompt_mode;
+pub mod quoted_insert;
+pub mod recent_files;
+pub mod recent_files_mode;
+pub mod rectangle;
+pub mod registers;
 pub mod renderer;
+pub mod running_command;
 pub mod scripted_mode;
 pub mod selection_menu;
 pub mod syntax;
+pub mod tabify;
+pub mod text_stats;
+pub mod tree_sitter_highlight;
 pub mod undo;
+pub mod whitespace;
 pub mod window;
 
 new_key_type! {
@@ -49,8 +83,8 @@ new_key_type! {
 pub use buffer::Buffer;
 pub use editor::{Editor, Frame, Window};
 pub use gutter::{
-    calculate_gutter_width, format_line_number, get_line_status, GutterConfig, GutterLine,
-    LineStatus,
+    calculate_gutter_width, format_line_number, get_line_status, line_number_column,
+    line_number_width, GutterConfig, GutterLine, LineStatus,
 };
 pub use keys::{Bindings, ConfigurableBindings, KeyState};
 pub use mode::{FileMode, Mode};
@@ -0,0 +1,217 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Preview of the highlighted entry in the buffer-switch and file-selector menus.
+//!
+//! Menu modes report which buffer or file is selected; the editor keeps the first
+//! lines of it for the renderers to draw beside the candidate list. Buffers are
+//! already in memory and preview immediately. Files are only read once the
+//! selection has rested on them for `PREVIEW_DEBOUNCE`, so holding down an arrow
+//! key doesn't open every file it passes.
+
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::buffer::Buffer;
+use crate::{BufferId, WindowId};
+
+/// How long the selection must stay on a file before it is read
+pub const PREVIEW_DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// Most lines kept for a preview; renderers clip to the window
+pub const PREVIEW_MAX_LINES: usize = 64;
+
+/// Most bytes read from a file for its preview
+const PREVIEW_MAX_BYTES: u64 = 16 * 1024;
+
+/// Narrowest menu, in columns, that gets a preview pane
+const PREVIEW_MIN_MENU_WIDTH: usize = 40;
+
+/// What the highlighted menu entry refers to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PreviewSource {
+    Buffer(BufferId),
+    File(PathBuf),
+}
+
+/// Preview state for a menu's command window
+#[derive(Debug)]
+pub struct MenuPreview {
+    /// The command window showing the menu
+    pub window_id: WindowId,
+    pub source: PreviewSource,
+    /// When the selection moved to `source`
+    pub selected_at: Instant,
+    /// Preview text, once loaded
+    pub lines: Option<Vec<String>>,
+}
+
+impl MenuPreview {
+    pub fn new(window_id: WindowId, source: PreviewSource) -> Self {
+        Self {
+            window_id,
+            source,
+            selected_at: Instant::now(),
+            lines: None,
+        }
+    }
+
+    /// Time left before the preview should be loaded, or `None` if it already is
+    pub fn due_in(&self) -> Option<Duration> {
+        if self.lines.is_some() {
+            return None;
+        }
+        match self.source {
+            PreviewSource::Buffer(_) => Some(Duration::ZERO),
+            PreviewSource::File(_) => {
+                Some(PREVIEW_DEBOUNCE.saturating_sub(self.selected_at.elapsed()))
+            }
+        }
+    }
+}
+
+/// Where the preview pane sits in a menu `content_width` columns wide: the
+/// column of its separator, and the width left for text after it and a space
+pub fn preview_pane(content_width: usize) -> Option<(usize, usize)> {
+    if content_width < PREVIEW_MIN_MENU_WIDTH {
+        return None;
+    }
+    let separator = content_width / 2;
+    Some((separator, content_width - separator - 2))
+}
+
+/// Replace tabs and strip line endings so a line can be drawn as-is
+fn preview_line(line: &str) -> String {
+    line.trim_end_matches(['\n', '\r']).replace('\t', "    ")
+}
+
+/// First `max_lines` lines of a buffer
+pub fn buffer_preview(buffer: &Buffer, max_lines: usize) -> Vec<String> {
+    buffer.with_read(|b| {
+        b.buffer
+            .lines()
+            .take(max_lines)
+            .map(|line| preview_line(&line.to_string()))
+            .collect()
+    })
+}
+
+/// First `max_lines` lines of a file, or the entries of a directory
+pub fn file_preview(path: &Path, max_lines: usize) -> Vec<String> {
+    if path.is_dir() {
+        return match fs::read_dir(path) {
+            Ok(entries) => {
+                let mut names: Vec<String> = entries
+                    .flatten()
+                    .map(|entry| {
+                        let name = entry.file_name().to_string_lossy().to_string();
+                        if entry.path().is_dir() {
+                            format!("{name}/")
+                        } else {
+                            name
+                        }
+                    })
+                    .collect();
+                names.sort();
+                names.truncate(max_lines);
+                names
+            }
+            Err(e) => vec![format!("(cannot read directory: {e})")],
+        };
+    }
+
+    // Opening a FIFO or reading a device could block, or never end
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.is_file() => {}
+        Ok(_) => return vec!["(not a regular file)".to_string()],
+        Err(e) => return vec![format!("(cannot read file: {e})")],
+    }
+
+    let mut bytes = Vec::new();
+    let read =
+        fs::File::open(path).and_then(|file| file.take(PREVIEW_MAX_BYTES).read_to_end(&mut bytes));
+    if let Err(e) = read {
+        return vec![format!("(cannot read file: {e})")];
+    }
+    if bytes.contains(&0) {
+        return vec!["(binary file)".to_string()];
+    }
+
+    String::from_utf8_lossy(&bytes)
+        .lines()
+        .take(max_lines)
+        .map(preview_line)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slotmap::SlotMap;
+
+    fn window_id() -> WindowId {
+        let mut windows: SlotMap<WindowId, ()> = SlotMap::with_key();
+        windows.insert(())
+    }
+
+    #[test]
+    fn test_buffer_preview() {
+        let buffer = Buffer::new(&[]);
+        buffer.load_str("one\n\ttwo\nthree\n");
+        assert_eq!(buffer_preview(&buffer, 2), vec!["one", "    two"]);
+    }
+
+    #[test]
+    fn test_file_preview() {
+        let dir = std::env::temp_dir().join(format!("roe-preview-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        let text = dir.join("text.txt");
+        fs::write(&text, "first\r\nsecond\nthird\n").unwrap();
+        let binary = dir.join("data.bin");
+        fs::write(&binary, [0x7f, b'E', b'L', b'F', 0, 1]).unwrap();
+
+        assert_eq!(file_preview(&text, 2), vec!["first", "second"]);
+        assert_eq!(file_preview(&binary, 10), vec!["(binary file)"]);
+        assert_eq!(file_preview(&dir, 10), vec!["data.bin", "sub/", "text.txt"]);
+        assert!(file_preview(&dir.join("missing"), 10)[0].starts_with("(cannot read"));
+        #[cfg(unix)]
+        assert_eq!(
+            file_preview(Path::new("/dev/zero"), 10),
+            vec!["(not a regular file)"]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_preview_pane() {
+        assert_eq!(preview_pane(20), None);
+        assert_eq!(preview_pane(80), Some((40, 38)));
+        assert_eq!(preview_pane(41), Some((20, 19)));
+    }
+
+    #[test]
+    fn test_file_preview_is_debounced() {
+        let preview = MenuPreview::new(window_id(), PreviewSource::File(PathBuf::from("x")));
+        let due = preview.due_in().unwrap();
+        assert!(due > Duration::ZERO && due <= PREVIEW_DEBOUNCE);
+
+        let mut buffers: SlotMap<BufferId, ()> = SlotMap::with_key();
+        let mut preview = MenuPreview::new(window_id(), PreviewSource::Buffer(buffers.insert(())));
+        assert_eq!(preview.due_in(), Some(Duration::ZERO));
+        preview.lines = Some(Vec::new());
+        assert_eq!(preview.due_in(), None);
+    }
+}
@@ -0,0 +1,179 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! What was entered at each kind of prompt this session, for recalling with
+//! Up and Down: the commands run from M-x and the files opened from find-file.
+
+use crate::fuzzy::fuzzy_match;
+use std::collections::{HashMap, VecDeque};
+
+/// Entries kept for each kind of prompt
+pub const HISTORY_SIZE: usize = 50;
+
+/// Prompts that keep a history
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HistoryKind {
+    /// Command names run from M-x
+    Command,
+    /// Paths of files opened from find-file and visit-file
+    File,
+}
+
+/// Histories of the prompts, kept in memory for the session
+#[derive(Debug, Default)]
+pub struct MinibufferHistory {
+    /// Each prompt's entries, most recent first, without duplicates
+    histories: HashMap<HistoryKind, VecDeque<String>>,
+}
+
+impl MinibufferHistory {
+    /// Record `entry` as the most recent for `kind`, moving it to the front if
+    /// it was already there and forgetting the oldest past `HISTORY_SIZE`
+    pub fn add(&mut self, kind: HistoryKind, entry: &str) {
+        if entry.is_empty() {
+            return;
+        }
+        let entries = self.histories.entry(kind).or_default();
+        entries.retain(|existing| existing != entry);
+        entries.push_front(entry.to_string());
+        entries.truncate(HISTORY_SIZE);
+    }
+
+    /// Entries for `kind`, most recent first
+    pub fn entries(&self, kind: HistoryKind) -> Vec<String> {
+        self.histories
+            .get(&kind)
+            .map(|entries| entries.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// A prompt's walk through its history: Up goes to older entries, Down back
+/// to newer ones and then to what was typed before. Only entries matching
+/// what was typed (fuzzily, as completion does) are visited.
+#[derive(Debug, Default)]
+pub struct HistoryRecall {
+    /// Entries, most recent first
+    entries: Vec<String>,
+    /// Entry being shown, or None when showing what was typed
+    position: Option<usize>,
+    /// Input typed before recalling started
+    typed: String,
+}
+
+impl HistoryRecall {
+    pub fn new(entries: Vec<String>) -> Self {
+        Self {
+            entries,
+            ..Self::default()
+        }
+    }
+
+    /// Whether an entry is being shown instead of what was typed
+    pub fn is_recalling(&self) -> bool {
+        self.position.is_some()
+    }
+
+    /// Go to the next older entry matching what was typed; `input` is what's
+    /// typed when recalling starts. None, staying put, when there's no older.
+    pub fn older(&mut self, input: &str) -> Option<&str> {
+        if self.position.is_none() {
+            self.typed = input.to_string();
+        }
+        let start = self.position.map_or(0, |position| position + 1);
+        let found = (start..self.entries.len()).find(|&i| self.matches(i))?;
+        self.position = Some(found);
+        Some(&self.entries[found])
+    }
+
+    /// Go to the next newer entry matching what was typed, or past the newest
+    /// back to what was typed. None when not recalling.
+    pub fn newer(&mut self) -> Option<&str> {
+        let position = self.position?;
+        self.position = (0..position).rev().find(|&i| self.matches(i));
+        Some(match self.position {
+            Some(found) => &self.entries[found],
+            None => &self.typed,
+        })
+    }
+
+    /// Stop recalling, keeping whatever is shown as the input, e.g. when the
+    /// user edits it
+    pub fn reset(&mut self) {
+        self.position = None;
+    }
+
+    fn matches(&self, index: usize) -> bool {
+        fuzzy_match(&self.typed, &self.entries[index]).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_most_recent_first() {
+        let mut history = MinibufferHistory::default();
+        history.add(HistoryKind::Command, "save-buffer");
+        history.add(HistoryKind::Command, "goto-line");
+        history.add(HistoryKind::File, "/tmp/notes.txt");
+        assert_eq!(
+            history.entries(HistoryKind::Command),
+            vec!["goto-line", "save-buffer"]
+        );
+
+        // Running one again moves it to the front rather than repeating it
+        history.add(HistoryKind::Command, "save-buffer");
+        assert_eq!(
+            history.entries(HistoryKind::Command),
+            vec!["save-buffer", "goto-line"]
+        );
+        assert_eq!(history.entries(HistoryKind::File), vec!["/tmp/notes.txt"]);
+    }
+
+    #[test]
+    fn test_history_is_capped() {
+        let mut history = MinibufferHistory::default();
+        for i in 0..HISTORY_SIZE + 10 {
+            history.add(HistoryKind::File, &format!("file-{i}"));
+        }
+        let entries = history.entries(HistoryKind::File);
+        assert_eq!(entries.len(), HISTORY_SIZE);
+        assert_eq!(entries[0], format!("file-{}", HISTORY_SIZE + 9));
+    }
+
+    #[test]
+    fn test_recall_walks_history() {
+        let mut recall = HistoryRecall::new(
+            ["goto-line", "save-buffer", "save-some-buffers"]
+                .map(String::from)
+                .to_vec(),
+        );
+        assert_eq!(recall.newer(), None);
+        assert_eq!(recall.older(""), Some("goto-line"));
+        assert_eq!(recall.older(""), Some("save-buffer"));
+        assert_eq!(recall.older(""), Some("save-some-buffers"));
+        assert_eq!(recall.older(""), None);
+        assert_eq!(recall.newer(), Some("save-buffer"));
+        assert_eq!(recall.newer(), Some("goto-line"));
+        assert_eq!(recall.newer(), Some(""));
+        assert!(!recall.is_recalling());
+
+        // With something typed, only entries matching it
+        assert_eq!(recall.older("sb"), Some("save-buffer"));
+        assert_eq!(recall.older("ignored"), Some("save-some-buffers"));
+        assert_eq!(recall.newer(), Some("save-buffer"));
+        assert_eq!(recall.newer(), Some("sb"));
+    }
+}
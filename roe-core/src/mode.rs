@@ -52,6 +52,9 @@ pub enum ModeAction {
     /// to the end of the inserted text (if in insert mode)
     /// Stick a piece of text somewhere else in the buffer.
     InsertText(ActionPosition, String),
+    /// Insert a newline at the cursor, indented like the line it breaks (or as
+    /// the major mode's `indent_line` hook says)
+    NewlineAndIndent,
     /// Delete a piece of text from the buffer
     DeleteText(ActionPosition, isize),
     /// Kill (cut) text and add it to kill-ring
@@ -71,6 +74,14 @@ pub enum ModeAction {
     Yank(ActionPosition),
     /// Yank from specific kill-ring index
     YankIndex(ActionPosition, usize),
+    /// Insert the kill-ring entry picked from browse-kill-ring
+    InsertKill(usize),
+    /// Reload the buffer from its file in the encoding picked
+    RevertWithEncoding(crate::encoding::Encoding),
+    /// Go to the bookmark picked from the bookmark list
+    JumpToBookmark(String),
+    /// Open the file picked from the open-recent list
+    OpenRecentFile(std::path::PathBuf),
     /// Set mark at cursor position
     SetMark,
     /// Clear the mark
@@ -83,12 +94,33 @@ pub enum ModeAction {
     ExecuteCommand(String),
     /// Switch to a specific buffer
     SwitchToBuffer(crate::BufferId),
+    /// Create an empty buffer with the given name and switch to it
+    CreateBuffer(String),
+    /// Insert a character chosen at the insert-char prompt
+    InsertChar(char),
     /// Kill a specific buffer
     KillBuffer(crate::BufferId),
-    /// Open a file by path with specified open type
+    /// Answer to the prompt before killing a modified buffer
+    ConfirmKillBuffer(crate::BufferId, crate::confirm_mode::KillConfirmation),
+    /// Answer to the prompt asking whether to save a modified buffer
+    ConfirmSaveBuffer(crate::BufferId, crate::confirm_mode::SaveConfirmation),
+    /// Answer to the prompt asking whether to revert a modified buffer whose
+    /// file changed on disk
+    ConfirmRevertBuffer(crate::BufferId, crate::confirm_mode::RevertConfirmation),
+    /// Go to the 1-based line or character number typed at the goto prompt
+    Goto(crate::goto_mode::GotoTarget, usize),
+    /// Bind the key sequence typed at the bind-key prompt to an action
+    BindKey(String, String),
+    /// Remove the binding of the key sequence typed at the unbind-key prompt
+    UnbindKey(String),
+    /// Run a command again with the answer typed at its prompt
+    PromptAnswered(crate::prompt_mode::PromptContinuation),
+    /// Open a file by path with specified open type, at a line and column if
+    /// given
     OpenFile {
         path: std::path::PathBuf,
         open_type: crate::editor::OpenType,
+        position: Option<crate::file_location::FilePosition>,
     },
     /// Move cursor to specific position (row, column)
     MoveCursor(u16, u16),
@@ -120,6 +152,11 @@ pub enum ModeAction {
         target_window_id: crate::WindowId,
         original_cursor: usize,
     },
+    /// Show a preview of the highlighted menu entry (None clears it)
+    PreviewSelection(Option<crate::menu_preview::PreviewSource>),
+    /// Draw character ranges of the mode's own buffer as the characters a
+    /// completion's input matched
+    HighlightMatches(Vec<(usize, usize)>),
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -173,6 +210,13 @@ pub trait Mode: Send + Sync {
         ModeResult::Ignored
     }
 
+    /// The window showing this mode's buffer was resized and now has room for
+    /// `list_rows` list entries; menus redraw their list to fit
+    /// Default implementation ignores it
+    fn resize(&mut self, _list_rows: usize) -> ModeResult {
+        ModeResult::Ignored
+    }
+
     /// Return commands that this mode provides
     /// Default implementation returns no commands
     fn available_commands(&self) -> Vec<Command> {
@@ -180,6 +224,21 @@ pub trait Mode: Send + Sync {
     }
 }
 
+/// Name of the buffer for notes that aren't saved to a file
+pub const SCRATCH_BUFFER_NAME: &str = "*scratch*";
+
+/// Major mode of the *scratch* buffer (defined in jl/major_modes.jl)
+pub const SCRATCH_MAJOR_MODE: &str = "scratch-mode";
+
+/// A fresh *scratch* buffer driven by the `ScratchMode` at `mode_id`
+pub fn scratch_buffer(mode_id: crate::ModeId) -> crate::Buffer {
+    let buffer = crate::Buffer::new(&[mode_id]);
+    buffer.set_object(SCRATCH_BUFFER_NAME.to_string());
+    buffer.set_major_mode(SCRATCH_MAJOR_MODE.to_string());
+    buffer.load_str("; This buffer is for text that is not saved.\n; To create a file, visit it with C-x C-f and enter text in its buffer.\n\n");
+    buffer
+}
+
 pub struct ScratchMode {}
 
 impl Mode for ScratchMode {
@@ -194,6 +253,10 @@ impl Mode for ScratchMode {
             KeyAction::InsertModeToggle => ModeResult::Ignored,
             KeyAction::Undo => ModeResult::Ignored,
             KeyAction::Redo => ModeResult::Ignored,
+            KeyAction::StartMacro => ModeResult::Ignored,
+            KeyAction::EndMacro => ModeResult::Ignored,
+            KeyAction::CallMacro => ModeResult::Ignored,
+            KeyAction::UniversalArgument => ModeResult::Ignored,
             KeyAction::MarkStart => ModeResult::Consumed(vec![ModeAction::SetMark]),
             KeyAction::MarkEnd => ModeResult::Ignored,
             KeyAction::KillRegion(destructive) => {
@@ -224,12 +287,7 @@ impl Mode for ScratchMode {
             KeyAction::Backspace => {
                 ModeResult::Consumed(vec![ModeAction::DeleteText(ActionPosition::cursor(), -1)])
             }
-            KeyAction::Enter => {
-                // Dispatch to newline-and-indent command (mode-aware)
-                ModeResult::Consumed(vec![ModeAction::ExecuteCommand(
-                    "newline-and-indent".to_string(),
-                )])
-            }
+            KeyAction::Enter => ModeResult::Consumed(vec![ModeAction::NewlineAndIndent]),
             KeyAction::Escape => ModeResult::Ignored,
             KeyAction::DeleteWord => ModeResult::Consumed(vec![ModeAction::ForwardKillWord]),
             KeyAction::ToggleCapsLock => ModeResult::Ignored,
@@ -255,6 +313,7 @@ impl Mode for ScratchMode {
             KeyAction::Unbound => ModeResult::Ignored,
             KeyAction::Command(_) => ModeResult::Ignored,
             KeyAction::Redraw => ModeResult::Ignored,
+            KeyAction::Recenter => ModeResult::Ignored,
         }
     }
 
@@ -290,6 +349,10 @@ impl Mode for FileMode {
             KeyAction::InsertModeToggle => ModeResult::Ignored,
             KeyAction::Undo => ModeResult::Ignored,
             KeyAction::Redo => ModeResult::Ignored,
+            KeyAction::StartMacro => ModeResult::Ignored,
+            KeyAction::EndMacro => ModeResult::Ignored,
+            KeyAction::CallMacro => ModeResult::Ignored,
+            KeyAction::UniversalArgument => ModeResult::Ignored,
             KeyAction::MarkStart => ModeResult::Consumed(vec![ModeAction::SetMark]),
             KeyAction::MarkEnd => ModeResult::Ignored,
             KeyAction::KillRegion(destructive) => {
@@ -320,12 +383,7 @@ impl Mode for FileMode {
             KeyAction::Backspace => {
                 ModeResult::Consumed(vec![ModeAction::DeleteText(ActionPosition::cursor(), -1)])
             }
-            KeyAction::Enter => {
-                // Dispatch to newline-and-indent command (mode-aware)
-                ModeResult::Consumed(vec![ModeAction::ExecuteCommand(
-                    "newline-and-indent".to_string(),
-                )])
-            }
+            KeyAction::Enter => ModeResult::Consumed(vec![ModeAction::NewlineAndIndent]),
             KeyAction::Escape => ModeResult::Ignored,
             KeyAction::DeleteWord => ModeResult::Consumed(vec![ModeAction::ForwardKillWord]),
             KeyAction::ToggleCapsLock => ModeResult::Ignored,
@@ -351,6 +409,7 @@ impl Mode for FileMode {
             KeyAction::Unbound => ModeResult::Ignored,
             KeyAction::Command(_) => ModeResult::Ignored,
             KeyAction::Redraw => ModeResult::Ignored,
+            KeyAction::Recenter => ModeResult::Ignored,
         }
     }
 
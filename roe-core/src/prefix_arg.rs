@@ -0,0 +1,153 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! `universal-argument` (`C-u`): a count for the next command.
+//!
+//! `C-u` on its own means 4, and each further `C-u` multiplies by four. Digits
+//! typed after it give the count instead (`C-u 1 2` is 12). Once digits have
+//! been typed, another `C-u` ends the number, so `C-u 5 C-u 0` inserts five
+//! zeros. The next command uses the count up: moves repeat, characters are
+//! inserted that many times, kills append to one another. Counts stop at
+//! `MAX_COUNT`: a key that would go past it is refused, and the prompt says so.
+
+use crate::keys::LogicalKey;
+
+/// What a single `C-u` counts for, and what each further one multiplies by
+const UNIVERSAL_FACTOR: usize = 4;
+
+/// Largest count accepted, so a stray run of digits can't tie the editor up
+/// repeating a command millions of times
+pub const MAX_COUNT: usize = 4096;
+
+/// A prefix argument being built up for the next command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefixArg {
+    count: usize,
+    /// Whether digits have been typed, replacing the powers of four
+    typed_digits: bool,
+    /// Whether further digits are part of the count rather than typed text
+    accepting_digits: bool,
+    /// Whether the last key was refused for taking the count past `MAX_COUNT`
+    refused: bool,
+}
+
+impl Default for PrefixArg {
+    fn default() -> Self {
+        Self {
+            count: UNIVERSAL_FACTOR,
+            typed_digits: false,
+            accepting_digits: true,
+            refused: false,
+        }
+    }
+}
+
+impl PrefixArg {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How many times the next command should run
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Echo area prompt showing the count so far
+    pub fn prompt(&self) -> String {
+        if self.refused {
+            format!("C-u {}- (at most {MAX_COUNT})", self.count)
+        } else {
+            format!("C-u {}-", self.count)
+        }
+    }
+
+    /// Set the count, unless it's over `MAX_COUNT`
+    fn set_count(&mut self, count: usize) {
+        self.refused = count > MAX_COUNT;
+        if !self.refused {
+            self.count = count;
+        }
+    }
+
+    /// Another `C-u`: four times the count, or the end of a typed number
+    pub fn universal(&mut self) {
+        if self.typed_digits {
+            self.accepting_digits = false;
+        } else {
+            self.set_count(self.count.saturating_mul(UNIVERSAL_FACTOR));
+        }
+    }
+
+    /// Take the keys of one key press as a digit of the count. False if they
+    /// aren't one, and should be handled as usual.
+    pub fn feed(&mut self, keys: &[LogicalKey]) -> bool {
+        let digit = match keys {
+            [LogicalKey::AlphaNumeric(c)] if self.accepting_digits => c.to_digit(10),
+            _ => None,
+        };
+        let Some(digit) = digit else {
+            return false;
+        };
+        if !self.typed_digits {
+            self.count = 0;
+            self.typed_digits = true;
+        }
+        self.set_count(self.count.saturating_mul(10).saturating_add(digit as usize));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix_arg_counts() {
+        let mut arg = PrefixArg::new();
+        assert_eq!(arg.prompt(), "C-u 4-");
+        arg.universal();
+        assert_eq!(arg.count(), 16);
+
+        // Digits replace the powers of four
+        assert!(arg.feed(&[LogicalKey::AlphaNumeric('1')]));
+        assert!(arg.feed(&[LogicalKey::AlphaNumeric('2')]));
+        assert_eq!(arg.prompt(), "C-u 12-");
+        assert!(!arg.feed(&[LogicalKey::AlphaNumeric('x')]));
+
+        // C-u after digits ends the number, leaving later digits to be typed
+        arg.universal();
+        assert_eq!(arg.count(), 12);
+        assert!(!arg.feed(&[LogicalKey::AlphaNumeric('0')]));
+    }
+
+    #[test]
+    fn test_prefix_arg_stops_at_max_count() {
+        // A digit that would go past the limit is taken but ignored
+        let mut arg = PrefixArg::new();
+        for c in "40960".chars() {
+            assert!(arg.feed(&[LogicalKey::AlphaNumeric(c)]));
+        }
+        assert_eq!(arg.count(), 4096);
+        assert_eq!(arg.prompt(), "C-u 4096- (at most 4096)");
+        assert!(arg.feed(&[LogicalKey::AlphaNumeric('1')]));
+        assert_eq!(arg.prompt(), "C-u 4096- (at most 4096)");
+
+        // So is a C-u
+        let mut arg = PrefixArg::new();
+        for _ in 0..10 {
+            arg.universal();
+        }
+        assert_eq!(arg.count(), 4096);
+        assert!(arg.prompt().ends_with("(at most 4096)"));
+    }
+}
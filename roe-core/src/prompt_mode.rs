@@ -0,0 +1,136 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Minibuffer prompts for commands that need an answer typed before they can
+//! go on.
+//!
+//! A command asks by returning `ChromeAction::Prompt` with a
+//! `PromptContinuation` naming itself. Once the answer is typed the editor runs
+//! the command again with every answer so far in its context's
+//! `prompt_answers`, so a command that needs several (search, then replace)
+//! prompts again with the continuation it was given until it has them all.
+
+use crate::keys::KeyAction;
+use crate::mode::{ActionPosition, Mode, ModeAction, ModeResult};
+
+/// Where a command picks up once its prompt is answered
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PromptContinuation {
+    /// Command to run again with the answer
+    pub command: String,
+    /// Answers to the command's earlier prompts, first first
+    pub answers: Vec<String>,
+}
+
+impl PromptContinuation {
+    /// Continue `command` with no answers yet, for its first prompt
+    pub fn new(command: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+            answers: Vec::new(),
+        }
+    }
+
+    /// Continue `command` with the `answers` it has so far, for a later prompt
+    pub fn with_answers(command: impl Into<String>, answers: Vec<String>) -> Self {
+        Self {
+            command: command.into(),
+            answers,
+        }
+    }
+}
+
+/// One-line prompt for free-form text, answered with RET
+pub struct PromptMode {
+    /// Shown before the text typed, e.g. "Query replace: "
+    message: String,
+    /// Text typed so far
+    input: String,
+    continuation: PromptContinuation,
+}
+
+impl PromptMode {
+    pub fn new(message: String, continuation: PromptContinuation) -> Self {
+        Self {
+            message,
+            input: String::new(),
+            continuation,
+        }
+    }
+
+    /// Generate buffer content string
+    pub fn generate_buffer_content(&self) -> String {
+        format!("{}{}\n", self.message, self.input)
+    }
+
+    fn redraw(&self) -> ModeResult {
+        ModeResult::Consumed(vec![
+            ModeAction::ClearText,
+            ModeAction::InsertText(ActionPosition::start(), self.generate_buffer_content()),
+        ])
+    }
+}
+
+impl Mode for PromptMode {
+    fn name(&self) -> &str {
+        "prompt"
+    }
+
+    fn perform(&mut self, action: &KeyAction) -> ModeResult {
+        match action {
+            KeyAction::AlphaNumeric(c) => {
+                self.input.push(*c);
+                self.redraw()
+            }
+            KeyAction::Backspace => {
+                self.input.pop();
+                self.redraw()
+            }
+            KeyAction::Enter => {
+                let mut continuation = self.continuation.clone();
+                continuation.answers.push(self.input.clone());
+                ModeResult::Consumed(vec![ModeAction::PromptAnswered(continuation)])
+            }
+            KeyAction::Escape | KeyAction::Cancel => ModeResult::Ignored,
+            _ => ModeResult::Consumed(vec![]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_answer_adds_to_continuation() {
+        let continuation = PromptContinuation::with_answers("replace", vec!["foo".to_string()]);
+        let mut mode = PromptMode::new("Replace foo with: ".to_string(), continuation);
+        for c in "bat".chars() {
+            mode.perform(&KeyAction::AlphaNumeric(c));
+        }
+        mode.perform(&KeyAction::Backspace);
+        mode.perform(&KeyAction::AlphaNumeric('r'));
+        assert_eq!(mode.generate_buffer_content(), "Replace foo with: bar\n");
+
+        assert_eq!(
+            mode.perform(&KeyAction::Enter),
+            ModeResult::Consumed(vec![ModeAction::PromptAnswered(
+                PromptContinuation::with_answers(
+                    "replace",
+                    vec!["foo".to_string(), "bar".to_string()]
+                )
+            )])
+        );
+        assert_eq!(mode.perform(&KeyAction::Escape), ModeResult::Ignored);
+    }
+}
@@ -0,0 +1,269 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! `quoted-insert` (`C-q`): insert the next key literally.
+//!
+//! After `C-q`, the next key is inserted as the character it stands for rather
+//! than run through the keybindings, so `C-q C-j` inserts a bare linefeed and
+//! `C-q Tab` a tab that no mode will turn into indentation. Like Emacs, digits
+//! give a character code instead: up to three octal digits (`C-q 1 0 1` is
+//! `A`), or `x` and up to six hex digits (`C-q x 4 1 RET`). A code ends at its
+//! last digit, or at the first other key: RET is consumed, anything else is
+//! handled normally after the character is inserted.
+
+use crate::keys::{KeyModifier, LogicalKey};
+
+/// Most octal digits in a character code
+const MAX_OCTAL_DIGITS: usize = 3;
+
+/// Most hex digits in a character code (enough for any Unicode scalar)
+const MAX_HEX_DIGITS: usize = 6;
+
+/// What to do with a key pressed during `quoted-insert`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuotedKey {
+    /// Part of a character code; keep reading
+    Pending,
+    /// Insert this character; the key has been used up
+    Insert(char),
+    /// Insert this character, then handle the key as if typed normally
+    InsertThenKey(char),
+    /// Nothing to insert; report this and stop
+    Invalid(String),
+}
+
+/// State of a `quoted-insert` waiting for its key
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuotedInsert {
+    /// Whether `x` was typed, switching to a hex code
+    hex: bool,
+    /// Code digits typed so far
+    digits: String,
+}
+
+impl QuotedInsert {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Echo area prompt showing what has been typed so far
+    pub fn prompt(&self) -> String {
+        let mut prompt = "C-q".to_string();
+        if self.hex {
+            prompt.push_str(" x");
+        }
+        for digit in self.digits.chars() {
+            prompt.push(' ');
+            prompt.push(digit);
+        }
+        prompt.push('-');
+        prompt
+    }
+
+    fn radix(&self) -> u32 {
+        if self.hex {
+            16
+        } else {
+            8
+        }
+    }
+
+    fn max_digits(&self) -> usize {
+        if self.hex {
+            MAX_HEX_DIGITS
+        } else {
+            MAX_OCTAL_DIGITS
+        }
+    }
+
+    /// The character the digits typed so far stand for
+    fn code_char(&self) -> Result<char, String> {
+        u32::from_str_radix(&self.digits, self.radix())
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| format!("Invalid character code: {}", self.digits))
+    }
+
+    /// Handle the keys of one key press (a chord: modifiers, then a key)
+    pub fn feed(&mut self, keys: &[LogicalKey]) -> QuotedKey {
+        let Some(&key) = keys.iter().find(|k| !matches!(k, LogicalKey::Modifier(_))) else {
+            // A modifier pressed on its own; wait for the key it goes with
+            return QuotedKey::Pending;
+        };
+        let plain = keys.len() == 1;
+
+        if let (true, LogicalKey::AlphaNumeric(c)) = (plain, key) {
+            if c.is_digit(self.radix()) {
+                self.digits.push(c);
+                if self.digits.len() == self.max_digits() {
+                    return self
+                        .code_char()
+                        .map_or_else(QuotedKey::Invalid, QuotedKey::Insert);
+                }
+                return QuotedKey::Pending;
+            }
+            if c == 'x' && !self.hex && self.digits.is_empty() {
+                self.hex = true;
+                return QuotedKey::Pending;
+            }
+        }
+
+        // Any other key ends a code
+        if !self.digits.is_empty() {
+            return match (self.code_char(), key) {
+                (Err(message), _) => QuotedKey::Invalid(message),
+                (Ok(c), LogicalKey::Enter) if plain => QuotedKey::Insert(c),
+                (Ok(c), _) => QuotedKey::InsertThenKey(c),
+            };
+        }
+        // An `x` that no hex digits followed was just an `x`
+        if self.hex {
+            return QuotedKey::InsertThenKey('x');
+        }
+
+        match literal_char(keys) {
+            Some(c) => QuotedKey::Insert(c),
+            None => QuotedKey::Invalid("Can't quote that key".to_string()),
+        }
+    }
+}
+
+/// The character a key press stands for when inserted literally
+fn literal_char(keys: &[LogicalKey]) -> Option<char> {
+    let is_ctrl = |k: &LogicalKey| matches!(k, LogicalKey::Modifier(KeyModifier::Control(_)));
+    let is_shift = |k: &LogicalKey| matches!(k, LogicalKey::Modifier(KeyModifier::Shift(_)));
+    let ctrl = keys.iter().any(is_ctrl);
+    let shift = keys.iter().any(is_shift);
+    // Meta, Super and the like have no character of their own
+    if keys
+        .iter()
+        .any(|k| matches!(k, LogicalKey::Modifier(_)) && !is_ctrl(k) && !is_shift(k))
+    {
+        return None;
+    }
+
+    match *keys.last()? {
+        LogicalKey::AlphaNumeric(c) if ctrl => control_char(c),
+        LogicalKey::AlphaNumeric(c) if shift => Some(c.to_ascii_uppercase()),
+        LogicalKey::AlphaNumeric(c) => Some(c),
+        _ if ctrl => None,
+        LogicalKey::Enter => Some('\r'),
+        LogicalKey::Tab => Some('\t'),
+        LogicalKey::Esc => Some('\x1b'),
+        LogicalKey::Backspace | LogicalKey::Delete => Some('\x7f'),
+        _ => None,
+    }
+}
+
+/// The control character for `C-c`: `C-a` is 0x01, `C-j` 0x0a, `C-@` NUL, `C-?` DEL
+fn control_char(c: char) -> Option<char> {
+    match c.to_ascii_uppercase() {
+        '?' => Some('\x7f'),
+        '@'..='_' => Some(((c.to_ascii_uppercase() as u8) & 0x1f) as char),
+        ' ' => Some('\0'),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::Side;
+
+    fn ctrl(c: char) -> Vec<LogicalKey> {
+        vec![
+            LogicalKey::Modifier(KeyModifier::Control(Side::Left)),
+            LogicalKey::AlphaNumeric(c),
+        ]
+    }
+
+    fn key(c: char) -> Vec<LogicalKey> {
+        vec![LogicalKey::AlphaNumeric(c)]
+    }
+
+    #[test]
+    fn test_literal_keys() {
+        assert_eq!(
+            QuotedInsert::new().feed(&ctrl('j')),
+            QuotedKey::Insert('\n')
+        );
+        assert_eq!(
+            QuotedInsert::new().feed(&ctrl('a')),
+            QuotedKey::Insert('\x01')
+        );
+        assert_eq!(
+            QuotedInsert::new().feed(&ctrl('g')),
+            QuotedKey::Insert('\x07')
+        );
+        assert_eq!(
+            QuotedInsert::new().feed(&[LogicalKey::Tab]),
+            QuotedKey::Insert('\t')
+        );
+        assert_eq!(
+            QuotedInsert::new().feed(&[LogicalKey::Enter]),
+            QuotedKey::Insert('\r')
+        );
+        assert_eq!(QuotedInsert::new().feed(&key('q')), QuotedKey::Insert('q'));
+        assert!(matches!(
+            QuotedInsert::new().feed(&[LogicalKey::Up]),
+            QuotedKey::Invalid(_)
+        ));
+    }
+
+    #[test]
+    fn test_octal_code() {
+        let mut quoted = QuotedInsert::new();
+        assert_eq!(quoted.feed(&key('1')), QuotedKey::Pending);
+        assert_eq!(quoted.feed(&key('0')), QuotedKey::Pending);
+        assert_eq!(quoted.prompt(), "C-q 1 0-");
+        assert_eq!(quoted.feed(&key('1')), QuotedKey::Insert('A'));
+
+        // A shorter code ends at the next key, which then runs normally
+        let mut quoted = QuotedInsert::new();
+        quoted.feed(&key('7'));
+        assert_eq!(quoted.feed(&key('a')), QuotedKey::InsertThenKey('\x07'));
+
+        // ... except RET, which only ends the code
+        let mut quoted = QuotedInsert::new();
+        quoted.feed(&key('4'));
+        quoted.feed(&key('1'));
+        assert_eq!(quoted.feed(&[LogicalKey::Enter]), QuotedKey::Insert('!'));
+    }
+
+    #[test]
+    fn test_hex_code() {
+        let mut quoted = QuotedInsert::new();
+        assert_eq!(quoted.feed(&key('x')), QuotedKey::Pending);
+        for c in "1f60".chars() {
+            assert_eq!(quoted.feed(&key(c)), QuotedKey::Pending);
+        }
+        assert_eq!(quoted.prompt(), "C-q x 1 f 6 0-");
+        assert_eq!(quoted.feed(&key('0')), QuotedKey::Pending);
+        assert_eq!(quoted.feed(&[LogicalKey::Enter]), QuotedKey::Insert('😀'));
+
+        // Surrogates aren't characters
+        let mut quoted = QuotedInsert::new();
+        for c in "xd800".chars() {
+            quoted.feed(&key(c));
+        }
+        assert!(matches!(
+            quoted.feed(&[LogicalKey::Enter]),
+            QuotedKey::Invalid(_)
+        ));
+
+        // An x with no digits after it is a literal x
+        let mut quoted = QuotedInsert::new();
+        quoted.feed(&key('x'));
+        assert_eq!(quoted.feed(&key(' ')), QuotedKey::InsertThenKey('x'));
+    }
+}
@@ -0,0 +1,139 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Files opened recently, most recent first, for `open-recent`. Unlike the
+//! find-file history they last between sessions: they're kept in
+//! `recent_files` in the config directory, one absolute path per line.
+
+use crate::config::config_dir;
+use std::path::{Path, PathBuf};
+
+/// Files remembered; opening another forgets the oldest
+pub const RECENT_FILES_SIZE: usize = 30;
+
+/// The files opened recently and the file they're saved to
+#[derive(Debug, Default)]
+pub struct RecentFiles {
+    /// Most recent first, without duplicates
+    files: Vec<PathBuf>,
+    /// None to keep them only for the session
+    path: Option<PathBuf>,
+}
+
+impl RecentFiles {
+    /// `recent_files` in the config directory, if there's a home to find it in
+    pub fn default_path() -> Option<PathBuf> {
+        Some(config_dir()?.join("recent_files"))
+    }
+
+    /// The files saved at `path`, none if it doesn't exist yet. Adding a file
+    /// saves them there.
+    pub fn load(path: PathBuf) -> Result<Self, String> {
+        let mut recent_files = Self::default();
+        match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                // Added oldest first, so a file listed twice keeps its first place
+                for line in text.lines().rev().filter(|line| !line.trim().is_empty()) {
+                    recent_files.push(PathBuf::from(line));
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("{}: {e}", path.display())),
+        }
+        recent_files.path = Some(path);
+        Ok(recent_files)
+    }
+
+    /// The files, most recent first
+    pub fn all(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Make `file` the most recent, moving it up if it's already listed and
+    /// forgetting the oldest past `RECENT_FILES_SIZE`
+    pub fn push(&mut self, file: PathBuf) {
+        self.files.retain(|existing| *existing != file);
+        self.files.insert(0, file);
+        self.files.truncate(RECENT_FILES_SIZE);
+    }
+
+    /// Forget `file`, e.g. once it's found to be gone
+    pub fn remove(&mut self, file: &Path) {
+        self.files.retain(|existing| existing != file);
+    }
+
+    /// Write the files to the file they were loaded from
+    pub fn save(&self) -> std::io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut text = String::new();
+        for file in &self.files {
+            text.push_str(&file.to_string_lossy());
+            text.push('\n');
+        }
+        std::fs::write(path, text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_most_recent_first_without_duplicates() {
+        let mut recent_files = RecentFiles::default();
+        for file in ["/a", "/b", "/c", "/a"] {
+            recent_files.push(PathBuf::from(file));
+        }
+        assert_eq!(
+            recent_files.all(),
+            [
+                PathBuf::from("/a"),
+                PathBuf::from("/c"),
+                PathBuf::from("/b")
+            ]
+        );
+
+        for n in 0..RECENT_FILES_SIZE {
+            recent_files.push(PathBuf::from(format!("/file{n}")));
+        }
+        assert_eq!(recent_files.all().len(), RECENT_FILES_SIZE);
+        assert!(!recent_files.all().contains(&PathBuf::from("/a")));
+
+        recent_files.remove(Path::new("/file0"));
+        assert_eq!(recent_files.all().len(), RECENT_FILES_SIZE - 1);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("roe-recent-{}", std::process::id()));
+        let path = dir.join("roe").join("recent_files");
+        let mut recent_files = RecentFiles::load(path.clone()).unwrap();
+        assert!(recent_files.is_empty());
+        recent_files.push(PathBuf::from("/tmp/old"));
+        recent_files.push(PathBuf::from("/tmp/new"));
+        recent_files.save().unwrap();
+
+        let loaded = RecentFiles::load(path).unwrap();
+        assert_eq!(loaded.all(), recent_files.all());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
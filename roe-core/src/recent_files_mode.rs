@@ -0,0 +1,131 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! `open-recent`: list the files opened recently, most recent first, and open
+//! the one picked.
+
+use crate::keys::KeyAction;
+use crate::mode::{Mode, ModeAction, ModeResult};
+use crate::selection_menu::{MenuItem, SelectionMenu, DEFAULT_MAX_VISIBLE_ITEMS};
+use std::path::PathBuf;
+
+/// Header line of the list
+const PROMPT: &str = "Open recent file:";
+
+/// A file opened before
+#[derive(Clone)]
+pub struct RecentFileItem {
+    pub path: PathBuf,
+    /// Whether it was still there when the list was opened
+    pub exists: bool,
+}
+
+impl MenuItem for RecentFileItem {
+    fn display_text(&self) -> String {
+        if self.exists {
+            self.path.display().to_string()
+        } else {
+            format!("{} (missing)", self.path.display())
+        }
+    }
+
+    fn matches_filter(&self, filter: &str) -> bool {
+        self.path
+            .to_string_lossy()
+            .to_lowercase()
+            .contains(&filter.to_lowercase())
+    }
+}
+
+/// List of recent files to pick one to open
+pub struct RecentFilesMode {
+    menu: SelectionMenu<RecentFileItem>,
+}
+
+impl RecentFilesMode {
+    /// The list of `files`, most recent first, marking those that are gone
+    pub fn new(files: &[PathBuf]) -> Self {
+        let mut menu = SelectionMenu::new(DEFAULT_MAX_VISIBLE_ITEMS);
+        menu.init_with_items(
+            files
+                .iter()
+                .map(|path| RecentFileItem {
+                    path: path.clone(),
+                    exists: path.is_file(),
+                })
+                .collect(),
+        );
+        Self { menu }
+    }
+
+    /// Set how many files are listed at once
+    pub fn set_max_visible_items(&mut self, max_visible_items: usize) {
+        self.menu.set_max_visible_items(max_visible_items);
+    }
+
+    /// Generate buffer content string
+    pub fn generate_buffer_content(&self) -> String {
+        self.menu.generate_buffer_content(Some(PROMPT))
+    }
+}
+
+impl Mode for RecentFilesMode {
+    fn name(&self) -> &str {
+        "recent-files"
+    }
+
+    fn perform(&mut self, action: &KeyAction) -> ModeResult {
+        if self.menu.handle_key_action(action) {
+            return ModeResult::Consumed(self.menu.generate_update_actions(Some(PROMPT)));
+        }
+
+        match action {
+            KeyAction::Enter => match self.menu.get_selected_item() {
+                Some(item) => {
+                    ModeResult::Consumed(vec![ModeAction::OpenRecentFile(item.path.clone())])
+                }
+                None => ModeResult::Consumed(vec![]),
+            },
+            _ => ModeResult::Ignored,
+        }
+    }
+
+    fn resize(&mut self, list_rows: usize) -> ModeResult {
+        self.set_max_visible_items(list_rows);
+        ModeResult::Consumed(self.menu.generate_update_actions(Some(PROMPT)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_recent_file() {
+        let missing = std::env::temp_dir().join("roe-recent-missing-file");
+        let mut mode = RecentFilesMode::new(&[PathBuf::from("/a/notes.md"), missing.clone()]);
+        assert!(mode
+            .generate_buffer_content()
+            .contains(&format!("{} (missing)", missing.display())));
+
+        for c in "notes".chars() {
+            mode.perform(&KeyAction::AlphaNumeric(c));
+        }
+        assert_eq!(
+            mode.perform(&KeyAction::Enter),
+            ModeResult::Consumed(vec![ModeAction::OpenRecentFile(PathBuf::from(
+                "/a/notes.md"
+            ))])
+        );
+    }
+}
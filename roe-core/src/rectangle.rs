@@ -0,0 +1,172 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Rectangles: `kill-rectangle` (`C-x r k`) and `yank-rectangle` (`C-x r y`).
+//!
+//! The rectangle is the block of columns between the mark's and the cursor's,
+//! on the lines from the mark's to the cursor's. Columns are display columns,
+//! as the line is drawn: a tab runs to the next tab stop and a wide character
+//! takes two. A character is in the rectangle if it starts in one of its
+//! columns. A killed rectangle is kept as its rows, with tabs turned into the
+//! spaces they took and padded with spaces to the same width, apart from the
+//! kill ring.
+
+use crate::buffer::{expand_tabs, text_display_width, WIDE_CHAR_TAIL};
+use crate::comment::LineEdit;
+
+/// `line` without its line ending
+fn line_text(line: &str) -> &str {
+    line.trim_end_matches(['\n', '\r'])
+}
+
+/// Whether `cells[i]` is the first cell of the character drawn there
+fn starts_char(cells: &[(char, usize)], i: usize) -> bool {
+    i == 0 || cells[i - 1].1 != cells[i].1
+}
+
+/// Kill the columns from `left` up to `right` of each line: the rows killed,
+/// padded with spaces where a line falls short, and the edits removing them
+pub fn kill(
+    lines: &[&str],
+    left: usize,
+    right: usize,
+    tab_width: usize,
+) -> (Vec<String>, Vec<LineEdit>) {
+    let width = right.saturating_sub(left);
+    let mut rows = Vec::with_capacity(lines.len());
+    let mut edits = Vec::new();
+    for (line, text) in lines.iter().enumerate() {
+        let cells = expand_tabs(line_text(text), tab_width);
+        let killed: Vec<usize> = (left..right.min(cells.len()))
+            .filter(|&i| starts_char(&cells, i))
+            .map(|i| cells[i].1)
+            .collect();
+        let row: String = cells
+            .iter()
+            .filter(|(c, source)| *c != WIDE_CHAR_TAIL && killed.contains(source))
+            .map(|(c, _)| c)
+            .collect();
+        let padding = width.saturating_sub(text_display_width(&row));
+        rows.push(row + &" ".repeat(padding));
+        if let Some(&column) = killed.first() {
+            edits.push(LineEdit {
+                line,
+                column,
+                delete: killed.len(),
+                insert: String::new(),
+            });
+        }
+    }
+    (rows, edits)
+}
+
+/// Edits putting `rows` in at display `column` of consecutive lines, pushing
+/// the text there to the right. A line too short to reach `column` is padded
+/// out with spaces. Rows past the last of `lines` go on new lines after it.
+pub fn yank(lines: &[&str], column: usize, rows: &[String], tab_width: usize) -> Vec<LineEdit> {
+    let Some(last_line) = lines.len().checked_sub(1) else {
+        return vec![];
+    };
+    let mut edits: Vec<LineEdit> = lines
+        .iter()
+        .zip(rows)
+        .enumerate()
+        .map(|(line, (text, row))| {
+            let text = line_text(text);
+            let cells = expand_tabs(text, tab_width);
+            // Before the first character starting at or after the column
+            let at = (column..cells.len()).find(|&i| starts_char(&cells, i));
+            LineEdit {
+                line,
+                column: at.map_or(text.chars().count(), |i| cells[i].1),
+                delete: 0,
+                insert: " ".repeat(column.saturating_sub(cells.len())) + row,
+            }
+        })
+        .collect();
+
+    let overflow: String = rows
+        .iter()
+        .skip(lines.len())
+        .map(|row| format!("\n{}{row}", " ".repeat(column)))
+        .collect();
+    if !overflow.is_empty() {
+        edits.push(LineEdit {
+            line: last_line,
+            column: line_text(lines[last_line]).chars().count(),
+            delete: 0,
+            insert: overflow,
+        });
+    }
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Apply `edits` to `lines`, for checking the text they produce
+    fn apply(lines: &[&str], edits: &[LineEdit]) -> Vec<String> {
+        let mut lines: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+        for edit in edits.iter().rev() {
+            let mut chars: Vec<char> = lines[edit.line].chars().collect();
+            chars.splice(edit.column..edit.column + edit.delete, edit.insert.chars());
+            lines[edit.line] = chars.into_iter().collect();
+        }
+        lines
+    }
+
+    #[test]
+    fn test_kill_pads_short_lines() {
+        let lines = ["abcdef", "gh", "ijklmn"];
+        let (rows, edits) = kill(&lines, 1, 4, 4);
+        assert_eq!(rows, ["bcd", "h  ", "jkl"]);
+        assert_eq!(apply(&lines, &edits), ["aef", "g", "imn"]);
+
+        // A line short of the left column has nothing to lose
+        let (rows, edits) = kill(&["abcdef", "", "ijklmn"], 2, 3, 4);
+        assert_eq!(rows, ["c", " ", "k"]);
+        assert_eq!(edits.len(), 2);
+    }
+
+    #[test]
+    fn test_kill_by_display_column() {
+        // Columns 4 to 6 are "cd" after a tab, and "漢" after two ASCII
+        // characters and a wide one
+        let lines = ["\tcdef", "ab字漢x", "abcdefgh"];
+        let (rows, edits) = kill(&lines, 4, 6, 4);
+        assert_eq!(rows, ["cd", "漢", "ef"]);
+        assert_eq!(apply(&lines, &edits), ["\tef", "ab字x", "abcdgh"]);
+
+        // A tab inside the rectangle comes out as the spaces it took
+        let (rows, _) = kill(&["a\tb"], 0, 5, 4);
+        assert_eq!(rows, ["a   b"]);
+    }
+
+    #[test]
+    fn test_yank_pads_and_adds_lines() {
+        let rows = ["12".to_string(), "34".to_string(), "56".to_string()];
+        let lines = ["abcd", "e"];
+        assert_eq!(
+            apply(&lines, &yank(&lines, 2, &rows, 4)),
+            ["ab12cd", "e 34\n  56"]
+        );
+
+        // The column is counted as drawn, past tabs and wide characters
+        let lines = ["\tab", "字cde", "x"];
+        assert_eq!(
+            apply(&lines, &yank(&lines, 4, &rows, 4)),
+            ["\t12ab", "字cd34e", "x   56"]
+        );
+    }
+}
@@ -0,0 +1,64 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Registers: named by a single character, each holds a piece of text or a
+//! position in a buffer until it's overwritten.
+//!
+//! `copy-to-register` (`C-x r s`) and `insert-register` (`C-x r i`) save and
+//! insert text; `point-to-register` (`C-x r SPC`) and `jump-to-register`
+//! (`C-x r j`) save a position and go back to it. Each reads the register's
+//! name from the next key typed.
+
+use crate::keys::{KeyModifier, LogicalKey};
+use crate::BufferId;
+
+/// What a register holds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Register {
+    Text(String),
+    /// A character position in a buffer, which may since have been killed
+    Point(BufferId, usize),
+}
+
+/// A register command waiting for the name of its register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegisterCommand {
+    CopyTo,
+    Insert,
+    PointTo,
+    JumpTo,
+}
+
+impl RegisterCommand {
+    /// Echo area prompt asking for the register
+    pub fn prompt(&self) -> &'static str {
+        match self {
+            RegisterCommand::CopyTo => "Copy to register: ",
+            RegisterCommand::Insert => "Insert register: ",
+            RegisterCommand::PointTo => "Point to register: ",
+            RegisterCommand::JumpTo => "Jump to register: ",
+        }
+    }
+}
+
+/// The register named by the keys of one key press: a character typed on its
+/// own or with Shift. None for anything else, like `C-g`.
+pub fn register_name(keys: &[LogicalKey]) -> Option<char> {
+    match keys {
+        [LogicalKey::AlphaNumeric(c)] => Some(*c),
+        [LogicalKey::Modifier(KeyModifier::Shift(_)), LogicalKey::AlphaNumeric(c)] => {
+            Some(c.to_ascii_uppercase())
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,138 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Script commands running off the event loop
+//!
+//! A Julia command can take as long as it likes, and awaiting it from the key
+//! handler froze the editor until it returned. Instead the command's future is
+//! spawned onto the tokio runtime and its result sent back over a channel;
+//! renderers wait on `finished` alongside their other events and hand the
+//! result to `Editor::take_command_result`.
+//!
+//! A Julia command holds the runtime's lock until it finishes, and the Julia
+//! thread runs one thing at a time, so what the editor asks of the runtime
+//! meanwhile (indenting a new line, a file's major mode, its comment prefix)
+//! uses `try_lock` and does without rather than waiting on the command.
+//!
+//! The command keeps the buffer it was run in, and that buffer's edit count at
+//! the time, so its buffer operations can be applied to that buffer rather
+//! than whichever is current when it finishes, and dropped if the buffer was
+//! edited meanwhile.
+
+use crate::command_registry::CommandResult;
+use crate::editor::ChromeAction;
+use crate::BufferId;
+use std::sync::Arc;
+use tokio::sync::{oneshot, Notify};
+use tokio::task::JoinHandle;
+
+/// A command spawned onto the runtime whose result hasn't been taken yet
+pub struct RunningCommand {
+    name: String,
+    buffer_id: BufferId,
+    edit_count: u64,
+    result: oneshot::Receiver<Result<Vec<ChromeAction>, String>>,
+    task: JoinHandle<()>,
+    notify: Arc<Notify>,
+}
+
+impl RunningCommand {
+    /// Run `future`, the body of the command `name`, on the tokio runtime.
+    /// `edit_count` is `buffer_id`'s edit count as the command starts.
+    pub fn spawn(
+        name: impl Into<String>,
+        buffer_id: BufferId,
+        edit_count: u64,
+        future: CommandResult,
+    ) -> Self {
+        let (result_tx, result) = oneshot::channel();
+        let notify = Arc::new(Notify::new());
+        let finished = notify.clone();
+        let task = tokio::spawn(async move {
+            let _ = result_tx.send(future.await);
+            finished.notify_one();
+        });
+        Self {
+            name: name.into(),
+            buffer_id,
+            edit_count,
+            result,
+            task,
+            notify,
+        }
+    }
+
+    /// Name of the command
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The buffer the command was run in
+    pub fn buffer_id(&self) -> BufferId {
+        self.buffer_id
+    }
+
+    /// The command's buffer's edit count when it started
+    pub fn edit_count(&self) -> u64 {
+        self.edit_count
+    }
+
+    /// The command's result if it has finished, or `None` while it runs
+    pub fn try_take(&mut self) -> Option<Result<Vec<ChromeAction>, String>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(oneshot::error::TryRecvError::Empty) => None,
+            Err(oneshot::error::TryRecvError::Closed) => {
+                Some(Err(format!("{} stopped without a result", self.name)))
+            }
+        }
+    }
+
+    /// Wait until the command finishes
+    pub async fn finished(&self) {
+        self.notify.notified().await;
+    }
+
+    /// Stop waiting for the command. Its task is aborted; work it already
+    /// handed to the Julia thread runs to the end there, and is dropped.
+    pub fn cancel(self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_result_arrives_when_finished() {
+        let (release_tx, release) = oneshot::channel::<()>();
+        let mut command = RunningCommand::spawn(
+            "slow",
+            BufferId::default(),
+            0,
+            Box::pin(async move {
+                let _ = release.await;
+                Ok(vec![ChromeAction::Echo("done".to_string())])
+            }),
+        );
+        assert_eq!(command.try_take(), None);
+
+        release_tx.send(()).unwrap();
+        command.finished().await;
+        assert_eq!(
+            command.try_take(),
+            Some(Ok(vec![ChromeAction::Echo("done".to_string())]))
+        );
+    }
+}
@@ -18,6 +18,7 @@
 use crate::editor::OpenType;
 use crate::julia_runtime::{JuliaModeAction, JuliaModeResult, SharedJuliaRuntime};
 use crate::keys::{CursorDirection, KeyAction};
+use crate::menu_preview::PreviewSource;
 use crate::mode::{ActionPosition, Mode, ModeAction, ModeResult};
 use crate::BufferId;
 use std::collections::HashMap;
@@ -106,6 +107,26 @@ impl ScriptedMode {
         dict
     }
 
+    /// Run the Julia handler for this mode on `action_dict`
+    fn call_julia(&self, action_dict: HashMap<String, String>) -> ModeResult {
+        let mode_name = self.mode_name.clone();
+        let runtime = self.runtime.clone();
+
+        // Use block_in_place to safely run blocking code from async context
+        let result = tokio::task::block_in_place(|| {
+            let runtime_guard = runtime.blocking_lock();
+            runtime_guard.call_mode_perform(&mode_name, action_dict)
+        });
+
+        match result {
+            Ok(result) => self.convert_result(result),
+            Err(e) => {
+                eprintln!("Scripted mode error: {}", e);
+                ModeResult::Ignored
+            }
+        }
+    }
+
     /// Convert Julia mode result to Rust ModeResult
     fn convert_result(&self, result: JuliaModeResult) -> ModeResult {
         let actions: Vec<ModeAction> = result
@@ -140,7 +161,11 @@ impl ScriptedMode {
                     Some("visit") => OpenType::Visit,
                     _ => OpenType::New,
                 };
-                Some(ModeAction::OpenFile { path, open_type })
+                Some(ModeAction::OpenFile {
+                    path,
+                    open_type,
+                    position: None,
+                })
             }
             "execute_command" => {
                 let name = action.command.unwrap_or_default();
@@ -155,6 +180,7 @@ impl ScriptedMode {
                     None
                 }
             }
+            "create_buffer" => action.buffer_name.map(ModeAction::CreateBuffer),
             "kill_buffer" => {
                 // Julia returns a buffer index, convert to BufferId using our mapping
                 let index = action.buffer_index.unwrap_or(0) as usize;
@@ -164,6 +190,19 @@ impl ScriptedMode {
                     None
                 }
             }
+            "preview_buffer" => {
+                let index = action.buffer_index.unwrap_or(0) as usize;
+                self.buffer_id_map.get(index).map(|&buffer_id| {
+                    ModeAction::PreviewSelection(Some(PreviewSource::Buffer(buffer_id)))
+                })
+            }
+            "preview_file" => {
+                let path = PathBuf::from(action.path.unwrap_or_default());
+                Some(ModeAction::PreviewSelection(Some(PreviewSource::File(
+                    path,
+                ))))
+            }
+            "clear_preview" => Some(ModeAction::PreviewSelection(None)),
             "cursor_up" => Some(ModeAction::CursorUp),
             "cursor_down" => Some(ModeAction::CursorDown),
             "cursor_left" => Some(ModeAction::CursorLeft),
@@ -189,6 +228,8 @@ fn cursor_direction_str(dir: &CursorDirection) -> String {
         CursorDirection::WordBackward => "word_backward",
         CursorDirection::ParagraphForward => "paragraph_forward",
         CursorDirection::ParagraphBackward => "paragraph_backward",
+        CursorDirection::SexpForward => "sexp_forward",
+        CursorDirection::SexpBackward => "sexp_backward",
     }
     .to_string()
 }
@@ -214,21 +255,19 @@ impl Mode for ScriptedMode {
             action_dict.insert("type".to_string(), "init".to_string());
         }
 
-        let mode_name = self.mode_name.clone();
-        let runtime = self.runtime.clone();
-
-        // Use block_in_place to safely run blocking code from async context
-        let result = tokio::task::block_in_place(|| {
-            let runtime_guard = runtime.blocking_lock();
-            runtime_guard.call_mode_perform(&mode_name, action_dict)
-        });
+        self.call_julia(action_dict)
+    }
 
-        match result {
-            Ok(result) => self.convert_result(result),
-            Err(e) => {
-                eprintln!("Scripted mode error: {}", e);
-                ModeResult::Ignored
-            }
+    fn resize(&mut self, list_rows: usize) -> ModeResult {
+        // Nothing is drawn before init, and init reads max_visible itself
+        if !self.initialized {
+            self.set_init_param("max_visible", &list_rows.to_string());
+            return ModeResult::Ignored;
         }
+        let action_dict = HashMap::from([
+            ("type".to_string(), "resize".to_string()),
+            ("max_visible".to_string(), list_rows.to_string()),
+        ]);
+        self.call_julia(action_dict)
     }
 }
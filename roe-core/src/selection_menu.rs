@@ -11,9 +11,14 @@
 // this program. If not, see <https://www.gnu.org/licenses/>.
 //
 
-use crate::keys::KeyAction;
+use crate::fuzzy::highlight_ranges;
+use crate::keys::{CursorDirection, KeyAction};
 use crate::mode::{ActionPosition, ModeAction};
 
+/// Rows shown by selection menus unless configured otherwise
+/// (`roe_config["menu"]["max_visible_items"]`)
+pub const DEFAULT_MAX_VISIBLE_ITEMS: usize = 4;
+
 /// Trait for items that can be displayed and selected in a menu
 pub trait MenuItem: Clone {
     /// Get the display text for this item
@@ -25,6 +30,19 @@ pub trait MenuItem: Clone {
             .to_lowercase()
             .contains(&filter.to_lowercase())
     }
+
+    /// How well this item matches the filter, higher being better, or None if
+    /// it doesn't. Matching items are listed best first, ties keeping their
+    /// order; by default they all tie.
+    fn match_score(&self, filter: &str) -> Option<i64> {
+        self.matches_filter(filter).then_some(0)
+    }
+
+    /// Characters of `display_text` (as character indices) to highlight as
+    /// matching the filter. None by default.
+    fn matched_chars(&self, _filter: &str) -> Vec<usize> {
+        Vec::new()
+    }
 }
 
 /// Generic selection menu widget that can be used by multiple modes
@@ -42,6 +60,10 @@ pub struct SelectionMenu<T: MenuItem> {
     pub max_visible_items: usize,
     /// Starting index for the visible window of items
     pub scroll_offset: usize,
+    /// Display text of the item the user last had selected, so it can be
+    /// re-selected when a filter change (even one that matched nothing)
+    /// brings it back into the list
+    preferred_item: Option<String>,
 }
 
 impl<T: MenuItem> SelectionMenu<T> {
@@ -52,19 +74,46 @@ impl<T: MenuItem> SelectionMenu<T> {
             all_items: Vec::new(),
             filtered_items: Vec::new(),
             selected_index: 0,
-            max_visible_items,
+            max_visible_items: max_visible_items.max(1),
             scroll_offset: 0,
+            preferred_item: None,
         }
     }
 
+    /// Change how many rows are shown at once, keeping the selection visible
+    pub fn set_max_visible_items(&mut self, max_visible_items: usize) {
+        self.max_visible_items = max_visible_items.max(1);
+        self.update_scroll_to_center();
+    }
+
     /// Initialize with a list of items
     pub fn init_with_items(&mut self, items: Vec<T>) {
         self.input.clear();
         self.all_items = items;
+        self.preferred_item = None;
         self.update_filtered_items();
         self.selected_index = 0;
         self.scroll_offset = 0;
         self.update_scroll_to_center();
+        self.remember_selection();
+    }
+
+    /// Replace the items, keeping the filter typed so far
+    pub fn set_items(&mut self, items: Vec<T>) {
+        self.all_items = items;
+        self.update_filtered_items();
+    }
+
+    /// Select the item at `index` in the filtered list, scrolling it into view
+    pub fn select_index(&mut self, index: usize) -> bool {
+        if index < self.filtered_items.len() {
+            self.selected_index = index;
+            self.update_scroll_to_center();
+            self.remember_selection();
+            true
+        } else {
+            false
+        }
     }
 
     /// Add a character to the filter and update matches
@@ -94,6 +143,7 @@ impl<T: MenuItem> SelectionMenu<T> {
                 self.selected_index = self.filtered_items.len() - 1;
             }
             self.update_scroll_to_center();
+            self.remember_selection();
             true
         } else {
             false
@@ -110,6 +160,7 @@ impl<T: MenuItem> SelectionMenu<T> {
                 self.selected_index = 0;
             }
             self.update_scroll_to_center();
+            self.remember_selection();
             true
         } else {
             false
@@ -121,12 +172,40 @@ impl<T: MenuItem> SelectionMenu<T> {
         if !self.filtered_items.is_empty() {
             self.selected_index = (self.selected_index + 1) % self.filtered_items.len();
             self.update_scroll_to_center();
+            self.remember_selection();
             true
         } else {
             false
         }
     }
 
+    /// Jump to the first item
+    pub fn select_first(&mut self) -> bool {
+        self.select_index(0)
+    }
+
+    /// Jump to the last item
+    pub fn select_last(&mut self) -> bool {
+        self.select_index(self.filtered_items.len().saturating_sub(1))
+    }
+
+    /// Move selection up by a page of visible rows, stopping at the first item
+    pub fn page_up(&mut self) -> bool {
+        if self.filtered_items.is_empty() {
+            return false;
+        }
+        self.select_index(self.selected_index.saturating_sub(self.max_visible_items))
+    }
+
+    /// Move selection down by a page of visible rows, stopping at the last item
+    pub fn page_down(&mut self) -> bool {
+        if self.filtered_items.is_empty() {
+            return false;
+        }
+        let last = self.filtered_items.len() - 1;
+        self.select_index((self.selected_index + self.max_visible_items).min(last))
+    }
+
     /// Get the currently selected item
     pub fn get_selected_item(&self) -> Option<&T> {
         self.filtered_items.get(self.selected_index)
@@ -149,7 +228,14 @@ impl<T: MenuItem> SelectionMenu<T> {
 
     /// Generate buffer content with the given header and selection indicator
     pub fn generate_buffer_content(&self, header: Option<&str>) -> String {
+        self.buffer_content_and_highlights(header).0
+    }
+
+    /// The buffer content, and the byte ranges in it of the characters of
+    /// each item the filter matched
+    fn buffer_content_and_highlights(&self, header: Option<&str>) -> (String, Vec<(usize, usize)>) {
         let mut content = String::new();
+        let mut highlights = Vec::new();
 
         // Add header if provided
         if let Some(header) = header {
@@ -161,18 +247,25 @@ impl<T: MenuItem> SelectionMenu<T> {
             content.push_str(&format!("{}\n", self.input));
         }
 
+        if self.filtered_items.is_empty() && !self.input.is_empty() {
+            content.push_str("  [no matches]\n");
+        }
+
         // Item lines with selection highlighting
         let visible_items = self.visible_items();
         for (idx, item) in visible_items.iter().enumerate() {
             let is_selected = self.visible_selection_index() == Some(idx);
-            if is_selected {
-                content.push_str(&format!("> {}\n", item.display_text()));
-            } else {
-                content.push_str(&format!("  {}\n", item.display_text()));
+            content.push_str(if is_selected { "> " } else { "  " });
+            let text = item.display_text();
+            if !self.input.is_empty() {
+                let matched = item.matched_chars(&self.input);
+                highlights.extend(highlight_ranges(&text, &matched, content.chars().count()));
             }
+            content.push_str(&text);
+            content.push('\n');
         }
 
-        content
+        (content, highlights)
     }
 
     /// Handle common key actions, returning true if the action was handled
@@ -183,14 +276,31 @@ impl<T: MenuItem> SelectionMenu<T> {
                 true
             }
             KeyAction::Backspace => self.remove_filter_char(),
-            KeyAction::Cursor(crate::keys::CursorDirection::Up) => {
+            KeyAction::Cursor(CursorDirection::Up) => {
                 self.move_selection_up();
                 true // Always consume arrow keys
             }
-            KeyAction::Cursor(crate::keys::CursorDirection::Down) => {
+            KeyAction::Cursor(CursorDirection::Down) => {
                 self.move_selection_down();
                 true // Always consume arrow keys
             }
+            KeyAction::Cursor(CursorDirection::PageUp) => {
+                self.page_up();
+                true
+            }
+            KeyAction::Cursor(CursorDirection::PageDown) => {
+                self.page_down();
+                true
+            }
+            // Home/End and M-</M-> jump to the ends of the list
+            KeyAction::Cursor(CursorDirection::LineStart | CursorDirection::BufferStart) => {
+                self.select_first();
+                true
+            }
+            KeyAction::Cursor(CursorDirection::LineEnd | CursorDirection::BufferEnd) => {
+                self.select_last();
+                true
+            }
             KeyAction::Tab => {
                 self.cycle_selection();
                 true
@@ -201,13 +311,15 @@ impl<T: MenuItem> SelectionMenu<T> {
 
     /// Generate the standard mode actions for updating buffer content
     pub fn generate_update_actions(&self, header: Option<&str>) -> Vec<ModeAction> {
-        vec![
+        let (content, highlights) = self.buffer_content_and_highlights(header);
+        let mut actions = vec![
             ModeAction::ClearText,
-            ModeAction::InsertText(
-                ActionPosition::start(),
-                self.generate_buffer_content(header),
-            ),
-        ]
+            ModeAction::InsertText(ActionPosition::start(), content),
+        ];
+        if !highlights.is_empty() {
+            actions.push(ModeAction::HighlightMatches(highlights));
+        }
+        actions
     }
 
     /// Update filtered items based on current input
@@ -215,20 +327,38 @@ impl<T: MenuItem> SelectionMenu<T> {
         if self.input.is_empty() {
             self.filtered_items = self.all_items.clone();
         } else {
-            self.filtered_items = self
+            let mut scored: Vec<(i64, &T)> = self
                 .all_items
                 .iter()
-                .filter(|item| item.matches_filter(&self.input))
-                .cloned()
+                .filter_map(|item| Some((item.match_score(&self.input)?, item)))
                 .collect();
+            // Stable, so equally good matches keep their order
+            scored.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+            self.filtered_items = scored.into_iter().map(|(_, item)| item.clone()).collect();
         }
 
-        // Reset selection to first match
-        self.selected_index = 0;
+        // Stay on the previously selected item if it still matches, otherwise
+        // fall back to the first match
+        self.selected_index = self
+            .preferred_item
+            .as_ref()
+            .and_then(|preferred| {
+                self.filtered_items
+                    .iter()
+                    .position(|item| &item.display_text() == preferred)
+            })
+            .unwrap_or(0);
         self.scroll_offset = 0;
         self.update_scroll_to_center();
     }
 
+    /// Remember the selected item as the one to keep across filter changes
+    fn remember_selection(&mut self) {
+        if let Some(item) = self.filtered_items.get(self.selected_index) {
+            self.preferred_item = Some(item.display_text());
+        }
+    }
+
     /// Update scroll offset to keep selection centered
     fn update_scroll_to_center(&mut self) {
         if self.filtered_items.len() <= self.max_visible_items {
@@ -277,7 +407,7 @@ impl<T: MenuItem> SelectionMenu<T> {
 
 impl<T: MenuItem> Default for SelectionMenu<T> {
     fn default() -> Self {
-        Self::new(8) // Default to 8 visible items
+        Self::new(DEFAULT_MAX_VISIBLE_ITEMS)
     }
 }
 
@@ -338,4 +468,120 @@ mod tests {
         assert_eq!(menu.len(), 2); // apple and apricot
         assert_eq!(menu.get_selected_item().unwrap().name, "apple");
     }
+
+    fn numbered_items(count: usize) -> Vec<TestItem> {
+        (0..count)
+            .map(|i| TestItem {
+                name: format!("item-{i:02}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_filter_to_zero_and_back() {
+        let mut menu = SelectionMenu::new(3);
+        menu.init_with_items(vec![
+            TestItem {
+                name: "apple".to_string(),
+            },
+            TestItem {
+                name: "apricot".to_string(),
+            },
+            TestItem {
+                name: "banana".to_string(),
+            },
+        ]);
+        menu.move_selection_down();
+        assert_eq!(menu.get_selected_item().unwrap().name, "apricot");
+
+        // "apx" matches nothing
+        for c in "apx".chars() {
+            menu.add_filter_char(c);
+        }
+        assert!(menu.is_empty());
+        assert!(menu.get_selected_item().is_none());
+        assert!(!menu.move_selection_down());
+        assert!(menu.generate_buffer_content(None).contains("[no matches]"));
+
+        // Widening again goes back to the item that was selected
+        menu.remove_filter_char();
+        assert_eq!(menu.len(), 2);
+        assert_eq!(menu.get_selected_item().unwrap().name, "apricot");
+        menu.remove_filter_char();
+        menu.remove_filter_char();
+        assert_eq!(menu.len(), 3);
+        assert_eq!(menu.get_selected_item().unwrap().name, "apricot");
+    }
+
+    #[test]
+    fn test_filter_falls_back_to_first_match() {
+        let mut menu = SelectionMenu::new(3);
+        menu.init_with_items(numbered_items(20));
+        menu.select_index(5);
+
+        // item-05 is filtered out; selection moves to a valid entry
+        menu.add_filter_char('1');
+        assert_eq!(menu.get_selected_item().unwrap().name, "item-01");
+        assert_eq!(menu.scroll_offset, 0);
+    }
+
+    #[test]
+    fn test_scrolling_keeps_selection_visible() {
+        let mut menu = SelectionMenu::new(4);
+        menu.init_with_items(numbered_items(20));
+
+        menu.select_index(10);
+        assert_eq!(menu.scroll_offset, 8);
+        let content = menu.generate_buffer_content(None);
+        assert_eq!(content.lines().count(), 4);
+        assert!(content.contains("> item-10"));
+
+        // Paging moves by a screenful and stops at the ends
+        menu.page_down();
+        assert_eq!(menu.get_selected_item().unwrap().name, "item-14");
+        menu.page_down();
+        menu.page_down();
+        assert_eq!(menu.get_selected_item().unwrap().name, "item-19");
+        assert_eq!(menu.scroll_offset, 16);
+        menu.page_up();
+        assert_eq!(menu.get_selected_item().unwrap().name, "item-15");
+
+        // Wrapping from the top shows the end of the list
+        menu.select_index(0);
+        menu.move_selection_up();
+        assert_eq!(menu.get_selected_item().unwrap().name, "item-19");
+        assert_eq!(menu.scroll_offset, 16);
+
+        // Wrapping from the bottom goes back to the top
+        menu.move_selection_down();
+        assert_eq!(menu.get_selected_item().unwrap().name, "item-00");
+        assert_eq!(menu.scroll_offset, 0);
+
+        // Shrinking the window keeps the selection on screen
+        menu.select_index(19);
+        menu.set_max_visible_items(2);
+        assert!(menu.generate_buffer_content(None).contains("> item-19"));
+    }
+
+    #[test]
+    fn test_home_end_keys() {
+        let mut menu = SelectionMenu::new(4);
+        menu.init_with_items(numbered_items(20));
+
+        assert!(menu.handle_key_action(&KeyAction::Cursor(CursorDirection::BufferEnd)));
+        assert_eq!(menu.get_selected_item().unwrap().name, "item-19");
+        assert_eq!(menu.scroll_offset, 16);
+
+        assert!(menu.handle_key_action(&KeyAction::Cursor(CursorDirection::LineStart)));
+        assert_eq!(menu.get_selected_item().unwrap().name, "item-00");
+        assert_eq!(menu.scroll_offset, 0);
+
+        assert!(menu.handle_key_action(&KeyAction::Cursor(CursorDirection::LineEnd)));
+        assert_eq!(menu.get_selected_item().unwrap().name, "item-19");
+
+        // Nothing to select, but the keys are still consumed
+        menu.add_filter_char('x');
+        assert!(menu.handle_key_action(&KeyAction::Cursor(CursorDirection::BufferStart)));
+        assert!(menu.get_selected_item().is_none());
+    }
 }
@@ -231,6 +231,13 @@ impl FaceRegistry {
                 .with_background(Color::from_hex("#ffff00").unwrap())
                 .with_foreground(Color::from_hex("#000000").unwrap()),
         );
+
+        // Characters of a completion candidate the typed input matched
+        self.define_face(
+            Face::new(crate::fuzzy::COMPLETION_MATCH_FACE)
+                .with_foreground(Color::from_hex("#e5c07b").unwrap())
+                .with_bold(true),
+        );
     }
 
     /// Define a new face and return its ID
@@ -241,6 +248,18 @@ impl FaceRegistry {
         id
     }
 
+    /// Define a face, or replace the attributes of an existing face with the
+    /// same name while keeping its ID stable. Returns the face's ID.
+    pub fn register_face(&mut self, face: Face) -> FaceId {
+        match self.get_id(&face.name) {
+            Some(id) => {
+                self.update_face(id, face);
+                id
+            }
+            None => self.define_face(face),
+        }
+    }
+
     /// Look up a face by ID
     pub fn get(&self, id: FaceId) -> Option<&Face> {
         self.faces.get(id)
@@ -521,6 +540,29 @@ mod tests {
         assert_eq!(face.name, "my-custom-face");
     }
 
+    #[test]
+    fn test_register_face_replaces_existing() {
+        let mut registry = FaceRegistry::new();
+
+        let keyword_id = registry.get_id("keyword").unwrap();
+        let id = registry.register_face(
+            Face::new("keyword")
+                .with_foreground(Color::rgb(1, 2, 3))
+                .with_underline(true),
+        );
+
+        // Same ID so existing spans keep resolving to the face
+        assert_eq!(id, keyword_id);
+        let face = registry.get(id).unwrap();
+        assert_eq!(face.foreground, Some(Color::rgb(1, 2, 3)));
+        assert!(face.underline);
+        assert!(!face.bold);
+
+        // Unknown names are defined fresh
+        let new_id = registry.register_face(Face::new("user-face").with_italic(true));
+        assert_eq!(registry.get_id("user-face"), Some(new_id));
+    }
+
     #[test]
     fn test_span_store_basic() {
         let mut store = SpanStore::new();
@@ -0,0 +1,175 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! `tabify` and `untabify`: rewrite the indentation of lines with tabs or with
+//! spaces, keeping it the same width.
+//!
+//! Only the whitespace a line starts with is touched. Tabified indentation is
+//! as many tabs as fit, then spaces for the rest; a line whose indentation is
+//! already written the right way is left alone.
+//!
+//! The depths indent guides are drawn to come from the same column math.
+
+use crate::comment::LineEdit;
+
+/// Columns `indent`, a run of spaces and tabs at the start of a line, takes up
+pub fn indent_width(indent: &str, tab_width: usize) -> usize {
+    let tab_width = tab_width.max(1);
+    indent.chars().fold(0, |column, c| match c {
+        '\t' => column + tab_width - column % tab_width,
+        _ => column + 1,
+    })
+}
+
+/// Indentation `width` columns wide: tabs then spaces, or all spaces
+pub fn indent_string(width: usize, tab_width: usize, use_tabs: bool) -> String {
+    let tab_width = tab_width.max(1);
+    if use_tabs {
+        "\t".repeat(width / tab_width) + &" ".repeat(width % tab_width)
+    } else {
+        " ".repeat(width)
+    }
+}
+
+/// The spaces and tabs `line` starts with
+fn leading_whitespace(line: &str) -> &str {
+    let text = line.trim_start_matches([' ', '\t']);
+    &line[..line.len() - text.len()]
+}
+
+/// `line` with its indentation rewritten with tabs or with spaces
+pub fn retab_line(line: &str, tab_width: usize, use_tabs: bool) -> String {
+    let indent = leading_whitespace(line);
+    let width = indent_width(indent, tab_width);
+    indent_string(width, tab_width, use_tabs) + &line[indent.len()..]
+}
+
+/// Indentation levels of `line`: how many whole `tab_width` stops its
+/// leading whitespace reaches
+pub fn indent_depth(line: &str, tab_width: usize) -> usize {
+    indent_width(leading_whitespace(line), tab_width) / tab_width.max(1)
+}
+
+/// Indent guides to draw beside each of `lines`, as the depth of its
+/// indentation: one guide at each multiple of `tab_width` columns short of
+/// it. Blank lines take the shallower of the nearest non-blank lines' above
+/// and below, so guides carry on through them inside a block but stop after
+/// its last line.
+pub fn indent_guide_depths(lines: &[&str], tab_width: usize) -> Vec<usize> {
+    let depths: Vec<Option<usize>> = lines
+        .iter()
+        .map(|line| (!line.trim().is_empty()).then(|| indent_depth(line, tab_width)))
+        .collect();
+
+    // Depth of the nearest non-blank line at or above each line
+    let mut above = Vec::with_capacity(depths.len());
+    let mut last = 0;
+    for depth in &depths {
+        last = depth.unwrap_or(last);
+        above.push(last);
+    }
+
+    let mut guides = vec![0; depths.len()];
+    let mut below = 0;
+    for (index, depth) in depths.iter().enumerate().rev() {
+        guides[index] = match depth {
+            Some(depth) => {
+                below = *depth;
+                *depth
+            }
+            None => above[index].min(below),
+        };
+    }
+    guides
+}
+
+/// Edits rewriting the indentation of each line that needs it
+fn retab(lines: &[&str], tab_width: usize, use_tabs: bool) -> Vec<LineEdit> {
+    lines
+        .iter()
+        .enumerate()
+        .filter_map(|(line, text)| {
+            let indent = leading_whitespace(text);
+            let retabbed = indent_string(indent_width(indent, tab_width), tab_width, use_tabs);
+            (retabbed != indent).then(|| LineEdit {
+                line,
+                column: 0,
+                delete: indent.chars().count(),
+                insert: retabbed,
+            })
+        })
+        .collect()
+}
+
+/// Edits indenting each line with tabs, as far as they go
+pub fn tabify(lines: &[&str], tab_width: usize) -> Vec<LineEdit> {
+    retab(lines, tab_width, true)
+}
+
+/// Edits indenting each line with spaces only
+pub fn untabify(lines: &[&str], tab_width: usize) -> Vec<LineEdit> {
+    retab(lines, tab_width, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tabify_and_untabify() {
+        let lines = ["        x", "  \ty", "\t z", "w"];
+
+        let edits = tabify(&lines, 4);
+        let inserts: Vec<&str> = edits.iter().map(|edit| edit.insert.as_str()).collect();
+        assert_eq!(inserts, ["\t\t", "\t"]);
+        assert_eq!(edits[1].line, 1);
+        assert_eq!(edits[1].delete, 3);
+
+        let edits = untabify(&lines, 8);
+        let inserts: Vec<&str> = edits.iter().map(|edit| edit.insert.as_str()).collect();
+        assert_eq!(inserts, [" ".repeat(8), " ".repeat(9)]);
+        assert_eq!(edits[0].line, 1);
+    }
+
+    #[test]
+    fn test_retab_line() {
+        assert_eq!(retab_line("      - item", 4, true), "\t  - item");
+        assert_eq!(
+            retab_line("\t\tfoo", 8, false),
+            format!("{}foo", " ".repeat(16))
+        );
+        assert_eq!(indent_width(" \t", 8), 8);
+    }
+
+    #[test]
+    fn test_indent_guide_depths() {
+        assert_eq!(indent_depth("        x", 4), 2);
+        assert_eq!(indent_depth("      x", 4), 1);
+        assert_eq!(indent_depth("\t  x", 4), 1);
+        assert_eq!(indent_depth("\t\tx", 8), 2);
+
+        // Blank lines inside a block keep its guides; after it they don't
+        let lines = [
+            "fn f() {",
+            "    if x {",
+            "        a",
+            "",
+            "        b",
+            "    }",
+            "   ",
+            "}",
+        ];
+        assert_eq!(indent_guide_depths(&lines, 4), [0, 1, 2, 2, 2, 1, 0, 0]);
+        assert_eq!(indent_guide_depths(&["", "    x", ""], 4), [0, 1, 0]);
+    }
+}
@@ -0,0 +1,99 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! `count-words`: how many lines, words, characters and bytes the region or
+//! the buffer has.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Counts for a piece of text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TextStats {
+    /// Lines, the last counting even without a newline at its end
+    pub lines: usize,
+    /// Runs of non-whitespace, as `wc -w` counts them
+    pub words: usize,
+    /// Characters as they're seen: an `e` and a combining accent after it are
+    /// one
+    pub characters: usize,
+    /// Bytes in UTF-8
+    pub bytes: usize,
+}
+
+impl TextStats {
+    pub fn of(text: &str) -> Self {
+        let newlines = text.matches('\n').count();
+        Self {
+            lines: newlines + usize::from(!text.is_empty() && !text.ends_with('\n')),
+            words: text.split_whitespace().count(),
+            characters: text.graphemes(true).count(),
+            bytes: text.len(),
+        }
+    }
+
+    /// The counts as a sentence about `what` ("Region", "Buffer")
+    pub fn describe(&self, what: &str) -> String {
+        format!(
+            "{what} has {}, {}, {}, {}",
+            plural(self.lines, "line"),
+            plural(self.words, "word"),
+            plural(self.characters, "character"),
+            plural(self.bytes, "byte")
+        )
+    }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {noun}")
+    } else {
+        format!("{count} {noun}s")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts() {
+        // "é" is an e and a combining acute accent: one character, three bytes
+        let stats = TextStats::of("The cafe\u{301} is\nopen\tlate\n\nno newline");
+        assert_eq!(
+            stats,
+            TextStats {
+                lines: 4,
+                words: 7,
+                characters: 33,
+                bytes: 35,
+            }
+        );
+        assert_eq!(
+            stats.describe("Region"),
+            "Region has 4 lines, 7 words, 33 characters, 35 bytes"
+        );
+        assert_eq!(
+            TextStats::of("one\n").describe("Buffer"),
+            "Buffer has 1 line, 1 word, 4 characters, 4 bytes"
+        );
+    }
+
+    #[test]
+    fn test_empty_region() {
+        assert_eq!(TextStats::of(""), TextStats::default());
+        assert_eq!(
+            TextStats::default().describe("Region"),
+            "Region has 0 lines, 0 words, 0 characters, 0 bytes"
+        );
+    }
+}
@@ -0,0 +1,434 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! Built-in tree-sitter highlighting, for languages whose major mode asks for
+//! it rather than highlighting from a Julia after-change hook.
+//!
+//! Each buffer keeps its own parser and last syntax tree, so an edit only
+//! re-parses what it touched, and only the lines it touched and the nodes
+//! whose syntax it changed are queried again. The grammar's own highlights
+//! query picks out the nodes, and its capture names (`keyword`,
+//! `string.special`, `function.method`...) are mapped onto the default faces
+//! by their first component. Tree-sitter works in bytes; spans are converted
+//! to the character offsets the buffer uses.
+
+use crate::after_change_queue::ChangeRange;
+use crate::syntax::{FaceRegistry, HighlightSpan};
+use std::collections::HashSet;
+use std::ops::Range;
+use tree_sitter::{
+    InputEdit, Language, Parser, Point, Query, QueryCursor, StreamingIterator, Tree,
+};
+
+/// A language with a built-in grammar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeSitterLanguage {
+    Rust,
+    Json,
+    Toml,
+}
+
+impl TreeSitterLanguage {
+    /// The language called `name`, as a major mode names it
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "rust" => Some(Self::Rust),
+            "json" => Some(Self::Json),
+            "toml" => Some(Self::Toml),
+            _ => None,
+        }
+    }
+
+    fn grammar(self) -> Language {
+        match self {
+            Self::Rust => tree_sitter_rust::LANGUAGE.into(),
+            Self::Json => tree_sitter_json::LANGUAGE.into(),
+            Self::Toml => tree_sitter_toml_ng::LANGUAGE.into(),
+        }
+    }
+
+    fn highlights_query(self) -> &'static str {
+        match self {
+            Self::Rust => tree_sitter_rust::HIGHLIGHTS_QUERY,
+            Self::Json => tree_sitter_json::HIGHLIGHTS_QUERY,
+            Self::Toml => tree_sitter_toml_ng::HIGHLIGHTS_QUERY,
+        }
+    }
+}
+
+/// The face for a highlights query capture, if it gets one
+fn capture_face(capture_name: &str) -> Option<&'static str> {
+    let face = match capture_name.split('.').next()? {
+        "keyword" => "keyword",
+        "string" => "string",
+        "comment" => "comment",
+        "function" | "constructor" => "function",
+        "type" => "type",
+        "constant" | "number" | "boolean" => "constant",
+        "variable" | "property" | "label" => "variable",
+        "operator" => "operator",
+        "punctuation" => "punctuation",
+        _ => return None,
+    };
+    Some(face)
+}
+
+/// The spans found by a highlight
+#[derive(Debug, Clone, PartialEq)]
+pub struct Highlights {
+    /// Character ranges whose spans these replace, or `None` for all of them
+    pub replaces: Option<Vec<Range<usize>>>,
+    pub spans: Vec<HighlightSpan>,
+}
+
+/// Parser and syntax tree for one buffer
+pub struct TreeSitterHighlighter {
+    language: TreeSitterLanguage,
+    parser: Parser,
+    query: Query,
+    /// Face for each of the query's captures, by capture index
+    capture_faces: Vec<Option<&'static str>>,
+    /// Tree for `source`, the text last highlighted
+    tree: Option<Tree>,
+    source: String,
+}
+
+impl TreeSitterHighlighter {
+    pub fn new(language: TreeSitterLanguage) -> Result<Self, String> {
+        let grammar = language.grammar();
+        let mut parser = Parser::new();
+        parser
+            .set_language(&grammar)
+            .map_err(|e| format!("{language:?} grammar: {e}"))?;
+        let query = Query::new(&grammar, language.highlights_query())
+            .map_err(|e| format!("{language:?} highlights query: {e}"))?;
+        let capture_faces = query
+            .capture_names()
+            .iter()
+            .map(|name| capture_face(name))
+            .collect();
+        Ok(Self {
+            language,
+            parser,
+            query,
+            capture_faces,
+            tree: None,
+            source: String::new(),
+        })
+    }
+
+    pub fn language(&self) -> TreeSitterLanguage {
+        self.language
+    }
+
+    /// Parse `text`, re-using the tree for the text last highlighted where
+    /// it's unchanged, and return its spans. When `change` is the edit made
+    /// since, only the spans it could have affected are returned. Faces
+    /// missing from `registry` are left out.
+    pub fn highlight(
+        &mut self,
+        text: &str,
+        change: Option<ChangeRange>,
+        registry: &FaceRegistry,
+    ) -> Highlights {
+        let edit = change.and_then(|change| change_edit(&self.source, text, change));
+        let mut old_tree = self.tree.take();
+        if let Some(ref mut tree) = old_tree {
+            tree.edit(&edit.unwrap_or_else(|| input_edit(&self.source, text)));
+        }
+        let Some(tree) = self.parser.parse(text, old_tree.as_ref()) else {
+            return Highlights {
+                replaces: None,
+                spans: Vec::new(),
+            };
+        };
+
+        // Only the edited lines, and whatever the edit changed the syntax of,
+        // need querying again
+        let byte_ranges = match (&old_tree, edit) {
+            (Some(old_tree), Some(edit)) => {
+                let mut ranges: Vec<Range<usize>> = old_tree
+                    .changed_ranges(&tree)
+                    .map(|range| range.start_byte..range.end_byte)
+                    .collect();
+                ranges.push(line_range(text, edit.start_byte..edit.new_end_byte));
+                Some(merge_ranges(ranges))
+            }
+            _ => None,
+        };
+
+        let offsets = CharOffsets::new(text);
+        let mut spans = Vec::new();
+        let mut captured = HashSet::new();
+        let mut cursor = QueryCursor::new();
+        for byte_range in byte_ranges.clone().unwrap_or_else(|| vec![0..text.len()]) {
+            cursor.set_byte_range(byte_range);
+            let mut captures = cursor.captures(&self.query, tree.root_node(), text.as_bytes());
+            while let Some((query_match, index)) = captures.next() {
+                let capture = query_match.captures[*index];
+                let Some(face_id) = self.capture_faces[capture.index as usize]
+                    .and_then(|face| registry.get_id(face))
+                else {
+                    continue;
+                };
+                // The first pattern to capture a node decides its face
+                let range = capture.node.byte_range();
+                if !captured.insert((range.start, range.end)) {
+                    continue;
+                }
+                spans.push(HighlightSpan::new(
+                    offsets.char_offset(range.start),
+                    offsets.char_offset(range.end),
+                    face_id,
+                ));
+            }
+        }
+
+        self.tree = Some(tree);
+        self.source = text.to_string();
+        Highlights {
+            replaces: byte_ranges.map(|ranges| {
+                ranges
+                    .into_iter()
+                    .map(|range| offsets.char_offset(range.start)..offsets.char_offset(range.end))
+                    .collect()
+            }),
+            spans,
+        }
+    }
+}
+
+/// Converts the byte offsets tree-sitter works in to character offsets
+struct CharOffsets<'a> {
+    text: &'a str,
+    /// Byte and character offset of each line's start, unless `text` is ASCII
+    line_starts: Option<Vec<(usize, usize)>>,
+}
+
+impl<'a> CharOffsets<'a> {
+    fn new(text: &'a str) -> Self {
+        let line_starts = (!text.is_ascii()).then(|| {
+            let mut line_starts = vec![(0, 0)];
+            for (chars, (byte, c)) in text.char_indices().enumerate() {
+                if c == '\n' {
+                    line_starts.push((byte + 1, chars + 1));
+                }
+            }
+            line_starts
+        });
+        Self { text, line_starts }
+    }
+
+    /// Character offset of byte `offset`, which starts a character
+    fn char_offset(&self, offset: usize) -> usize {
+        let Some(ref line_starts) = self.line_starts else {
+            return offset;
+        };
+        let line = line_starts.partition_point(|&(byte, _)| byte <= offset) - 1;
+        let (line_byte, line_char) = line_starts[line];
+        line_char + self.text[line_byte..offset].chars().count()
+    }
+}
+
+/// Byte offset of character `offset` in `text`, which may be its end
+fn byte_offset(text: &str, offset: usize) -> Option<usize> {
+    text.char_indices()
+        .map(|(byte, _)| byte)
+        .chain(std::iter::once(text.len()))
+        .nth(offset)
+}
+
+/// `change`, in characters, as the edit that turns `old` into `new`, if it
+/// accounts for all that differs between them
+fn change_edit(old: &str, new: &str, change: ChangeRange) -> Option<InputEdit> {
+    let start_byte = byte_offset(new, change.start)?;
+    let old_end_byte = byte_offset(old, change.old_end)?;
+    let new_end_byte = byte_offset(new, change.new_end)?;
+    let (old_bytes, new_bytes) = (old.as_bytes(), new.as_bytes());
+    let unchanged = start_byte <= old_end_byte
+        && start_byte <= new_end_byte
+        && old_bytes.len() - old_end_byte == new_bytes.len() - new_end_byte
+        && old_bytes[..start_byte] == new_bytes[..start_byte]
+        && old_bytes[old_end_byte..] == new_bytes[new_end_byte..];
+    unchanged.then(|| InputEdit {
+        start_byte,
+        old_end_byte,
+        new_end_byte,
+        start_position: point_at(new_bytes, start_byte),
+        old_end_position: point_at(old_bytes, old_end_byte),
+        new_end_position: point_at(new_bytes, new_end_byte),
+    })
+}
+
+/// `range` widened to the whole lines it covers, newlines included
+fn line_range(text: &str, range: Range<usize>) -> Range<usize> {
+    let start = text[..range.start].rfind('\n').map_or(0, |i| i + 1);
+    let end = text[range.end..]
+        .find('\n')
+        .map_or(text.len(), |i| range.end + i + 1);
+    start..end
+}
+
+/// `ranges` sorted, with those that overlap or touch joined
+fn merge_ranges(mut ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    ranges.sort_by_key(|range| range.start);
+    let mut merged: Vec<Range<usize>> = Vec::new();
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// The edit that turns `old` into `new`: everything between their common
+/// prefix and common suffix
+fn input_edit(old: &str, new: &str) -> InputEdit {
+    let (old_bytes, new_bytes) = (old.as_bytes(), new.as_bytes());
+    let prefix = old_bytes
+        .iter()
+        .zip(new_bytes)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old_bytes[prefix..]
+        .iter()
+        .rev()
+        .zip(new_bytes[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let old_end_byte = old_bytes.len() - suffix;
+    let new_end_byte = new_bytes.len() - suffix;
+    InputEdit {
+        start_byte: prefix,
+        old_end_byte,
+        new_end_byte,
+        start_position: point_at(new_bytes, prefix),
+        old_end_position: point_at(old_bytes, old_end_byte),
+        new_end_position: point_at(new_bytes, new_end_byte),
+    }
+}
+
+/// Row and byte column of byte `offset` in `text`
+fn point_at(text: &[u8], offset: usize) -> Point {
+    let before = &text[..offset];
+    let row = before.iter().filter(|&&b| b == b'\n').count();
+    let line_start = before
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map_or(0, |i| i + 1);
+    Point::new(row, offset - line_start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The spans of `text` as (highlighted text, face name)
+    fn faces(highlighter: &mut TreeSitterHighlighter, text: &str) -> Vec<(String, String)> {
+        let registry = FaceRegistry::new();
+        highlighter
+            .highlight(text, None, &registry)
+            .spans
+            .iter()
+            .map(|span| {
+                let face = registry.get(span.face_id).unwrap().name.clone();
+                let spanned = text.chars().skip(span.start).take(span.end - span.start);
+                (spanned.collect(), face)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rust_spans() {
+        let mut highlighter = TreeSitterHighlighter::new(TreeSitterLanguage::Rust).unwrap();
+        let text = "// hi\nfn main() { let s = \"héllo\"; }\n";
+        let registry = FaceRegistry::new();
+        let spans = highlighter.highlight(text, None, &registry).spans;
+        let face = |name| registry.get_id(name).unwrap();
+
+        // Character ranges, so the string's é counts once
+        for (start, end, name) in [
+            (0, 5, "comment"),
+            (6, 8, "keyword"),
+            (18, 21, "keyword"),
+            (26, 33, "string"),
+        ] {
+            assert!(
+                spans.contains(&HighlightSpan::new(start, end, face(name))),
+                "no {name} span at {start}..{end} in {spans:?}"
+            );
+        }
+        assert!(
+            faces(&mut highlighter, text).contains(&("main".to_string(), "function".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_reparse_after_edit() {
+        let mut highlighter = TreeSitterHighlighter::new(TreeSitterLanguage::Rust).unwrap();
+        faces(&mut highlighter, "fn main() {}\n");
+
+        // The tree edited to match still finds what was typed
+        let spans = faces(&mut highlighter, "fn main() { return; }\n");
+        assert!(spans.contains(&("return".to_string(), "keyword".to_string())));
+        let spans = faces(&mut highlighter, "fn main() {}\n");
+        assert!(!spans.iter().any(|(text, _)| text == "return"));
+    }
+
+    #[test]
+    fn test_edit_requeries_only_what_it_changed() {
+        let mut highlighter = TreeSitterHighlighter::new(TreeSitterLanguage::Rust).unwrap();
+        let registry = FaceRegistry::new();
+        let old = "fn main() {}\n// é\nfn other() {}\n";
+        highlighter.highlight(old, None, &registry);
+
+        // " return; " typed into main's body
+        let new = "fn main() { return; }\n// é\nfn other() {}\n";
+        let change = ChangeRange::new(11, 11, 20);
+        let highlights = highlighter.highlight(new, Some(change), &registry);
+        let replaces = highlights.replaces.expect("only the edit is re-queried");
+        assert!(replaces.contains(&(0..22)));
+        assert!(replaces.iter().all(|range| range.end <= 22));
+        let keyword = registry.get_id("keyword").unwrap();
+        assert!(highlights
+            .spans
+            .contains(&HighlightSpan::new(12, 18, keyword)));
+        assert!(highlights.spans.iter().all(|span| span.end <= 22));
+
+        // A change that doesn't account for the text is highlighted in full,
+        // in characters after the é
+        let edited = "fn main() { return; }\n// é\nfn other() { }\n";
+        let highlights = highlighter.highlight(edited, Some(change), &registry);
+        assert_eq!(highlights.replaces, None);
+        assert!(highlights
+            .spans
+            .contains(&HighlightSpan::new(27, 29, keyword)));
+    }
+
+    #[test]
+    fn test_json_and_toml_spans() {
+        let mut json = TreeSitterHighlighter::new(TreeSitterLanguage::Json).unwrap();
+        let spans = faces(&mut json, "{\"a\": 1, \"b\": true}");
+        assert!(spans.contains(&("1".to_string(), "constant".to_string())));
+        assert!(spans.contains(&("true".to_string(), "constant".to_string())));
+
+        let mut toml = TreeSitterHighlighter::new(TreeSitterLanguage::Toml).unwrap();
+        let spans = faces(&mut toml, "# deps\nname = \"roe\"\n");
+        assert!(spans.contains(&("# deps".to_string(), "comment".to_string())));
+        assert!(spans.contains(&("\"roe\"".to_string(), "string".to_string())));
+        // Keys are captured as types by the TOML grammar
+        assert!(spans.contains(&("name".to_string(), "type".to_string())));
+    }
+}
@@ -90,6 +90,14 @@ impl EditOp {
         }
     }
 
+    /// Number of single inserts and deletes this operation is made of
+    pub fn edit_count(&self) -> usize {
+        match self {
+            EditOp::Insert { .. } | EditOp::Delete { .. } => 1,
+            EditOp::Group(ops) => ops.iter().map(|op| op.edit_count()).sum(),
+        }
+    }
+
     /// Get the cursor position after reversing this operation
     pub fn cursor_after_reverse(&self) -> usize {
         self.reverse().cursor_after()
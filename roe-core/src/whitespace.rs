@@ -0,0 +1,105 @@
+// Copyright (C) 2025 Ryan Daum <ryan.daum@gmail.com> This program is free
+// software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, version
+// 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with
+// this program. If not, see <https://www.gnu.org/licenses/>.
+//
+
+//! `delete-trailing-whitespace`: strip the spaces and tabs ending each line,
+//! and the blank lines ending the text.
+//!
+//! The last line with any text keeps its line ending, so a file ending in a
+//! newline still does. Line endings themselves, `\r\n` included, are left as
+//! they are.
+
+use std::ops::Range;
+
+/// Character ranges of `text` to delete: the trailing whitespace of each line,
+/// then the run of blank lines at the end. In order and not overlapping, and
+/// empty if there's nothing to delete.
+pub fn trailing_whitespace(text: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut line_start = 0;
+    // End of the last line with text, its line ending included
+    let mut text_end = 0;
+    for line in text.split_inclusive('\n') {
+        let body = line.strip_suffix('\n').unwrap_or(line);
+        let body = body.strip_suffix('\r').unwrap_or(body);
+        let kept = body.trim_end_matches([' ', '\t']);
+        let kept_len = kept.chars().count();
+        let body_len = kept_len + body[kept.len()..].chars().count();
+        if kept_len < body_len {
+            ranges.push(line_start + kept_len..line_start + body_len);
+        }
+        let line_len = body_len + line[body.len()..].chars().count();
+        if !kept.is_empty() {
+            text_end = line_start + line_len;
+        }
+        line_start += line_len;
+    }
+
+    // The blank lines at the end go as a whole, whitespace and all
+    if text_end < line_start {
+        ranges.retain(|range| range.start < text_end);
+        ranges.push(text_end..line_start);
+    }
+    ranges
+}
+
+/// Where a character position ends up once `ranges` have been deleted. One
+/// inside a deleted range moves to where it started.
+pub fn shift_position(ranges: &[Range<usize>], pos: usize) -> usize {
+    let deleted: usize = ranges
+        .iter()
+        .take_while(|range| range.start < pos)
+        .map(|range| pos.min(range.end) - range.start)
+        .sum();
+    pos - deleted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `text` with `ranges` deleted
+    fn apply(text: &str, ranges: &[Range<usize>]) -> String {
+        text.chars()
+            .enumerate()
+            .filter(|(pos, _)| !ranges.iter().any(|range| range.contains(pos)))
+            .map(|(_, c)| c)
+            .collect()
+    }
+
+    #[test]
+    fn test_trailing_whitespace() {
+        let text = "fn main() {  \n\tlet x = 1;\t \r\n  \n}\n\n \t\n";
+        let ranges = trailing_whitespace(text);
+        assert_eq!(apply(text, &ranges), "fn main() {\n\tlet x = 1;\r\n\n}\n");
+
+        // A blank run inside the text only loses its whitespace
+        assert_eq!(
+            apply("a\n  \nb  ", &trailing_whitespace("a\n  \nb  ")),
+            "a\n\nb"
+        );
+
+        // Clean text has nothing to delete
+        assert!(trailing_whitespace("a\n\tb\n").is_empty());
+        assert!(trailing_whitespace("").is_empty());
+    }
+
+    #[test]
+    fn test_shift_position() {
+        let ranges = [2..4, 6..9];
+        assert_eq!(shift_position(&ranges, 1), 1);
+        assert_eq!(shift_position(&ranges, 3), 2);
+        assert_eq!(shift_position(&ranges, 5), 3);
+        assert_eq!(shift_position(&ranges, 8), 4);
+        assert_eq!(shift_position(&ranges, 10), 5);
+    }
+}
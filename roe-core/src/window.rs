@@ -13,6 +13,50 @@
 
 use crate::editor::Window;
 
+/// Drawn over the first or last visible column of a line the window cuts off
+pub const TRUNCATION_MARK: char = '$';
+
+/// Drawn in the gutter beside the rows a wrapped line continues onto
+pub const CONTINUATION_MARK: char = '↪';
+
+/// How a buffer's lines longer than the window are shown
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WrapMode {
+    /// Cut off at the window edge, scrolling sideways to follow the cursor
+    #[default]
+    None,
+    /// Continued on the next row, broken after the last space that fits
+    Word,
+    /// Continued on the next row, broken at the window edge
+    Char,
+}
+
+/// Character offsets at which `line` starts each row when shown `width`
+/// columns wide. The first row always starts at 0. A line that exactly fills
+/// its last row gets an empty row after it, for the cursor at its end.
+pub fn wrap_rows(line: &str, width: usize, mode: WrapMode) -> Vec<usize> {
+    let mut starts = vec![0];
+    if mode == WrapMode::None || width == 0 {
+        return starts;
+    }
+    let chars: Vec<char> = line.chars().collect();
+    let mut start = 0;
+    while chars.len() - start >= width {
+        let mut end = start + width;
+        let mid_word =
+            end < chars.len() && !chars[end].is_whitespace() && !chars[end - 1].is_whitespace();
+        if mode == WrapMode::Word && mid_word {
+            // Break after the last space in the row, unless the row is one long word
+            if let Some(space) = chars[start..end].iter().rposition(|c| c.is_whitespace()) {
+                end = start + space + 1;
+            }
+        }
+        starts.push(end);
+        start = end;
+    }
+    starts
+}
+
 impl Window {
     /// Compute the physical cursor position relative to the window's top.
     /// This is relative to the window, not the frame.
@@ -32,6 +76,27 @@ impl Window {
         // Content area is above the modeline
         (self.x + rel_col + 1, self.y + rel_line + 1)
     }
+
+    /// Which of the `content_width` columns shown, if any, display column
+    /// `column` of a line falls in, given how far the window is scrolled right
+    pub fn visible_column(&self, column: usize, content_width: usize) -> Option<usize> {
+        column
+            .checked_sub(self.start_column as usize)
+            .filter(|&offset| offset < content_width)
+    }
+
+    /// Whether a line of `line_len` characters shown `content_width` columns wide
+    /// has text hidden to the left (scrolled past) and to the right (past the
+    /// edge), to be flagged with `TRUNCATION_MARK` as Emacs does
+    pub fn truncation_marks(&self, line_len: usize, content_width: usize) -> (bool, bool) {
+        if content_width < 2 {
+            return (false, false);
+        }
+        let start_column = self.start_column as usize;
+        let left = start_column > 0 && line_len > 0;
+        let right = line_len > start_column + content_width;
+        (left, right)
+    }
 }
 
 #[cfg(test)]
@@ -90,4 +155,51 @@ mod tests {
         // saturating_sub clamps to 0
         assert_eq!(line, 0);
     }
+
+    #[test]
+    fn test_wrap_rows() {
+        let line = "the quick brown fox";
+        assert_eq!(wrap_rows(line, 10, WrapMode::None), vec![0]);
+        assert_eq!(wrap_rows(line, 10, WrapMode::Char), vec![0, 10]);
+        assert_eq!(wrap_rows(line, 10, WrapMode::Word), vec![0, 10]);
+        assert_eq!(wrap_rows(line, 8, WrapMode::Char), vec![0, 8, 16]);
+        assert_eq!(wrap_rows(line, 8, WrapMode::Word), vec![0, 4, 10, 16]);
+
+        // A word too long for a row is broken anywhere
+        assert_eq!(wrap_rows("abcdefghij", 4, WrapMode::Word), vec![0, 4, 8]);
+
+        // Short lines take one row; full ones get an empty row for the cursor
+        assert_eq!(wrap_rows("", 4, WrapMode::Word), vec![0]);
+        assert_eq!(wrap_rows("abc", 4, WrapMode::Word), vec![0]);
+        assert_eq!(wrap_rows("abcd", 4, WrapMode::Char), vec![0, 4]);
+    }
+
+    #[test]
+    fn test_truncation_marks() {
+        let mut window = test_window();
+        assert_eq!(window.truncation_marks(10, 10), (false, false));
+        assert_eq!(window.truncation_marks(11, 10), (false, true));
+        assert_eq!(window.truncation_marks(0, 10), (false, false));
+
+        // Scrolled right: every line with text has some hidden to the left
+        window.start_column = 5;
+        assert_eq!(window.truncation_marks(3, 10), (true, false));
+        assert_eq!(window.truncation_marks(15, 10), (true, false));
+        assert_eq!(window.truncation_marks(16, 10), (true, true));
+        assert_eq!(window.truncation_marks(0, 10), (false, false));
+    }
+
+    #[test]
+    fn test_visible_column() {
+        let mut window = test_window();
+        assert_eq!(window.visible_column(70, 80), Some(70));
+        assert_eq!(window.visible_column(80, 80), None);
+
+        // Scrolled right, columns shift left and those scrolled past are gone
+        window.start_column = 30;
+        assert_eq!(window.visible_column(80, 80), Some(50));
+        assert_eq!(window.visible_column(30, 80), Some(0));
+        assert_eq!(window.visible_column(29, 80), None);
+        assert_eq!(window.visible_column(110, 80), None);
+    }
 }
@@ -19,20 +19,28 @@ use crossterm::style::{Color, Print, Stylize};
 use crossterm::terminal::{Clear, ClearType};
 use crossterm::{cursor, queue};
 use futures::{future::FutureExt, select, StreamExt};
-use roe_core::editor::{BorderInfo, ChromeAction, DragType, Frame, MouseDragState, Window};
+use roe_core::buffer::{clip_wide_chars, expand_tabs, WIDE_CHAR_TAIL};
+use roe_core::editor::{ChromeAction, DragType, Frame, MouseDragState, Window};
+use roe_core::file_watcher::EVENT_DRAIN_INTERVAL;
 use roe_core::gutter::{
-    calculate_gutter_width, format_line_number, get_line_status, GutterConfig, LineStatus,
+    calculate_gutter_width, format_line_number, get_line_status, line_number_width, GutterConfig,
+    LineStatus,
 };
 use roe_core::julia_runtime::face_registry;
 use roe_core::keys::{KeyModifier, LogicalKey, Side};
+use roe_core::menu_preview::preview_pane;
 use roe_core::renderer::{DirtyRegion, DirtyTracker, ModelineComponent, Renderer};
 use roe_core::syntax::Color as SyntaxColor;
+use roe_core::window::TRUNCATION_MARK;
 use roe_core::{Editor, HighlightSpan, WindowId};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::io::Write;
 use tokio::time::{interval, Duration};
 
 pub const ECHO_AREA_HEIGHT: u16 = 1;
+/// Rows of a window that aren't text: the top border and the bottom border
+/// the modeline is drawn on. Menu previews cover the same rows as the menu text.
+const WINDOW_CHROME_ROWS: u16 = 2;
 pub const BG_COLOR: Color = Color::Black;
 pub const FG_COLOR: Color = Color::White;
 pub const MODE_LINE_BG_COLOR: Color = Color::Blue;
@@ -107,14 +115,6 @@ fn syntax_color_to_crossterm(color: &SyntaxColor, default: Color) -> Color {
     }
 }
 
-/// Convert a character position to byte position in a string
-fn char_to_byte(s: &str, char_pos: usize) -> usize {
-    s.char_indices()
-        .nth(char_pos)
-        .map(|(byte_idx, _)| byte_idx)
-        .unwrap_or(s.len())
-}
-
 /// Cached theme colors loaded from Julia at startup
 #[derive(Clone)]
 pub struct CachedTheme {
@@ -126,6 +126,12 @@ pub struct CachedTheme {
     pub rune_color: Color,
     pub border_color: Color,
     pub active_border_color: Color,
+    pub gutter_bg_color: Color,
+    pub gutter_fg_color: Color,
+    pub gutter_modified_color: Color,
+    pub gutter_saved_color: Color,
+    pub gutter_conflict_color: Color,
+    pub gutter_separator_color: Color,
 }
 
 impl Default for CachedTheme {
@@ -139,10 +145,34 @@ impl Default for CachedTheme {
             rune_color: RUNE_COLOR,
             border_color: BORDER_COLOR,
             active_border_color: ACTIVE_BORDER_COLOR,
+            gutter_bg_color: GUTTER_BG_COLOR,
+            gutter_fg_color: GUTTER_FG_COLOR,
+            gutter_modified_color: GUTTER_MODIFIED_COLOR,
+            gutter_saved_color: GUTTER_SAVED_COLOR,
+            gutter_conflict_color: GUTTER_CONFLICT_COLOR,
+            gutter_separator_color: GUTTER_SEPARATOR_COLOR,
         }
     }
 }
 
+impl CachedTheme {
+    /// Set a gutter color from a hex string, given its key under `colors`
+    /// in the config. Returns whether the key names a gutter color.
+    pub fn set_gutter_color(&mut self, key: &str, hex: &str) -> bool {
+        let color = parse_hex_color(hex);
+        match key {
+            "gutter-bg" => self.gutter_bg_color = color,
+            "gutter-fg" => self.gutter_fg_color = color,
+            "gutter-modified" => self.gutter_modified_color = color,
+            "gutter-saved" => self.gutter_saved_color = color,
+            "gutter-conflict" => self.gutter_conflict_color = color,
+            "gutter-separator" => self.gutter_separator_color = color,
+            _ => return false,
+        }
+        true
+    }
+}
+
 /// Load theme colors from Julia runtime at startup
 pub async fn load_julia_theme(editor: &Editor) -> CachedTheme {
     let mut theme = CachedTheme::default();
@@ -197,6 +227,30 @@ pub async fn load_julia_theme(editor: &Editor) -> CachedTheme {
             }
         }
 
+        // Gutter colors, as the GUI loads them
+        for key in [
+            "gutter-bg",
+            "gutter-fg",
+            "gutter-modified",
+            "gutter-saved",
+            "gutter-conflict",
+            "gutter-separator",
+        ] {
+            let result = {
+                let runtime = julia_runtime.lock().await;
+                match runtime.get_config(&format!("colours.{key}")).await {
+                    Ok(Some(value)) => Ok(Some(value)),
+                    _ => runtime.get_config(&format!("colors.{key}")).await,
+                }
+            };
+            if let Ok(Some(value)) = result {
+                if let Some(color_str) = value.as_string() {
+                    loaded_colors.push(format!("{key}:{color_str}"));
+                    theme.set_gutter_color(key, &color_str);
+                }
+            }
+        }
+
         // Note: loaded_colors is used for tracking what was loaded
         let _ = loaded_colors;
     }
@@ -211,6 +265,11 @@ pub struct TerminalRenderer<W: Write> {
     device: W,
     dirty_tracker: DirtyTracker,
     theme: CachedTheme,
+    /// Whether the terminal reports mouse events to us (on at startup); while
+    /// off, the terminal's own selection and copy work instead
+    mouse_captured: bool,
+    /// Whether each window's modeline last showed its buffer as modified
+    modified_markers: HashMap<WindowId, bool>,
 }
 
 impl<W: Write> TerminalRenderer<W> {
@@ -219,6 +278,8 @@ impl<W: Write> TerminalRenderer<W> {
             device,
             dirty_tracker: DirtyTracker::new(),
             theme: CachedTheme::default(),
+            mouse_captured: true,
+            modified_markers: HashMap::new(),
         }
     }
 
@@ -227,6 +288,28 @@ impl<W: Write> TerminalRenderer<W> {
             device,
             dirty_tracker: DirtyTracker::new(),
             theme,
+            mouse_captured: true,
+            modified_markers: HashMap::new(),
+        }
+    }
+
+    /// Mark modelines dirty whose buffer has been modified or saved since they
+    /// were drawn. Edits only dirty the lines they touch, so the `**`/`--`
+    /// marker would otherwise wait for the next full redraw.
+    fn mark_modified_modelines(&mut self, editor: &Editor) {
+        self.modified_markers
+            .retain(|window_id, _| editor.windows.contains_key(*window_id));
+        for (window_id, window) in editor.windows.iter() {
+            let modified = editor
+                .buffers
+                .get(window.active_buffer)
+                .is_some_and(|buffer| buffer.is_modified());
+            if self.modified_markers.insert(window_id, modified) != Some(modified) {
+                self.dirty_tracker.mark_dirty(DirtyRegion::Modeline {
+                    window_id,
+                    component: ModelineComponent::All,
+                });
+            }
         }
     }
 
@@ -256,33 +339,41 @@ impl<W: Write> TerminalRenderer<W> {
         let show_gutter = buffer.show_gutter();
 
         // Calculate gutter width
-        let (gutter_width, modified_lines): (usize, HashSet<usize>) = if show_gutter {
-            let total_lines = buffer.buffer_len_lines();
-            let config = GutterConfig::default();
+        let config = GutterConfig::default();
+        let total_lines = buffer.buffer_len_lines();
+        let (gutter_width, modified_lines, saved_lines, conflict_lines) = if show_gutter {
             let width = calculate_gutter_width(total_lines, &config);
-            let buffer_content = buffer.content();
-            let modified = editor
-                .file_watcher
-                .get_modified_lines(window.active_buffer, &buffer_content);
-            (width, modified)
+            let lines = buffer_line..buffer_line + 1;
+            let (modified, saved) =
+                editor
+                    .file_watcher
+                    .line_changes(window.active_buffer, buffer, lines.clone());
+            let conflicts = editor
+                .conflict_cache
+                .marker_lines(window.active_buffer, buffer, lines);
+            (width, modified, saved, conflicts)
         } else {
-            (0, HashSet::new())
+            (0, HashSet::new(), HashSet::new(), HashSet::new())
         };
 
         let base_content_x = window.x + 1;
         let total_content_width = window.width_chars.saturating_sub(2);
         let content_x = base_content_x + gutter_width as u16;
         let content_width = total_content_width.saturating_sub(gutter_width as u16);
-        let line_number_width = gutter_width.saturating_sub(2);
+        let line_number_width = line_number_width(total_lines, &config);
 
-        if buffer_line >= buffer.buffer_len_lines() {
+        if buffer_line >= total_lines {
             // Past end of buffer - draw gutter with tilde and clear content
             if show_gutter {
                 queue!(&mut self.device, cursor::MoveTo(base_content_x, screen_row))?;
                 let empty_gutter = format!(" {:>width$}│", "~", width = line_number_width);
                 queue!(
                     &mut self.device,
-                    Print(empty_gutter.with(GUTTER_FG_COLOR).on(GUTTER_BG_COLOR))
+                    Print(
+                        empty_gutter
+                            .with(self.theme.gutter_fg_color)
+                            .on(self.theme.gutter_bg_color)
+                    )
                 )?;
             }
 
@@ -304,43 +395,48 @@ impl<W: Write> TerminalRenderer<W> {
         let line_end_char = line_start_char + line_char_count;
         let start_column = window.start_column as usize;
 
-        // Get buffer content for byte<->char position conversion
-        // (spans use byte positions for tree-sitter/Julia compatibility)
-        let buffer_content = buffer.content();
-        let line_start_byte = char_to_byte(&buffer_content, line_start_char);
-        let line_end_byte = char_to_byte(&buffer_content, line_end_char);
-
         // Draw gutter
         if show_gutter {
-            let merged_lines: HashSet<usize> = HashSet::new();
             let line_status =
-                get_line_status(line_text, buffer_line, &modified_lines, &merged_lines);
+                get_line_status(buffer_line, &modified_lines, &saved_lines, &conflict_lines);
 
             queue!(&mut self.device, cursor::MoveTo(base_content_x, screen_row))?;
 
             // Status indicator
             let (status_char, status_color) = match line_status {
-                LineStatus::Clean => (" ", GUTTER_FG_COLOR),
-                LineStatus::Modified => ("│", GUTTER_MODIFIED_COLOR),
-                LineStatus::ModifiedSaved => ("│", GUTTER_SAVED_COLOR),
-                LineStatus::Conflict => ("!", GUTTER_CONFLICT_COLOR),
+                LineStatus::Clean => (" ", self.theme.gutter_fg_color),
+                LineStatus::Modified => ("│", self.theme.gutter_modified_color),
+                LineStatus::ModifiedSaved => ("│", self.theme.gutter_saved_color),
+                LineStatus::Conflict => ("!", self.theme.gutter_conflict_color),
             };
             queue!(
                 &mut self.device,
-                Print(status_char.with(status_color).on(GUTTER_BG_COLOR))
+                Print(
+                    status_char
+                        .with(status_color)
+                        .on(self.theme.gutter_bg_color)
+                )
             )?;
 
             // Line number
             let line_num_str = format_line_number(buffer_line + 1, line_number_width);
             queue!(
                 &mut self.device,
-                Print(line_num_str.with(GUTTER_FG_COLOR).on(GUTTER_BG_COLOR))
+                Print(
+                    line_num_str
+                        .with(self.theme.gutter_fg_color)
+                        .on(self.theme.gutter_bg_color)
+                )
             )?;
 
             // Separator
             queue!(
                 &mut self.device,
-                Print("│".with(GUTTER_SEPARATOR_COLOR).on(GUTTER_BG_COLOR))
+                Print(
+                    "│"
+                        .with(self.theme.gutter_separator_color)
+                        .on(self.theme.gutter_bg_color)
+                )
             )?;
         }
 
@@ -357,41 +453,46 @@ impl<W: Write> TerminalRenderer<W> {
         )?;
         queue!(&mut self.device, cursor::MoveTo(content_x, screen_row))?;
 
-        // Apply horizontal scroll - skip start_column characters, then take content_width
-        let chars_to_render: Vec<char> = line_text
-            .chars()
+        // Tabs expanded to the next tab stop, each cell with the character it's for
+        let cells = expand_tabs(line_text, buffer.tab_width());
+
+        // Apply horizontal scroll - skip start_column cells, then take content_width
+        let mut chars_to_render: Vec<(char, usize)> = cells
+            .iter()
+            .copied()
             .skip(start_column)
             .take(content_width as usize)
             .collect();
+        clip_wide_chars(&mut chars_to_render);
 
-        // Get syntax spans for this line (using byte positions)
+        // Get syntax spans for this line
         let syntax_spans: Vec<HighlightSpan> =
-            buffer.spans_in_range(line_start_byte..line_end_byte);
+            buffer.spans_in_range(line_start_char..line_end_char);
 
         // Get face registry for looking up face colors
         let face_registry_guard = face_registry().lock().ok();
 
         // Render character by character with merged highlighting
-        for (char_idx, ch) in chars_to_render.iter().enumerate() {
-            // Account for horizontal scroll when calculating buffer position (in chars)
-            let buffer_pos_char = line_start_char + start_column + char_idx;
-            // Convert to byte position for span lookup
-            let buffer_pos_byte = char_to_byte(&buffer_content, buffer_pos_char);
+        for (ch, char_idx) in chars_to_render.iter() {
+            // The wide character before it already took this column
+            if *ch == WIDE_CHAR_TAIL {
+                continue;
+            }
+            let buffer_pos_char = line_start_char + char_idx;
 
             // Determine the style for this character
             // Priority: region selection > syntax highlighting > default
-            // Note: region_bounds uses char positions, span lookup uses byte positions
             let (fg, bg) = if let Some((region_start, region_end)) = region_bounds {
                 if buffer_pos_char >= region_start && buffer_pos_char < region_end {
                     // Character is in selection region
                     (Color::Black, self.theme.selection_color)
                 } else {
                     // Check syntax highlighting
-                    self.get_syntax_colors(buffer_pos_byte, &syntax_spans, &face_registry_guard)
+                    self.get_syntax_colors(buffer_pos_char, &syntax_spans, &face_registry_guard)
                 }
             } else {
                 // No region, check syntax highlighting
-                self.get_syntax_colors(buffer_pos_byte, &syntax_spans, &face_registry_guard)
+                self.get_syntax_colors(buffer_pos_char, &syntax_spans, &face_registry_guard)
             };
 
             queue!(&mut self.device, Print(ch.to_string().with(fg).on(bg)))?;
@@ -416,6 +517,31 @@ impl<W: Write> TerminalRenderer<W> {
             }
         }
 
+        // Flag text hidden past either edge of the window
+        let (left_mark, right_mark) = window.truncation_marks(cells.len(), content_width as usize);
+        if left_mark {
+            queue!(
+                &mut self.device,
+                cursor::MoveTo(content_x, screen_row),
+                Print(
+                    TRUNCATION_MARK
+                        .with(self.theme.gutter_fg_color)
+                        .on(self.theme.bg_color)
+                )
+            )?;
+        }
+        if right_mark {
+            queue!(
+                &mut self.device,
+                cursor::MoveTo(content_x + content_width - 1, screen_row),
+                Print(
+                    TRUNCATION_MARK
+                        .with(self.theme.gutter_fg_color)
+                        .on(self.theme.bg_color)
+                )
+            )?;
+        }
+
         Ok(())
     }
 
@@ -641,6 +767,17 @@ impl<W: Write> Renderer for TerminalRenderer<W> {
             }
         }
 
+        // Menu previews share rows with the list, so put them back over any
+        // list lines just redrawn
+        for (window_id, window) in &editor.windows {
+            if matches!(
+                window.window_type,
+                roe_core::editor::WindowType::Command { .. }
+            ) {
+                draw_menu_preview(&mut self.device, editor, window_id, &self.theme)?;
+            }
+        }
+
         // Flush all queued drawing commands first
         self.device.flush()?;
 
@@ -705,27 +842,7 @@ impl<W: Write> Renderer for TerminalRenderer<W> {
 
         // Draw echo area
         if !editor.echo_message.is_empty() {
-            let (x, y) = echo_area_position(&editor.frame);
-            let available_width = editor.frame.columns.saturating_sub(x); // Use full terminal width
-            let truncated_message = if editor.echo_message.len() > available_width as usize {
-                &editor.echo_message[..available_width.saturating_sub(3) as usize]
-            } else {
-                &editor.echo_message
-            };
-            queue!(
-                &mut self.device,
-                cursor::MoveTo(x, y),
-                Clear(ClearType::CurrentLine)
-            )?;
-            queue!(
-                &mut self.device,
-                cursor::MoveTo(x, y),
-                Print(
-                    truncated_message
-                        .with(self.theme.fg_color)
-                        .on(self.theme.bg_color)
-                )
-            )?;
+            draw_echo_lines(&mut self.device, editor, &self.theme)?;
         }
 
         // Flush all drawing commands first
@@ -968,16 +1085,36 @@ fn draw_window_modeline(
     // Build the rest of the modeline content
     let mut rest_content = String::new();
 
-    // Add buffer object name
-    let object_part = format!("{} ", buffer.object());
+    // Emacs-style `**` for unsaved changes, `--` otherwise (`%` first when
+    // read-only), then the buffer object name
+    let modified = editor.modified_flag(window.active_buffer);
+    let object_part = format!("{} {} ", modified, buffer.object());
     rest_content.push_str(&object_part);
 
+    // Flag buffers whose file was deleted or moved away externally
+    if let Some(indicator) = editor.file_status_indicator(window.active_buffer) {
+        rest_content.push_str(&indicator);
+        rest_content.push(' ');
+    }
+
     // Add major mode name (if set)
     if let Some(major_mode) = buffer.major_mode() {
         let mode_part = format!("({}) ", major_mode);
         rest_content.push_str(&mode_part);
     }
 
+    // Encoding and line endings of the file the buffer visits
+    if let Some(file_format) = editor.file_format_indicator(window.active_buffer) {
+        rest_content.push_str(&file_format);
+        rest_content.push(' ');
+    }
+
+    // Word count and reading time, for prose modes
+    if let Some(word_count) = editor.word_count_indicator(window.active_buffer) {
+        rest_content.push_str(&word_count);
+        rest_content.push(' ');
+    }
+
     // Add cursor position
     let (col, line) = buffer.to_column_line(window.cursor);
     let position_part = format!("{}:{} ", line + 1, col + 1); // 1-based for display
@@ -1075,26 +1212,32 @@ pub fn draw_window(
     let base_content_x = window.x + 1;
     let content_y = window.y + 1;
     let total_content_width = window.width_chars.saturating_sub(2);
-    let content_height = window.height_chars.saturating_sub(2);
+    let content_height = window.height_chars.saturating_sub(WINDOW_CHROME_ROWS);
 
     // Check if gutter should be shown (controlled by major mode / Julia)
     let show_gutter = buffer.show_gutter();
 
     // Calculate gutter width and get modified lines
-    let (gutter_width, modified_lines): (usize, HashSet<usize>) = if show_gutter {
-        let total_lines = buffer.buffer_len_lines();
-        let config = GutterConfig::default();
+    let config = GutterConfig::default();
+    let total_lines = buffer.buffer_len_lines();
+    let (gutter_width, modified_lines, saved_lines, conflict_lines) = if show_gutter {
         let width = calculate_gutter_width(total_lines, &config);
 
-        // Get modified lines from file watcher
-        let buffer_content = buffer.content();
-        let modified = editor
-            .file_watcher
-            .get_modified_lines(window.active_buffer, &buffer_content);
+        // Get line status for the visible lines from the file watcher and
+        // any merge conflict markers
+        let start_line = window.start_line as usize;
+        let lines = start_line..start_line + content_height as usize;
+        let (modified, saved) =
+            editor
+                .file_watcher
+                .line_changes(window.active_buffer, buffer, lines.clone());
+        let conflicts = editor
+            .conflict_cache
+            .marker_lines(window.active_buffer, buffer, lines);
 
-        (width, modified)
+        (width, modified, saved, conflicts)
     } else {
-        (0, HashSet::new())
+        (0, HashSet::new(), HashSet::new(), HashSet::new())
     };
 
     // Adjust content area for gutter
@@ -1117,12 +1260,9 @@ pub fn draw_window(
     // Get face registry for looking up face colors
     let face_registry_guard = face_registry().lock().ok();
 
-    // For detecting conflict lines
-    let merged_lines: HashSet<usize> = HashSet::new(); // TODO: track merged lines separately
-
-    // Calculate line number width (for formatting)
+    // Line numbers fill the column reserved for them, ending at the separator
     let line_number_width = if show_gutter {
-        gutter_width.saturating_sub(2) // Subtract status indicator and separator
+        line_number_width(total_lines, &config)
     } else {
         0
     };
@@ -1146,7 +1286,8 @@ pub fn draw_window(
         // Draw gutter for this line
         if show_gutter {
             // Get line status
-            let line_status = get_line_status(&line_text, line_idx, &modified_lines, &merged_lines);
+            let line_status =
+                get_line_status(line_idx, &modified_lines, &saved_lines, &conflict_lines);
 
             // Draw gutter background
             queue!(
@@ -1156,27 +1297,35 @@ pub fn draw_window(
 
             // Status indicator
             let (status_char, status_color) = match line_status {
-                LineStatus::Clean => (" ", GUTTER_FG_COLOR),
-                LineStatus::Modified => ("│", GUTTER_MODIFIED_COLOR),
-                LineStatus::ModifiedSaved => ("│", GUTTER_SAVED_COLOR),
-                LineStatus::Conflict => ("!", GUTTER_CONFLICT_COLOR),
+                LineStatus::Clean => (" ", theme.gutter_fg_color),
+                LineStatus::Modified => ("│", theme.gutter_modified_color),
+                LineStatus::ModifiedSaved => ("│", theme.gutter_saved_color),
+                LineStatus::Conflict => ("!", theme.gutter_conflict_color),
             };
             queue!(
                 device,
-                Print(status_char.with(status_color).on(GUTTER_BG_COLOR))
+                Print(status_char.with(status_color).on(theme.gutter_bg_color))
             )?;
 
             // Line number (1-based, right-aligned)
             let line_num_str = format_line_number(line_idx + 1, line_number_width);
             queue!(
                 device,
-                Print(line_num_str.with(GUTTER_FG_COLOR).on(GUTTER_BG_COLOR))
+                Print(
+                    line_num_str
+                        .with(theme.gutter_fg_color)
+                        .on(theme.gutter_bg_color)
+                )
             )?;
 
             // Separator
             queue!(
                 device,
-                Print("│".with(GUTTER_SEPARATOR_COLOR).on(GUTTER_BG_COLOR))
+                Print(
+                    "│"
+                        .with(theme.gutter_separator_color)
+                        .on(theme.gutter_bg_color)
+                )
             )?;
         }
 
@@ -1186,35 +1335,34 @@ pub fn draw_window(
         let line_end_char = line_start_char + line_char_count;
         let start_column = window.start_column as usize;
 
-        // Get buffer content for byte<->char conversion (spans use byte positions)
-        let buffer_content = buffer.content();
-        let line_start_byte = char_to_byte(&buffer_content, line_start_char);
-        let line_end_byte = char_to_byte(&buffer_content, line_end_char);
+        // Tabs expanded to the next tab stop, each cell with the character it's for
+        let cells = expand_tabs(line_text.trim_end_matches('\n'), buffer.tab_width());
 
-        // Apply horizontal scroll - skip start_column characters, then take content_width
-        let line_str = line_text;
-        let visible_chars: Vec<char> = line_str
-            .chars()
+        // Apply horizontal scroll - skip start_column cells, then take content_width
+        let mut visible_chars: Vec<(char, usize)> = cells
+            .iter()
+            .copied()
             .skip(start_column)
             .take(content_width as usize)
             .collect();
+        clip_wide_chars(&mut visible_chars);
 
-        // Get syntax spans for this line (using byte positions)
+        // Get syntax spans for this line
         let syntax_spans: Vec<HighlightSpan> =
-            buffer.spans_in_range(line_start_byte..line_end_byte);
+            buffer.spans_in_range(line_start_char..line_end_char);
 
         // Move cursor to the start of the text content
         queue!(device, cursor::MoveTo(content_x, content_y + content_line))?;
 
         // Render character by character with merged highlighting (region + syntax)
-        for (char_idx, ch) in visible_chars.iter().enumerate() {
-            // Account for horizontal scroll when calculating buffer position (char position)
-            let buffer_pos_char = line_start_char + start_column + char_idx;
-            // Convert to byte position for span lookup
-            let buffer_pos_byte = char_to_byte(&buffer_content, buffer_pos_char);
+        for (ch, char_idx) in visible_chars.iter() {
+            // The wide character before it already took this column
+            if *ch == WIDE_CHAR_TAIL {
+                continue;
+            }
+            let buffer_pos_char = line_start_char + char_idx;
 
             // Determine colors: region selection > syntax > default
-            // Note: region_bounds uses char positions, span lookup uses byte positions
             let (fg, bg) = if let Some((region_start, region_end)) = region_bounds {
                 if buffer_pos_char >= region_start && buffer_pos_char < region_end {
                     // Character is in selection region
@@ -1222,7 +1370,7 @@ pub fn draw_window(
                 } else {
                     // Check syntax highlighting
                     get_syntax_colors_standalone(
-                        buffer_pos_byte,
+                        buffer_pos_char,
                         &syntax_spans,
                         &face_registry_guard,
                         theme,
@@ -1231,7 +1379,7 @@ pub fn draw_window(
             } else {
                 // No region, check syntax highlighting
                 get_syntax_colors_standalone(
-                    buffer_pos_byte,
+                    buffer_pos_char,
                     &syntax_spans,
                     &face_registry_guard,
                     theme,
@@ -1255,6 +1403,32 @@ pub fn draw_window(
                 }
             }
         }
+
+        // Flag text hidden past either edge of the window
+        let (left_mark, right_mark) = window.truncation_marks(cells.len(), content_width as usize);
+        let row = content_y + content_line;
+        if left_mark {
+            queue!(
+                device,
+                cursor::MoveTo(content_x, row),
+                Print(
+                    TRUNCATION_MARK
+                        .with(theme.gutter_fg_color)
+                        .on(theme.bg_color)
+                )
+            )?;
+        }
+        if right_mark {
+            queue!(
+                device,
+                cursor::MoveTo(content_x + content_width - 1, row),
+                Print(
+                    TRUNCATION_MARK
+                        .with(theme.gutter_fg_color)
+                        .on(theme.bg_color)
+                )
+            )?;
+        }
     }
 
     // Draw gutter for empty lines (lines that exist in the window but not in buffer)
@@ -1275,7 +1449,11 @@ pub fn draw_window(
                 let empty_gutter = format!(" {:>width$}│", "~", width = line_number_width);
                 queue!(
                     device,
-                    Print(empty_gutter.with(GUTTER_FG_COLOR).on(GUTTER_BG_COLOR))
+                    Print(
+                        empty_gutter
+                            .with(theme.gutter_fg_color)
+                            .on(theme.gutter_bg_color)
+                    )
                 )?;
             }
         }
@@ -1348,29 +1526,17 @@ fn crossterm_key_translate(ck: &KeyCode, modifiers: KeyModifiers) -> LogicalKey
     }
 }
 
+/// Draw the editor's echo message, which was just set
 pub fn echo(
     device: &mut impl Write,
-    editor: &mut Editor,
-    message: &str,
+    editor: &Editor,
     theme: &CachedTheme,
 ) -> Result<(), std::io::Error> {
-    let (x, y) = echo_area_position(&editor.frame);
-
     // Stash the cursor position
     let cursor_pos = crossterm::cursor::position()?;
 
-    let available_width = editor.frame.columns.saturating_sub(x); // Use full terminal width
-    let truncated_message = if message.len() > available_width as usize {
-        &message[..available_width.saturating_sub(3) as usize]
-    } else {
-        message
-    };
-    queue!(device, cursor::MoveTo(x, y), Clear(ClearType::CurrentLine))?;
-    queue!(
-        device,
-        cursor::MoveTo(x, y),
-        Print(truncated_message.with(theme.fg_color).on(theme.bg_color))
-    )?;
+    draw_echo_lines(device, editor, theme)?;
+
     // Restore the cursor position
     queue!(device, cursor::MoveTo(cursor_pos.0, cursor_pos.1))?;
 
@@ -1378,14 +1544,80 @@ pub fn echo(
     Ok(())
 }
 
+/// Draw the echo area's rows below the windows. A message that needs more rows
+/// than the layout has made room for yet is cut short until the editor's
+/// `fit_echo_area` lays the windows out again.
+fn draw_echo_lines(
+    device: &mut impl Write,
+    editor: &Editor,
+    theme: &CachedTheme,
+) -> Result<(), std::io::Error> {
+    let (x, y) = echo_area_position(&editor.frame);
+    // The frame's rows plus the echo area's own first row
+    let rows_below = (editor.frame.rows + ECHO_AREA_HEIGHT).saturating_sub(y);
+    let lines = editor.echo_lines();
+    for row in 0..rows_below {
+        queue!(
+            device,
+            cursor::MoveTo(x, y + row),
+            Clear(ClearType::CurrentLine)
+        )?;
+        if let Some(line) = lines.get(row as usize) {
+            queue!(
+                device,
+                Print(line.as_str().with(theme.fg_color).on(theme.bg_color))
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Resolves when the editor's queued after-change hooks finish a batch; never
+/// resolves if there is no Julia runtime.
+async fn after_change_completed(editor: &Editor) {
+    match editor.after_change_queue {
+        Some(ref queue) => queue.completed().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Waits until the command running in the background finishes, or forever
+/// if there isn't one.
+async fn command_finished(editor: &Editor) {
+    match editor.running_command {
+        Some(ref command) => command.finished().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Sleeps for `duration`, or forever if there is nothing to wait for.
+async fn sleep_for(duration: Option<Duration>) {
+    match duration {
+        Some(duration) => tokio::time::sleep(duration).await,
+        None => std::future::pending().await,
+    }
+}
+
 pub async fn event_loop_with_renderer<W: Write>(
     renderer: &mut TerminalRenderer<W>,
     editor: &mut Editor,
 ) -> Result<(), std::io::Error> {
     let mut event_stream = EventStream::new();
     let mut echo_timer = interval(Duration::from_millis(500)); // Check every 500ms
+    let mut file_poll_timer = interval(EVENT_DRAIN_INTERVAL); // Fallback for missed wakeups
 
     loop {
+        let mut poll_files = false;
+
+        // Hand debounced edits to the Julia thread, and wake up when the next batch is due
+        let next_after_change_flush = editor.flush_after_changes();
+        // Wake up to read the menu preview once the selection settles
+        let next_menu_preview = editor.menu_preview_due();
+        // Wake up to catch the modeline word count up once typing pauses
+        let next_word_count = editor.word_count_due();
+        // Wake up to list the keys that can follow a chord left pending
+        let next_key_hints = editor.key_hints_due();
+
         // Get the next event asynchronously
         let event = select! {
             event = event_stream.next().fuse() => {
@@ -1396,9 +1628,24 @@ pub async fn event_loop_with_renderer<W: Write>(
                 }
             }
             _ = echo_timer.tick().fuse() => None, // Timer tick, check for expired echo
+            _ = after_change_completed(editor).fuse() => None, // Highlighting updated
+            _ = command_finished(editor).fuse() => None, // Background command done
+            _ = sleep_for(next_after_change_flush).fuse() => None, // Debounced edits due
+            _ = sleep_for(next_menu_preview).fuse() => None, // Menu preview due
+            _ = sleep_for(next_word_count).fuse() => None, // Word count due
+            _ = sleep_for(next_key_hints).fuse() => None, // Key hints due
+            _ = editor.file_watcher.changed().fuse() => {
+                poll_files = true;
+                None
+            }
+            _ = file_poll_timer.tick().fuse() => {
+                poll_files = true;
+                None
+            }
         };
 
-        // Always poll for file changes and expired echo (every event, not just timer)
+        // Check expired echo and finished highlighting on every wakeup; file changes
+        // only when the watcher or its timer says so
         {
             let mut needs_redraw = false;
 
@@ -1407,36 +1654,80 @@ pub async fn event_loop_with_renderer<W: Write>(
                 needs_redraw = true;
             }
 
-            // Poll for external file changes
-            let file_change_actions = editor.poll_file_changes();
+            // Read the menu preview if the selection has settled
+            if editor.load_menu_preview() {
+                needs_redraw = true;
+            }
+
+            // Show the word count once typing has paused
+            if editor.refresh_word_counts() {
+                needs_redraw = true;
+            }
+
+            // List the keys that can follow a pending chord in the echo area
+            if editor.load_key_hints() {
+                needs_redraw = true;
+            }
+
+            // Redraw with fresh highlighting once queued after-change hooks finish
+            if editor.take_after_change_completion() {
+                if editor.collect_julia_errors() {
+                    echo(&mut renderer.device, editor, &renderer.theme)?;
+                }
+                needs_redraw = true;
+            }
+
+            // Drain external file changes
+            let file_change_actions = if poll_files {
+                editor.poll_file_changes()
+            } else {
+                Vec::new()
+            };
             if !file_change_actions.is_empty() {
                 for action in file_change_actions {
                     match action {
                         roe_core::editor::ChromeAction::Echo(msg) => {
-                            editor.set_echo_message(msg.clone());
-                            echo(&mut renderer.device, editor, &msg, &renderer.theme)?;
+                            editor.set_echo_message(msg);
+                            echo(&mut renderer.device, editor, &renderer.theme)?;
                         }
                         roe_core::editor::ChromeAction::MarkDirty(_) => {
                             needs_redraw = true;
                         }
+                        roe_core::editor::ChromeAction::BufferChanged {
+                            buffer_id,
+                            start,
+                            old_end,
+                            new_end,
+                        } => {
+                            editor.buffer_reloaded(buffer_id, start, old_end, new_end);
+                        }
                         _ => {}
                     }
                 }
             }
 
+            // A message that came or went may have resized the echo area
+            if editor.fit_echo_area().await {
+                needs_redraw = true;
+            }
+
             if needs_redraw {
                 renderer.render_full(editor)?;
             }
         }
 
+        // The actions of a background command that has finished are handled
+        // like a key's
+        let finished_actions = editor.take_command_result().unwrap_or_default();
+
         // Handle timer tick - just continue to next iteration
-        if event.is_none() {
+        if event.is_none() && finished_actions.is_empty() {
             continue;
         }
 
-        let event = event.expect("Event stream should provide valid events");
         let keys = match event {
-            Event::Key(keystroke) => {
+            None => vec![],
+            Some(Event::Key(keystroke)) => {
                 let key = crossterm_key_translate(&keystroke.code, keystroke.modifiers);
 
                 let mut keys = vec![];
@@ -1459,15 +1750,17 @@ pub async fn event_loop_with_renderer<W: Write>(
                 keys.push(key);
                 keys
             }
-            Event::Resize(width, height) => {
+            Some(Event::Resize(width, height)) => {
                 // Handle terminal resize event - subtract echo area height
-                editor.handle_resize(width, height.saturating_sub(ECHO_AREA_HEIGHT));
+                editor
+                    .handle_resize(width, height.saturating_sub(ECHO_AREA_HEIGHT))
+                    .await;
                 // Trigger full screen redraw
                 renderer.mark_dirty(DirtyRegion::FullScreen);
                 // No keys to process for resize event
                 vec![]
             }
-            Event::Mouse(mouse_event) => {
+            Some(Event::Mouse(mouse_event)) => {
                 // Handle mouse events for window resizing
                 handle_mouse_event(editor, renderer, mouse_event).await;
                 // No keys to process for mouse events
@@ -1478,19 +1771,18 @@ pub async fn event_loop_with_renderer<W: Write>(
 
         // Display the keys pressed in echo with - between, using as_display_string, but only if there's
         // modifiers in play
-        let mut actions: std::collections::VecDeque<_> = if keys.is_empty() {
-            // No keys to process (e.g., mouse events, resize events)
-            std::collections::VecDeque::new()
-        } else {
-            editor.key_event(keys).await?.into()
-        };
+        let mut actions: std::collections::VecDeque<_> = finished_actions.into();
+        if !keys.is_empty() {
+            // No keys to process for e.g. mouse events, resize events
+            actions.extend(editor.key_event(keys).await?);
+        }
 
         while let Some(action) = actions.pop_front() {
             match action {
                 ChromeAction::Echo(message) => {
                     // Set the echo message in the editor and render it
-                    editor.set_echo_message(message.clone());
-                    echo(&mut renderer.device, editor, &message, &renderer.theme)?;
+                    editor.set_echo_message(message);
+                    echo(&mut renderer.device, editor, &renderer.theme)?;
                 }
 
                 ChromeAction::OpenFile(_) => {}
@@ -1559,7 +1851,7 @@ pub async fn event_loop_with_renderer<W: Write>(
                     // Buffer operations are handled in Editor::process_chrome_actions
                     // This case should not be reached, but we handle it for completeness
                 }
-                ChromeAction::DumpMessages(_) => {
+                ChromeAction::DumpMessages(_) | ChromeAction::WriteFile(_) => {
                     // Handled in Editor::process_chrome_actions
                 }
                 ChromeAction::BufferChanged {
@@ -1568,40 +1860,15 @@ pub async fn event_loop_with_renderer<W: Write>(
                     old_end,
                     new_end,
                 } => {
-                    // Call major mode after-change hook for syntax highlighting
-                    let Some(buffer) = editor.buffers.get(buffer_id) else {
-                        continue;
-                    };
-                    let Some(major_mode) = buffer.major_mode() else {
-                        continue;
-                    };
-                    let Some(ref julia_runtime) = editor.julia_runtime else {
-                        continue;
-                    };
-
-                    roe_core::julia_runtime::set_current_buffer(buffer.clone());
-                    let runtime = julia_runtime.lock().await;
-                    let _ = runtime
-                        .call_major_mode_after_change(
-                            &major_mode,
-                            start as i64,
-                            old_end as i64,
-                            new_end as i64,
-                        )
-                        .await;
-                    roe_core::julia_runtime::clear_current_buffer();
+                    // Updates gutter line tracking, then re-highlights on the Julia
+                    // thread after a debounce; we redraw when it completes
+                    editor.buffer_changed(buffer_id, start, old_end, new_end);
                 }
                 ChromeAction::ExecuteCommand(command_name) => {
                     // Execute another command via the command registry
                     let context = editor.create_command_context();
                     if editor.julia_runtime.is_some() {
-                        match roe_core::command_mode::CommandMode::execute_command(
-                            &command_name,
-                            &editor.command_registry,
-                            context,
-                        )
-                        .await
-                        {
+                        match editor.run_command(&command_name, context).await {
                             Ok(command_actions) => {
                                 // Process through editor to handle BufferOps etc.
                                 let processed = editor.process_chrome_actions(command_actions);
@@ -1617,16 +1884,100 @@ pub async fn event_loop_with_renderer<W: Write>(
                 }
                 ChromeAction::FileWatcherStatus => {
                     let status = editor.file_watcher.status();
-                    editor.set_echo_message(status.clone());
-                    echo(&mut renderer.device, editor, &status, &renderer.theme)?;
+                    editor.set_echo_message(status);
+                    echo(&mut renderer.device, editor, &renderer.theme)?;
                 }
-                ChromeAction::ISearchForward | ChromeAction::ISearchBackward => {
+                ChromeAction::ISearchForward
+                | ChromeAction::ISearchBackward
+                | ChromeAction::ShowJuliaErrors
+                | ChromeAction::DescribeBindingConflicts
+                | ChromeAction::QuotedInsert
+                | ChromeAction::InsertChar
+                | ChromeAction::Goto(_)
+                | ChromeAction::BindKey(_)
+                | ChromeAction::Prompt { .. }
+                | ChromeAction::ReadRegister(_)
+                | ChromeAction::CopyName(_)
+                | ChromeAction::CommandPalette
+                | ChromeAction::ToggleWrap(_)
+                | ChromeAction::NextConflict
+                | ChromeAction::PreviousConflict
+                | ChromeAction::ResolveConflict(_)
+                | ChromeAction::CommentRegion
+                | ChromeAction::UncommentRegion
+                | ChromeAction::Tabify
+                | ChromeAction::Untabify
+                | ChromeAction::DeleteTrailingWhitespace
+                | ChromeAction::JoinLine
+                | ChromeAction::DuplicateLine
+                | ChromeAction::CaseRegion(_)
+                | ChromeAction::CaseWord(_)
+                | ChromeAction::FillParagraph
+                | ChromeAction::SetFillColumn
+                | ChromeAction::KillRectangle
+                | ChromeAction::YankRectangle
+                | ChromeAction::YankPop
+                | ChromeAction::BrowseKillRing
+                | ChromeAction::NarrowToRegion
+                | ChromeAction::Widen
+                | ChromeAction::SaveAllBuffers
+                | ChromeAction::SaveSomeBuffers
+                | ChromeAction::ToggleReadOnly
+                | ChromeAction::SetBufferFileEol
+                | ChromeAction::RevertBufferWithEncoding
+                | ChromeAction::Bookmark(_)
+                | ChromeAction::SetBookmark(_)
+                | ChromeAction::OpenRecent
+                | ChromeAction::GotoMatchingParen
+                | ChromeAction::NextBuffer
+                | ChromeAction::PreviousBuffer
+                | ChromeAction::WinnerUndo
+                | ChromeAction::WinnerRedo
+                | ChromeAction::BalanceWindows
+                | ChromeAction::ResizeWindow { .. } => {
                     // Handled in Editor::process_chrome_actions
                 }
+                ChromeAction::ToggleMouse => {
+                    renderer.mouse_captured = !renderer.mouse_captured;
+                    let message = if renderer.mouse_captured {
+                        queue!(renderer.device, crossterm::event::EnableMouseCapture)?;
+                        "Mouse capture on"
+                    } else {
+                        queue!(renderer.device, crossterm::event::DisableMouseCapture)?;
+                        "Mouse capture off; the terminal's own selection works now"
+                    }
+                    .to_string();
+                    editor.set_echo_message(message);
+                    echo(&mut renderer.device, editor, &renderer.theme)?;
+                }
+                ChromeAction::ReloadConfig => {
+                    // Keep the previous theme if the reload fails
+                    let message = match editor.reload_config().await {
+                        Ok(message) => {
+                            renderer.theme = load_julia_theme(editor).await;
+                            renderer.mark_dirty(DirtyRegion::FullScreen);
+                            message
+                        }
+                        Err(error_msg) => error_msg,
+                    };
+                    editor.set_echo_message(message);
+                    echo(&mut renderer.device, editor, &renderer.theme)?;
+                }
             }
         }
 
+        // Surface any exceptions raised by Julia commands or hooks
+        if editor.collect_julia_errors() {
+            echo(&mut renderer.device, editor, &renderer.theme)?;
+        }
+
+        // Make room for the echo message, or give it back to the windows
+        if editor.fit_echo_area().await {
+            renderer.mark_dirty(DirtyRegion::FullScreen);
+        }
+
         // Render any dirty regions
+        renderer.mark_modified_modelines(editor);
         renderer.render_incremental(editor)?;
         renderer.clear_dirty();
     }
@@ -1644,6 +1995,46 @@ fn draw_command_window(
     // Just draw the command window like a normal window with dark blue background
     // The buffer content will handle showing the completions and highlighting
     draw_window(device, editor, window, theme)?;
+    draw_menu_preview(device, editor, window_id, theme)?;
+
+    Ok(())
+}
+
+/// Draw the highlighted menu entry's preview over the right half of a command window
+fn draw_menu_preview(
+    device: &mut impl Write,
+    editor: &Editor,
+    window_id: WindowId,
+    theme: &CachedTheme,
+) -> Result<(), std::io::Error> {
+    let window = &editor.windows[window_id];
+    let Some(lines) = editor.menu_preview_lines(window_id) else {
+        return Ok(());
+    };
+
+    let content_x = window.x + 1;
+    let content_y = window.y + 1;
+    let content_width = window.width_chars.saturating_sub(2);
+    let content_height = window.height_chars.saturating_sub(WINDOW_CHROME_ROWS);
+    let Some((separator, text_width)) = preview_pane(content_width as usize) else {
+        return Ok(());
+    };
+
+    let separator_x = content_x + separator as u16;
+    for row in 0..content_height {
+        let line = lines.get(row as usize).map(String::as_str).unwrap_or("");
+        let text: String = line.chars().take(text_width).collect();
+        queue!(
+            device,
+            cursor::MoveTo(separator_x, content_y + row),
+            Print(BORDER_VERTICAL.with(theme.border_color).on(theme.bg_color)),
+            Print(
+                format!(" {text:<text_width$}")
+                    .with(GUTTER_FG_COLOR)
+                    .on(theme.bg_color)
+            )
+        )?;
+    }
 
     Ok(())
 }
@@ -1657,7 +2048,7 @@ async fn handle_mouse_event<W: Write>(
     match mouse_event.kind {
         MouseEventKind::Down(MouseButton::Left) => {
             if let Some((border_info, target_window)) =
-                detect_border_click(editor, mouse_event.column, mouse_event.row)
+                editor.border_at(mouse_event.column, mouse_event.row)
             {
                 editor.mouse_drag_state = Some(MouseDragState {
                     drag_type: DragType::WindowBorder,
@@ -1710,10 +2101,7 @@ async fn handle_mouse_event<W: Write>(
             };
 
             let new_pos = (mouse_event.column, mouse_event.row);
-            let dx = new_pos.0 as i32 - drag_state.last_pos.0 as i32;
-            let dy = new_pos.1 as i32 - drag_state.last_pos.1 as i32;
-
-            if dx == 0 && dy == 0 {
+            if new_pos == drag_state.last_pos {
                 return;
             }
 
@@ -1726,13 +2114,14 @@ async fn handle_mouse_event<W: Write>(
                 return;
             };
 
-            update_window_resize_incremental(
-                editor,
-                drag_state.target_window,
-                &border_info,
-                dx,
-                dy,
-            );
+            // Measured from where the drag started so the border stays under
+            // the pointer, even after being held at a window's minimum size
+            let offset = if border_info.is_vertical {
+                new_pos.0 as i32 - drag_state.start_pos.0 as i32
+            } else {
+                new_pos.1 as i32 - drag_state.start_pos.1 as i32
+            };
+            editor.drag_border(&border_info, offset);
             renderer.mark_dirty(DirtyRegion::FullScreen);
         }
         MouseEventKind::Up(MouseButton::Left) => {
@@ -1787,159 +2176,6 @@ async fn handle_mode_mouse_event(
     None
 }
 
-/// Detect if a mouse click is on a window border
-fn detect_border_click(editor: &Editor, x: u16, y: u16) -> Option<(BorderInfo, WindowId)> {
-    // Check all windows to see if the click is on a border
-    for (window_id, window) in &editor.windows {
-        // Check if click is on window borders
-        let left_border = window.x;
-        let right_border = window.x + window.width_chars - 1;
-        let top_border = window.y;
-        let bottom_border = window.y + window.height_chars - 1;
-
-        // Check vertical borders (left and right sides)
-        if (x == left_border || x == right_border) && y >= top_border && y <= bottom_border {
-            // This is a vertical border
-            if let Some(split_info) = find_split_for_border(editor, window_id, true) {
-                return Some((
-                    BorderInfo {
-                        is_vertical: true,
-                        split_node_path: split_info.0,
-                        original_ratio: split_info.1,
-                    },
-                    window_id,
-                ));
-            }
-        }
-
-        // Check horizontal borders (top and bottom sides)
-        if (y == top_border || y == bottom_border) && x >= left_border && x <= right_border {
-            // This is a horizontal border
-            if let Some(split_info) = find_split_for_border(editor, window_id, false) {
-                return Some((
-                    BorderInfo {
-                        is_vertical: false,
-                        split_node_path: split_info.0,
-                        original_ratio: split_info.1,
-                    },
-                    window_id,
-                ));
-            }
-        }
-    }
-
-    None
-}
-
-/// Find the split node that controls the given border
-fn find_split_for_border(
-    editor: &Editor,
-    window_id: WindowId,
-    is_vertical_border: bool,
-) -> Option<(Vec<usize>, f32)> {
-    // This is a simplified implementation
-    // In a real implementation, we would traverse the window tree to find the exact split node
-    // For now, we'll return a placeholder that works with simple two-window splits
-
-    // Find if this window has a sibling that shares the border
-    for (other_window_id, other_window) in &editor.windows {
-        if other_window_id == window_id {
-            continue;
-        }
-
-        let window = &editor.windows[window_id];
-
-        if is_vertical_border {
-            // Check if windows are horizontally adjacent
-            if (window.x + window.width_chars == other_window.x
-                || other_window.x + other_window.width_chars == window.x)
-                && window.y < other_window.y + other_window.height_chars
-                && other_window.y < window.y + window.height_chars
-            {
-                return Some((vec![0], 0.5)); // Simplified path and ratio
-            }
-        } else {
-            // Check if windows are vertically adjacent
-            if (window.y + window.height_chars == other_window.y
-                || other_window.y + other_window.height_chars == window.y)
-                && window.x < other_window.x + other_window.width_chars
-                && other_window.x < window.x + window.width_chars
-            {
-                return Some((vec![0], 0.5)); // Simplified path and ratio
-            }
-        }
-    }
-
-    None
-}
-
-/// Update window layout based on incremental mouse drag
-fn update_window_resize_incremental(
-    editor: &mut Editor,
-    target_window_id: Option<WindowId>,
-    border_info: &BorderInfo,
-    dx: i32,
-    dy: i32,
-) {
-    // Use incremental changes with much finer granularity
-    if let Some(_target_window_id) = target_window_id {
-        // Use a sensitivity factor to make resizing smoother
-        // Each pixel of mouse movement = 0.5% ratio change (adjustable)
-        const SENSITIVITY: f32 = 0.005;
-
-        // Calculate the incremental ratio change
-        if border_info.is_vertical && dx != 0 {
-            // For vertical borders, adjust the split ratio based on horizontal movement
-            let ratio_change = dx as f32 * SENSITIVITY;
-            adjust_window_tree_ratio_incremental(&mut editor.window_tree, ratio_change, true);
-        } else if !border_info.is_vertical && dy != 0 {
-            // For horizontal borders, adjust the split ratio based on vertical movement
-            let ratio_change = dy as f32 * SENSITIVITY;
-            adjust_window_tree_ratio_incremental(&mut editor.window_tree, ratio_change, false);
-        }
-
-        // Recalculate layout to apply the new ratios
-        editor.calculate_window_layout();
-    }
-}
-
-/// Recursively adjust window tree ratios for incremental resizing
-fn adjust_window_tree_ratio_incremental(
-    node: &mut roe_core::editor::WindowNode,
-    ratio_change: f32,
-    is_vertical: bool,
-) {
-    use roe_core::editor::{SplitDirection, WindowNode};
-
-    match node {
-        WindowNode::Leaf { .. } => {
-            // Nothing to adjust for leaf nodes
-        }
-        WindowNode::Split {
-            direction,
-            ratio,
-            first,
-            second,
-        } => {
-            // Only adjust if the split direction matches the resize direction
-            let should_adjust = match direction {
-                SplitDirection::Vertical => is_vertical,
-                SplitDirection::Horizontal => !is_vertical,
-            };
-
-            if should_adjust {
-                // Adjust the ratio incrementally, keeping it within bounds
-                // Use tighter bounds to prevent extreme layouts
-                *ratio = (*ratio + ratio_change).clamp(0.15, 0.85);
-            } else {
-                // Recurse into child nodes
-                adjust_window_tree_ratio_incremental(first, ratio_change, is_vertical);
-                adjust_window_tree_ratio_incremental(second, ratio_change, is_vertical);
-            }
-        }
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1950,6 +2186,24 @@ mod tests {
         assert!(!renderer.dirty_tracker.is_full_screen_dirty());
     }
 
+    #[test]
+    fn test_theme_overrides_gutter_colors() {
+        let mut theme = CachedTheme::default();
+        assert_eq!(theme.gutter_modified_color, GUTTER_MODIFIED_COLOR);
+
+        assert!(theme.set_gutter_color("gutter-modified", "#ff8800"));
+        assert_eq!(
+            theme.gutter_modified_color,
+            Color::Rgb {
+                r: 0xff,
+                g: 0x88,
+                b: 0x00
+            }
+        );
+        assert_eq!(theme.gutter_saved_color, GUTTER_SAVED_COLOR);
+        assert!(!theme.set_gutter_color("background", "#ff8800"));
+    }
+
     #[test]
     fn test_mark_dirty_functionality() {
         let output = Vec::new();
@@ -13,11 +13,11 @@
 
 //! Roe editor with Vello/GPU rendering backend.
 
+use roe_core::clipboard::SystemClipboard;
+use roe_core::file_location::{FileLocation, FilePosition};
 use roe_core::{
-    buffer_host, command_registry, editor,
-    julia_runtime::{clear_current_buffer, set_current_buffer},
-    kill_ring, mode, Buffer, BufferId, ConfigurableBindings, Editor, Frame, KeyState, Mode, ModeId,
-    Window, WindowId,
+    buffer_host, command_registry, editor, kill_ring, mode, Buffer, BufferId, ConfigurableBindings,
+    Editor, Frame, KeyState, Mode, ModeId, Window, WindowId,
 };
 use slotmap::SlotMap;
 use std::collections::HashMap;
@@ -29,8 +29,9 @@ const DEFAULT_LINES: u16 = 40;
 /// Parse command line arguments
 fn parse_args() -> EditorConfig {
     let args: Vec<String> = std::env::args().collect();
-    let mut file_paths = Vec::new();
+    let mut files = Vec::new();
     let mut init_file = None;
+    let mut split = false;
     let mut i = 1; // Skip program name
 
     while i < args.len() {
@@ -44,6 +45,10 @@ fn parse_args() -> EditorConfig {
                     std::process::exit(1);
                 }
             }
+            "--split" | "-s" => {
+                split = true;
+                i += 1;
+            }
             "--help" | "-h" => {
                 print_help();
                 std::process::exit(0);
@@ -54,15 +59,16 @@ fn parse_args() -> EditorConfig {
                 std::process::exit(1);
             }
             _ => {
-                file_paths.push(args[i].clone());
+                files.push(FileLocation::resolve(&args[i]));
                 i += 1;
             }
         }
     }
 
     EditorConfig {
-        file_paths,
+        files,
         init_file,
+        split,
     }
 }
 
@@ -74,11 +80,13 @@ fn print_help() {
     println!();
     println!("OPTIONS:");
     println!("    -i, --init <FILE>    Specify Julia init file (default: init.jl)");
+    println!("    -s, --split          Show each file in a window of its own");
     println!("    -h, --help           Print this help message");
     println!();
     println!("EXAMPLES:");
     println!("    roe-vello                      # Start with welcome screen");
     println!("    roe-vello file.txt             # Open file.txt");
+    println!("    roe-vello src/main.rs:120      # Open at line 120");
     println!("    roe-vello --init myconfig.jl   # Use custom init file");
 }
 
@@ -108,8 +116,11 @@ fn create_welcome_screen_content() -> String {
 }
 
 struct EditorConfig {
-    file_paths: Vec<String>,
+    /// Files to open, each at a line and column if given
+    files: Vec<FileLocation>,
     init_file: Option<String>,
+    /// Tile the files into windows, not just the first
+    split: bool,
 }
 
 async fn create_editor(config: EditorConfig) -> Editor {
@@ -153,6 +164,8 @@ async fn create_editor(config: EditorConfig) -> Editor {
             eprintln!("[roe-vello] Make sure to run from the roe directory or install properly.");
             std::process::exit(1);
         }
+        // The defaults, before the user config has a chance to override them
+        let default_bindings = runtime.list_keybindings().await.unwrap_or_default();
         drop(runtime);
 
         // Load user config
@@ -170,9 +183,7 @@ async fn create_editor(config: EditorConfig) -> Editor {
                     "[roe-vello] Loaded {} keybindings from Julia",
                     julia_bindings.len()
                 );
-                for (key_seq, action) in julia_bindings {
-                    bindings.add_binding(&key_seq, &action);
-                }
+                bindings.add_layered_bindings(&default_bindings, &julia_bindings);
             }
             Err(e) => {
                 eprintln!("[roe-vello] Warning: Failed to query keybindings: {e}");
@@ -183,11 +194,38 @@ async fn create_editor(config: EditorConfig) -> Editor {
 
     let mut buffers: SlotMap<BufferId, Buffer> = SlotMap::default();
     let mut buffer_hosts: HashMap<BufferId, buffer_host::BufferHostClient> = HashMap::new();
+    let mut file_positions: HashMap<BufferId, FilePosition> = HashMap::new();
     let mut modes: SlotMap<ModeId, Box<dyn Mode>> = SlotMap::default();
 
     let mut first_buffer_id = None;
+    let mut file_buffer_ids = Vec::new();
+
+    // With no files given, start in the welcome screen or in *scratch*
+    // (roe_config["startup"]["buffer"] = "welcome" | "scratch")
+    let startup_buffer = match julia_runtime {
+        Some(ref jr) => {
+            jr.lock()
+                .await
+                .get_config_string("startup.buffer", "welcome")
+                .await
+        }
+        None => "welcome".to_string(),
+    };
+
+    // Files given are buffers behind the first unless tiled into windows
+    // (roe_config["startup"]["split"] = true, or --split)
+    let split = config.split
+        || match julia_runtime {
+            Some(ref jr) => {
+                jr.lock()
+                    .await
+                    .get_config_bool("startup.split", false)
+                    .await
+            }
+            None => false,
+        };
 
-    if config.file_paths.is_empty() {
+    if config.files.is_empty() && startup_buffer != "scratch" {
         // No files specified, create welcome screen buffer
         let welcome_mode = Box::new(mode::MessagesMode {});
         let welcome_mode_id = modes.insert(welcome_mode);
@@ -195,6 +233,7 @@ async fn create_editor(config: EditorConfig) -> Editor {
         let buffer = Buffer::new(&[welcome_mode_id]);
         buffer.set_object("*Welcome*".to_string());
         buffer.load_str(&create_welcome_screen_content());
+        buffer.set_read_only(true);
 
         let buffer_id = buffers.insert(buffer.clone());
         first_buffer_id = Some(buffer_id);
@@ -209,7 +248,11 @@ async fn create_editor(config: EditorConfig) -> Editor {
         buffer_hosts.insert(buffer_id, buffer_client);
     } else {
         // Create buffers for all specified files
-        for file_path in config.file_paths {
+        for FileLocation {
+            path: file_path,
+            position,
+        } in config.files
+        {
             let file_mode = Box::new(mode::FileMode {
                 file_path: file_path.clone(),
             });
@@ -217,11 +260,15 @@ async fn create_editor(config: EditorConfig) -> Editor {
 
             let buffer = match Buffer::from_file(&file_path, &[file_mode_id]).await {
                 Ok(buffer) => buffer,
-                Err(_) => {
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
                     let buffer = Buffer::new(&[file_mode_id]);
                     buffer.set_object(file_path.clone());
                     buffer
                 }
+                Err(e) => {
+                    eprintln!("Error: Can't open {file_path}: {e}");
+                    std::process::exit(1);
+                }
             };
 
             // Get and apply major mode for this file
@@ -231,14 +278,18 @@ async fn create_editor(config: EditorConfig) -> Editor {
                     buffer.set_major_mode(major_mode.clone());
 
                     // Call the major mode's init hook
-                    set_current_buffer(buffer.clone());
-                    let _ = runtime.call_major_mode_init(&major_mode).await;
-                    clear_current_buffer();
+                    let _ = runtime
+                        .call_major_mode_init(&major_mode, buffer.clone())
+                        .await;
                 }
                 drop(runtime);
             }
 
             let buffer_id = buffers.insert(buffer.clone());
+            file_buffer_ids.push(buffer_id);
+            if let Some(position) = position {
+                file_positions.insert(buffer_id, position);
+            }
 
             if first_buffer_id.is_none() {
                 first_buffer_id = Some(buffer_id);
@@ -257,6 +308,33 @@ async fn create_editor(config: EditorConfig) -> Editor {
         }
     }
 
+    // Always have a *scratch* buffer to jot in, so `C-x b scratch` works
+    let scratch_mode_id = modes.insert(Box::new(mode::ScratchMode {}));
+    let scratch_buffer = mode::scratch_buffer(scratch_mode_id);
+    if let Some(ref jr) = julia_runtime {
+        let runtime = jr.lock().await;
+        let _ = runtime
+            .call_major_mode_init(mode::SCRATCH_MAJOR_MODE, scratch_buffer.clone())
+            .await;
+        drop(runtime);
+    }
+    let scratch_buffer_id = buffers.insert(scratch_buffer.clone());
+    if first_buffer_id.is_none() {
+        first_buffer_id = Some(scratch_buffer_id);
+    }
+
+    let scratch_mode = modes
+        .remove(scratch_mode_id)
+        .expect("ScratchMode should exist in modes SlotMap");
+    let mode_list = vec![(scratch_mode_id, "scratch".to_string(), scratch_mode)];
+    let (buffer_client, _buffer_handle) = buffer_host::create_buffer_host(
+        scratch_buffer,
+        mode_list,
+        scratch_buffer_id,
+        julia_runtime.clone(),
+    );
+    buffer_hosts.insert(scratch_buffer_id, buffer_client);
+
     // Create single window (Vello will resize it properly)
     let mut windows: SlotMap<WindowId, Window> = SlotMap::default();
 
@@ -279,6 +357,12 @@ async fn create_editor(config: EditorConfig) -> Editor {
     let mut file_watcher = roe_core::file_watcher::FileWatcher::new();
     let _ = file_watcher.init(); // Ignore errors for now
 
+    // Major mode after-change hooks are queued rather than awaited on the runtime lock
+    let after_change_queue = match julia_runtime {
+        Some(ref jr) => Some(jr.lock().await.after_change_queue()),
+        None => None,
+    };
+
     let mut editor = Editor {
         frame: Frame::new(DEFAULT_COLS, DEFAULT_LINES),
         buffers,
@@ -291,18 +375,58 @@ async fn create_editor(config: EditorConfig) -> Editor {
         bindings: Box::new(bindings),
         window_tree,
         kill_ring: kill_ring::KillRing::new(),
+        last_yank: None,
+        killed_rectangle: Vec::new(),
         command_registry: command_registry::create_default_registry(),
         buffer_history: Vec::new(),
         echo_message: String::new(),
         echo_message_time: None,
         current_key_chord: Vec::new(),
+        key_hints: roe_core::editor::KeyHints::default(),
         mouse_drag_state: None,
         messages_buffer_id: None,
+        julia_errors_buffer_id: None,
         julia_runtime,
+        after_change_queue,
+        pending_after_changes: HashMap::new(),
         file_watcher,
         last_search_term: String::new(),
+        menu_max_visible_items: roe_core::selection_menu::DEFAULT_MAX_VISIBLE_ITEMS,
+        menu_preview: None,
+        buffer_views: HashMap::new(),
+        collapsed_layout: None,
+        winner: roe_core::editor::WinnerHistory::default(),
+        minibuffer_history: roe_core::minibuffer_history::MinibufferHistory::default(),
+        pending_prompt: None,
+        running_command: None,
+        echo_max_lines: roe_core::editor::DEFAULT_ECHO_MAX_LINES,
+        echo_scroll: 0,
+        scroll_page_overlap: roe_core::editor::DEFAULT_PAGE_OVERLAP,
+        scroll_step: 0,
+        scroll_margin: 0,
+        goal_column: None,
+        recenter_position: None,
+        buffer_cycle: None,
+        bookmarks: roe_core::bookmarks::Bookmarks::default(),
+        recent_files: roe_core::recent_files::RecentFiles::default(),
+        keyboard_macro: roe_core::keyboard_macro::KeyboardMacro::new(),
+        prefix_arg: None,
+        registers: HashMap::new(),
+        register_prompt: None,
+        delete_trailing_whitespace_on_save: false,
+        auto_revert: false,
+        fill_column: roe_core::fill::DEFAULT_FILL_COLUMN,
+        quoted_insert: None,
+        word_counts: HashMap::new(),
+        tree_sitter_highlighters: HashMap::new(),
+        conflict_cache: roe_core::conflict::ConflictCache::default(),
     };
 
+    // Kills go to the system clipboard, and text copied elsewhere can be yanked
+    if let Some(clipboard) = SystemClipboard::new() {
+        editor.kill_ring.set_clipboard(clipboard);
+    }
+
     // Initialize buffer history
     editor.record_buffer_access(active_buffer);
 
@@ -324,6 +448,27 @@ async fn create_editor(config: EditorConfig) -> Editor {
         command_registry::register_julia_commands(&mut editor.command_registry, julia_runtime)
             .await;
     }
+    editor.load_menu_config().await;
+    editor.load_echo_config().await;
+    editor.load_scroll_config().await;
+    editor.load_editor_config().await;
+    editor.load_bookmarks();
+    editor.load_recent_files();
+    editor.record_recent_files(&file_buffer_ids);
+    editor.warn_binding_conflicts();
+
+    editor.show_startup_buffers(&file_buffer_ids, split);
+
+    // Put the cursor at the line given with each file shown, once the scroll
+    // margin is known
+    let window_ids: Vec<WindowId> = editor.windows.keys().collect();
+    for window_id in window_ids {
+        if let Some(&position) = file_positions.get(&editor.windows[window_id].active_buffer) {
+            editor.goto_file_position(window_id, position);
+        }
+    }
+    // Pending-chord hints get a panel of their own over the windows
+    editor.key_hints.in_echo_area = false;
 
     editor
 }
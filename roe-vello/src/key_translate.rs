@@ -54,6 +54,55 @@ pub fn translate_key_event(event: &KeyEvent, modifiers: ModifiersState) -> Vec<L
     keys
 }
 
+/// Text a key press types that a single LogicalKey can't carry: several
+/// characters at once (a dead key that didn't combine, a compose sequence), or,
+/// on Windows, an AltGr character, which is reported with Ctrl+Alt held.
+/// Ordinary single-character keys return `None` so they still go through the
+/// keybindings.
+pub fn typed_text(event: &KeyEvent, modifiers: ModifiersState) -> Option<String> {
+    let text = event.text.as_ref()?;
+    if text.is_empty() || text.chars().any(char::is_control) {
+        return None;
+    }
+
+    let altgr = cfg!(target_os = "windows") && modifiers.control_key() && modifiers.alt_key();
+    if altgr {
+        return Some(text.to_string());
+    }
+    if modifiers.control_key() || modifiers.alt_key() || modifiers.super_key() {
+        return None;
+    }
+
+    match &event.logical_key {
+        Key::Character(s) if s.chars().count() == 1 && s.as_str() == text.as_str() => None,
+        _ if text.chars().count() > 1 => Some(text.to_string()),
+        _ => None,
+    }
+}
+
+/// The accent a dead key is waiting to combine with the next key, if this is one
+pub fn dead_key(event: &KeyEvent) -> Option<char> {
+    match event.logical_key {
+        Key::Dead(accent) => Some(accent.unwrap_or('?')),
+        _ => None,
+    }
+}
+
+/// Whether this is a press of a modifier key on its own
+pub fn is_modifier_key(event: &KeyEvent) -> bool {
+    matches!(
+        event.logical_key,
+        Key::Named(
+            NamedKey::Shift
+                | NamedKey::Control
+                | NamedKey::Alt
+                | NamedKey::AltGraph
+                | NamedKey::Super
+                | NamedKey::Meta
+        )
+    )
+}
+
 /// Translate a winit Key to a LogicalKey
 fn translate_key(key: &Key) -> LogicalKey {
     match key {
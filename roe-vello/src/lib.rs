@@ -23,19 +23,26 @@ mod theme;
 
 pub use renderer::VelloRenderer;
 pub use text::StyledSpan;
-pub use theme::VelloTheme;
+pub use theme::{CursorShape, VelloTheme};
 
+use roe_core::buffer::{clip_wide_chars, expand_tabs, whitespace_marks, WhitespaceMark};
 use roe_core::editor::{
-    BorderInfo, ChromeAction, DragType, MouseDragState, SplitDirection, WindowNode,
+    key_hint_rows, BorderInfo, ChromeAction, DragType, MouseDragState, WindowType,
 };
+use roe_core::file_watcher::EVENT_DRAIN_INTERVAL;
 use roe_core::gutter::{
-    calculate_gutter_width, format_line_number, get_line_status, GutterConfig, LineStatus,
+    calculate_gutter_width, format_line_number, get_line_status, line_number_column,
+    line_number_width, GutterConfig, LineStatus,
 };
-use roe_core::julia_runtime::face_registry;
+use roe_core::julia_runtime::{face_registry, ConfigValue};
+use roe_core::menu_preview::preview_pane;
 use roe_core::syntax::Color as SyntaxColor;
-use roe_core::{Editor, WindowId};
+use roe_core::tabify::indent_guide_depths;
+use roe_core::window::{wrap_rows, WrapMode, CONTINUATION_MARK, TRUNCATION_MARK};
+use roe_core::{Buffer, Editor, WindowId};
 use std::collections::HashSet;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use text::TextRenderer;
 use vello::kurbo::{Affine, Rect};
 use vello::peniko::Color;
@@ -44,7 +51,7 @@ use vello::wgpu;
 use vello::{AaConfig, RenderParams, RendererOptions, Scene};
 use winit::application::ApplicationHandler;
 use winit::dpi::LogicalSize;
-use winit::event::{ElementState, MouseButton, WindowEvent};
+use winit::event::{ElementState, Ime, MouseButton, MouseScrollDelta, WindowEvent};
 use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
 use winit::keyboard::ModifiersState;
 use winit::window::{CursorIcon, Window};
@@ -72,29 +79,28 @@ fn syntax_color_to_vello(color: &SyntaxColor, default: Color) -> Color {
     }
 }
 
-/// Convert a character position to byte position in a string
-fn char_to_byte(s: &str, char_pos: usize) -> usize {
-    s.char_indices()
-        .nth(char_pos)
-        .map(|(byte_idx, _)| byte_idx)
-        .unwrap_or(s.len())
-}
-
-/// Convert a byte position to character position in a string
-fn byte_to_char(s: &str, byte_pos: usize) -> usize {
-    s[..byte_pos.min(s.len())].chars().count()
-}
-
 /// Scrollbar width in logical pixels
 const SCROLLBAR_WIDTH: f64 = 14.0;
 
-/// Gutter colors
-const GUTTER_BG_COLOR: Color = Color::from_rgba8(0x14, 0x14, 0x14, 0xFF); // Slightly darker than bg
-const GUTTER_FG_COLOR: Color = Color::from_rgba8(0x60, 0x60, 0x60, 0xFF); // Dimmed line numbers
-const GUTTER_SEPARATOR_COLOR: Color = Color::from_rgba8(0x40, 0x40, 0x40, 0xFF);
-const GUTTER_MODIFIED_COLOR: Color = Color::from_rgba8(0xFF, 0xD7, 0x00, 0xFF); // Yellow
-const GUTTER_SAVED_COLOR: Color = Color::from_rgba8(0x00, 0xC8, 0x00, 0xFF); // Green
-const GUTTER_CONFLICT_COLOR: Color = Color::from_rgba8(0xFF, 0x40, 0x40, 0xFF); // Red
+/// Rows of a window that aren't text: top border, modeline and the horizontal
+/// scrollbar. Menu previews cover the same rows as the menu text.
+const WINDOW_CHROME_ROWS: u16 = 3;
+
+/// Lines (or, sideways, columns) scrolled per mouse wheel notch
+const WHEEL_SCROLL_LINES: f64 = 3.0;
+
+/// How often to check for finished after-change hooks or background commands
+/// while any are pending
+const AFTER_CHANGE_POLL_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Longest gap between clicks that still counts them as a double or triple click
+const MULTI_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// Drawn where a tab starts, when whitespace is shown
+const TAB_MARK: &str = "→";
+
+/// Drawn in place of each trailing space, when whitespace is shown
+const TRAILING_SPACE_MARK: &str = "·";
 
 /// Application state for the Vello renderer
 pub struct RoeVelloApp<'a> {
@@ -122,10 +128,120 @@ pub struct RoeVelloApp<'a> {
     mouse_dragging: bool,
     /// Position where mouse drag started (to set mark on first movement)
     drag_start_cursor: Option<usize>,
+    /// Previous click in text, to count double and triple clicks
+    last_click: Option<Click>,
+    /// After a double or triple click, the unit a drag selects by and the
+    /// range first selected, which the selection always keeps
+    drag_selection: Option<(SelectionUnit, (usize, usize))>,
     /// Whether vertical scrollbar is being dragged
     scrollbar_dragging: Option<roe_core::WindowId>,
     /// Whether horizontal scrollbar is being dragged
     hscrollbar_dragging: Option<roe_core::WindowId>,
+    /// Wheel scrolling not yet amounting to a whole (column, line), as
+    /// trackpads report it in pixels
+    wheel_remainder: (f64, f64),
+    /// When to next drain file watcher events
+    next_file_poll: Instant,
+    /// Text being composed at the cursor, not yet in the buffer
+    preedit: Option<Preedit>,
+    /// Where the cursor was last drawn (x, y, line height), so the input
+    /// method can place its candidate window beside it
+    ime_cursor_area: Option<(f64, f64, f64)>,
+}
+
+/// Text being composed: an input method's preedit, or a dead key's accent
+/// waiting for the key it combines with
+struct Preedit {
+    text: String,
+    /// Byte offset of the input method's cursor within `text`
+    cursor: Option<usize>,
+    /// Whether this came from a dead key rather than an input method
+    dead_key: bool,
+}
+
+/// A click in text: when and on which cell, and how many clicks in a row it
+/// makes
+struct Click {
+    at: Instant,
+    cell: (u16, u16),
+    count: u8,
+}
+
+/// What a double or triple click selects, and a drag after it extends by
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SelectionUnit {
+    Word,
+    Line,
+}
+
+impl SelectionUnit {
+    /// Start and end of the word or line at `pos`
+    fn bounds(self, buffer: &Buffer, pos: usize) -> (usize, usize) {
+        match self {
+            SelectionUnit::Word => buffer.word_bounds(pos),
+            SelectionUnit::Line => {
+                let line = buffer.buffer_char_to_line(pos);
+                (
+                    buffer.buffer_line_to_char(line),
+                    buffer.buffer_line_to_char(line + 1),
+                )
+            }
+        }
+    }
+}
+
+/// One row of a window's content area: a whole line, or with wrapping on, the
+/// part of one that fits
+struct ContentRow {
+    /// Buffer line shown
+    line: usize,
+    /// Buffer position the line starts at
+    line_start: usize,
+    /// The whole line, without its newline
+    text: String,
+    /// The line as drawn, with tabs expanded to the next tab stop
+    display: String,
+    /// For each cell of `display`, the character of `text` it's drawn for
+    sources: Vec<usize>,
+    /// Column of the line the row starts at: the horizontal scroll, or where a
+    /// wrapped row picks up
+    first_column: usize,
+    /// How many of the line's cells the row shows, when wrapped
+    wrapped_len: Option<usize>,
+}
+
+impl ContentRow {
+    /// Cell of the line the character `offset` characters into it is drawn
+    /// from, or the cell past the end for the end of the line
+    fn column_of(&self, offset: usize) -> usize {
+        self.sources.partition_point(|&source| source < offset)
+    }
+}
+
+/// A line as drawn, without its newline and with tabs expanded to the next
+/// tab stop
+fn display_line(text: &str, tab_width: usize) -> String {
+    expand_tabs(text.trim_end_matches('\n'), tab_width)
+        .into_iter()
+        .map(|(c, _)| c)
+        .collect()
+}
+
+/// Left edges of the rulers at display `columns` that are scrolled into view,
+/// for a window whose text starts at `content_x` and is `content_width` cells
+/// wide
+fn ruler_xs(
+    window: &roe_core::editor::Window,
+    columns: &[usize],
+    content_x: f64,
+    char_width: f64,
+    content_width: usize,
+) -> Vec<f64> {
+    columns
+        .iter()
+        .filter_map(|&column| window.visible_column(column, content_width))
+        .map(|offset| content_x + offset as f64 * char_width)
+        .collect()
 }
 
 struct RenderState<'s> {
@@ -148,16 +264,66 @@ impl<'a> RoeVelloApp<'a> {
             renderers: vec![],
             state: None,
             scene: Scene::new(),
-            text_renderer: TextRenderer::new(font_size, font_family),
+            text_renderer: TextRenderer::new(font_size, font_family, theme.font_fallback.clone()),
             theme,
             quit_requested: false,
             modifiers: ModifiersState::empty(),
             cursor_position: None,
             mouse_dragging: false,
             drag_start_cursor: None,
+            last_click: None,
+            drag_selection: None,
             scrollbar_dragging: None,
             hscrollbar_dragging: None,
+            wheel_remainder: (0.0, 0.0),
+            next_file_poll: Instant::now(),
+            preedit: None,
+            ime_cursor_area: None,
+        }
+    }
+
+    /// Apply external file changes reported by the file watcher.
+    /// Returns true if anything was reported and the window should be redrawn.
+    fn process_file_changes(&mut self) -> bool {
+        let file_change_actions = self.editor.poll_file_changes();
+        let changed = !file_change_actions.is_empty();
+        for action in file_change_actions {
+            match action {
+                ChromeAction::Echo(msg) => {
+                    self.editor.set_echo_message(msg);
+                }
+                ChromeAction::BufferChanged {
+                    buffer_id,
+                    start,
+                    old_end,
+                    new_end,
+                } => {
+                    // The hook is queued for the Julia thread so the frame isn't
+                    // held up; about_to_wait redraws once highlighting is updated
+                    self.editor
+                        .buffer_reloaded(buffer_id, start, old_end, new_end);
+                }
+                _ => {}
+            }
         }
+        changed
+    }
+
+    /// Replace the current theme, rebuilding the text renderer if the font changed
+    fn apply_theme(&mut self, theme: VelloTheme) {
+        if theme.font_size != self.theme.font_size
+            || theme.font_family != self.theme.font_family
+            || theme.font_fallback != self.theme.font_fallback
+        {
+            let font_family = if theme.font_family.is_empty() {
+                None
+            } else {
+                Some(theme.font_family.clone())
+            };
+            self.text_renderer =
+                TextRenderer::new(theme.font_size, font_family, theme.font_fallback.clone());
+        }
+        self.theme = theme;
     }
 
     fn create_window(&mut self, event_loop: &ActiveEventLoop) -> Arc<Window> {
@@ -197,13 +363,23 @@ impl<'a> RoeVelloApp<'a> {
         // Update editor frame dimensions (using logical dimensions)
         let cols = (logical_width as f32 / char_width).floor() as u16;
         let lines = (logical_height as f32 / line_height).floor() as u16;
-        self.editor
-            .handle_resize(cols.max(1), lines.saturating_sub(1).max(1)); // -1 for echo area
+        pollster::block_on(
+            self.editor
+                .handle_resize(cols.max(1), lines.saturating_sub(1).max(1)), // -1 for echo area
+        );
 
         // Build the scene in logical coordinates, then scale for physical rendering
         self.scene.reset();
         self.build_scene(logical_width, logical_height);
 
+        // Keep the input method's candidate window next to the cursor
+        if let (Some((x, y, h)), Some(ref state)) = (self.ime_cursor_area, &self.state) {
+            state.window.set_ime_cursor_area(
+                winit::dpi::LogicalPosition::new(x, y),
+                LogicalSize::new(self.text_renderer.char_width() as f64, h),
+            );
+        }
+
         // Apply scale factor transform to the scene
         if scale_factor != 1.0 {
             let mut scaled_scene = Scene::new();
@@ -290,16 +466,22 @@ impl<'a> RoeVelloApp<'a> {
         // Draw each window
         for window_id in self.editor.windows.keys().collect::<Vec<_>>() {
             self.draw_window(window_id);
+            self.draw_menu_preview(window_id);
         }
 
         // Draw echo area at bottom
         self.draw_echo_area(width, height);
+        self.draw_key_hints(width, height);
     }
 
     fn draw_window(&mut self, window_id: roe_core::WindowId) {
         let char_width = self.text_renderer.char_width() as f64;
         let line_height = self.text_renderer.line_height() as f64;
 
+        if window_id == self.editor.active_window {
+            self.scroll_wrapped_cursor_into_view(window_id);
+        }
+
         let window = &self.editor.windows[window_id];
         let is_active = window_id == self.editor.active_window;
 
@@ -380,7 +562,7 @@ impl<'a> RoeVelloApp<'a> {
         let base_content_x = x + char_width;
         let content_y = y + line_height;
         // Reserve space for horizontal scrollbar at bottom
-        let content_height = window.height_chars.saturating_sub(3) as usize; // -3 for top border, modeline, h-scrollbar
+        let content_height = window.height_chars.saturating_sub(WINDOW_CHROME_ROWS) as usize;
         let start_line = window.start_line as usize;
         let start_column = window.start_column as usize;
 
@@ -388,18 +570,22 @@ impl<'a> RoeVelloApp<'a> {
         let show_gutter = buffer.show_gutter();
 
         // Calculate gutter width and get modified lines
-        let (gutter_width_chars, modified_lines): (usize, HashSet<usize>) = if show_gutter {
-            let total_lines = buffer.buffer_len_lines();
-            let config = GutterConfig::default();
+        let config = GutterConfig::default();
+        let total_lines = buffer.buffer_len_lines();
+        let (gutter_width_chars, modified_lines, saved_lines, conflict_lines) = if show_gutter {
             let width = calculate_gutter_width(total_lines, &config);
-            let buffer_content = buffer.content();
-            let modified = self
-                .editor
-                .file_watcher
-                .get_modified_lines(window.active_buffer, &buffer_content);
-            (width, modified)
+            let lines = start_line..start_line + content_height;
+            let (modified, saved) =
+                self.editor
+                    .file_watcher
+                    .line_changes(window.active_buffer, buffer, lines.clone());
+            let conflicts =
+                self.editor
+                    .conflict_cache
+                    .marker_lines(window.active_buffer, buffer, lines);
+            (width, modified, saved, conflicts)
         } else {
-            (0, HashSet::new())
+            (0, HashSet::new(), HashSet::new(), HashSet::new())
         };
 
         let gutter_width_px = gutter_width_chars as f64 * char_width;
@@ -410,11 +596,47 @@ impl<'a> RoeVelloApp<'a> {
         let content_width = content_width_px as f32;
         let content_width_chars = (content_width_px / char_width) as usize;
 
-        // Calculate line number width for formatting
-        let line_number_width = gutter_width_chars.saturating_sub(2); // Subtract status indicator and separator
+        // Long lines either run off the right edge, scrolled by start_column,
+        // or wrap onto as many rows as they need
+        let wrap_mode = buffer.wrap_mode();
+        let wrapping = wrap_mode != WrapMode::None;
+        let mut max_line_len: usize = 0;
+        let mut rows: Vec<ContentRow> = Vec::with_capacity(content_height);
+        let tab_width = buffer.tab_width();
+        let buffer_lines = buffer.buffer_lines();
+        for (idx, text) in buffer_lines.iter().enumerate() {
+            let text = text.trim_end_matches('\n');
+            let cells = expand_tabs(text, tab_width);
+            let len = cells.len();
+            max_line_len = max_line_len.max(len);
+            if idx < start_line || rows.len() >= content_height {
+                continue;
+            }
+            let line_start = buffer.to_char_index(0, idx as u16);
+            let display: String = cells.iter().map(|(c, _)| c).collect();
+            let sources: Vec<usize> = cells.iter().map(|(_, source)| *source).collect();
+            let row_starts = wrap_rows(&display, content_width_chars, wrap_mode);
+            for (i, &row_start) in row_starts.iter().enumerate() {
+                if rows.len() >= content_height {
+                    break;
+                }
+                let row_end = row_starts.get(i + 1).copied().unwrap_or(len);
+                rows.push(ContentRow {
+                    line: idx,
+                    line_start,
+                    text: text.to_string(),
+                    display: display.clone(),
+                    sources: sources.clone(),
+                    first_column: if wrapping { row_start } else { start_column },
+                    wrapped_len: wrapping.then_some(row_end - row_start),
+                });
+            }
+        }
 
-        // For tracking merged lines (TODO: track separately)
-        let merged_lines: HashSet<usize> = HashSet::new();
+        // Line numbers are right-aligned in the column reserved for them, which
+        // ends one cell before the separator line at the gutter's right edge
+        let line_number_width = line_number_width(total_lines, &config);
+        let line_num_x = base_content_x + line_number_column(&config) as f64 * char_width;
 
         // Draw gutter background and content (outside clip region)
         if show_gutter {
@@ -428,7 +650,7 @@ impl<'a> RoeVelloApp<'a> {
             self.scene.fill(
                 vello::peniko::Fill::NonZero,
                 Affine::IDENTITY,
-                GUTTER_BG_COLOR,
+                self.theme.gutter_bg_color,
                 None,
                 &gutter_rect,
             );
@@ -444,29 +666,42 @@ impl<'a> RoeVelloApp<'a> {
             self.scene.fill(
                 vello::peniko::Fill::NonZero,
                 Affine::IDENTITY,
-                GUTTER_SEPARATOR_COLOR,
+                self.theme.gutter_separator_color,
                 None,
                 &separator_rect,
             );
 
             // Draw line numbers and status indicators for visible lines
-            let total_buffer_lines = buffer.buffer_len_lines();
             for visual_row in 0..content_height {
-                let buffer_line = start_line + visual_row;
                 let gutter_y = content_y + (visual_row as f64 * line_height);
 
-                if buffer_line < total_buffer_lines {
-                    // Get line content for status check
-                    let line_text = buffer.buffer_line(buffer_line);
-                    let line_status =
-                        get_line_status(&line_text, buffer_line, &modified_lines, &merged_lines);
+                let row = rows.get(visual_row);
+                if row.is_some_and(|row| wrapping && row.first_column > 0) {
+                    // Continuation of a wrapped line
+                    let mark = format!("{:>width$}", CONTINUATION_MARK, width = line_number_width);
+                    self.text_renderer.render_line(
+                        &mut self.scene,
+                        &mark,
+                        line_num_x as f32,
+                        gutter_y as f32,
+                        self.theme.gutter_fg_color,
+                        None,
+                    );
+                } else if let Some(row) = row {
+                    let buffer_line = row.line;
+                    let line_status = get_line_status(
+                        buffer_line,
+                        &modified_lines,
+                        &saved_lines,
+                        &conflict_lines,
+                    );
 
                     // Draw status indicator bar
                     let status_color = match line_status {
                         LineStatus::Clean => None,
-                        LineStatus::Modified => Some(GUTTER_MODIFIED_COLOR),
-                        LineStatus::ModifiedSaved => Some(GUTTER_SAVED_COLOR),
-                        LineStatus::Conflict => Some(GUTTER_CONFLICT_COLOR),
+                        LineStatus::Modified => Some(self.theme.gutter_modified_color),
+                        LineStatus::ModifiedSaved => Some(self.theme.gutter_saved_color),
+                        LineStatus::Conflict => Some(self.theme.gutter_conflict_color),
                     };
 
                     if let Some(color) = status_color {
@@ -487,25 +722,23 @@ impl<'a> RoeVelloApp<'a> {
 
                     // Draw line number (right-aligned)
                     let line_num_str = format_line_number(buffer_line + 1, line_number_width);
-                    let line_num_x = base_content_x + char_width; // After status indicator
                     self.text_renderer.render_line(
                         &mut self.scene,
                         &line_num_str,
                         line_num_x as f32,
                         gutter_y as f32,
-                        GUTTER_FG_COLOR,
+                        self.theme.gutter_fg_color,
                         None,
                     );
                 } else {
                     // Empty line (past end of buffer) - show tilde
                     let tilde_str = format!("{:>width$}", "~", width = line_number_width);
-                    let line_num_x = base_content_x + char_width;
                     self.text_renderer.render_line(
                         &mut self.scene,
                         &tilde_str,
                         line_num_x as f32,
                         gutter_y as f32,
-                        GUTTER_FG_COLOR,
+                        self.theme.gutter_fg_color,
                         None,
                     );
                 }
@@ -526,6 +759,40 @@ impl<'a> RoeVelloApp<'a> {
             &clip_rect,
         );
 
+        // Row the cursor is on: the line's only row, or the last of its wrapped
+        // rows starting at or before the cursor
+        let (cursor_col, cursor_line) = buffer.to_column_line(window.cursor);
+        let (cursor_col, cursor_line) = (cursor_col as usize, cursor_line as usize);
+        let cursor_row = rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| {
+                row.line == cursor_line && (!wrapping || row.first_column <= cursor_col)
+            })
+            .last();
+
+        // Tint the cursor's row in the active window, behind selection and text.
+        // Command windows are left plain so prompts aren't tinted.
+        let is_command_window = matches!(window.window_type, WindowType::Command { .. });
+        if let Some((cursor_visual_line, _)) =
+            cursor_row.filter(|_| is_active && !is_command_window)
+        {
+            let line_y = content_y + cursor_visual_line as f64 * line_height;
+            let line_rect = Rect::new(
+                content_x,
+                line_y,
+                content_x + content_width_px,
+                line_y + line_height,
+            );
+            self.scene.fill(
+                vello::peniko::Fill::NonZero,
+                Affine::IDENTITY,
+                self.theme.current_line_bg,
+                None,
+                &line_rect,
+            );
+        }
+
         // Get selection region (only for active window)
         let region_bounds = if is_active {
             buffer.get_region(window.cursor)
@@ -533,40 +800,18 @@ impl<'a> RoeVelloApp<'a> {
             None
         };
 
-        // Collect lines to render with their buffer positions, track max width
-        let mut max_line_len: usize = 0;
-        let lines_to_render: Vec<(usize, usize, String)> = buffer
-            .buffer_lines()
-            .into_iter()
-            .enumerate()
-            .inspect(|(_, text)| {
-                let len = text.trim_end_matches('\n').chars().count();
-                if len > max_line_len {
-                    max_line_len = len;
-                }
-            })
-            .filter(|(idx, _)| *idx >= start_line && (*idx - start_line) < content_height)
-            .map(|(idx, text)| {
-                let line_start_pos = buffer.to_char_index(0, idx as u16);
-                (
-                    idx - start_line,
-                    line_start_pos,
-                    text.trim_end_matches('\n').to_string(),
-                )
-            })
-            .collect();
-
         // Draw selection highlights first (behind text), accounting for horizontal scroll
         if let Some((region_start, region_end)) = region_bounds {
             let selection_color = self.theme.selection_color;
-            for (visual_line, line_start_pos, line_text) in &lines_to_render {
-                let line_char_len = line_text.chars().count();
+            for (visual_line, row) in rows.iter().enumerate() {
+                let line_start_pos = row.line_start;
+                let line_char_len = row.text.chars().count();
                 let line_end_pos = line_start_pos + line_char_len;
 
                 // Check if this line intersects with selection
-                if *line_start_pos < region_end && line_end_pos > region_start {
+                if line_start_pos < region_end && line_end_pos > region_start {
                     // Calculate selection bounds within this line
-                    let sel_start_in_line = if region_start > *line_start_pos {
+                    let sel_start_in_line = if region_start > line_start_pos {
                         region_start - line_start_pos
                     } else {
                         0
@@ -576,14 +821,24 @@ impl<'a> RoeVelloApp<'a> {
                     } else {
                         line_char_len
                     };
-
-                    // Adjust for horizontal scroll
-                    let visible_sel_start = sel_start_in_line.saturating_sub(start_column);
-                    let visible_sel_end = sel_end_in_line.saturating_sub(start_column);
-
-                    if visible_sel_end > 0 && visible_sel_start < content_width_chars {
+                    // In cells, with any tabs before them expanded
+                    let sel_start_in_line = row.column_of(sel_start_in_line);
+                    let sel_end_in_line = row.column_of(sel_end_in_line);
+
+                    // Adjust for horizontal scroll, or the part of a wrapped line on this row
+                    let row_len = row.wrapped_len.unwrap_or(usize::MAX);
+                    let visible_sel_start = sel_start_in_line
+                        .saturating_sub(row.first_column)
+                        .min(row_len);
+                    let visible_sel_end = sel_end_in_line
+                        .saturating_sub(row.first_column)
+                        .min(row_len);
+
+                    if visible_sel_end > visible_sel_start
+                        && visible_sel_start < content_width_chars
+                    {
                         let sel_x = content_x + (visible_sel_start as f64 * char_width);
-                        let sel_y = content_y + (*visual_line as f64 * line_height);
+                        let sel_y = content_y + (visual_line as f64 * line_height);
                         let sel_width = (visible_sel_end - visible_sel_start) as f64 * char_width;
 
                         let sel_rect =
@@ -600,16 +855,161 @@ impl<'a> RoeVelloApp<'a> {
             }
         }
 
+        // Highlight the bracket by the cursor along with its partner, when both
+        // are on screen, or on its own in the warning color if nothing pairs with it
+        if let Some(found) = is_active
+            .then(|| buffer.bracket_match_at(window.cursor))
+            .flatten()
+        {
+            // Row and column a buffer position is drawn at, if it's visible
+            let cell = |pos: usize| {
+                let (col, line) = buffer.to_column_line(pos);
+                let (col, line) = (col as usize, line as usize);
+                let (visual_line, row) = rows
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, row)| row.line == line && row.first_column <= col)
+                    .last()?;
+                let visible_col = col - row.first_column;
+                let row_len = row.wrapped_len.unwrap_or(content_width_chars);
+                (visible_col < row_len).then_some((visual_line, visible_col))
+            };
+            let (match_color, mismatch_color) = (
+                self.theme.bracket_match_color,
+                self.theme.bracket_mismatch_color,
+            );
+            let highlighted = match (cell(found.bracket), found.partner.map(cell)) {
+                (Some(bracket), Some(Some(partner))) => {
+                    [Some((bracket, match_color)), Some((partner, match_color))]
+                }
+                (Some(bracket), None) => [Some((bracket, mismatch_color)), None],
+                _ => [None, None],
+            };
+            for ((visual_line, column), color) in highlighted.into_iter().flatten() {
+                let cell_x = content_x + column as f64 * char_width;
+                let cell_y = content_y + visual_line as f64 * line_height;
+                let cell_rect =
+                    Rect::new(cell_x, cell_y, cell_x + char_width, cell_y + line_height);
+                self.scene.fill(
+                    vello::peniko::Fill::NonZero,
+                    Affine::IDENTITY,
+                    color,
+                    None,
+                    &cell_rect,
+                );
+            }
+        }
+
+        // Rulers, behind the text, at columns scrolled into view
+        if self.theme.show_ruler && !wrapping {
+            let fill_column = [self.editor.fill_column];
+            let columns = match self.theme.ruler_columns.as_slice() {
+                [] => &fill_column[..],
+                columns => columns,
+            };
+            for ruler_x in ruler_xs(window, columns, content_x, char_width, content_width_chars) {
+                let ruler_rect = Rect::new(
+                    ruler_x,
+                    content_y,
+                    ruler_x + 1.0,
+                    content_y + content_height as f64 * line_height,
+                );
+                self.scene.fill(
+                    vello::peniko::Fill::NonZero,
+                    Affine::IDENTITY,
+                    self.theme.ruler_color,
+                    None,
+                    &ruler_rect,
+                );
+            }
+        }
+
+        // Indent guides, behind the text: a rule at each indentation stop
+        // short of the line's depth, on the first row of each line
+        if self.theme.indent_guides {
+            let lines: Vec<&str> = buffer_lines.iter().map(String::as_str).collect();
+            let depths = indent_guide_depths(&lines, tab_width);
+            for (visual_line, row) in rows.iter().enumerate() {
+                if wrapping && row.first_column > 0 {
+                    continue;
+                }
+                let depth = depths.get(row.line).copied().unwrap_or(0);
+                let row_y = content_y + visual_line as f64 * line_height;
+                for column in (0..depth).map(|level| level * tab_width) {
+                    if column < row.first_column {
+                        continue;
+                    }
+                    let guide_x = content_x + (column - row.first_column) as f64 * char_width;
+                    let guide_rect = Rect::new(guide_x, row_y, guide_x + 1.0, row_y + line_height);
+                    self.scene.fill(
+                        vello::peniko::Fill::NonZero,
+                        Affine::IDENTITY,
+                        self.theme.indent_guide_color,
+                        None,
+                        &guide_rect,
+                    );
+                }
+            }
+        }
+
+        // Tabs and trailing spaces, over the selection but behind the text,
+        // which draws nothing in those cells
+        if self.theme.show_whitespace {
+            for (visual_line, row) in rows.iter().enumerate() {
+                let row_width = row.wrapped_len.unwrap_or(content_width_chars);
+                let row_y = content_y as f32 + visual_line as f32 * line_height as f32;
+                for (column, mark) in whitespace_marks(&row.text, tab_width) {
+                    let Some(offset) = column
+                        .checked_sub(row.first_column)
+                        .filter(|&offset| offset < row_width)
+                    else {
+                        continue;
+                    };
+                    let glyph = match mark {
+                        WhitespaceMark::Tab => TAB_MARK,
+                        WhitespaceMark::TrailingSpace => TRAILING_SPACE_MARK,
+                    };
+                    self.text_renderer.render_line(
+                        &mut self.scene,
+                        glyph,
+                        (content_x + offset as f64 * char_width) as f32,
+                        row_y,
+                        self.theme.whitespace_color,
+                        None,
+                    );
+                }
+            }
+        }
+
         // Render each line of text with horizontal scroll offset and syntax highlighting
         let fg_color = self.theme.fg_color;
         let face_registry_guard = face_registry().lock().ok();
 
-        // Get full buffer content for byte<->char conversion
-        let buffer_content = buffer.content();
+        let mut truncated_lines = Vec::new();
+        // Character under a block cursor and whether it's bold and italic, to
+        // redraw inverted on top of the cursor
+        let mut cursor_glyph: Option<(String, bool, bool)> = None;
+        for (visual_line, row) in rows.iter().enumerate() {
+            let (line_start_char, line_text) = (row.line_start, row.text.as_str());
+            let start_column = row.first_column;
+            if !wrapping {
+                let marks = window.truncation_marks(row.sources.len(), content_width_chars);
+                if marks != (false, false) {
+                    truncated_lines.push((visual_line, marks));
+                }
+            }
 
-        for (visual_line, line_start_char, line_text) in lines_to_render {
-            // Apply horizontal scroll - skip start_column characters
-            let visible_text: String = line_text.chars().skip(start_column).collect();
+            // Apply horizontal scroll - skip start_column cells, and keep to
+            // this row's part of a wrapped line
+            let mut visible_cells: Vec<(char, usize)> = row
+                .display
+                .chars()
+                .zip(row.sources.iter().copied())
+                .skip(start_column)
+                .take(row.wrapped_len.unwrap_or(usize::MAX))
+                .collect();
+            clip_wide_chars(&mut visible_cells);
+            let visible_text: String = visible_cells.iter().map(|(c, _)| c).collect();
             if visible_text.is_empty() {
                 continue;
             }
@@ -617,28 +1017,24 @@ impl<'a> RoeVelloApp<'a> {
             let text_x = content_x as f32;
             let text_y = content_y as f32 + (visual_line as f32) * line_height as f32;
 
-            // Convert char positions to byte positions for span query
-            // (spans use byte positions for tree-sitter/Julia compatibility)
-            let line_start_byte = char_to_byte(&buffer_content, line_start_char);
-            let line_end_byte =
-                char_to_byte(&buffer_content, line_start_char + line_text.chars().count());
-            let syntax_spans = buffer.spans_in_range(line_start_byte..line_end_byte);
+            let line_char_count = line_text.chars().count();
+            let syntax_spans =
+                buffer.spans_in_range(line_start_char..line_start_char + line_char_count);
 
             // Draw background rectangles for spans with background colors
-            let line_char_count = line_text.chars().count();
             let visible_char_count = visible_text.chars().count();
             if let Some(ref registry) = face_registry_guard {
                 for span in &syntax_spans {
                     if let Some(face) = registry.get(span.face_id) {
                         if let Some(ref bg_color) = face.background {
-                            // Convert span byte positions to char positions within line
-                            let span_byte_start_in_line =
-                                span.start.saturating_sub(line_start_byte);
-                            let span_byte_end_in_line = span.end.saturating_sub(line_start_byte);
+                            // Span positions within the line
                             let span_start_in_line =
-                                byte_to_char(&line_text, span_byte_start_in_line);
-                            let span_end_in_line = byte_to_char(&line_text, span_byte_end_in_line)
-                                .min(line_char_count);
+                                row.column_of(span.start.saturating_sub(line_start_char));
+                            let span_end_in_line = row.column_of(
+                                span.end
+                                    .saturating_sub(line_start_char)
+                                    .min(line_char_count),
+                            );
 
                             // Adjust for horizontal scroll
                             if span_end_in_line <= start_column
@@ -684,12 +1080,14 @@ impl<'a> RoeVelloApp<'a> {
                     .iter()
                     .filter_map(|span| {
                         let face = registry.get(span.face_id)?;
-                        // Convert span byte positions to char positions within line
-                        let span_byte_start_in_line = span.start.saturating_sub(line_start_byte);
-                        let span_byte_end_in_line = span.end.saturating_sub(line_start_byte);
-                        let span_start_in_line = byte_to_char(&line_text, span_byte_start_in_line);
-                        let span_end_in_line =
-                            byte_to_char(&line_text, span_byte_end_in_line).min(line_char_count);
+                        // Span positions within the line
+                        let span_start_in_line =
+                            row.column_of(span.start.saturating_sub(line_start_char));
+                        let span_end_in_line = row.column_of(
+                            span.end
+                                .saturating_sub(line_start_char)
+                                .min(line_char_count),
+                        );
 
                         // Adjust for horizontal scroll
                         if span_end_in_line <= start_column
@@ -724,6 +1122,24 @@ impl<'a> RoeVelloApp<'a> {
                 Vec::new()
             };
 
+            if is_active && self.theme.cursor_shape == CursorShape::Block {
+                let cursor_offset = (row.line == cursor_line)
+                    .then(|| cursor_col.checked_sub(start_column))
+                    .flatten();
+                if let Some((offset, ch)) = cursor_offset
+                    .and_then(|offset| Some((offset, visible_text.chars().nth(offset)?)))
+                {
+                    let span = styled_spans
+                        .iter()
+                        .find(|span| span.start <= offset && offset < span.end);
+                    cursor_glyph = Some((
+                        ch.to_string(),
+                        span.is_some_and(|span| span.bold),
+                        span.is_some_and(|span| span.italic),
+                    ));
+                }
+            }
+
             // Use styled rendering if we have spans, otherwise plain rendering
             if styled_spans.is_empty() {
                 self.text_renderer.render_line(
@@ -747,24 +1163,118 @@ impl<'a> RoeVelloApp<'a> {
             }
         }
 
+        // Flag text hidden past either edge of the window
+        let mark = TRUNCATION_MARK.to_string();
+        for (visual_line, (left_mark, right_mark)) in truncated_lines {
+            let mark_y = content_y + visual_line as f64 * line_height;
+            let columns = [(left_mark, 0), (right_mark, content_width_chars - 1)];
+            for (_, column) in columns.into_iter().filter(|(shown, _)| *shown) {
+                let mark_x = content_x + column as f64 * char_width;
+                let mark_rect =
+                    Rect::new(mark_x, mark_y, mark_x + char_width, mark_y + line_height);
+                self.scene.fill(
+                    vello::peniko::Fill::NonZero,
+                    Affine::IDENTITY,
+                    self.theme.bg_color,
+                    None,
+                    &mark_rect,
+                );
+                self.text_renderer.render_line(
+                    &mut self.scene,
+                    &mark,
+                    mark_x as f32,
+                    mark_y as f32,
+                    self.theme.gutter_fg_color,
+                    None,
+                );
+            }
+        }
+
         // Draw cursor (inside clipping region), accounting for horizontal scroll
         if is_active {
-            let (col, line) = buffer.to_column_line(window.cursor);
-            let line = line as usize;
-            let col = col as usize;
-            if line >= start_line {
-                let cursor_visual_line = line - start_line;
+            let col = cursor_col;
+            if let Some((cursor_visual_line, row)) = cursor_row {
                 // Check if cursor is horizontally visible
-                if cursor_visual_line < content_height
-                    && col >= start_column
-                    && col < start_column + content_width_chars
-                {
-                    let visual_col = col - start_column;
+                if col >= row.first_column && col < row.first_column + content_width_chars {
+                    let visual_col = col - row.first_column;
                     let cursor_x = content_x + (visual_col as f64 * char_width);
                     let cursor_y = content_y + (cursor_visual_line as f64) * line_height;
 
-                    let cursor_rect =
-                        Rect::new(cursor_x, cursor_y, cursor_x + 2.0, cursor_y + line_height);
+                    self.ime_cursor_area = Some((cursor_x, cursor_y, line_height));
+
+                    // Composed text sits at the cursor until it's committed
+                    let caret_x = match self.preedit {
+                        Some(ref preedit) => {
+                            let text = preedit.text.clone();
+                            // As wide as the cells the text will take
+                            let preedit_width = self.text_renderer.advance_width(&text) as f64;
+                            let caret_width = preedit
+                                .cursor
+                                .and_then(|byte| text.get(..byte))
+                                .map_or(preedit_width, |before| {
+                                    self.text_renderer.advance_width(before) as f64
+                                });
+                            let preedit_rect = Rect::new(
+                                cursor_x,
+                                cursor_y,
+                                cursor_x + preedit_width,
+                                cursor_y + line_height,
+                            );
+                            self.scene.fill(
+                                vello::peniko::Fill::NonZero,
+                                Affine::IDENTITY,
+                                self.theme.selection_color,
+                                None,
+                                &preedit_rect,
+                            );
+                            self.text_renderer.render_line(
+                                &mut self.scene,
+                                &text,
+                                cursor_x as f32,
+                                cursor_y as f32,
+                                self.theme.fg_color,
+                                None,
+                            );
+                            let underline = Rect::new(
+                                cursor_x,
+                                cursor_y + line_height - 1.0,
+                                cursor_x + preedit_width,
+                                cursor_y + line_height,
+                            );
+                            self.scene.fill(
+                                vello::peniko::Fill::NonZero,
+                                Affine::IDENTITY,
+                                self.theme.fg_color,
+                                None,
+                                &underline,
+                            );
+                            cursor_x + caret_width
+                        }
+                        None => cursor_x,
+                    };
+
+                    // A block covers both cells of a wide character
+                    let cursor_width = cursor_glyph
+                        .as_ref()
+                        .filter(|_| self.preedit.is_none())
+                        .map_or(char_width, |(glyph, _, _)| {
+                            (self.text_renderer.advance_width(glyph) as f64).max(char_width)
+                        });
+                    let cursor_bottom = cursor_y + line_height;
+                    let cursor_rect = match self.theme.cursor_shape {
+                        CursorShape::Bar => {
+                            Rect::new(caret_x, cursor_y, caret_x + 2.0, cursor_bottom)
+                        }
+                        CursorShape::Block => {
+                            Rect::new(caret_x, cursor_y, caret_x + cursor_width, cursor_bottom)
+                        }
+                        CursorShape::Underline => Rect::new(
+                            caret_x,
+                            cursor_bottom - 2.0,
+                            caret_x + char_width,
+                            cursor_bottom,
+                        ),
+                    };
                     self.scene.fill(
                         vello::peniko::Fill::NonZero,
                         Affine::IDENTITY,
@@ -772,6 +1282,25 @@ impl<'a> RoeVelloApp<'a> {
                         None,
                         &cursor_rect,
                     );
+
+                    // The character under a block cursor shows through in the background color
+                    if let Some((glyph, bold, italic)) =
+                        cursor_glyph.filter(|_| self.preedit.is_none())
+                    {
+                        let bg_color = self.theme.bg_color;
+                        let style = StyledSpan::new(0, glyph.chars().count(), bg_color)
+                            .with_bold(bold)
+                            .with_italic(italic);
+                        self.text_renderer.render_line_with_styles(
+                            &mut self.scene,
+                            &glyph,
+                            caret_x as f32,
+                            cursor_y as f32,
+                            bg_color,
+                            &[style],
+                            None,
+                        );
+                    }
                 }
             }
         }
@@ -780,15 +1309,30 @@ impl<'a> RoeVelloApp<'a> {
         self.scene.pop_layer();
 
         // Draw modeline text (outside clip)
-        let buffer_name = buffer.object();
+        let mut buffer_name = buffer.object();
+        if let Some(indicator) = self.editor.file_status_indicator(window.active_buffer) {
+            buffer_name = format!("{} {}", buffer_name, indicator);
+        }
+        // Emacs-style `**` for unsaved changes, `--` otherwise (`%` first
+        // when read-only)
+        let modified = self.editor.modified_flag(window.active_buffer);
         let (col, line) = buffer.to_column_line(window.cursor);
-        let major_mode_str = buffer
+        let mut major_mode_str = buffer
             .major_mode()
             .map(|m| format!("({}) ", m))
             .unwrap_or_default();
+        if let Some(file_format) = self.editor.file_format_indicator(window.active_buffer) {
+            major_mode_str.push_str(&file_format);
+            major_mode_str.push(' ');
+        }
+        if let Some(word_count) = self.editor.word_count_indicator(window.active_buffer) {
+            major_mode_str.push_str(&word_count);
+            major_mode_str.push(' ');
+        }
         let modeline_text = if is_active {
             format!(
-                " ᚱᛟ {} {}{}:{}",
+                " ᚱᛟ {} {} {}{}:{}",
+                modified,
                 buffer_name,
                 major_mode_str,
                 line + 1,
@@ -796,7 +1340,8 @@ impl<'a> RoeVelloApp<'a> {
             )
         } else {
             format!(
-                "    {} {}{}:{}",
+                "    {} {} {}{}:{}",
+                modified,
                 buffer_name,
                 major_mode_str,
                 line + 1,
@@ -864,8 +1409,9 @@ impl<'a> RoeVelloApp<'a> {
             &thumb_rect,
         );
 
-        // Draw horizontal scrollbar (only if content exceeds visible width)
-        if max_line_len > content_width_chars {
+        // Draw horizontal scrollbar (only if content exceeds visible width and
+        // isn't wrapped)
+        if !wrapping && max_line_len > content_width_chars {
             let hscroll_y = y + h - line_height - SCROLLBAR_WIDTH - 2.0; // Above modeline
             let hscroll_x = x + 2.0; // After left border
             let hscroll_width = w - SCROLLBAR_WIDTH - 6.0; // Before vertical scrollbar
@@ -910,11 +1456,89 @@ impl<'a> RoeVelloApp<'a> {
                 &hthumb_rect,
             );
         }
+
+        // Dim inactive windows' content, leaving borders and modeline as they are.
+        // The window a prompt was opened from stays bright while it's in use.
+        let prompted_from = self.editor.find_command_window().is_some()
+            && self.editor.previous_active_window == Some(window_id);
+        if !is_active && !prompted_from && self.theme.dim_inactive && self.theme.inactive_dim > 0.0
+        {
+            let dim_rect = Rect::new(x + 2.0, y + 2.0, x + w - 2.0, modeline_y);
+            self.scene.fill(
+                vello::peniko::Fill::NonZero,
+                Affine::IDENTITY,
+                self.theme.bg_color.with_alpha(self.theme.inactive_dim),
+                None,
+                &dim_rect,
+            );
+        }
+    }
+
+    /// Draw the highlighted menu entry's preview over the right half of a command window
+    fn draw_menu_preview(&mut self, window_id: roe_core::WindowId) {
+        let Some(lines) = self.editor.menu_preview_lines(window_id) else {
+            return;
+        };
+        let char_width = self.text_renderer.char_width() as f64;
+        let line_height = self.text_renderer.line_height() as f64;
+
+        let window = &self.editor.windows[window_id];
+        let content_x = (window.x + 1) as f64 * char_width;
+        let content_y = (window.y + 1) as f64 * line_height;
+        let content_height = window.height_chars.saturating_sub(WINDOW_CHROME_ROWS) as usize;
+        let content_width = window.width_chars.saturating_sub(2) as usize;
+        let Some((separator, text_width)) = preview_pane(content_width) else {
+            return;
+        };
+
+        // Cover the list text under the pane, then draw the divider
+        let separator_x = content_x + separator as f64 * char_width;
+        let pane_rect = Rect::new(
+            separator_x,
+            content_y,
+            content_x + content_width as f64 * char_width - SCROLLBAR_WIDTH,
+            content_y + content_height as f64 * line_height,
+        );
+        self.scene.fill(
+            vello::peniko::Fill::NonZero,
+            Affine::IDENTITY,
+            self.theme.bg_color,
+            None,
+            &pane_rect,
+        );
+        let divider = Rect::new(
+            separator_x + char_width / 2.0 - 1.0,
+            content_y,
+            separator_x + char_width / 2.0,
+            content_y + content_height as f64 * line_height,
+        );
+        self.scene.fill(
+            vello::peniko::Fill::NonZero,
+            Affine::IDENTITY,
+            self.theme.border_color,
+            None,
+            &divider,
+        );
+
+        let text_x = separator_x + 2.0 * char_width;
+        let max_width = (text_width.saturating_sub(1) as f64 * char_width) as f32;
+        for (row, line) in lines.iter().take(content_height).enumerate() {
+            self.text_renderer.render_line(
+                &mut self.scene,
+                line,
+                text_x as f32,
+                (content_y + row as f64 * line_height) as f32,
+                self.theme.gutter_fg_color,
+                Some(max_width),
+            );
+        }
     }
 
     fn draw_echo_area(&mut self, width: u32, height: u32) {
         let line_height = self.text_renderer.line_height() as f64;
-        let echo_y = height as f64 - line_height;
+        // A long message takes several rows, which the layout has left free
+        let lines = self.editor.echo_lines();
+        let echo_y = height as f64 - line_height * lines.len().max(1) as f64;
 
         // Echo area background
         let echo_rect = Rect::new(0.0, echo_y, width as f64, height as f64);
@@ -927,25 +1551,92 @@ impl<'a> RoeVelloApp<'a> {
         );
 
         // Draw echo message text
-        if !self.editor.echo_message.is_empty() {
-            let message = self.editor.echo_message.clone();
-            let fg_color = self.theme.fg_color;
+        let fg_color = self.theme.fg_color;
+        for (row, line) in lines.iter().enumerate() {
             self.text_renderer.render_line(
                 &mut self.scene,
-                &message,
+                line,
                 4.0, // Small left padding
-                echo_y as f32,
+                (echo_y + row as f64 * line_height) as f32,
                 fg_color,
                 Some(width as f32 - 8.0),
             );
         }
     }
 
+    /// List the keys that can follow a pending chord in a panel over the
+    /// bottom of the windows, just above the echo area
+    fn draw_key_hints(&mut self, width: u32, height: u32) {
+        if self.editor.key_hints.hints.is_empty() {
+            return;
+        }
+        let char_width = self.text_renderer.char_width() as f64;
+        let line_height = self.text_renderer.line_height() as f64;
+        let columns = ((width as f64 - 8.0) / char_width).max(1.0) as usize;
+        let rows = key_hint_rows(&self.editor.key_hints.hints, columns);
+        let echo_rows = self.editor.echo_lines().len().max(1);
+        // Leave at least half the windows in view
+        let max_rows = (self.editor.frame.rows as usize / 2).max(1);
+        let shown = rows.len().min(max_rows);
+
+        let bottom = height as f64 - line_height * echo_rows as f64;
+        let top = bottom - line_height * shown as f64 - line_height / 2.0;
+        let panel_rect = Rect::new(0.0, top, width as f64, bottom);
+        self.scene.fill(
+            vello::peniko::Fill::NonZero,
+            Affine::IDENTITY,
+            self.theme.mode_line_bg_color,
+            None,
+            &panel_rect,
+        );
+        let divider = Rect::new(0.0, top, width as f64, top + 1.0);
+        self.scene.fill(
+            vello::peniko::Fill::NonZero,
+            Affine::IDENTITY,
+            self.theme.border_color,
+            None,
+            &divider,
+        );
+
+        for (row, line) in rows.iter().take(shown).enumerate() {
+            self.text_renderer.render_line(
+                &mut self.scene,
+                line,
+                4.0,
+                (top + line_height / 4.0 + row as f64 * line_height) as f32,
+                self.theme.fg_color,
+                Some(width as f32 - 8.0),
+            );
+        }
+    }
+
     async fn handle_key_event(&mut self, event: winit::event::KeyEvent) -> Vec<ChromeAction> {
         if event.state != ElementState::Pressed {
             return vec![];
         }
 
+        // A dead key shows its accent until the next key combines with it
+        if let Some(accent) = key_translate::dead_key(&event) {
+            self.preedit = Some(Preedit {
+                text: accent.to_string(),
+                cursor: None,
+                dead_key: true,
+            });
+            return vec![];
+        }
+        if !key_translate::is_modifier_key(&event)
+            && self
+                .preedit
+                .as_ref()
+                .is_some_and(|preedit| preedit.dead_key)
+        {
+            self.preedit = None;
+        }
+
+        if let Some(text) = key_translate::typed_text(&event, self.modifiers) {
+            return self.editor.text_input(&text).await;
+        }
+
         let keys = key_translate::translate_key_event(&event, self.modifiers);
         if keys.is_empty() {
             return vec![];
@@ -954,6 +1645,157 @@ impl<'a> RoeVelloApp<'a> {
         self.editor.key_event(keys).await.unwrap_or_default()
     }
 
+    /// Handle an input method event: show the text being composed, and insert
+    /// it once committed
+    fn handle_ime(&mut self, event_loop: &ActiveEventLoop, ime: Ime) {
+        match ime {
+            Ime::Preedit(text, cursor) => {
+                self.preedit = (!text.is_empty()).then(|| Preedit {
+                    text,
+                    cursor: cursor.map(|(start, _)| start),
+                    dead_key: false,
+                });
+            }
+            Ime::Commit(text) => {
+                self.preedit = None;
+                let actions = pollster::block_on(self.editor.text_input(&text));
+                self.apply_chrome_actions(event_loop, actions);
+            }
+            Ime::Disabled => {
+                self.preedit = None;
+            }
+            Ime::Enabled => {}
+        }
+
+        if let Some(ref state) = self.state {
+            state.window.request_redraw();
+        }
+    }
+
+    /// Carry out the actions a key press or input method commit produced
+    fn apply_chrome_actions(&mut self, event_loop: &ActiveEventLoop, actions: Vec<ChromeAction>) {
+        let mut actions: std::collections::VecDeque<_> = actions.into();
+
+        while let Some(action) = actions.pop_front() {
+            match action {
+                ChromeAction::Quit => {
+                    self.quit_requested = true;
+                    event_loop.exit();
+                }
+                ChromeAction::SplitHorizontal => {
+                    self.editor.split_horizontal();
+                }
+                ChromeAction::SplitVertical => {
+                    self.editor.split_vertical();
+                }
+                ChromeAction::SwitchWindow => {
+                    self.editor.switch_window();
+                }
+                ChromeAction::DeleteWindow => {
+                    self.editor.delete_window();
+                }
+                ChromeAction::DeleteOtherWindows => {
+                    self.editor.delete_other_windows();
+                }
+                ChromeAction::Echo(msg) => {
+                    self.editor.set_echo_message(msg);
+                }
+                ChromeAction::NewBufferWithMode {
+                    buffer_name,
+                    mode_name,
+                    initial_content,
+                } => {
+                    // Create a new buffer with the specified mode (e.g., Julia REPL)
+                    let cursor_pos = initial_content.len();
+                    if let Some(buffer_id) =
+                        self.editor
+                            .create_buffer_with_mode(buffer_name, mode_name, initial_content)
+                    {
+                        // Switch current window to the new buffer
+                        if let Some(current_window) =
+                            self.editor.windows.get_mut(self.editor.active_window)
+                        {
+                            current_window.active_buffer = buffer_id;
+                            current_window.cursor = cursor_pos;
+                        }
+                    }
+                }
+                ChromeAction::ShowMessages => {
+                    // Create or show messages buffer
+                    let messages_buffer_id = self.editor.get_messages_buffer();
+                    if let Some(current_window) =
+                        self.editor.windows.get_mut(self.editor.active_window)
+                    {
+                        current_window.active_buffer = messages_buffer_id;
+                        current_window.cursor = 0;
+                    }
+                }
+                ChromeAction::BufferChanged {
+                    buffer_id,
+                    start,
+                    old_end,
+                    new_end,
+                } => {
+                    // Queued for the Julia thread so the frame isn't held up;
+                    // about_to_wait redraws once highlighting is updated
+                    self.editor
+                        .buffer_changed(buffer_id, start, old_end, new_end);
+                }
+                ChromeAction::ExecuteCommand(command_name) => {
+                    // Execute another command via the command registry
+                    let context = self.editor.create_command_context();
+                    if self.editor.julia_runtime.is_some() {
+                        match pollster::block_on(self.editor.run_command(&command_name, context)) {
+                            Ok(command_actions) => {
+                                // Process through editor to handle BufferOps etc.
+                                let processed = self.editor.process_chrome_actions(command_actions);
+                                for a in processed {
+                                    actions.push_back(a);
+                                }
+                            }
+                            Err(error_msg) => {
+                                self.editor
+                                    .set_echo_message(format!("Command error: {error_msg}"));
+                            }
+                        }
+                    }
+                }
+                ChromeAction::FileWatcherStatus => {
+                    let status = self.editor.file_watcher.status();
+                    self.editor.set_echo_message(status);
+                }
+                ChromeAction::ToggleMouse => {
+                    // Only the terminal captures the mouse away from its own selection
+                    self.editor
+                        .set_echo_message("Mouse capture only applies in the terminal".to_string());
+                }
+                ChromeAction::ReloadConfig => {
+                    // Keep the previous theme if the reload fails
+                    match pollster::block_on(self.editor.reload_config()) {
+                        Ok(message) => {
+                            self.apply_theme(pollster::block_on(load_theme_from_julia(
+                                self.editor,
+                            )));
+                            self.editor.set_echo_message(message);
+                        }
+                        Err(error_msg) => {
+                            self.editor.set_echo_message(error_msg);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Surface any exceptions raised by Julia commands or hooks
+        self.editor.collect_julia_errors();
+
+        // Request redraw after input
+        if let Some(ref state) = self.state {
+            state.window.request_redraw();
+        }
+    }
+
     /// Handle mouse click at the given pixel position
     async fn handle_mouse_click(&mut self, x: f64, y: f64) {
         let char_width = self.text_renderer.char_width() as f64;
@@ -981,12 +1823,12 @@ impl<'a> RoeVelloApp<'a> {
         let buffer = &self.editor.buffers[window.active_buffer];
 
         // Position relative to window content area (+1 for border)
-        let relative_x = grid_x.saturating_sub(window.x + 1);
+        let relative_px = (x - (window.x + 1) as f64 * char_width).max(0.0);
         let relative_y = grid_y.saturating_sub(window.y + 1);
 
-        // Convert to buffer position (account for scroll offsets)
-        let buffer_line = relative_y as usize + window.start_line as usize;
-        let buffer_col = relative_x as usize + window.start_column as usize;
+        // Convert to buffer position (account for scroll offsets and wrapped rows)
+        let (buffer_line, first_column, last_column) =
+            self.row_origin(window_id, relative_y as usize);
 
         // Clamp line to valid range
         let total_lines = buffer.buffer_len_lines();
@@ -1004,7 +1846,15 @@ impl<'a> RoeVelloApp<'a> {
             .into_iter()
             .nth(clamped_line)
             .unwrap_or_default();
-        let line_len = line_text.trim_end_matches('\n').len();
+        let line_text = display_line(&line_text, buffer.tab_width());
+        let line_len = line_text.chars().count();
+
+        // Walk the characters drawn from the row's first column to the one
+        // under the pointer, wide ones taking two cells
+        let drawn: String = line_text.chars().skip(first_column).collect();
+        let buffer_col = (first_column
+            + self.text_renderer.column_at_x(&drawn, relative_px as f32))
+        .min(last_column);
         let clamped_col = buffer_col.min(line_len);
 
         // Get the new cursor position using clamped values
@@ -1026,13 +1876,44 @@ impl<'a> RoeVelloApp<'a> {
         buffer.clear_mark();
     }
 
+    /// Count a click in text at the given pixel position: 1, or 2 or 3 when it
+    /// comes soon after the last near the same cell. A fourth starts over.
+    fn count_click(&mut self, x: f64, y: f64) -> u8 {
+        let char_width = self.text_renderer.char_width() as f64;
+        let line_height = self.text_renderer.line_height() as f64;
+        let cell = ((x / char_width) as u16, (y / line_height) as u16);
+        let at = Instant::now();
+        let count = match self.last_click {
+            Some(ref last)
+                if at.duration_since(last.at) <= MULTI_CLICK_INTERVAL
+                    && last.cell.0.abs_diff(cell.0) <= 1
+                    && last.cell.1.abs_diff(cell.1) <= 1 =>
+            {
+                last.count % 3 + 1
+            }
+            _ => 1,
+        };
+        self.last_click = Some(Click { at, cell, count });
+        count
+    }
+
+    /// Select the word or line at `pos` in the active window, and have a drag
+    /// from here extend the selection by whole words or lines
+    fn select_unit_at(&mut self, unit: SelectionUnit, pos: usize) {
+        let window = &mut self.editor.windows[self.editor.active_window];
+        let buffer = &self.editor.buffers[window.active_buffer];
+        let (start, end) = unit.bounds(buffer, pos);
+        buffer.set_mark(start);
+        window.cursor = end;
+        self.drag_selection = Some((unit, (start, end)));
+    }
+
     /// Handle mouse drag to update selection
     fn handle_mouse_drag(&mut self, x: f64, y: f64) {
         let char_width = self.text_renderer.char_width() as f64;
         let line_height = self.text_renderer.line_height() as f64;
 
-        // Convert pixel position to character grid position
-        let grid_x = (x / char_width) as u16;
+        // Rows are on the character grid; the column comes from the text
         let grid_y = (y / line_height) as u16;
 
         // Only update cursor in the active window during drag
@@ -1041,12 +1922,12 @@ impl<'a> RoeVelloApp<'a> {
         let buffer = &self.editor.buffers[window.active_buffer];
 
         // Position relative to window content area (+1 for border)
-        let relative_x = grid_x.saturating_sub(window.x + 1);
+        let relative_px = (x - (window.x + 1) as f64 * char_width).max(0.0);
         let relative_y = grid_y.saturating_sub(window.y + 1);
 
-        // Convert to buffer position (account for scroll offsets)
-        let buffer_line = relative_y as usize + window.start_line as usize;
-        let buffer_col = relative_x as usize + window.start_column as usize;
+        // Convert to buffer position (account for scroll offsets and wrapped rows)
+        let (buffer_line, first_column, last_column) =
+            self.row_origin(window_id, relative_y as usize);
 
         // Clamp line to valid range
         let total_lines = buffer.buffer_len_lines();
@@ -1061,7 +1942,15 @@ impl<'a> RoeVelloApp<'a> {
             .into_iter()
             .nth(clamped_line)
             .unwrap_or_default();
-        let line_len = line_text.trim_end_matches('\n').len();
+        let line_text = display_line(&line_text, buffer.tab_width());
+        let line_len = line_text.chars().count();
+
+        // Walk the characters drawn from the row's first column to the one
+        // under the pointer, wide ones taking two cells
+        let drawn: String = line_text.chars().skip(first_column).collect();
+        let buffer_col = (first_column
+            + self.text_renderer.column_at_x(&drawn, relative_px as f32))
+        .min(last_column);
         let clamped_col = buffer_col.min(line_len);
 
         // Get the new cursor position using clamped values
@@ -1075,6 +1964,20 @@ impl<'a> RoeVelloApp<'a> {
             new_cursor.min(buffer_len - 1)
         };
 
+        // After a double or triple click, select whole words or lines from the
+        // first one clicked to the one under the pointer
+        if let Some((unit, (start, end))) = self.drag_selection {
+            let (from, to) = unit.bounds(buffer, clamped_cursor);
+            let (mark, cursor) = if from < start {
+                (end, from)
+            } else {
+                (start, to.max(end))
+            };
+            buffer.set_mark(mark);
+            self.editor.windows[window_id].cursor = cursor;
+            return;
+        }
+
         // On first drag movement, set the mark at the starting position
         if let Some(start_cursor) = self.drag_start_cursor.take() {
             buffer.set_mark(start_cursor);
@@ -1211,6 +2114,109 @@ impl<'a> RoeVelloApp<'a> {
         None
     }
 
+    /// Columns of text a window's content area fits, as laid out by `draw_window`
+    fn content_width_chars(&self, window_id: roe_core::WindowId) -> usize {
+        let char_width = self.text_renderer.char_width() as f64;
+        let window = &self.editor.windows[window_id];
+        let Some(buffer) = self.editor.buffers.get(window.active_buffer) else {
+            return 0;
+        };
+        let gutter_width_chars = if buffer.show_gutter() {
+            calculate_gutter_width(buffer.buffer_len_lines(), &GutterConfig::default())
+        } else {
+            0
+        };
+        let w = window.width_chars as f64 * char_width;
+        let content_width_px =
+            w - (2.0 * char_width) - SCROLLBAR_WIDTH - 4.0 - gutter_width_chars as f64 * char_width;
+        (content_width_px / char_width) as usize
+    }
+
+    /// Buffer line shown on content row `visual_row` of a window, the column of
+    /// it the row starts at, and the last column a click on the row can reach
+    fn row_origin(
+        &self,
+        window_id: roe_core::WindowId,
+        visual_row: usize,
+    ) -> (usize, usize, usize) {
+        let window = &self.editor.windows[window_id];
+        let start_line = window.start_line as usize;
+        let Some(buffer) = self
+            .editor
+            .buffers
+            .get(window.active_buffer)
+            .filter(|buffer| buffer.wrap_mode() != WrapMode::None)
+        else {
+            return (
+                start_line + visual_row,
+                window.start_column as usize,
+                usize::MAX,
+            );
+        };
+
+        let width = self.content_width_chars(window_id);
+        let mut row = 0;
+        for line in start_line..buffer.buffer_len_lines() {
+            let text = buffer.buffer_line(line);
+            let text = display_line(&text, buffer.tab_width());
+            let row_starts = wrap_rows(&text, width, buffer.wrap_mode());
+            if let Some(&first_column) = row_starts.get(visual_row - row) {
+                // Clicks past the end of a row land on its last character
+                // rather than at the start of the next row
+                let last_column = row_starts
+                    .get(visual_row - row + 1)
+                    .map_or(usize::MAX, |next| next - 1);
+                return (line, first_column, last_column);
+            }
+            row += row_starts.len();
+        }
+        // Below the end of the buffer
+        (buffer.buffer_len_lines(), 0, usize::MAX)
+    }
+
+    /// With wrapping on, long lines above the cursor can take enough rows to push
+    /// it below the window even though its line is in range. Scroll down a line
+    /// at a time until the cursor's row shows.
+    fn scroll_wrapped_cursor_into_view(&mut self, window_id: roe_core::WindowId) {
+        let window = &self.editor.windows[window_id];
+        let Some(buffer) = self.editor.buffers.get(window.active_buffer) else {
+            return;
+        };
+        let wrap_mode = buffer.wrap_mode();
+        if wrap_mode == WrapMode::None {
+            return;
+        }
+        let width = self.content_width_chars(window_id);
+        let content_height = window.height_chars.saturating_sub(3) as usize;
+        let (col, cursor_line) = buffer.to_column_line(window.cursor);
+        let (col, cursor_line) = (col as usize, cursor_line as usize);
+        let mut start_line = window.start_line as usize;
+        if cursor_line < start_line {
+            return;
+        }
+
+        let line_rows = |line: usize| {
+            let text = display_line(&buffer.buffer_line(line), buffer.tab_width());
+            wrap_rows(&text, width, wrap_mode)
+        };
+        let rows_above: Vec<usize> = (start_line..cursor_line)
+            .map(|line| line_rows(line).len())
+            .collect();
+        let cursor_rows = line_rows(cursor_line)
+            .into_iter()
+            .filter(|&row_start| row_start <= col)
+            .count();
+        let mut total: usize = rows_above.iter().sum::<usize>() + cursor_rows;
+        for rows in rows_above {
+            if total <= content_height {
+                break;
+            }
+            total -= rows;
+            start_line += 1;
+        }
+        self.editor.windows[window_id].start_line = start_line as u16;
+    }
+
     /// Get max line length for a buffer
     fn get_max_line_len(&self, window_id: roe_core::WindowId) -> usize {
         let window = &self.editor.windows[window_id];
@@ -1218,7 +2224,7 @@ impl<'a> RoeVelloApp<'a> {
         buffer
             .buffer_lines()
             .into_iter()
-            .map(|line| line.trim_end_matches('\n').chars().count())
+            .map(|line| expand_tabs(line.trim_end_matches('\n'), buffer.tab_width()).len())
             .max()
             .unwrap_or(0)
     }
@@ -1274,98 +2280,84 @@ impl<'a> RoeVelloApp<'a> {
         window.start_column = new_start as u16;
     }
 
-    /// Check if a pixel position is on a window border that can be dragged to resize
-    fn check_border_hit(&self, px: f64, py: f64) -> Option<(BorderInfo, WindowId)> {
+    /// Scroll the window under the mouse pointer, without focusing it. Shift
+    /// turns a vertical wheel into a horizontal one.
+    fn handle_mouse_wheel(&mut self, delta: MouseScrollDelta) {
+        let Some((px, py)) = self.cursor_position else {
+            return;
+        };
         let char_width = self.text_renderer.char_width() as f64;
         let line_height = self.text_renderer.line_height() as f64;
+        let Some(window_id) =
+            self.find_window_at_position((px / char_width) as u16, (py / line_height) as u16)
+        else {
+            return;
+        };
 
-        // Convert to grid coordinates
-        let grid_x = (px / char_width) as u16;
-        let grid_y = (py / line_height) as u16;
-
-        // Check all windows to see if the click is on a border
-        for (window_id, window) in &self.editor.windows {
-            let left_border = window.x;
-            let right_border = window.x + window.width_chars - 1;
-            let top_border = window.y;
-            let bottom_border = window.y + window.height_chars - 1;
-
-            // Check vertical borders (left and right sides)
-            if (grid_x == left_border || grid_x == right_border)
-                && grid_y >= top_border
-                && grid_y <= bottom_border
-            {
-                // This is a vertical border
-                if let Some(split_info) = self.find_split_for_border(window_id, true) {
-                    return Some((
-                        BorderInfo {
-                            is_vertical: true,
-                            split_node_path: split_info.0,
-                            original_ratio: split_info.1,
-                        },
-                        window_id,
-                    ));
-                }
+        // winit reports positive deltas for scrolling towards the start
+        let (mut columns, mut lines) = match delta {
+            MouseScrollDelta::LineDelta(x, y) => {
+                (x as f64 * WHEEL_SCROLL_LINES, y as f64 * WHEEL_SCROLL_LINES)
             }
-
-            // Check horizontal borders (top and bottom sides)
-            if (grid_y == top_border || grid_y == bottom_border)
-                && grid_x >= left_border
-                && grid_x <= right_border
-            {
-                // This is a horizontal border
-                if let Some(split_info) = self.find_split_for_border(window_id, false) {
-                    return Some((
-                        BorderInfo {
-                            is_vertical: false,
-                            split_node_path: split_info.0,
-                            original_ratio: split_info.1,
-                        },
-                        window_id,
-                    ));
-                }
+            MouseScrollDelta::PixelDelta(position) => {
+                let scale_factor = self
+                    .state
+                    .as_ref()
+                    .map(|s| s.window.scale_factor())
+                    .unwrap_or(1.0);
+                (
+                    position.x / scale_factor / char_width,
+                    position.y / scale_factor / line_height,
+                )
             }
+        };
+        if self.modifiers.shift_key() && columns == 0.0 {
+            (columns, lines) = (lines, 0.0);
         }
 
-        None
-    }
+        self.wheel_remainder.0 += columns;
+        self.wheel_remainder.1 += lines;
+        let columns = self.wheel_remainder.0.trunc();
+        let lines = self.wheel_remainder.1.trunc();
+        self.wheel_remainder.0 -= columns;
+        self.wheel_remainder.1 -= lines;
 
-    /// Find the split node that controls the given border
-    fn find_split_for_border(
-        &self,
-        window_id: WindowId,
-        is_vertical_border: bool,
-    ) -> Option<(Vec<usize>, f32)> {
-        // Find if this window has a sibling that shares the border
-        for (other_window_id, other_window) in &self.editor.windows {
-            if other_window_id == window_id {
-                continue;
-            }
+        if lines != 0.0 {
+            let window = &self.editor.windows[window_id];
+            let buffer = &self.editor.buffers[window.active_buffer];
+            let total_lines = buffer.buffer_len_lines();
+            let content_height = window.height_chars.saturating_sub(2) as usize;
+            let max_start = total_lines.saturating_sub(content_height) as f64;
+            let new_start = (window.start_line as f64 - lines).clamp(0.0, max_start);
+
+            let window = self.editor.windows.get_mut(window_id).unwrap();
+            window.start_line = new_start as u16;
+        }
 
+        if columns != 0.0 {
             let window = &self.editor.windows[window_id];
+            let w = window.width_chars as f64 * char_width;
+            let content_width_px = w - (2.0 * char_width) - SCROLLBAR_WIDTH - 4.0;
+            let content_width_chars = (content_width_px / char_width) as usize;
+            let max_line_len = self.get_max_line_len(window_id);
+            let max_start = max_line_len.saturating_sub(content_width_chars) as f64;
+            let new_start = (window.start_column as f64 - columns).clamp(0.0, max_start);
 
-            if is_vertical_border {
-                // Check if windows are horizontally adjacent
-                if (window.x + window.width_chars == other_window.x
-                    || other_window.x + other_window.width_chars == window.x)
-                    && window.y < other_window.y + other_window.height_chars
-                    && other_window.y < window.y + window.height_chars
-                {
-                    return Some((vec![0], 0.5)); // Simplified path and ratio
-                }
-            } else {
-                // Check if windows are vertically adjacent
-                if (window.y + window.height_chars == other_window.y
-                    || other_window.y + other_window.height_chars == window.y)
-                    && window.x < other_window.x + other_window.width_chars
-                    && other_window.x < window.x + window.width_chars
-                {
-                    return Some((vec![0], 0.5)); // Simplified path and ratio
-                }
-            }
+            let window = self.editor.windows.get_mut(window_id).unwrap();
+            window.start_column = new_start as u16;
         }
+    }
 
-        None
+    /// Check if a pixel position is on a window border that can be dragged to resize
+    fn check_border_hit(&self, px: f64, py: f64) -> Option<(BorderInfo, WindowId)> {
+        let char_width = self.text_renderer.char_width() as f64;
+        let line_height = self.text_renderer.line_height() as f64;
+
+        // Convert to grid coordinates
+        let grid_x = (px / char_width) as u16;
+        let grid_y = (py / line_height) as u16;
+
+        self.editor.border_at(grid_x, grid_y)
     }
 
     /// Handle border drag for window resizing
@@ -1382,15 +2374,12 @@ impl<'a> RoeVelloApp<'a> {
         let grid_y = (py / line_height) as u16;
 
         let new_pos = (grid_x, grid_y);
-        let dx = new_pos.0 as i32 - drag_state.last_pos.0 as i32;
-        let dy = new_pos.1 as i32 - drag_state.last_pos.1 as i32;
-
-        if dx == 0 && dy == 0 {
+        if new_pos == drag_state.last_pos {
             return;
         }
 
         let border_info = drag_state.border_info.clone();
-        let target_window = drag_state.target_window;
+        let start_pos = drag_state.start_pos;
 
         // Update drag state positions
         if let Some(ref mut drag_state_mut) = self.editor.mouse_drag_state {
@@ -1402,19 +2391,14 @@ impl<'a> RoeVelloApp<'a> {
             return;
         };
 
-        // Apply the resize
-        update_window_resize_incremental(
-            &mut self.editor.window_tree,
-            &mut self.editor.windows,
-            &self.editor.frame,
-            target_window,
-            &border_info,
-            dx,
-            dy,
-        );
-
-        // Recalculate window layout
-        self.editor.calculate_window_layout();
+        // Measured from where the drag started so the border stays under the
+        // pointer, even after being held at a window's minimum size
+        let offset = if border_info.is_vertical {
+            new_pos.0 as i32 - start_pos.0 as i32
+        } else {
+            new_pos.1 as i32 - start_pos.1 as i32
+        };
+        self.editor.drag_border(&border_info, offset);
     }
 }
 
@@ -1434,6 +2418,9 @@ impl<'a> ApplicationHandler for RoeVelloApp<'a> {
         ))
         .expect("Failed to create surface");
 
+        // Receive composed text from input methods (CJK, accents, emoji pickers)
+        window.set_ime_allowed(true);
+
         self.state = Some(RenderState { window, surface });
     }
 
@@ -1458,174 +2445,17 @@ impl<'a> ApplicationHandler for RoeVelloApp<'a> {
                 }
             }
             WindowEvent::RedrawRequested => {
-                // Poll for external file changes
-                let file_change_actions = self.editor.poll_file_changes();
-                for action in file_change_actions {
-                    match action {
-                        ChromeAction::Echo(msg) => {
-                            self.editor.set_echo_message(msg);
-                        }
-                        ChromeAction::MarkDirty(_) => {
-                            // Will be redrawn anyway
-                        }
-                        ChromeAction::BufferChanged {
-                            buffer_id,
-                            start,
-                            old_end,
-                            new_end,
-                        } => {
-                            // Call major mode after-change hook for syntax highlighting
-                            let Some(buffer) = self.editor.buffers.get(buffer_id) else {
-                                continue;
-                            };
-                            let Some(major_mode) = buffer.major_mode() else {
-                                continue;
-                            };
-                            let Some(ref julia_runtime) = self.editor.julia_runtime else {
-                                continue;
-                            };
-
-                            roe_core::julia_runtime::set_current_buffer(buffer.clone());
-                            let runtime = pollster::block_on(julia_runtime.lock());
-                            let _ = pollster::block_on(runtime.call_major_mode_after_change(
-                                &major_mode,
-                                start as i64,
-                                old_end as i64,
-                                new_end as i64,
-                            ));
-                            roe_core::julia_runtime::clear_current_buffer();
-                        }
-                        _ => {}
-                    }
-                }
+                // Surface any exceptions raised by Julia hooks
+                self.editor.collect_julia_errors();
 
                 self.render();
             }
             WindowEvent::KeyboardInput { event, .. } => {
-                let mut actions: std::collections::VecDeque<_> =
-                    pollster::block_on(self.handle_key_event(event)).into();
-
-                while let Some(action) = actions.pop_front() {
-                    match action {
-                        ChromeAction::Quit => {
-                            self.quit_requested = true;
-                            event_loop.exit();
-                        }
-                        ChromeAction::SplitHorizontal => {
-                            self.editor.split_horizontal();
-                        }
-                        ChromeAction::SplitVertical => {
-                            self.editor.split_vertical();
-                        }
-                        ChromeAction::SwitchWindow => {
-                            self.editor.switch_window();
-                        }
-                        ChromeAction::DeleteWindow => {
-                            self.editor.delete_window();
-                        }
-                        ChromeAction::DeleteOtherWindows => {
-                            self.editor.delete_other_windows();
-                        }
-                        ChromeAction::Echo(msg) => {
-                            self.editor.set_echo_message(msg);
-                        }
-                        ChromeAction::NewBufferWithMode {
-                            buffer_name,
-                            mode_name,
-                            initial_content,
-                        } => {
-                            // Create a new buffer with the specified mode (e.g., Julia REPL)
-                            let cursor_pos = initial_content.len();
-                            if let Some(buffer_id) = self.editor.create_buffer_with_mode(
-                                buffer_name,
-                                mode_name,
-                                initial_content,
-                            ) {
-                                // Switch current window to the new buffer
-                                if let Some(current_window) =
-                                    self.editor.windows.get_mut(self.editor.active_window)
-                                {
-                                    current_window.active_buffer = buffer_id;
-                                    current_window.cursor = cursor_pos;
-                                }
-                            }
-                        }
-                        ChromeAction::ShowMessages => {
-                            // Create or show messages buffer
-                            let messages_buffer_id = self.editor.get_messages_buffer();
-                            if let Some(current_window) =
-                                self.editor.windows.get_mut(self.editor.active_window)
-                            {
-                                current_window.active_buffer = messages_buffer_id;
-                                current_window.cursor = 0;
-                            }
-                        }
-                        ChromeAction::BufferChanged {
-                            buffer_id,
-                            start,
-                            old_end,
-                            new_end,
-                        } => {
-                            // Call major mode after-change hook for syntax highlighting
-                            let Some(buffer) = self.editor.buffers.get(buffer_id) else {
-                                continue;
-                            };
-                            let Some(major_mode) = buffer.major_mode() else {
-                                continue;
-                            };
-                            let Some(ref julia_runtime) = self.editor.julia_runtime else {
-                                continue;
-                            };
-
-                            roe_core::julia_runtime::set_current_buffer(buffer.clone());
-                            let runtime = pollster::block_on(julia_runtime.lock());
-                            let _ = pollster::block_on(runtime.call_major_mode_after_change(
-                                &major_mode,
-                                start as i64,
-                                old_end as i64,
-                                new_end as i64,
-                            ));
-                            roe_core::julia_runtime::clear_current_buffer();
-                        }
-                        ChromeAction::ExecuteCommand(command_name) => {
-                            // Execute another command via the command registry
-                            let context = self.editor.create_command_context();
-                            if self.editor.julia_runtime.is_some() {
-                                match pollster::block_on(
-                                    roe_core::command_mode::CommandMode::execute_command(
-                                        &command_name,
-                                        &self.editor.command_registry,
-                                        context,
-                                    ),
-                                ) {
-                                    Ok(command_actions) => {
-                                        // Process through editor to handle BufferOps etc.
-                                        let processed =
-                                            self.editor.process_chrome_actions(command_actions);
-                                        for a in processed {
-                                            actions.push_back(a);
-                                        }
-                                    }
-                                    Err(error_msg) => {
-                                        self.editor.set_echo_message(format!(
-                                            "Command error: {error_msg}"
-                                        ));
-                                    }
-                                }
-                            }
-                        }
-                        ChromeAction::FileWatcherStatus => {
-                            let status = self.editor.file_watcher.status();
-                            self.editor.set_echo_message(status);
-                        }
-                        _ => {}
-                    }
-                }
-
-                // Request redraw after key events
-                if let Some(ref state) = self.state {
-                    state.window.request_redraw();
-                }
+                let actions = pollster::block_on(self.handle_key_event(event));
+                self.apply_chrome_actions(event_loop, actions);
+            }
+            WindowEvent::Ime(ime) => {
+                self.handle_ime(event_loop, ime);
             }
             WindowEvent::CursorMoved { position, .. } => {
                 // Convert physical to logical coordinates
@@ -1708,6 +2538,12 @@ impl<'a> ApplicationHandler for RoeVelloApp<'a> {
                     state.window.set_cursor(cursor);
                 }
             }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.handle_mouse_wheel(delta);
+                if let Some(ref render_state) = self.state {
+                    render_state.window.request_redraw();
+                }
+            }
             WindowEvent::MouseInput { state, button, .. } => {
                 if button == MouseButton::Left {
                     match state {
@@ -1759,12 +2595,18 @@ impl<'a> ApplicationHandler for RoeVelloApp<'a> {
                                         state.window.set_cursor(CursorIcon::Grabbing);
                                     }
                                 } else {
-                                    // Normal text click
+                                    // Normal text click: a second selects the word
+                                    // there and a third the line
+                                    let clicks = self.count_click(x, y);
                                     pollster::block_on(self.handle_mouse_click(x, y));
-                                    // Save cursor position for potential drag selection
                                     let cursor =
                                         self.editor.windows[self.editor.active_window].cursor;
-                                    self.drag_start_cursor = Some(cursor);
+                                    match clicks {
+                                        2 => self.select_unit_at(SelectionUnit::Word, cursor),
+                                        3 => self.select_unit_at(SelectionUnit::Line, cursor),
+                                        // Save cursor position for potential drag selection
+                                        _ => self.drag_start_cursor = Some(cursor),
+                                    }
                                     self.mouse_dragging = true;
                                 }
                                 if let Some(ref render_state) = self.state {
@@ -1775,6 +2617,7 @@ impl<'a> ApplicationHandler for RoeVelloApp<'a> {
                         ElementState::Released => {
                             self.mouse_dragging = false;
                             self.drag_start_cursor = None;
+                            self.drag_selection = None;
                             self.scrollbar_dragging = None;
                             self.hscrollbar_dragging = None;
                             // Clear border drag state
@@ -1788,66 +2631,68 @@ impl<'a> ApplicationHandler for RoeVelloApp<'a> {
             _ => {}
         }
     }
-}
 
-/// Update window layout based on incremental mouse drag
-fn update_window_resize_incremental(
-    window_tree: &mut WindowNode,
-    _windows: &mut slotmap::SlotMap<WindowId, roe_core::editor::Window>,
-    _frame: &roe_core::editor::Frame,
-    _target_window_id: Option<WindowId>,
-    border_info: &BorderInfo,
-    dx: i32,
-    dy: i32,
-) {
-    // Use a sensitivity factor to make resizing smoother
-    // Each pixel of mouse movement = 0.5% ratio change
-    const SENSITIVITY: f32 = 0.005;
-
-    // Calculate the incremental ratio change
-    if border_info.is_vertical && dx != 0 {
-        // For vertical borders, adjust the split ratio based on horizontal movement
-        let ratio_change = dx as f32 * SENSITIVITY;
-        adjust_window_tree_ratio_incremental(window_tree, ratio_change, true);
-    } else if !border_info.is_vertical && dy != 0 {
-        // For horizontal borders, adjust the split ratio based on vertical movement
-        let ratio_change = dy as f32 * SENSITIVITY;
-        adjust_window_tree_ratio_incremental(window_tree, ratio_change, false);
-    }
-}
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        let now = Instant::now();
+        let mut needs_redraw = false;
 
-/// Recursively adjust window tree ratios for incremental resizing
-fn adjust_window_tree_ratio_incremental(
-    node: &mut WindowNode,
-    ratio_change: f32,
-    is_vertical: bool,
-) {
-    match node {
-        WindowNode::Leaf { .. } => {
-            // Nothing to adjust for leaf nodes
-        }
-        WindowNode::Split {
-            direction,
-            ratio,
-            first,
-            second,
-        } => {
-            // Only adjust if the split direction matches the resize direction
-            let should_adjust = match direction {
-                SplitDirection::Vertical => is_vertical,
-                SplitDirection::Horizontal => !is_vertical,
-            };
+        // Drain external file changes on a timer rather than on every frame
+        if now >= self.next_file_poll {
+            self.next_file_poll = now + EVENT_DRAIN_INTERVAL;
+            needs_redraw |= self.process_file_changes();
+        }
 
-            if should_adjust {
-                // Adjust the ratio incrementally, keeping it within bounds
-                // Use tighter bounds to prevent extreme layouts
-                *ratio = (*ratio + ratio_change).clamp(0.15, 0.85);
-            } else {
-                // Recurse into child nodes
-                adjust_window_tree_ratio_incremental(first, ratio_change, is_vertical);
-                adjust_window_tree_ratio_incremental(second, ratio_change, is_vertical);
+        // Hand debounced edits to the Julia thread
+        let next_flush = self.editor.flush_after_changes();
+
+        // Read the menu preview once the selection has settled
+        needs_redraw |= self.editor.load_menu_preview();
+        let next_preview = self.editor.menu_preview_due();
+
+        // Catch the modeline word count up once typing pauses
+        needs_redraw |= self.editor.refresh_word_counts();
+        let next_word_count = self.editor.word_count_due();
+
+        // List the keys that can follow a chord left pending
+        needs_redraw |= self.editor.load_key_hints();
+        let next_key_hints = self.editor.key_hints_due();
+
+        // Highlighting changed on the Julia thread; pick it up in the next frame
+        needs_redraw |= self.editor.take_after_change_completion();
+
+        // Carry out what a background command returned once it finishes
+        if let Some(actions) = self.editor.take_command_result() {
+            self.apply_chrome_actions(event_loop, actions);
+            self.editor.collect_julia_errors();
+            needs_redraw = true;
+        }
+
+        if needs_redraw {
+            if let Some(ref state) = self.state {
+                state.window.request_redraw();
             }
         }
+
+        // Wake for the next file poll, or sooner while edits or hooks are pending
+        let mut wake_at = self.next_file_poll;
+        if let Some(due) = next_flush
+            .into_iter()
+            .chain(next_preview)
+            .chain(next_word_count)
+            .chain(next_key_hints)
+            .min()
+        {
+            wake_at = wake_at.min(now + due);
+        }
+        let hooks_running = self
+            .editor
+            .after_change_queue
+            .as_ref()
+            .is_some_and(|queue| !queue.is_idle());
+        if hooks_running || self.editor.running_command.is_some() {
+            wake_at = wake_at.min(now + AFTER_CHANGE_POLL_INTERVAL);
+        }
+        event_loop.set_control_flow(ControlFlow::WaitUntil(wake_at));
     }
 }
 
@@ -1866,12 +2711,24 @@ async fn load_theme_from_julia(editor: &Editor) -> VelloTheme {
         ("background", "bg"),
         ("foreground", "fg"),
         ("selection", "sel"),
+        ("current-line", "current-line"),
+        ("bracket-match", "bracket-match"),
+        ("bracket-mismatch", "bracket-mismatch"),
         ("modeline", "mode-line"),
         ("modeline_inactive", "mode-line-inactive"),
         ("border", "border"),
         ("border_active", "active-border"),
         ("cursor", "cursor"),
         ("rune", "rune"),
+        ("gutter-bg", "gutter-bg"),
+        ("gutter-fg", "gutter-fg"),
+        ("gutter-modified", "gutter-modified"),
+        ("gutter-saved", "gutter-saved"),
+        ("gutter-conflict", "gutter-conflict"),
+        ("gutter-separator", "gutter-separator"),
+        ("indent-guide", "indent-guide"),
+        ("ruler", "ruler"),
+        ("whitespace", "whitespace"),
     ];
 
     for (key, alias) in color_keys {
@@ -1906,6 +2763,15 @@ async fn load_theme_from_julia(editor: &Editor) -> VelloTheme {
         theme.set_font_family(&family);
     }
 
+    // Fonts for characters the family lacks: a list, or a single name
+    if let Ok(Some(fallback)) = runtime.get_config("font.fallback").await {
+        let fallback = match fallback {
+            ConfigValue::Array(fallback) => fallback,
+            family => vec![family],
+        };
+        theme.font_fallback = fallback.iter().filter_map(ConfigValue::as_string).collect();
+    }
+
     // Load font size
     let font_size = match runtime.get_config("font.size").await {
         Ok(Some(v)) => v.as_integer().map(|i| i as f32),
@@ -1915,6 +2781,52 @@ async fn load_theme_from_julia(editor: &Editor) -> VelloTheme {
         theme.set_font_size(size);
     }
 
+    // Cursor shape: "bar", "block" or "underline"
+    if let Ok(Some(shape)) = runtime.get_config("cursor.shape").await {
+        if let Some(shape) = shape
+            .as_string()
+            .and_then(|name| CursorShape::from_name(&name))
+        {
+            theme.cursor_shape = shape;
+        }
+    }
+
+    // Indent guides
+    theme.indent_guides = runtime
+        .get_config_bool("editor.indent_guides", theme.indent_guides)
+        .await;
+
+    // Tabs and trailing spaces
+    theme.show_whitespace = runtime
+        .get_config_bool("editor.show_whitespace", theme.show_whitespace)
+        .await;
+
+    // Rulers: at the columns given, a number or a list, or else the fill column
+    theme.show_ruler = runtime
+        .get_config_bool("ruler.show", theme.show_ruler)
+        .await;
+    if let Ok(Some(columns)) = runtime.get_config("ruler.columns").await {
+        let columns = match columns {
+            ConfigValue::Array(columns) => columns,
+            column => vec![column],
+        };
+        theme.ruler_columns = columns
+            .iter()
+            .filter_map(ConfigValue::as_integer)
+            .filter_map(|column| usize::try_from(column).ok())
+            .collect();
+    }
+
+    // Dimming of inactive windows
+    theme.dim_inactive = runtime
+        .get_config_bool("windows.dim_inactive", theme.dim_inactive)
+        .await;
+    if let Ok(Some(percent)) = runtime.get_config("windows.dim_percent").await {
+        if let Some(percent) = percent.as_integer() {
+            theme.set_inactive_dim_percent(percent);
+        }
+    }
+
     theme
 }
 
@@ -1931,3 +2843,37 @@ pub fn run_vello(editor: &mut Editor) -> Result<(), Box<dyn std::error::Error>>
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ruler_xs_follow_scroll() {
+        let mut window = roe_core::editor::Window {
+            x: 0,
+            y: 0,
+            width_chars: 84,
+            height_chars: 22,
+            active_buffer: roe_core::BufferId::default(),
+            start_line: 0,
+            start_column: 0,
+            cursor: 0,
+            window_type: WindowType::Normal,
+        };
+        // Text starts 48px in, past the gutter, in 8px cells
+        let (content_x, char_width) = (48.0, 8.0);
+        assert_eq!(
+            ruler_xs(&window, &[0, 80], content_x, char_width, 80),
+            [48.0]
+        );
+
+        // Scrolled 30 columns right, column 80 is drawn 50 cells in and
+        // column 0 is gone
+        window.start_column = 30;
+        assert_eq!(
+            ruler_xs(&window, &[0, 80], content_x, char_width, 80),
+            [48.0 + 50.0 * 8.0]
+        );
+    }
+}
@@ -14,8 +14,9 @@
 //! Text rendering with Parley.
 
 use parley::layout::{Alignment, AlignmentOptions, Layout};
-use parley::style::{FontFamily, FontStack, StyleProperty};
+use parley::style::{FontFamily, FontStack, FontStyle, FontWeight, StyleProperty};
 use parley::{FontContext, LayoutContext};
+use roe_core::buffer::text_display_width;
 use std::borrow::Cow;
 use vello::kurbo::Affine;
 use vello::peniko::{Brush, Color, Fill};
@@ -27,14 +28,116 @@ pub const DEFAULT_FONT_SIZE: f32 = 14.0;
 /// Line height multiplier
 pub const LINE_HEIGHT_FACTOR: f32 = 1.3;
 
-type TextBrush = [u8; 4];
+/// Slant of synthetic italics, in degrees
+const SYNTHETIC_ITALIC_ANGLE: f32 = 14.0;
+
+/// What parley carries along with each run of text: its color, and the
+/// emphasis to fake because the family has no face for it
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct TextBrush {
+    color: [u8; 4],
+    synthetic_bold: bool,
+    synthetic_italic: bool,
+}
 
 fn brush_from_color(color: Color) -> TextBrush {
-    color.to_rgba8().to_u8_array()
+    TextBrush {
+        color: color.to_rgba8().to_u8_array(),
+        ..TextBrush::default()
+    }
 }
 
 fn color_from_brush(brush: TextBrush) -> Color {
-    Color::from_rgba8(brush[0], brush[1], brush[2], brush[3])
+    let [r, g, b, a] = brush.color;
+    Color::from_rgba8(r, g, b, a)
+}
+
+/// Which emphasised faces a font family ships
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct FamilyFaces {
+    bold: bool,
+    italic: bool,
+    bold_italic: bool,
+}
+
+impl FamilyFaces {
+    /// For a family whose faces couldn't be looked up: leave the choice to
+    /// parley, which fakes what it can't find
+    const ALL: FamilyFaces = FamilyFaces {
+        bold: true,
+        italic: true,
+        bold_italic: true,
+    };
+
+    /// Faces of `font_family`, or of the system monospace when there's none
+    fn load(font_cx: &mut FontContext, font_family: Option<&str>) -> Option<Self> {
+        let family = match font_family {
+            Some(name) => font_cx.collection.family_by_name(name)?,
+            None => {
+                let id = font_cx
+                    .collection
+                    .generic_families(parley::style::GenericFamily::Monospace)
+                    .next()?;
+                font_cx.collection.family(id)?
+            }
+        };
+
+        let mut faces = FamilyFaces::default();
+        for font in family.fonts() {
+            let bold = font.weight().value() >= FontWeight::BOLD.value();
+            let italic = !matches!(font.style(), FontStyle::Normal);
+            match (bold, italic) {
+                (true, true) => faces.bold_italic = true,
+                (true, false) => faces.bold = true,
+                (false, true) => faces.italic = true,
+                (false, false) => {}
+            }
+        }
+        Some(faces)
+    }
+
+    /// The face to draw a span in: the real one for its emphasis when the
+    /// family has it, otherwise the closest there is with the rest faked
+    fn select(&self, bold: bool, italic: bool) -> FaceSelection {
+        let (real_bold, real_italic) = match (bold, italic) {
+            (false, false) => (false, false),
+            (true, false) => (self.bold, false),
+            (false, true) => (false, self.italic),
+            (true, true) if self.bold_italic => (true, true),
+            (true, true) if self.bold => (true, false),
+            (true, true) => (false, self.italic),
+        };
+        FaceSelection {
+            bold: real_bold,
+            italic: real_italic,
+            synthetic_bold: bold && !real_bold,
+            synthetic_italic: italic && !real_italic,
+        }
+    }
+}
+
+/// How a span's emphasis is drawn: with the family's own faces, or faked
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct FaceSelection {
+    bold: bool,
+    italic: bool,
+    synthetic_bold: bool,
+    synthetic_italic: bool,
+}
+
+/// Fonts to draw text in, in order: the named family if any, then the
+/// fallbacks, then the system monospace. Parley picks per cluster, so a
+/// character missing from one font is drawn in the next that has it.
+fn font_stack(font_family: Option<&str>, font_fallback: &[String]) -> FontStack<'static> {
+    let families: Vec<FontFamily<'static>> = font_family
+        .into_iter()
+        .chain(font_fallback.iter().map(String::as_str))
+        .map(|name| FontFamily::Named(Cow::Owned(name.to_string())))
+        .chain(std::iter::once(FontFamily::Generic(
+            parley::style::GenericFamily::Monospace,
+        )))
+        .collect();
+    FontStack::List(Cow::Owned(families))
 }
 
 /// A styled span for rendering text with syntax highlighting
@@ -82,16 +185,20 @@ pub struct TextRenderer {
     line_height: f32,
     char_width: f32,
     font_family: Option<String>,
+    /// Fonts to try, in order, for characters the font family lacks
+    font_fallback: Vec<String>,
+    /// Emphasised faces the font family ships
+    faces: FamilyFaces,
 }
 
 impl Default for TextRenderer {
     fn default() -> Self {
-        Self::new(DEFAULT_FONT_SIZE, None)
+        Self::new(DEFAULT_FONT_SIZE, None, Vec::new())
     }
 }
 
 impl TextRenderer {
-    pub fn new(font_size: f32, font_family: Option<String>) -> Self {
+    pub fn new(font_size: f32, font_family: Option<String>, font_fallback: Vec<String>) -> Self {
         let mut font_cx = FontContext::default();
         let mut layout_cx = LayoutContext::new();
 
@@ -101,7 +208,10 @@ impl TextRenderer {
             &mut layout_cx,
             font_size,
             font_family.as_deref(),
+            &font_fallback,
         );
+        let faces =
+            FamilyFaces::load(&mut font_cx, font_family.as_deref()).unwrap_or(FamilyFaces::ALL);
 
         Self {
             font_cx,
@@ -110,6 +220,8 @@ impl TextRenderer {
             line_height,
             char_width,
             font_family,
+            font_fallback,
+            faces,
         }
     }
 
@@ -119,6 +231,7 @@ impl TextRenderer {
         layout_cx: &mut LayoutContext<TextBrush>,
         font_size: f32,
         font_family: Option<&str>,
+        font_fallback: &[String],
     ) -> (f32, f32) {
         // Use a test string to measure character width - 'M' is typically the widest
         let test_str = "MMMMMMMMMM";
@@ -126,16 +239,10 @@ impl TextRenderer {
 
         builder.push_default(StyleProperty::FontSize(font_size));
 
-        if let Some(family_name) = font_family {
-            builder.push_default(StyleProperty::FontStack(FontStack::List(Cow::Borrowed(&[
-                FontFamily::Named(Cow::Owned(family_name.to_string())),
-                FontFamily::Generic(parley::style::GenericFamily::Monospace),
-            ]))));
-        } else {
-            builder.push_default(StyleProperty::FontStack(FontStack::Single(
-                FontFamily::Generic(parley::style::GenericFamily::Monospace),
-            )));
-        }
+        builder.push_default(StyleProperty::FontStack(font_stack(
+            font_family,
+            font_fallback,
+        )));
 
         builder.push_default(StyleProperty::Brush(brush_from_color(Color::WHITE)));
 
@@ -179,6 +286,33 @@ impl TextRenderer {
         self.char_width
     }
 
+    /// Width `text` is drawn at: a cell per column it takes, so wide (e.g.
+    /// CJK) characters take two cells whatever font they fall back to
+    pub fn advance_width(&self, text: &str) -> f32 {
+        text_display_width(text) as f32 * self.char_width
+    }
+
+    /// Column of `line`, as drawn (tabs already expanded), under the pixel
+    /// `x` from where the line starts. Characters are walked by the cells they
+    /// take, so a wide character covers two columns' worth of pixels; past
+    /// the end each further cell counts as another column.
+    pub fn column_at_x(&self, line: &str, x: f32) -> usize {
+        let mut left = 0.0f32;
+        let mut columns = 0;
+        for c in line.chars() {
+            let right = left + self.advance_width(c.encode_utf8(&mut [0; 4]));
+            if x < right {
+                return columns;
+            }
+            left = right;
+            columns += 1;
+        }
+        if self.char_width <= 0.0 {
+            return columns;
+        }
+        columns + ((x - left) / self.char_width) as usize
+    }
+
     /// Render a single line of text
     pub fn render_line(
         &mut self,
@@ -193,38 +327,8 @@ impl TextRenderer {
             return;
         }
 
-        // Build layout
-        let mut builder = self
-            .layout_cx
-            .ranged_builder(&mut self.font_cx, text, 1.0, true);
-
-        // Set styles
-        builder.push_default(StyleProperty::FontSize(self.font_size));
-
-        // Use custom font family if specified, otherwise fall back to system monospace
-        if let Some(ref family_name) = self.font_family {
-            // Create a font stack with the named font, falling back to monospace
-            builder.push_default(StyleProperty::FontStack(FontStack::List(Cow::Borrowed(&[
-                FontFamily::Named(Cow::Owned(family_name.clone())),
-                FontFamily::Generic(parley::style::GenericFamily::Monospace),
-            ]))));
-        } else {
-            builder.push_default(StyleProperty::FontStack(FontStack::Single(
-                FontFamily::Generic(parley::style::GenericFamily::Monospace),
-            )));
-        }
-
-        builder.push_default(StyleProperty::Brush(brush_from_color(color)));
-
-        let mut layout: Layout<TextBrush> = builder.build(text);
-
-        // Don't wrap lines - let clipping handle overflow
-        // For proper wrapping we'd need to pre-calculate visual line counts
-        layout.break_all_lines(None);
-        layout.align(None, Alignment::Start, AlignmentOptions::default());
-
-        // Render glyphs
-        self.render_layout(scene, &layout, x, y);
+        let layout = self.layout_line(text, color, &[]);
+        self.render_layout(scene, &layout, text, x, y);
     }
 
     /// Render a single line of text with multiple styled spans
@@ -242,6 +346,18 @@ impl TextRenderer {
             return;
         }
 
+        let layout = self.layout_line(text, default_color, spans);
+        self.render_layout(scene, &layout, text, x, y);
+    }
+
+    /// Lay out `text` on one unwrapped line in `default_color`, with `spans`
+    /// styled over it
+    fn layout_line(
+        &mut self,
+        text: &str,
+        default_color: Color,
+        spans: &[StyledSpan],
+    ) -> Layout<TextBrush> {
         // Build layout with ranged styles
         let mut builder = self
             .layout_cx
@@ -250,17 +366,11 @@ impl TextRenderer {
         // Set default styles
         builder.push_default(StyleProperty::FontSize(self.font_size));
 
-        // Use custom font family if specified, otherwise fall back to system monospace
-        if let Some(ref family_name) = self.font_family {
-            builder.push_default(StyleProperty::FontStack(FontStack::List(Cow::Borrowed(&[
-                FontFamily::Named(Cow::Owned(family_name.clone())),
-                FontFamily::Generic(parley::style::GenericFamily::Monospace),
-            ]))));
-        } else {
-            builder.push_default(StyleProperty::FontStack(FontStack::Single(
-                FontFamily::Generic(parley::style::GenericFamily::Monospace),
-            )));
-        }
+        // Use custom font family if specified, then the fallbacks, then system monospace
+        builder.push_default(StyleProperty::FontStack(font_stack(
+            self.font_family.as_deref(),
+            &self.font_fallback,
+        )));
 
         builder.push_default(StyleProperty::Brush(brush_from_color(default_color)));
 
@@ -287,24 +397,30 @@ impl TextRenderer {
                 .map(|(i, _)| i)
                 .unwrap_or(text.len());
 
-            // Apply color for this span
+            // Draw bold and italic in the family's own faces where it has
+            // them, faking the rest
+            let face = self.faces.select(span.bold, span.italic);
+
+            // Apply color for this span, with any emphasis to fake
             builder.push(
-                StyleProperty::Brush(brush_from_color(span.color)),
+                StyleProperty::Brush(TextBrush {
+                    synthetic_bold: face.synthetic_bold,
+                    synthetic_italic: face.synthetic_italic,
+                    ..brush_from_color(span.color)
+                }),
                 start_idx..end_idx,
             );
 
-            // Apply bold if set
-            if span.bold {
+            if face.bold {
                 builder.push(
-                    StyleProperty::FontWeight(parley::style::FontWeight::BOLD),
+                    StyleProperty::FontWeight(FontWeight::BOLD),
                     start_idx..end_idx,
                 );
             }
 
-            // Apply italic if set
-            if span.italic {
+            if face.italic {
                 builder.push(
-                    StyleProperty::FontStyle(parley::style::FontStyle::Italic),
+                    StyleProperty::FontStyle(FontStyle::Italic),
                     start_idx..end_idx,
                 );
             }
@@ -315,75 +431,228 @@ impl TextRenderer {
         // Don't wrap lines - let clipping handle overflow
         layout.break_all_lines(None);
         layout.align(None, Alignment::Start, AlignmentOptions::default());
+        layout
+    }
+
+    /// The glyphs of each run of `layout`, laid out from `text`, placed on the
+    /// cell grid from the start of their line. Each cluster takes the cells
+    /// its characters do, as the cursor and selection count them, whatever
+    /// advance the font (or a fallback) gives it: two for a wide character,
+    /// none for the `WIDE_CHAR_TAIL` after it.
+    fn place_glyphs(&self, layout: &Layout<TextBrush>, text: &str) -> Vec<Vec<vello::Glyph>> {
+        let mut placed = Vec::new();
+        for line in layout.lines() {
+            let mut line_x = 0.0f32;
+            // A run's glyphs can be split over several glyph runs, one per
+            // style, in order: how many of this run's came before
+            let mut run_glyphs_seen = (None, 0);
+            for item in line.items() {
+                let parley::layout::PositionedLayoutItem::GlyphRun(glyph_run) = item else {
+                    continue;
+                };
+                let run = glyph_run.run();
+                let run_range = Some(run.text_range());
+                if run_glyphs_seen.0 != run_range {
+                    run_glyphs_seen = (run_range, 0);
+                }
 
-        // Render glyphs
-        self.render_layout(scene, &layout, x, y);
+                // Cells for each of the run's glyphs, the first glyph of a
+                // cluster taking all of the cluster's
+                let cells = run.visual_clusters().flat_map(|cluster| {
+                    let cells = text.get(cluster.text_range()).map_or(0, text_display_width);
+                    cluster
+                        .glyphs()
+                        .enumerate()
+                        .map(move |(i, _)| if i == 0 { cells } else { 0 })
+                });
+
+                let mut glyph_count = 0;
+                let glyphs = glyph_run
+                    .glyphs()
+                    .zip(cells.skip(run_glyphs_seen.1))
+                    .map(|(glyph, cells)| {
+                        glyph_count += 1;
+                        let gx = line_x + glyph.x;
+                        line_x += cells as f32 * self.char_width;
+                        vello::Glyph {
+                            id: glyph.id as u32,
+                            x: gx,
+                            y: glyph.y,
+                        }
+                    })
+                    .collect();
+                run_glyphs_seen.1 += glyph_count;
+                placed.push(glyphs);
+            }
+        }
+        placed
     }
 
-    /// Render a pre-built layout
-    fn render_layout(&self, scene: &mut Scene, layout: &Layout<TextBrush>, x: f32, y: f32) {
+    /// Render a pre-built layout of `text`
+    fn render_layout(
+        &self,
+        scene: &mut Scene,
+        layout: &Layout<TextBrush>,
+        text: &str,
+        x: f32,
+        y: f32,
+    ) {
+        let mut placed = self.place_glyphs(layout, text).into_iter();
         for line in layout.lines() {
             for item in line.items() {
                 let parley::layout::PositionedLayoutItem::GlyphRun(glyph_run) = item else {
                     continue;
                 };
+                let glyphs = placed.next().unwrap_or_default();
 
                 let run = glyph_run.run();
                 let font = run.font();
                 let font_size = run.font_size();
                 let synthesis = run.synthesis();
-                let brush = color_from_brush(glyph_run.style().brush);
+                let text_brush = glyph_run.style().brush;
+                let brush = color_from_brush(text_brush);
 
-                let run_x = x + glyph_run.offset();
                 let run_y = y + glyph_run.baseline();
 
-                // Build transform for the glyph run
-                let transform = Affine::translate((run_x as f64, run_y as f64));
-
-                // Separate transform for italic/skew
+                // Separate transform for italic/skew, whether parley or the
+                // span's face selection asked for it
                 let glyph_xform = synthesis
                     .skew()
+                    .or(text_brush
+                        .synthetic_italic
+                        .then_some(SYNTHETIC_ITALIC_ANGLE))
                     .map(|angle| Affine::skew(angle.to_radians().tan() as f64, 0.0));
 
+                // Synthetic bold draws the run a second time, nudged right
+                let embolden = synthesis.embolden() || text_brush.synthetic_bold;
+                let offsets = [Some(0.0), embolden.then_some((font_size / 24.0).max(0.5))];
+
                 // Get normalized coordinates for variable fonts
                 let coords: Vec<NormalizedCoord> =
                     run.normalized_coords().iter().copied().collect();
 
-                // Track cumulative x position - glyphs need to be advanced manually
-                let mut cursor_x = 0.0f32;
+                let solid_brush = Brush::Solid(brush);
+                for offset in offsets.into_iter().flatten() {
+                    // Build transform for the glyph run
+                    let transform = Affine::translate(((x + offset) as f64, run_y as f64));
+
+                    let mut builder = scene
+                        .draw_glyphs(font)
+                        .font_size(font_size)
+                        .transform(transform)
+                        .brush(&solid_brush)
+                        .hint(true);
+
+                    if let Some(xform) = glyph_xform {
+                        builder = builder.glyph_transform(Some(xform));
+                    }
 
-                // Collect glyphs with proper positioning
-                let glyphs: Vec<vello::Glyph> = glyph_run
-                    .glyphs()
-                    .map(|glyph| {
-                        let gx = cursor_x + glyph.x;
-                        cursor_x += glyph.advance;
-                        vello::Glyph {
-                            id: glyph.id as u32,
-                            x: gx,
-                            y: glyph.y,
-                        }
-                    })
-                    .collect();
+                    if !coords.is_empty() {
+                        builder = builder.normalized_coords(&coords);
+                    }
 
-                let solid_brush = Brush::Solid(brush);
-                let mut builder = scene
-                    .draw_glyphs(font)
-                    .font_size(font_size)
-                    .transform(transform)
-                    .brush(&solid_brush)
-                    .hint(true);
-
-                if let Some(xform) = glyph_xform {
-                    builder = builder.glyph_transform(Some(xform));
+                    builder.draw(Fill::NonZero, glyphs.iter().cloned());
                 }
+            }
+        }
+    }
+}
 
-                if !coords.is_empty() {
-                    builder = builder.normalized_coords(&coords);
-                }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bold_span_uses_real_bold_face() {
+        let faces = FamilyFaces {
+            bold: true,
+            ..FamilyFaces::default()
+        };
+        assert_eq!(
+            faces.select(true, false),
+            FaceSelection {
+                bold: true,
+                ..FaceSelection::default()
+            }
+        );
+        // No bold italic face: the bold one, slanted
+        assert_eq!(
+            faces.select(true, true),
+            FaceSelection {
+                bold: true,
+                synthetic_italic: true,
+                ..FaceSelection::default()
+            }
+        );
+        // No italic face at all
+        assert_eq!(
+            faces.select(false, true),
+            FaceSelection {
+                synthetic_italic: true,
+                ..FaceSelection::default()
+            }
+        );
 
-                builder.draw(Fill::NonZero, glyphs.into_iter());
+        let faces = FamilyFaces::default();
+        assert_eq!(
+            faces.select(true, false),
+            FaceSelection {
+                synthetic_bold: true,
+                ..FaceSelection::default()
             }
+        );
+        assert_eq!(faces.select(false, false), FaceSelection::default());
+    }
+
+    #[test]
+    fn test_column_at_x_with_tab() {
+        let renderer = TextRenderer::default();
+        let char_width = renderer.char_width();
+        // A tab to the first stop, then ASCII, as the line is drawn
+        let line: String = roe_core::buffer::expand_tabs("\tx = 1", 4)
+            .into_iter()
+            .map(|(c, _)| c)
+            .collect();
+        assert_eq!(line, "    x = 1");
+
+        // Monospace cells give what dividing by the cell width gives, past the
+        // end of the line too
+        for cell in 0..12 {
+            let x = (cell as f32 + 0.5) * char_width;
+            assert_eq!(renderer.column_at_x(&line, x), (x / char_width) as usize);
         }
+        assert_eq!(renderer.column_at_x(&line, 4.2 * char_width), 4);
+
+        // A wide character covers two cells but is one column
+        assert_eq!(renderer.column_at_x("中x", 1.5 * char_width), 0);
+        assert_eq!(renderer.column_at_x("中x", 2.5 * char_width), 1);
+    }
+
+    #[test]
+    fn test_wide_chars_take_two_cells() {
+        let mut renderer = TextRenderer::default();
+        let char_width = renderer.char_width();
+        // Two CJK characters, each with its tail cell, then ASCII, as the line is drawn
+        let line: String = roe_core::buffer::expand_tabs("中文x", 4)
+            .into_iter()
+            .map(|(c, _)| c)
+            .collect();
+        assert_eq!(line.chars().count(), 5);
+
+        // Whatever font the CJK glyphs come from, each starts on its own
+        // cell and the ASCII after them lands on the fifth, where the cursor
+        // and selection put it
+        let layout = renderer.layout_line(&line, Color::WHITE, &[]);
+        let xs: Vec<f32> = renderer
+            .place_glyphs(&layout, &line)
+            .concat()
+            .iter()
+            .map(|glyph| glyph.x)
+            .collect();
+        let cells = |x: f32| (x / char_width).round() as usize;
+        assert_eq!(cells(xs[0]), 0);
+        assert!(xs.iter().any(|&x| cells(x) == 2));
+        assert_eq!(cells(*xs.last().unwrap()), 4);
+        assert_eq!(renderer.advance_width(&line), 5.0 * char_width);
     }
 }
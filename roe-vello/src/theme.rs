@@ -15,20 +15,81 @@
 
 use vello::peniko::Color;
 
+/// How the cursor is drawn
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CursorShape {
+    /// A thin vertical bar before the character
+    #[default]
+    Bar,
+    /// The whole character cell, with the character drawn over it inverted
+    Block,
+    /// A strip along the bottom of the character cell
+    Underline,
+}
+
+impl CursorShape {
+    /// Parse a shape name from the config (`"bar"`, `"block"`, `"underline"`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "bar" | "beam" => Some(Self::Bar),
+            "block" | "box" => Some(Self::Block),
+            "underline" | "hbar" => Some(Self::Underline),
+            _ => None,
+        }
+    }
+}
+
 /// Theme colors and font settings for the Vello renderer
 #[derive(Clone)]
 pub struct VelloTheme {
     pub bg_color: Color,
     pub fg_color: Color,
     pub selection_color: Color,
+    /// Tint behind the cursor's line in the active window
+    pub current_line_bg: Color,
+    /// Behind the bracket by the cursor and the one it pairs with
+    pub bracket_match_color: Color,
+    /// Behind the bracket by the cursor when nothing pairs with it
+    pub bracket_mismatch_color: Color,
     pub mode_line_bg_color: Color,
     pub inactive_mode_line_bg_color: Color,
     pub rune_color: Color,
     pub border_color: Color,
     pub active_border_color: Color,
     pub cursor_color: Color,
+    pub cursor_shape: CursorShape,
+    /// Behind the line numbers
+    pub gutter_bg_color: Color,
+    /// Line numbers, and the marks for wrapped lines and lines past the end
+    pub gutter_fg_color: Color,
+    /// Bar beside lines changed since the file was loaded
+    pub gutter_modified_color: Color,
+    /// Bar beside lines changed and since saved
+    pub gutter_saved_color: Color,
+    /// Bar beside lines in a merge conflict
+    pub gutter_conflict_color: Color,
+    /// Line between the gutter and the text
+    pub gutter_separator_color: Color,
+    /// Whether to draw a rule at each indentation stop of a line's indentation
+    pub indent_guides: bool,
+    pub indent_guide_color: Color,
+    /// Whether to draw rulers down the content area
+    pub show_ruler: bool,
+    /// Columns to draw rulers at; the fill column when empty
+    pub ruler_columns: Vec<usize>,
+    pub ruler_color: Color,
+    /// Whether to mark tabs and trailing spaces
+    pub show_whitespace: bool,
+    pub whitespace_color: Color,
     pub font_family: String,
+    /// Fonts to try, in order, for characters the font family lacks
+    pub font_fallback: Vec<String>,
     pub font_size: f32,
+    /// Whether inactive windows' content is dimmed, so the active one stands out
+    pub dim_inactive: bool,
+    /// How much inactive windows are dimmed: the opacity of the background
+    /// color laid over them, from 0.0 (not at all) to 1.0 (hidden)
+    pub inactive_dim: f32,
 }
 
 impl Default for VelloTheme {
@@ -38,14 +99,34 @@ impl Default for VelloTheme {
             bg_color: Color::from_rgb8(0x1e, 0x1e, 0x1e),
             fg_color: Color::from_rgb8(0xd4, 0xd4, 0xd4),
             selection_color: Color::from_rgb8(0x26, 0x4f, 0x78),
+            current_line_bg: Color::from_rgb8(0x2a, 0x2a, 0x2a),
+            bracket_match_color: Color::from_rgb8(0x3a, 0x5a, 0x40),
+            bracket_mismatch_color: Color::from_rgb8(0x8b, 0x2a, 0x2a),
             mode_line_bg_color: Color::from_rgb8(0x00, 0x7a, 0xcc),
             inactive_mode_line_bg_color: Color::from_rgb8(0x3c, 0x3c, 0x3c),
             rune_color: Color::from_rgb8(0xdc, 0xdc, 0xaa),
             border_color: Color::from_rgb8(0x3c, 0x3c, 0x3c),
             active_border_color: Color::from_rgb8(0x00, 0x7a, 0xcc),
             cursor_color: Color::from_rgb8(0xae, 0xaf, 0xad),
+            cursor_shape: CursorShape::Bar,
+            gutter_bg_color: Color::from_rgb8(0x14, 0x14, 0x14), // Slightly darker than bg
+            gutter_fg_color: Color::from_rgb8(0x60, 0x60, 0x60),
+            gutter_modified_color: Color::from_rgb8(0xff, 0xd7, 0x00), // Yellow
+            gutter_saved_color: Color::from_rgb8(0x00, 0xc8, 0x00),    // Green
+            gutter_conflict_color: Color::from_rgb8(0xff, 0x40, 0x40), // Red
+            gutter_separator_color: Color::from_rgb8(0x40, 0x40, 0x40),
+            indent_guides: false,
+            indent_guide_color: Color::from_rgb8(0x40, 0x40, 0x40),
+            show_ruler: false,
+            ruler_columns: Vec::new(),
+            ruler_color: Color::from_rgb8(0x33, 0x33, 0x33),
+            show_whitespace: false,
+            whitespace_color: Color::from_rgb8(0x50, 0x50, 0x50),
             font_family: String::new(), // Empty means use system monospace
+            font_fallback: Vec::new(),
             font_size: 14.0,
+            dim_inactive: true,
+            inactive_dim: 0.3,
         }
     }
 }
@@ -75,6 +156,9 @@ impl VelloTheme {
                 "background" | "bg" => self.bg_color = color,
                 "foreground" | "fg" => self.fg_color = color,
                 "selection" | "sel" => self.selection_color = color,
+                "current_line" | "current-line" => self.current_line_bg = color,
+                "bracket_match" | "bracket-match" => self.bracket_match_color = color,
+                "bracket_mismatch" | "bracket-mismatch" => self.bracket_mismatch_color = color,
                 "modeline" | "mode_line" | "mode-line" => self.mode_line_bg_color = color,
                 "modeline_inactive" | "mode_line_inactive" | "mode-line-inactive" => {
                     self.inactive_mode_line_bg_color = color
@@ -85,6 +169,15 @@ impl VelloTheme {
                 }
                 "cursor" => self.cursor_color = color,
                 "rune" => self.rune_color = color,
+                "gutter_bg" | "gutter-bg" => self.gutter_bg_color = color,
+                "gutter_fg" | "gutter-fg" => self.gutter_fg_color = color,
+                "gutter_modified" | "gutter-modified" => self.gutter_modified_color = color,
+                "gutter_saved" | "gutter-saved" => self.gutter_saved_color = color,
+                "gutter_conflict" | "gutter-conflict" => self.gutter_conflict_color = color,
+                "gutter_separator" | "gutter-separator" => self.gutter_separator_color = color,
+                "indent_guide" | "indent-guide" => self.indent_guide_color = color,
+                "ruler" => self.ruler_color = color,
+                "whitespace" => self.whitespace_color = color,
                 _ => {}
             }
         }
@@ -101,6 +194,11 @@ impl VelloTheme {
             self.font_size = size;
         }
     }
+
+    /// Set how much inactive windows are dimmed, as a percentage
+    pub fn set_inactive_dim_percent(&mut self, percent: i64) {
+        self.inactive_dim = percent.clamp(0, 100) as f32 / 100.0;
+    }
 }
 
 /// Parse a hex color string like "#272822" to a Color
@@ -117,3 +215,30 @@ pub fn parse_hex_color(hex: &str) -> Option<Color> {
 
     Some(Color::from_rgb8(r, g, b))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_overrides_gutter_colors() {
+        let mut theme = VelloTheme::default();
+        let saved = theme.gutter_saved_color;
+
+        theme.set_color("gutter-modified", "#ff8800");
+        theme.set_color("gutter_separator", "#102030");
+        assert_eq!(
+            theme.gutter_modified_color,
+            Color::from_rgb8(0xff, 0x88, 0x00)
+        );
+        assert_eq!(
+            theme.gutter_separator_color,
+            Color::from_rgb8(0x10, 0x20, 0x30)
+        );
+        assert_eq!(theme.gutter_saved_color, saved);
+
+        // Colors that don't parse leave the default
+        theme.set_color("gutter-saved", "green");
+        assert_eq!(theme.gutter_saved_color, saved);
+    }
+}
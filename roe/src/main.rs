@@ -17,11 +17,11 @@ use crossterm::event::{
 };
 use crossterm::execute;
 use crossterm::terminal::disable_raw_mode;
+use roe_core::clipboard::SystemClipboard;
+use roe_core::file_location::{FileLocation, FilePosition};
 use roe_core::{
-    buffer_host, command_registry, editor,
-    julia_runtime::{clear_current_buffer, set_current_buffer},
-    kill_ring, mode, Buffer, BufferId, ConfigurableBindings, Editor, Frame, KeyState, Mode, ModeId,
-    Renderer, Window, WindowId,
+    buffer_host, command_registry, editor, kill_ring, mode, Buffer, BufferId, ConfigurableBindings,
+    Editor, Frame, KeyState, Mode, ModeId, Renderer, Window, WindowId,
 };
 use roe_terminal::{TerminalRenderer, ECHO_AREA_HEIGHT};
 use slotmap::SlotMap;
@@ -31,8 +31,9 @@ use std::io::Write;
 /// Parse command line arguments
 fn parse_args() -> EditorConfig {
     let args: Vec<String> = std::env::args().collect();
-    let mut file_paths = Vec::new();
+    let mut files = Vec::new();
     let mut init_file = None;
+    let mut split = false;
     let mut i = 1; // Skip program name
 
     while i < args.len() {
@@ -47,6 +48,10 @@ fn parse_args() -> EditorConfig {
                     std::process::exit(1);
                 }
             }
+            "--split" | "-s" => {
+                split = true;
+                i += 1;
+            }
             "--help" | "-h" => {
                 print_help();
                 std::process::exit(0);
@@ -57,16 +62,17 @@ fn parse_args() -> EditorConfig {
                 std::process::exit(1);
             }
             _ => {
-                // Regular file argument
-                file_paths.push(args[i].clone());
+                // Regular file argument, with an optional :line[:col] after it
+                files.push(FileLocation::resolve(&args[i]));
                 i += 1;
             }
         }
     }
 
     EditorConfig {
-        file_paths,
+        files,
         init_file,
+        split,
     }
 }
 
@@ -79,12 +85,14 @@ fn print_help() {
     println!();
     println!("OPTIONS:");
     println!("    -i, --init <FILE>    Specify Julia init file (default: init.jl)");
+    println!("    -s, --split          Show each file in a window of its own");
     println!("    -h, --help           Print this help message");
     println!();
     println!("EXAMPLES:");
     println!("    roe                          # Start with welcome screen");
     println!("    roe file.txt                 # Open file.txt");
     println!("    roe file1.txt file2.txt      # Open multiple files");
+    println!("    roe src/main.rs:120:5        # Open at line 120, column 5");
     println!("    roe --init myconfig.jl       # Use custom init file");
     println!("    roe -i ~/.config/init.jl main.rs   # Custom init + file");
 }
@@ -124,8 +132,11 @@ fn create_welcome_screen_content() -> String {
 
 // Configuration for the editor
 struct EditorConfig {
-    file_paths: Vec<String>,
+    /// Files to open, each at a line and column if given
+    files: Vec<FileLocation>,
     init_file: Option<String>,
+    /// Tile the files into windows, not just the first
+    split: bool,
 }
 
 // Everything to run in raw_mode
@@ -166,6 +177,8 @@ async fn terminal_main<W: Write>(stdout: W, config: EditorConfig) -> Result<(),
             eprintln!("Make sure to run from the roe directory or install properly.");
             std::process::exit(1);
         }
+        // The defaults, before the user config has a chance to override them
+        let default_bindings = runtime.list_keybindings().await.unwrap_or_default();
         drop(runtime);
 
         // Load user config (may override default keybindings)
@@ -176,20 +189,45 @@ async fn terminal_main<W: Write>(stdout: W, config: EditorConfig) -> Result<(),
         // Query keybindings from Julia and populate ConfigurableBindings
         let runtime = julia_runtime.lock().await;
         if let Ok(julia_bindings) = runtime.list_keybindings().await {
-            for (key_seq, action) in julia_bindings {
-                bindings.add_binding(&key_seq, &action);
-            }
+            bindings.add_layered_bindings(&default_bindings, &julia_bindings);
         }
         drop(runtime);
     }
 
     let mut buffers: SlotMap<BufferId, Buffer> = SlotMap::default();
     let mut buffer_hosts: HashMap<BufferId, buffer_host::BufferHostClient> = HashMap::new();
+    let mut file_positions: HashMap<BufferId, FilePosition> = HashMap::new();
     let mut modes: SlotMap<ModeId, Box<dyn Mode>> = SlotMap::default();
 
     let mut first_buffer_id = None;
+    let mut file_buffer_ids = Vec::new();
+
+    // With no files given, start in the welcome screen or in *scratch*
+    // (roe_config["startup"]["buffer"] = "welcome" | "scratch")
+    let startup_buffer = match julia_runtime {
+        Some(ref jr) => {
+            jr.lock()
+                .await
+                .get_config_string("startup.buffer", "welcome")
+                .await
+        }
+        None => "welcome".to_string(),
+    };
+
+    // Files given are buffers behind the first unless tiled into windows
+    // (roe_config["startup"]["split"] = true, or --split)
+    let split = config.split
+        || match julia_runtime {
+            Some(ref jr) => {
+                jr.lock()
+                    .await
+                    .get_config_bool("startup.split", false)
+                    .await
+            }
+            None => false,
+        };
 
-    if config.file_paths.is_empty() {
+    if config.files.is_empty() && startup_buffer != "scratch" {
         // No files specified, create welcome screen buffer
         let welcome_mode = Box::new(mode::MessagesMode {});
         let welcome_mode_id = modes.insert(welcome_mode);
@@ -197,6 +235,7 @@ async fn terminal_main<W: Write>(stdout: W, config: EditorConfig) -> Result<(),
         let buffer = Buffer::new(&[welcome_mode_id]);
         buffer.set_object("*Welcome*".to_string());
         buffer.load_str(&create_welcome_screen_content());
+        buffer.set_read_only(true);
 
         let buffer_id = buffers.insert(buffer.clone());
         first_buffer_id = Some(buffer_id);
@@ -212,7 +251,11 @@ async fn terminal_main<W: Write>(stdout: W, config: EditorConfig) -> Result<(),
         buffer_hosts.insert(buffer_id, buffer_client);
     } else {
         // Create buffers for all specified files
-        for file_path in config.file_paths {
+        for FileLocation {
+            path: file_path,
+            position,
+        } in config.files
+        {
             // Create FileMode for this file
             let file_mode = Box::new(mode::FileMode {
                 file_path: file_path.clone(),
@@ -222,12 +265,18 @@ async fn terminal_main<W: Write>(stdout: W, config: EditorConfig) -> Result<(),
             // Try to load the file, create empty buffer if it doesn't exist
             let buffer = match Buffer::from_file(&file_path, &[file_mode_id]).await {
                 Ok(buffer) => buffer,
-                Err(_) => {
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
                     // File doesn't exist, create empty buffer with FileMode
                     let buffer = Buffer::new(&[file_mode_id]);
                     buffer.set_object(file_path.clone());
                     buffer
                 }
+                Err(e) => {
+                    return Err(std::io::Error::new(
+                        e.kind(),
+                        format!("Can't open {file_path}: {e}"),
+                    ))
+                }
             };
 
             // Get and apply major mode for this file
@@ -237,14 +286,18 @@ async fn terminal_main<W: Write>(stdout: W, config: EditorConfig) -> Result<(),
                     buffer.set_major_mode(major_mode.clone());
 
                     // Call the major mode's init hook
-                    set_current_buffer(buffer.clone());
-                    let _ = runtime.call_major_mode_init(&major_mode).await;
-                    clear_current_buffer();
+                    let _ = runtime
+                        .call_major_mode_init(&major_mode, buffer.clone())
+                        .await;
                 }
                 drop(runtime);
             }
 
             let buffer_id = buffers.insert(buffer.clone());
+            file_buffer_ids.push(buffer_id);
+            if let Some(position) = position {
+                file_positions.insert(buffer_id, position);
+            }
 
             // Remember the first buffer for the initial window
             if first_buffer_id.is_none() {
@@ -268,79 +321,61 @@ async fn terminal_main<W: Write>(stdout: W, config: EditorConfig) -> Result<(),
         }
     }
 
-    // Create windows - split horizontally if we have 2+ files, single window otherwise
-    let mut windows: SlotMap<WindowId, Window> = SlotMap::default();
-    let window_tree;
-    let active_window_id;
-
-    let buffer_ids: Vec<BufferId> = buffers.keys().collect();
-
-    if buffer_ids.len() >= 2 {
-        // Two-window horizontal split - frame already accounts for echo area
-        let available_height = tsize.1 - ECHO_AREA_HEIGHT;
-        let window_height = available_height / 2;
-
-        // Top window (first file)
-        let top_window = Window {
-            x: 0,
-            y: 0,
-            width_chars: tsize.0,
-            height_chars: window_height,
-            active_buffer: buffer_ids[0],
-            start_line: 0,
-            start_column: 0,
-            cursor: 0,
-            window_type: editor::WindowType::Normal,
-        };
-        let top_window_id = windows.insert(top_window);
-
-        // Bottom window (second file)
-        let bottom_window = Window {
-            x: 0,
-            y: window_height,
-            width_chars: tsize.0,
-            height_chars: available_height - window_height,
-            active_buffer: buffer_ids[1],
-            start_line: 0,
-            start_column: 0,
-            cursor: 0,
-            window_type: editor::WindowType::Normal,
-        };
-        let bottom_window_id = windows.insert(bottom_window);
-
-        // Create horizontal split tree
-        window_tree = editor::WindowNode::new_split(
-            editor::SplitDirection::Horizontal,
-            0.5, // 50/50 split
-            editor::WindowNode::new_leaf(top_window_id),
-            editor::WindowNode::new_leaf(bottom_window_id),
-        );
-
-        active_window_id = top_window_id; // Start with top window active
-    } else {
-        // Single window (full screen)
-        let active_buffer = first_buffer_id.expect("Should have at least one buffer");
-        let window = Window {
-            x: 0,
-            y: 0,
-            width_chars: tsize.0,
-            height_chars: tsize.1 - ECHO_AREA_HEIGHT,
-            active_buffer,
-            start_line: 0,
-            start_column: 0,
-            cursor: 0,
-            window_type: editor::WindowType::Normal,
-        };
-        active_window_id = windows.insert(window);
-        window_tree = editor::WindowNode::new_leaf(active_window_id);
+    // Always have a *scratch* buffer to jot in, so `C-x b scratch` works
+    let scratch_mode_id = modes.insert(Box::new(mode::ScratchMode {}));
+    let scratch_buffer = mode::scratch_buffer(scratch_mode_id);
+    if let Some(ref jr) = julia_runtime {
+        let runtime = jr.lock().await;
+        let _ = runtime
+            .call_major_mode_init(mode::SCRATCH_MAJOR_MODE, scratch_buffer.clone())
+            .await;
+        drop(runtime);
+    }
+    let scratch_buffer_id = buffers.insert(scratch_buffer.clone());
+    if first_buffer_id.is_none() {
+        first_buffer_id = Some(scratch_buffer_id);
     }
 
+    let scratch_mode = modes
+        .remove(scratch_mode_id)
+        .expect("ScratchMode should exist in modes SlotMap");
+    let mode_list = vec![(scratch_mode_id, "scratch".to_string(), scratch_mode)];
+    let (buffer_client, _buffer_handle) = buffer_host::create_buffer_host(
+        scratch_buffer,
+        mode_list,
+        scratch_buffer_id,
+        julia_runtime.clone(),
+    );
+    buffer_hosts.insert(scratch_buffer_id, buffer_client);
+
+    // One window to start with; the files are tiled into more with --split
+    let mut windows: SlotMap<WindowId, Window> = SlotMap::default();
+    let window = Window {
+        x: 0,
+        y: 0,
+        width_chars: tsize.0,
+        height_chars: tsize.1 - ECHO_AREA_HEIGHT,
+        active_buffer: first_buffer_id.expect("Should have at least one buffer"),
+        start_line: 0,
+        start_column: 0,
+        cursor: 0,
+        window_type: editor::WindowType::Normal,
+    };
+    let active_window_id = windows.insert(window);
+    let window_tree = editor::WindowNode::new_leaf(active_window_id);
+
     // Initialize file watcher
     let mut file_watcher = roe_core::file_watcher::FileWatcher::new();
     if let Err(e) = file_watcher.init() {
         eprintln!("Warning: Failed to initialize file watcher: {e}");
     }
 
+    // Major mode after-change hooks are queued rather than awaited on the runtime lock
+    let after_change_queue = match julia_runtime {
+        Some(ref jr) => Some(jr.lock().await.after_change_queue()),
+        None => None,
+    };
+
     let mut editor = Editor {
         frame: Frame::new(tsize.0, tsize.1 - ECHO_AREA_HEIGHT),
         buffers,
@@ -353,18 +388,58 @@ async fn terminal_main<W: Write>(stdout: W, config: EditorConfig) -> Result<(),
         bindings: Box::new(bindings),
         window_tree,
         kill_ring: kill_ring::KillRing::new(),
+        last_yank: None,
+        killed_rectangle: Vec::new(),
         command_registry: command_registry::create_default_registry(),
         buffer_history: Vec::new(),
         echo_message: String::new(),
         echo_message_time: None,
         current_key_chord: Vec::new(),
+        key_hints: roe_core::editor::KeyHints::default(),
         mouse_drag_state: None,
         messages_buffer_id: None,
+        julia_errors_buffer_id: None,
         julia_runtime,
+        after_change_queue,
+        pending_after_changes: HashMap::new(),
         file_watcher,
         last_search_term: String::new(),
+        menu_max_visible_items: roe_core::selection_menu::DEFAULT_MAX_VISIBLE_ITEMS,
+        menu_preview: None,
+        buffer_views: HashMap::new(),
+        collapsed_layout: None,
+        winner: roe_core::editor::WinnerHistory::default(),
+        minibuffer_history: roe_core::minibuffer_history::MinibufferHistory::default(),
+        pending_prompt: None,
+        running_command: None,
+        echo_max_lines: roe_core::editor::DEFAULT_ECHO_MAX_LINES,
+        echo_scroll: 0,
+        scroll_page_overlap: roe_core::editor::DEFAULT_PAGE_OVERLAP,
+        scroll_step: 0,
+        scroll_margin: 0,
+        goal_column: None,
+        recenter_position: None,
+        buffer_cycle: None,
+        bookmarks: roe_core::bookmarks::Bookmarks::default(),
+        recent_files: roe_core::recent_files::RecentFiles::default(),
+        keyboard_macro: roe_core::keyboard_macro::KeyboardMacro::new(),
+        prefix_arg: None,
+        registers: HashMap::new(),
+        register_prompt: None,
+        delete_trailing_whitespace_on_save: false,
+        auto_revert: false,
+        fill_column: roe_core::fill::DEFAULT_FILL_COLUMN,
+        quoted_insert: None,
+        word_counts: HashMap::new(),
+        tree_sitter_highlighters: HashMap::new(),
+        conflict_cache: roe_core::conflict::ConflictCache::default(),
     };
 
+    // Kills go to the system clipboard, and text copied elsewhere can be yanked
+    if let Some(clipboard) = SystemClipboard::new() {
+        editor.kill_ring.set_clipboard(clipboard);
+    }
+
     // Initialize buffer history with the current buffer
     let initial_buffer_id = editor.windows[active_window_id].active_buffer;
     editor.record_buffer_access(initial_buffer_id);
@@ -390,6 +465,25 @@ async fn terminal_main<W: Write>(stdout: W, config: EditorConfig) -> Result<(),
         command_registry::register_julia_commands(&mut editor.command_registry, julia_runtime)
             .await;
     }
+    editor.load_menu_config().await;
+    editor.load_echo_config().await;
+    editor.load_scroll_config().await;
+    editor.load_editor_config().await;
+    editor.load_bookmarks();
+    editor.load_recent_files();
+    editor.record_recent_files(&file_buffer_ids);
+    editor.warn_binding_conflicts();
+
+    editor.show_startup_buffers(&file_buffer_ids, split);
+
+    // Put the cursor at the line given with each file shown, once the scroll
+    // margin is known
+    let window_ids: Vec<WindowId> = editor.windows.keys().collect();
+    for window_id in window_ids {
+        if let Some(&position) = file_positions.get(&editor.windows[window_id].active_buffer) {
+            editor.goto_file_position(window_id, position);
+        }
+    }
 
     // Load Julia theme and create terminal renderer with it
     let julia_theme = if editor.julia_runtime.is_some() {